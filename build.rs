@@ -4,12 +4,20 @@ use fs_extra::dir::CopyOptions;
 use std::env;
 
 fn main() -> Result<()> {
+    //profiling-procmacros defines impl_block once per backend, so with both of these it fails
+    //with an E0428 that doesn't mention either feature. a compile_error! in the crate would never
+    //be reached since the dependency fails first, the build script can at least say why when it
+    //gets to run, e.g. under --keep-going
+    if env::var_os("CARGO_FEATURE_PROFILE_PUFFIN").is_some()
+        && env::var_os("CARGO_FEATURE_PROFILE_TRACING").is_some()
+    {
+        bail!("the profile-puffin and profile-tracing features can't be enabled together, pick one");
+    }
     println!("cargo:rerun-if-changed=res/*");
     let out_dir = env::var("OUT_DIR")?;
     let mut copy_options = CopyOptions::new();
     copy_options.overwrite = true;
-    let mut paths_to_copy = Vec::new();
-    paths_to_copy.push("res/");
+    let paths_to_copy = vec!["res/"];
     copy_items(&paths_to_copy, out_dir, &copy_options)?;
     Ok(())
 }
@@ -0,0 +1,15 @@
+// structs shared by the scene shaders, each shader declares its own bindings for them
+
+// matches camera::CameraUniform
+struct CameraUniform {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+
+// matches the light uniform written by the app. ambient fills the padding after position, the
+// padding after color is implied
+struct Light {
+    position: vec3<f32>,
+    ambient: f32,
+    color: vec3<f32>,
+}
@@ -0,0 +1,198 @@
+use crate::ui_layer::DebugPanel;
+use accesskit::{
+    Action, ActionHandler, ActionRequest, ActivationHandler, DeactivationHandler, NodeBuilder,
+    NodeId, Role, Toggled, Tree, TreeUpdate,
+};
+use std::sync::mpsc;
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+const ROOT: NodeId = NodeId(0);
+const OVERLAY_TOGGLE: NodeId = NodeId(1);
+const CAMERA_WINDOW: NodeId = NodeId(2);
+const LIGHT_WINDOW: NodeId = NodeId(3);
+const STATS_WINDOW: NodeId = NodeId(4);
+//the text inside the windows is numbered from here in the order it is added, the same layout
+//always gets the same ids so screen readers don't lose their place between updates
+const FIRST_LABEL: u64 = 16;
+
+//something a screen reader asked for that the app has to carry out, focus changes are handled
+//inside Accessibility
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccessibilityRequest {
+    ToggleOverlay,
+}
+
+//exposes the window and the debug overlay's windows as an accesskit tree. the tree is only built
+//while assistive technology is listening, and every update carries the whole tree since it is
+//only a handful of nodes. the adapter has to exist before the window is first shown
+pub struct Accessibility {
+    adapter: accesskit_winit::Adapter,
+    actions: mpsc::Receiver<ActionRequest>,
+    focus: NodeId,
+}
+
+//the first frame after activation sends the full tree, until then the platform shows a placeholder
+struct DeferredActivation;
+
+impl ActivationHandler for DeferredActivation {
+    fn request_initial_tree(&mut self) -> Option<TreeUpdate> {
+        None
+    }
+}
+
+//actions can arrive on any thread, they are queued for the event loop to pick up
+struct QueuedActions(mpsc::Sender<ActionRequest>);
+
+impl ActionHandler for QueuedActions {
+    fn do_action(&mut self, request: ActionRequest) {
+        let _ = self.0.send(request);
+    }
+}
+
+struct NoDeactivation;
+
+impl DeactivationHandler for NoDeactivation {
+    fn deactivate_accessibility(&mut self) {}
+}
+
+impl Accessibility {
+    pub fn new(window: &Window) -> Self {
+        let (sender, actions) = mpsc::channel();
+        let adapter = accesskit_winit::Adapter::with_direct_handlers(
+            window,
+            DeferredActivation,
+            QueuedActions(sender),
+            NoDeactivation,
+        );
+        Self {
+            adapter,
+            actions,
+            focus: ROOT,
+        }
+    }
+
+    //has to see every window event before the app handles it
+    pub fn process_event(&mut self, window: &Window, event: &WindowEvent) {
+        self.adapter.process_event(window, event);
+    }
+
+    //applies queued focus changes and returns the requests the app has to carry out
+    pub fn take_requests(&mut self) -> Vec<AccessibilityRequest> {
+        let mut requests = Vec::new();
+        for request in self.actions.try_iter() {
+            match request.action {
+                Action::Focus => self.focus = request.target,
+                Action::Default if request.target == OVERLAY_TOGGLE => {
+                    requests.push(AccessibilityRequest::ToggleOverlay)
+                }
+                _ => (),
+            }
+        }
+        requests
+    }
+
+    //rebuilds the tree if anything is listening. has_overlay is false when built without a ui
+    //feature, the panel is only given while the overlay is showing
+    pub fn update(&mut self, title: &str, has_overlay: bool, panel: Option<&DebugPanel<'_>>) {
+        let focus = &mut self.focus;
+        self.adapter.update_if_active(|| {
+            let mut tree = TreeBuilder::default();
+            let mut children = Vec::new();
+            if has_overlay {
+                let mut toggle = NodeBuilder::new(Role::CheckBox);
+                toggle.set_name("Debug overlay");
+                toggle.set_toggled(if panel.is_some() {
+                    Toggled::True
+                } else {
+                    Toggled::False
+                });
+                toggle.add_action(Action::Focus);
+                toggle.add_action(Action::Default);
+                tree.nodes.push((OVERLAY_TOGGLE, toggle.build()));
+                children.push(OVERLAY_TOGGLE);
+            }
+            if let Some(panel) = panel {
+                children.extend(tree.debug_windows(panel));
+            }
+            let mut root = NodeBuilder::new(Role::Window);
+            root.set_name(title);
+            root.set_children(children);
+            tree.nodes.push((ROOT, root.build()));
+            //focus on something that has since been removed falls back to the window
+            if !tree.nodes.iter().any(|(id, _)| *id == *focus) {
+                *focus = ROOT;
+            }
+            TreeUpdate {
+                nodes: tree.nodes,
+                tree: Some(Tree::new(ROOT)),
+                focus: *focus,
+            }
+        });
+    }
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    nodes: Vec<(NodeId, accesskit::Node)>,
+    labels: u64,
+}
+
+impl TreeBuilder {
+    //the same windows the ui layers draw, with their values read out as text
+    fn debug_windows(&mut self, panel: &DebugPanel<'_>) -> [NodeId; 3] {
+        let camera = &panel.camera;
+        let camera_labels = [
+            format!("eye {:.2}, {:.2}, {:.2}", camera.eye.x, camera.eye.y, camera.eye.z),
+            format!(
+                "target {:.2}, {:.2}, {:.2}",
+                camera.target.x, camera.target.y, camera.target.z
+            ),
+            format!("fov {:.1}", camera.fovy),
+            format!("speed {:.3}", panel.camera_speed),
+            format!("near {:.2}", camera.znear),
+            format!("far {:.1}", camera.zfar),
+        ];
+        let [x, y, z] = *panel.light_position;
+        let [r, g, b] = *panel.light_color;
+        let light_labels = [
+            format!("position {:.2}, {:.2}, {:.2}", x, y, z),
+            format!("color {:.2}, {:.2}, {:.2}", r, g, b),
+        ];
+        let stats = &panel.frame_stats;
+        let draws = &panel.draw_stats;
+        let stats_labels = [
+            format!("{:.0} fps", stats.fps),
+            format!("{:.2}ms avg, {:.2}ms p95", stats.average_frame_ms, stats.p95_frame_ms),
+            format!("{} instances", stats.instance_count),
+            format!("{} draws", draws.draws),
+            format!(
+                "{} pipeline, {} material and {} mesh binds, {} saved",
+                draws.pipeline_binds, draws.material_binds, draws.mesh_binds, draws.saved_binds
+            ),
+        ];
+        [
+            self.window(CAMERA_WINDOW, "Camera", &camera_labels),
+            self.window(LIGHT_WINDOW, "Light", &light_labels),
+            self.window(STATS_WINDOW, "Frame stats", &stats_labels),
+        ]
+    }
+
+    fn window(&mut self, id: NodeId, name: &str, labels: &[String]) -> NodeId {
+        let mut children = Vec::with_capacity(labels.len());
+        for text in labels {
+            let label_id = NodeId(FIRST_LABEL + self.labels);
+            self.labels += 1;
+            let mut label = NodeBuilder::new(Role::Label);
+            label.set_name(text.as_str());
+            self.nodes.push((label_id, label.build()));
+            children.push(label_id);
+        }
+        let mut window = NodeBuilder::new(Role::Group);
+        window.set_name(name);
+        window.set_children(children);
+        window.add_action(Action::Focus);
+        self.nodes.push((id, window.build()));
+        id
+    }
+}
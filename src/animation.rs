@@ -0,0 +1,405 @@
+use cgmath::prelude::*;
+use cgmath::{Quaternion, Vector3, Vector4};
+
+//shapes the progress of a tween, all of them start at 0 and end at 1
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineInOut,
+    //overshoots the end a little before settling on it
+    BackOut,
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::SineInOut => -((std::f32::consts::PI * t).cos() - 1.0) / 2.0,
+            Easing::BackOut => {
+                const C1: f32 = 1.70158;
+                const C3: f32 = C1 + 1.0;
+                1.0 + C3 * (t - 1.0).powi(3) + C1 * (t - 1.0).powi(2)
+            }
+        }
+    }
+}
+
+//something in the scene a tween can drive. instances are addressed by index
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Property {
+    InstancePosition(usize),
+    InstanceRotation(usize),
+    InstanceScale(usize),
+    InstanceTint(usize),
+    LightPosition,
+    LightColor,
+    //the light's ambient strength
+    Ambient,
+    CameraEye,
+    CameraTarget,
+    //vertical field of view in degrees
+    CameraFov,
+}
+
+//a property's value. rotations are slerped, everything else is interpolated component wise
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Value {
+    Float(f32),
+    Vec3(Vector3<f32>),
+    Vec4(Vector4<f32>),
+    Quat(Quaternion<f32>),
+}
+
+impl Value {
+    //None when the two are different kinds of value
+    pub fn interpolate(&self, to: &Value, t: f32) -> Option<Value> {
+        Some(match (self, to) {
+            (Value::Float(a), Value::Float(b)) => Value::Float(a + (b - a) * t),
+            (Value::Vec3(a), Value::Vec3(b)) => Value::Vec3(a.lerp(*b, t)),
+            (Value::Vec4(a), Value::Vec4(b)) => Value::Vec4(a.lerp(*b, t)),
+            (Value::Quat(a), Value::Quat(b)) => Value::Quat(a.slerp(*b, t)),
+            _ => return None,
+        })
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<Vector3<f32>> for Value {
+    fn from(value: Vector3<f32>) -> Self {
+        Value::Vec3(value)
+    }
+}
+
+impl From<[f32; 3]> for Value {
+    fn from(value: [f32; 3]) -> Self {
+        Value::Vec3(value.into())
+    }
+}
+
+impl From<[f32; 4]> for Value {
+    fn from(value: [f32; 4]) -> Self {
+        Value::Vec4(value.into())
+    }
+}
+
+impl From<Quaternion<f32>> for Value {
+    fn from(value: Quaternion<f32>) -> Self {
+        Value::Quat(value)
+    }
+}
+
+//what animations read their starting values from and write to, GameState implements it for
+//the scene
+pub trait Animatable {
+    //None when the property doesn't exist, e.g. an instance index past the end
+    fn get(&self, property: Property) -> Option<Value>;
+
+    fn set(&mut self, property: Property, value: Value);
+}
+
+//moves one property to a value over time
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tween {
+    pub property: Property,
+    //None starts from whatever the property is when the tween begins
+    pub from: Option<Value>,
+    pub to: Value,
+    pub seconds: f32,
+    //waited out before the tween starts, counted from the start of its step
+    pub delay: f32,
+    pub easing: Easing,
+}
+
+impl Tween {
+    pub fn to(property: Property, to: impl Into<Value>, seconds: f32) -> Self {
+        Self {
+            property,
+            from: None,
+            to: to.into(),
+            seconds,
+            delay: 0.0,
+            easing: Easing::default(),
+        }
+    }
+
+    pub fn from(mut self, from: impl Into<Value>) -> Self {
+        self.from = Some(from.into());
+        self
+    }
+
+    pub fn delay(mut self, seconds: f32) -> Self {
+        self.delay = seconds;
+        self
+    }
+
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    fn end(&self) -> f32 {
+        self.delay + self.seconds.max(0.0)
+    }
+
+    fn progress(&self, elapsed: f32) -> f32 {
+        if self.seconds <= 0.0 {
+            return 1.0;
+        }
+        self.easing.apply((elapsed - self.delay) / self.seconds)
+    }
+}
+
+//steps played one after the other, the tweens within a step run together. the step ends when
+//its longest tween does
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    steps: Vec<Vec<Tween>>,
+    looping: bool,
+}
+
+impl Animation {
+    pub fn new(tween: Tween) -> Self {
+        Self {
+            steps: vec![vec![tween]],
+            looping: false,
+        }
+    }
+
+    //runs alongside the tweens of the last step
+    pub fn with(mut self, tween: Tween) -> Self {
+        if let Some(step) = self.steps.last_mut() {
+            step.push(tween);
+        }
+        self
+    }
+
+    //starts a new step once the last one has finished
+    pub fn then(mut self, tween: Tween) -> Self {
+        self.steps.push(vec![tween]);
+        self
+    }
+
+    //goes back to the first step after the last, until stopped
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.steps.iter().map(|step| step_length(step)).sum()
+    }
+}
+
+fn step_length(step: &[Tween]) -> f32 {
+    step.iter().map(Tween::end).fold(0.0, f32::max)
+}
+
+//returned by Animator::play to stop the animation again
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AnimationId(u64);
+
+struct Playing {
+    id: AnimationId,
+    animation: Animation,
+    step: usize,
+    elapsed: f32,
+    //the start value of each tween in the step, filled in as each begins
+    starts: Vec<Option<Value>>,
+}
+
+impl Playing {
+    //moves the animation on, returning false once it has finished
+    fn advance(&mut self, seconds: f32, target: &mut dyn Animatable) -> bool {
+        self.elapsed += seconds;
+        loop {
+            let step = &self.animation.steps[self.step];
+            let length = step_length(step);
+            let finished = self.elapsed >= length;
+            let elapsed = self.elapsed.min(length);
+            for (tween, start) in step.iter().zip(self.starts.iter_mut()) {
+                if elapsed < tween.delay {
+                    continue;
+                }
+                if start.is_none() {
+                    *start = tween.from.or_else(|| target.get(tween.property));
+                }
+                let Some(from) = *start else {
+                    continue;
+                };
+                if let Some(value) = from.interpolate(&tween.to, tween.progress(elapsed)) {
+                    target.set(tween.property, value);
+                }
+            }
+            if !finished {
+                return true;
+            }
+            self.elapsed -= length;
+            self.step += 1;
+            if self.step == self.animation.steps.len() {
+                //a loop with nothing to wait for would never hand back control
+                let duration = self.animation.duration();
+                if !self.animation.looping || duration <= 0.0 {
+                    return false;
+                }
+                self.step = 0;
+                //whole loops skipped by a long tick end up in the same place
+                self.elapsed %= duration;
+            }
+            self.starts = vec![None; self.animation.steps[self.step].len()];
+        }
+    }
+}
+
+//the animations playing in the scene, ticked once per update
+#[derive(Default)]
+pub struct Animator {
+    playing: Vec<Playing>,
+    next_id: u64,
+}
+
+impl Animator {
+    pub fn play(&mut self, animation: Animation) -> AnimationId {
+        let id = AnimationId(self.next_id);
+        self.next_id += 1;
+        let starts = vec![None; animation.steps[0].len()];
+        self.playing.push(Playing {
+            id,
+            animation,
+            step: 0,
+            elapsed: 0.0,
+            starts,
+        });
+        id
+    }
+
+    //leaves its properties where they are, returns false if it had already finished
+    pub fn stop(&mut self, id: AnimationId) -> bool {
+        let count = self.playing.len();
+        self.playing.retain(|playing| playing.id != id);
+        self.playing.len() != count
+    }
+
+    pub fn is_playing(&self, id: AnimationId) -> bool {
+        self.playing.iter().any(|playing| playing.id == id)
+    }
+
+    pub fn clear(&mut self) {
+        self.playing.clear();
+    }
+
+    //moves every animation on by the time since the last tick, in the order they were played so
+    //later ones win when two drive the same property
+    pub fn tick(&mut self, seconds: f32, target: &mut dyn Animatable) {
+        self.playing
+            .retain_mut(|playing| playing.advance(seconds, target));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct Scene(HashMap<Property, Value>);
+
+    impl Animatable for Scene {
+        fn get(&self, property: Property) -> Option<Value> {
+            self.0.get(&property).copied()
+        }
+
+        fn set(&mut self, property: Property, value: Value) {
+            self.0.insert(property, value);
+        }
+    }
+
+    fn fov(scene: &Scene) -> f32 {
+        match scene.get(Property::CameraFov) {
+            Some(Value::Float(fov)) => fov,
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn easings_start_at_zero_and_end_at_one() {
+        for easing in [
+            Easing::Linear,
+            Easing::QuadIn,
+            Easing::QuadOut,
+            Easing::QuadInOut,
+            Easing::CubicIn,
+            Easing::CubicOut,
+            Easing::CubicInOut,
+            Easing::SineInOut,
+            Easing::BackOut,
+        ] {
+            assert!(easing.apply(0.0).abs() < 1e-5, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-5, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn steps_play_in_sequence_from_the_current_value() {
+        let mut scene = Scene::default();
+        scene.set(Property::CameraFov, Value::Float(45.0));
+        let mut animator = Animator::default();
+        let id = animator.play(
+            Animation::new(Tween::to(Property::CameraFov, 90.0, 1.0))
+                .then(Tween::to(Property::CameraFov, 60.0, 2.0).delay(1.0)),
+        );
+        animator.tick(0.5, &mut scene);
+        assert_eq!(fov(&scene), 67.5);
+        //past the first step, into the second's delay
+        animator.tick(1.0, &mut scene);
+        assert_eq!(fov(&scene), 90.0);
+        animator.tick(1.5, &mut scene);
+        assert_eq!(fov(&scene), 75.0);
+        animator.tick(10.0, &mut scene);
+        assert_eq!(fov(&scene), 60.0);
+        assert!(!animator.is_playing(id));
+    }
+
+    #[test]
+    fn loops_restart_until_stopped() {
+        let mut scene = Scene::default();
+        let mut animator = Animator::default();
+        let id = animator
+            .play(Animation::new(Tween::to(Property::Ambient, 1.0, 1.0).from(0.0)).looping());
+        animator.tick(2.25, &mut scene);
+        assert_eq!(scene.get(Property::Ambient), Some(Value::Float(0.25)));
+        assert!(animator.stop(id));
+        animator.tick(0.5, &mut scene);
+        assert_eq!(scene.get(Property::Ambient), Some(Value::Float(0.25)));
+    }
+}
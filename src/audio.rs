@@ -0,0 +1,322 @@
+use crate::camera::Camera;
+use crate::picking::InstanceId;
+use crate::resources;
+use anyhow::*;
+use cgmath::prelude::*;
+use kira::manager::backend::DefaultBackend;
+use kira::manager::{AudioManager, AudioManagerSettings};
+use kira::sound::static_sound::{StaticSoundData, StaticSoundHandle};
+use kira::spatial::emitter::{EmitterHandle, EmitterSettings};
+use kira::spatial::listener::{ListenerHandle, ListenerSettings};
+use kira::spatial::scene::{SpatialSceneHandle, SpatialSceneSettings};
+use kira::track::{TrackBuilder, TrackHandle};
+use kira::tween::Tween;
+use std::collections::HashMap;
+use std::time::Duration;
+
+//the range emitters attached by play_at are heard over, in world units
+const DEFAULT_MIN_DISTANCE: f32 = 1.0;
+const DEFAULT_MAX_DISTANCE: f32 = 50.0;
+
+//the mixer tracks sounds play through, each with its own volume. sfx and music both feed master
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Bus {
+    Master,
+    Sfx,
+    Music,
+}
+
+//an emitter and where it was last sent, positions are only sent to the audio thread when they
+//change since its command queue is small
+struct Emitter {
+    handle: EmitterHandle,
+    position: [f32; 3],
+}
+
+//plays sounds loaded from the res folder. effects are fire and forget, music loops on its own
+//bus and a new track fades the old one out. effects can also come from an instance, panned and
+//attenuated from where it is relative to the camera. the app owns it, pausing it while suspended
+//and dropping it on exit
+pub struct Audio {
+    manager: AudioManager<DefaultBackend>,
+    sfx: TrackHandle,
+    music_track: TrackHandle,
+    volumes: HashMap<Bus, f64>,
+    //decoded once, playing a sound only clones the shared samples
+    sounds: HashMap<String, StaticSoundData>,
+    music: Option<StaticSoundHandle>,
+    spatial: SpatialSceneHandle,
+    //follows the camera, its output goes through the sfx bus
+    listener: ListenerHandle,
+    //the position and orientation last sent to the listener
+    listener_pose: ([f32; 3], [f32; 4]),
+    emitters: HashMap<InstanceId, Emitter>,
+}
+
+impl Audio {
+    pub fn new() -> Result<Self> {
+        let mut manager = AudioManager::<DefaultBackend>::new(AudioManagerSettings::default())
+            .map_err(|e| anyhow!("failed to open the audio device: {:?}", e))?;
+        let sfx = manager.add_sub_track(TrackBuilder::new())?;
+        let music_track = manager.add_sub_track(TrackBuilder::new())?;
+        let mut spatial = manager.add_spatial_scene(SpatialSceneSettings::default())?;
+        let listener_pose = ([0.0; 3], [0.0, 0.0, 0.0, 1.0]);
+        let listener = spatial.add_listener(
+            listener_pose.0,
+            listener_pose.1,
+            ListenerSettings::new().track(&sfx),
+        )?;
+        Ok(Self {
+            manager,
+            sfx,
+            music_track,
+            volumes: HashMap::new(),
+            sounds: HashMap::new(),
+            music: None,
+            spatial,
+            listener,
+            listener_pose,
+            emitters: HashMap::new(),
+        })
+    }
+
+    //adds an already decoded sound under a name, replacing any sound with the same name
+    pub fn insert_sound(&mut self, name: &str, sound: StaticSoundData) {
+        self.sounds.insert(name.to_string(), sound);
+    }
+
+    //loads and decodes a sound from the res folder, named after its file
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(&mut self, file_name: &str) -> Result<()> {
+        let sound = pollster::block_on(resources::load_sound(file_name))?;
+        self.insert_sound(file_name, sound);
+        Ok(())
+    }
+
+    pub fn has_sound(&self, name: &str) -> bool {
+        self.sounds.contains_key(name)
+    }
+
+    //plays a loaded sound once on the sfx bus, it is cleaned up when it finishes
+    pub fn play(&mut self, name: &str) -> Result<()> {
+        let sound = self
+            .sound(name)?
+            .with_modified_settings(|settings| settings.output_destination(&self.sfx));
+        self.manager.play(sound)?;
+        Ok(())
+    }
+
+    //gives an instance an emitter that sounds can be played from. it is heard at full volume
+    //within min_distance and fades out completely by max_distance. replaces any emitter the
+    //instance already had, cutting off what it was playing
+    pub fn attach_emitter(
+        &mut self,
+        instance: InstanceId,
+        min_distance: f32,
+        max_distance: f32,
+    ) -> Result<()> {
+        //placed properly by the next update_spatial
+        let position = [0.0; 3];
+        let handle = self.spatial.add_emitter(
+            position,
+            EmitterSettings::new().distances((min_distance, max_distance)),
+        )?;
+        self.emitters.insert(instance, Emitter { handle, position });
+        Ok(())
+    }
+
+    //removes the instance's emitter, sounds still playing from it stop
+    pub fn detach_emitter(&mut self, instance: InstanceId) {
+        self.emitters.remove(&instance);
+    }
+
+    pub fn has_emitter(&self, instance: InstanceId) -> bool {
+        self.emitters.contains_key(&instance)
+    }
+
+    //plays a loaded sound once from the instance's emitter, attaching one with the default
+    //distances if it has none
+    pub fn play_at(&mut self, name: &str, instance: InstanceId) -> Result<()> {
+        if !self.has_emitter(instance) {
+            self.attach_emitter(instance, DEFAULT_MIN_DISTANCE, DEFAULT_MAX_DISTANCE)?;
+        }
+        let emitter = &self.emitters[&instance].handle;
+        let sound = self
+            .sound(name)?
+            .with_modified_settings(|settings| settings.output_destination(emitter));
+        self.manager.play(sound)?;
+        Ok(())
+    }
+
+    //moves the listener to the camera and every emitter to its instance. called once per frame
+    //after the update, with the instance positions the renderer draws from. emitters whose
+    //instance no longer exists are detached
+    pub(crate) fn update_spatial(
+        &mut self,
+        camera: &Camera,
+        instance_position: impl Fn(InstanceId) -> Option<cgmath::Vector3<f32>>,
+    ) {
+        let pose = (camera.eye.into(), listener_orientation(camera));
+        if pose != self.listener_pose {
+            let moved = self.listener.set_position(pose.0, Tween::default()).is_ok()
+                && self
+                    .listener
+                    .set_orientation(pose.1, Tween::default())
+                    .is_ok();
+            //a full command queue is retried next frame
+            if moved {
+                self.listener_pose = pose;
+            }
+        }
+        self.emitters.retain(|instance, emitter| {
+            let Some(position) = instance_position(*instance) else {
+                return false;
+            };
+            let position: [f32; 3] = position.into();
+            if position != emitter.position
+                && emitter
+                    .handle
+                    .set_position(position, Tween::default())
+                    .is_ok()
+            {
+                emitter.position = position;
+            }
+            true
+        });
+    }
+
+    //loops a loaded sound on the music bus, fading out whatever was playing before
+    pub fn play_music(&mut self, name: &str, fade: Duration) -> Result<()> {
+        let sound = self.sound(name)?.with_modified_settings(|settings| {
+            settings
+                .output_destination(&self.music_track)
+                .loop_region(..)
+                .fade_in_tween(Self::tween(fade))
+        });
+        self.stop_music(fade);
+        self.music = Some(self.manager.play(sound)?);
+        Ok(())
+    }
+
+    pub fn stop_music(&mut self, fade: Duration) {
+        if let Some(mut music) = self.music.take() {
+            let _ = music.stop(Self::tween(fade));
+        }
+    }
+
+    //amplitude, 1.0 plays sounds as they are and 0.0 silences the bus
+    pub fn set_volume(&mut self, bus: Bus, volume: f64) {
+        let volume = volume.max(0.0);
+        let result = match bus {
+            Bus::Master => self.manager.main_track().set_volume(volume, Tween::default()),
+            Bus::Sfx => self.sfx.set_volume(volume, Tween::default()),
+            Bus::Music => self.music_track.set_volume(volume, Tween::default()),
+        };
+        if result.is_ok() {
+            self.volumes.insert(bus, volume);
+        }
+    }
+
+    pub fn volume(&self, bus: Bus) -> f64 {
+        self.volumes.get(&bus).copied().unwrap_or(1.0)
+    }
+
+    //pauses everything, e.g. while the app is suspended
+    pub(crate) fn pause(&mut self) {
+        let _ = self.manager.pause(Tween::default());
+    }
+
+    pub(crate) fn resume(&mut self) {
+        let _ = self.manager.resume(Tween::default());
+    }
+
+    fn sound(&self, name: &str) -> Result<&StaticSoundData> {
+        self.sounds
+            .get(name)
+            .ok_or_else(|| anyhow!("no sound loaded as {:?}", name))
+    }
+
+    fn tween(duration: Duration) -> Tween {
+        Tween {
+            duration,
+            ..Default::default()
+        }
+    }
+}
+
+//the rotation that turns an unrotated listener, facing -z with +y up, to look where the camera
+//looks. as a quaternion in x, y, z, w order
+fn listener_orientation(camera: &Camera) -> [f32; 4] {
+    let forward = camera.target - camera.eye;
+    let right = forward.cross(camera.up);
+    if forward.magnitude2() == 0.0 || right.magnitude2() == 0.0 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+    let forward = forward.normalize();
+    let right = right.normalize();
+    let up = right.cross(forward);
+    let rotation = cgmath::Quaternion::from(cgmath::Matrix3::from_cols(right, up, -forward));
+    [rotation.v.x, rotation.v.y, rotation.v.z, rotation.s]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Point3, Quaternion, Vector3};
+
+    fn camera(eye: [f32; 3], target: [f32; 3]) -> Camera {
+        let mut camera = Camera::new(1.0, 1.0);
+        camera.eye = eye.into();
+        camera.target = target.into();
+        camera
+    }
+
+    //where kira hears a position from, +x being the right ear
+    fn listener_space(camera: &Camera, position: [f32; 3]) -> Vector3<f32> {
+        let [x, y, z, w] = listener_orientation(camera);
+        let rotation = Quaternion::new(w, x, y, z);
+        rotation.invert() * (Point3::from(position) - camera.eye)
+    }
+
+    fn assert_near(actual: &[f32], expected: &[f32]) {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn looking_down_negative_z_is_unrotated() {
+        let orientation = listener_orientation(&camera([0.0, 0.0, 0.0], [0.0, 0.0, -1.0]));
+        assert_near(&orientation, &[0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn emitter_to_the_right_is_positive_x() {
+        //the default camera, one looking along +x and one looking back along +z
+        let cameras = [
+            camera([0.0, 1.0, 2.0], [0.0, 0.0, 0.0]),
+            camera([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]),
+            camera([3.0, 0.0, 0.0], [3.0, 0.0, 5.0]),
+        ];
+        for camera in &cameras {
+            let right = (camera.target - camera.eye).cross(camera.up).normalize();
+            let emitter = camera.eye + right * 4.0;
+            let position = listener_space(camera, emitter.into());
+            assert_near(&[position.x, position.y, position.z], &[4.0, 0.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn emitter_ahead_is_negative_z() {
+        let camera = camera([0.0, 1.0, 2.0], [0.0, 0.0, 0.0]);
+        let ahead = (camera.target - camera.eye).normalize() * 2.0;
+        let position = listener_space(&camera, (camera.eye + ahead).into());
+        assert_near(&[position.x, position.y, position.z], &[0.0, 0.0, -2.0]);
+    }
+
+    #[test]
+    fn degenerate_camera_falls_back_to_unrotated() {
+        let looking_up = camera([0.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        assert_near(&listener_orientation(&looking_up), &[0.0, 0.0, 0.0, 1.0]);
+    }
+}
@@ -0,0 +1,403 @@
+use crate::camera_bookmarks::CameraPose;
+use crate::instance_grid::{InstanceGrid, RotationRule};
+use crate::transform::Transform;
+use anyhow::Context;
+use serde::Serialize;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+//the spacing of the benchmark grid, the same as the demo grid
+const SPACING: f32 = 3.0;
+
+//where the camera goes over a run, the same way every time so runs can be compared
+#[derive(Debug, Clone, PartialEq)]
+pub enum CameraPath {
+    //circles a point looking at it
+    Orbit {
+        center: [f32; 3],
+        radius: f32,
+        height: f32,
+        revolutions: f32,
+    },
+    //flies through the poses in order, taking as long between each pair
+    Waypoints(Vec<CameraPose>),
+}
+
+impl CameraPath {
+    //the pose a fraction t of the way along, from 0 to 1
+    pub fn pose_at(&self, t: f32) -> CameraPose {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            CameraPath::Orbit {
+                center,
+                radius,
+                height,
+                revolutions,
+            } => {
+                let angle = std::f32::consts::TAU * revolutions * t;
+                CameraPose {
+                    eye: [
+                        center[0] + radius * angle.cos(),
+                        center[1] + height,
+                        center[2] + radius * angle.sin(),
+                    ],
+                    target: *center,
+                    fovy: 45.0,
+                }
+            }
+            CameraPath::Waypoints(poses) => match poses.len() {
+                0 => CameraPose {
+                    eye: [0.0, 5.0, 10.0],
+                    target: [0.0; 3],
+                    fovy: 45.0,
+                },
+                1 => poses[0],
+                count => {
+                    let along = t * (count - 1) as f32;
+                    let from = (along as usize).min(count - 2);
+                    poses[from].lerp(&poses[from + 1], along - from as f32)
+                }
+            },
+        }
+    }
+}
+
+//how a benchmark run is set up
+//
+//    let config = BenchmarkConfig::new(20_000, 30.0).report("bench.csv").label("a1b2c3d");
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkConfig {
+    pub instances: u32,
+    //how long frames are measured for, after the warmup
+    pub seconds: f32,
+    //frames left out of the numbers at the start while pipelines and caches settle
+    pub warmup_seconds: f32,
+    //None orbits the whole grid once
+    pub path: Option<CameraPath>,
+    //written when the run finishes. a .json path gets one json report, anything else gets a csv
+    //row appended, with the header when the file is new
+    pub report: Option<PathBuf>,
+    //stored with the results, e.g. a commit hash, to tell runs apart
+    pub label: String,
+}
+
+impl BenchmarkConfig {
+    pub fn new(instances: u32, seconds: f32) -> Self {
+        Self {
+            instances,
+            seconds,
+            warmup_seconds: 1.0,
+            path: None,
+            report: None,
+            label: String::new(),
+        }
+    }
+
+    pub fn warmup(mut self, seconds: f32) -> Self {
+        self.warmup_seconds = seconds;
+        self
+    }
+
+    pub fn path(mut self, path: CameraPath) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn report(mut self, path: impl Into<PathBuf>) -> Self {
+        self.report = Some(path.into());
+        self
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    //exactly instances transforms, a grid spread out around the origin
+    pub fn transforms(&self) -> Vec<Transform> {
+        InstanceGrid::cube(self.instances, SPACING)
+            .rotation(RotationRule::RandomYaw)
+            .transforms()
+    }
+
+    //the configured path, or a slow orbit taking in the whole grid
+    pub fn camera_path(&self) -> CameraPath {
+        self.path.clone().unwrap_or_else(|| {
+            let side = (self.instances as f32).cbrt().ceil().max(1.0);
+            let extent = side * SPACING;
+            CameraPath::Orbit {
+                center: [0.0; 3],
+                radius: extent,
+                height: extent * 0.5,
+                revolutions: 1.0,
+            }
+        })
+    }
+}
+
+//a run in progress, fed each frame's timing and draw counts
+#[derive(Debug)]
+pub struct Benchmark {
+    config: BenchmarkConfig,
+    path: CameraPath,
+    started: f64,
+    last_frame: Option<f64>,
+    frame_ms: Vec<f32>,
+    draw_calls: u64,
+    drawn_instances: u64,
+}
+
+impl Benchmark {
+    //starts the clock at now, in seconds
+    pub fn new(config: BenchmarkConfig, now: f64) -> Self {
+        Self {
+            path: config.camera_path(),
+            config,
+            started: now,
+            last_frame: None,
+            frame_ms: Vec::new(),
+            draw_calls: 0,
+            drawn_instances: 0,
+        }
+    }
+
+    pub fn config(&self) -> &BenchmarkConfig {
+        &self.config
+    }
+
+    fn measured_seconds(&self, now: f64) -> f32 {
+        (now - self.started) as f32 - self.config.warmup_seconds
+    }
+
+    //where the camera should be, the path is held at its start through the warmup
+    pub fn pose(&self, now: f64) -> CameraPose {
+        let t = self.measured_seconds(now) / self.config.seconds.max(f32::EPSILON);
+        self.path.pose_at(t)
+    }
+
+    //call once per presented frame. returns true once the run is over
+    pub fn record_frame(&mut self, now: f64, draw_calls: u32, drawn_instances: u32) -> bool {
+        if self.measured_seconds(now) >= 0.0 {
+            if let Some(last) = self.last_frame {
+                self.frame_ms.push(((now - last) * 1000.0) as f32);
+                self.draw_calls += draw_calls as u64;
+                self.drawn_instances += drawn_instances as u64;
+            }
+            self.last_frame = Some(now);
+        }
+        self.is_finished(now)
+    }
+
+    pub fn is_finished(&self, now: f64) -> bool {
+        self.measured_seconds(now) >= self.config.seconds
+    }
+
+    //the numbers so far, labelled with the adapter they came from
+    pub fn report(&self, adapter: &wgpu::AdapterInfo) -> BenchmarkReport {
+        let mut sorted = self.frame_ms.clone();
+        sorted.sort_by(f32::total_cmp);
+        let frames = sorted.len();
+        let total_ms: f32 = sorted.iter().sum();
+        let per_frame = |total: u64| match frames {
+            0 => 0.0,
+            _ => total as f32 / frames as f32,
+        };
+        let percentile = |p: f32| {
+            sorted
+                .get(((frames as f32 * p).ceil() as usize).saturating_sub(1))
+                .copied()
+                .unwrap_or_default()
+        };
+        let average_ms = if frames > 0 {
+            total_ms / frames as f32
+        } else {
+            0.0
+        };
+        BenchmarkReport {
+            label: self.config.label.clone(),
+            adapter: adapter.name.clone(),
+            backend: format!("{:?}", adapter.backend),
+            instances: self.config.instances,
+            frames: frames as u32,
+            seconds: total_ms / 1000.0,
+            fps: if average_ms > 0.0 {
+                1000.0 / average_ms
+            } else {
+                0.0
+            },
+            average_ms,
+            min_ms: sorted.first().copied().unwrap_or_default(),
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p95_ms: percentile(0.95),
+            p99_ms: percentile(0.99),
+            max_ms: sorted.last().copied().unwrap_or_default(),
+            average_draw_calls: per_frame(self.draw_calls),
+            average_drawn_instances: per_frame(self.drawn_instances),
+        }
+    }
+}
+
+//the results of a run, frame times in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchmarkReport {
+    pub label: String,
+    pub adapter: String,
+    pub backend: String,
+    pub instances: u32,
+    pub frames: u32,
+    pub seconds: f32,
+    pub fps: f32,
+    pub average_ms: f32,
+    pub min_ms: f32,
+    pub p50_ms: f32,
+    pub p90_ms: f32,
+    pub p95_ms: f32,
+    pub p99_ms: f32,
+    pub max_ms: f32,
+    pub average_draw_calls: f32,
+    pub average_drawn_instances: f32,
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} instances, {} frames | {:.0} fps | {:.2}ms avg | {:.2}ms p50 | {:.2}ms p95 | {:.2}ms p99 | {:.0} draws",
+            self.instances,
+            self.frames,
+            self.fps,
+            self.average_ms,
+            self.p50_ms,
+            self.p95_ms,
+            self.p99_ms,
+            self.average_draw_calls
+        )
+    }
+}
+
+impl BenchmarkReport {
+    pub const CSV_HEADER: &'static str = "label,adapter,backend,instances,frames,seconds,fps,average_ms,min_ms,p50_ms,p90_ms,p95_ms,p99_ms,max_ms,average_draw_calls,average_drawn_instances";
+
+    //one line matching CSV_HEADER, without the newline
+    pub fn csv_row(&self) -> String {
+        //quoted so commas in adapter names don't shift the columns
+        let quote = |text: &str| format!("\"{}\"", text.replace('"', "\"\""));
+        format!(
+            "{},{},{},{},{},{:.3},{:.2},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.1},{:.1}",
+            quote(&self.label),
+            quote(&self.adapter),
+            self.backend,
+            self.instances,
+            self.frames,
+            self.seconds,
+            self.fps,
+            self.average_ms,
+            self.min_ms,
+            self.p50_ms,
+            self.p90_ms,
+            self.p95_ms,
+            self.p99_ms,
+            self.max_ms,
+            self.average_draw_calls,
+            self.average_drawn_instances
+        )
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    //writes json to a .json path and appends a csv row to anything else, so repeated runs
+    //across commits and machines build up one table
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let write = || -> anyhow::Result<()> {
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                std::fs::write(path, self.to_json()?)?;
+                return Ok(());
+            }
+            let new = !path.exists();
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            if new {
+                writeln!(file, "{}", Self::CSV_HEADER)?;
+            }
+            writeln!(file, "{}", self.csv_row())?;
+            Ok(())
+        };
+        write().with_context(|| format!("writing benchmark report {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_spawns_exactly_the_instances_asked_for() {
+        let config = BenchmarkConfig::new(30, 5.0);
+        assert_eq!(config.transforms().len(), 30);
+        let CameraPath::Orbit { radius, .. } = config.camera_path() else {
+            panic!("the default path orbits");
+        };
+        assert_eq!(radius, 4.0 * SPACING);
+        let waypoints = CameraPath::Waypoints(vec![
+            CameraPose {
+                eye: [0.0; 3],
+                target: [0.0, 0.0, -1.0],
+                fovy: 45.0,
+            },
+            CameraPose {
+                eye: [10.0, 0.0, 0.0],
+                target: [0.0, 0.0, -1.0],
+                fovy: 45.0,
+            },
+        ]);
+        assert_eq!(waypoints.pose_at(0.5).eye, [5.0, 0.0, 0.0]);
+        assert_eq!(waypoints.pose_at(2.0).eye, [10.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn report_skips_the_warmup_and_ranks_frame_times() {
+        let mut benchmark = Benchmark::new(BenchmarkConfig::new(10, 1.0).warmup(0.5), 0.0);
+        //a few frames inside the warmup are left out
+        for now in [0.1, 0.2, 0.4] {
+            assert!(!benchmark.record_frame(now, 100, 100));
+        }
+        //then frames 10ms apart, with one 50ms hitch
+        let mut now = 0.5;
+        let mut finished = benchmark.record_frame(now, 3, 10);
+        while !finished {
+            now += if (now - 1.0).abs() < 1e-6 { 0.05 } else { 0.01 };
+            now = (now * 1000.0).round() / 1000.0;
+            finished = benchmark.record_frame(now, 3, 10);
+        }
+        let report = benchmark.report(&wgpu::AdapterInfo {
+            name: "test, adapter".into(),
+            vendor: 0,
+            device: 0,
+            device_type: wgpu::DeviceType::Cpu,
+            driver: String::new(),
+            driver_info: String::new(),
+            backend: wgpu::Backend::Empty,
+        });
+        assert!((report.p50_ms - 10.0).abs() < 0.01);
+        assert!((report.max_ms - 50.0).abs() < 0.01);
+        assert!((report.seconds - 1.0).abs() < 0.01);
+        assert_eq!(report.average_draw_calls, 3.0);
+        assert!(report
+            .csv_row()
+            .starts_with("\"\",\"test, adapter\",Empty,10,"));
+        assert_eq!(
+            report.csv_row().split(',').count(),
+            BenchmarkReport::CSV_HEADER.split(',').count() + 1
+        );
+    }
+}
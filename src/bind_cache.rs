@@ -0,0 +1,268 @@
+use crate::texture;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+//identifies one binding of a bind group by the resource it points at
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ResourceKey {
+    Buffer {
+        buffer: wgpu::Id<wgpu::Buffer>,
+        offset: wgpu::BufferAddress,
+        size: Option<wgpu::BufferSize>,
+    },
+    TextureView(wgpu::Id<wgpu::TextureView>),
+    Sampler(wgpu::Id<wgpu::Sampler>),
+}
+
+impl ResourceKey {
+    pub fn of(resource: &wgpu::BindingResource) -> Option<Self> {
+        match resource {
+            wgpu::BindingResource::Buffer(binding) => Some(ResourceKey::Buffer {
+                buffer: binding.buffer.global_id(),
+                offset: binding.offset,
+                size: binding.size,
+            }),
+            wgpu::BindingResource::TextureView(view) => {
+                Some(ResourceKey::TextureView(view.global_id()))
+            }
+            wgpu::BindingResource::Sampler(sampler) => Some(ResourceKey::Sampler(sampler.global_id())),
+            //arrays of resources aren't cached
+            _ => None,
+        }
+    }
+}
+
+//whether shaders can write to a storage buffer binding
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StorageAccess {
+    ReadOnly,
+    ReadWrite,
+}
+
+//shorthands for the layout entries bind groups are made of, so new bindings don't spell out
+//every field
+
+pub fn uniform_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    buffer_entry(binding, visibility, wgpu::BufferBindingType::Uniform)
+}
+
+//a storage buffer, e.g. skinning matrices, a light list or extra per instance data. vertex
+//shaders can't write to storage buffers, so VERTEX is left out of a read-write binding's
+//visibility. whether the device has storage buffers in a stage at all is down to the device, see
+//GameState::storage_stages
+pub fn storage_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    access: StorageAccess,
+) -> wgpu::BindGroupLayoutEntry {
+    let visibility = match access {
+        StorageAccess::ReadOnly => visibility,
+        StorageAccess::ReadWrite => visibility - wgpu::ShaderStages::VERTEX,
+    };
+    buffer_entry(
+        binding,
+        visibility,
+        wgpu::BufferBindingType::Storage {
+            read_only: access == StorageAccess::ReadOnly,
+        },
+    )
+}
+
+fn buffer_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    ty: wgpu::BufferBindingType,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+//a filterable float texture
+pub fn texture_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    view_dimension: wgpu::TextureViewDimension,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+pub fn sampler_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    }
+}
+
+//a depth texture, read with textureSampleCompare or textureLoad
+pub fn depth_texture_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Depth,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+//the sampler_comparison a depth texture is compared through
+pub fn comparison_sampler_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+        count: None,
+    }
+}
+
+type BindGroupKey = (wgpu::Id<wgpu::BindGroupLayout>, Vec<(u32, ResourceKey)>);
+
+//shares bind group layouts, bind groups and loaded textures. layouts are keyed by their entries,
+//bind groups by (layout, resources) and textures by their path in res, so fifty materials
+//pointing at the same image end up with one texture and one bind group. bind groups and textures
+//are handed out as Arcs and trim drops the ones nothing else holds any more
+#[derive(Default)]
+pub struct BindingCache {
+    layouts: HashMap<Vec<wgpu::BindGroupLayoutEntry>, Arc<wgpu::BindGroupLayout>>,
+    bind_groups: HashMap<BindGroupKey, Arc<wgpu::BindGroup>>,
+    textures: HashMap<String, Arc<texture::Texture>>,
+}
+
+impl BindingCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //the label is only used when the layout is created, a cached layout keeps its first label
+    pub fn layout(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        entries: &[wgpu::BindGroupLayoutEntry],
+    ) -> Arc<wgpu::BindGroupLayout> {
+        self.layouts
+            .entry(entries.to_vec())
+            .or_insert_with(|| {
+                Arc::new(device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(label),
+                    entries,
+                }))
+            })
+            .clone()
+    }
+
+    //returns the existing bind group for this layout and set of resources or creates it. bind
+    //groups using resource arrays can't be keyed and are always created fresh
+    pub fn bind_group(
+        &mut self,
+        device: &wgpu::Device,
+        label: Option<&str>,
+        layout: &wgpu::BindGroupLayout,
+        entries: &[wgpu::BindGroupEntry],
+    ) -> Arc<wgpu::BindGroup> {
+        let create = || {
+            Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label,
+                layout,
+                entries,
+            }))
+        };
+        let resources: Option<Vec<_>> = entries
+            .iter()
+            .map(|entry| ResourceKey::of(&entry.resource).map(|key| (entry.binding, key)))
+            .collect();
+        let Some(resources) = resources else {
+            return create();
+        };
+        self.bind_groups
+            .entry((layout.global_id(), resources))
+            .or_insert_with(create)
+            .clone()
+    }
+
+    pub fn texture(&self, name: &str) -> Option<Arc<texture::Texture>> {
+        self.textures.get(name).cloned()
+    }
+
+    pub fn insert_texture(&mut self, name: &str, texture: texture::Texture) -> Arc<texture::Texture> {
+        let texture = Arc::new(texture);
+        self.textures.insert(name.to_string(), texture.clone());
+        texture
+    }
+
+    //drops bind groups and textures that are only kept alive by the cache, e.g. after the model
+    //using them was replaced. returns how many were dropped. layouts are small and stay
+    pub fn trim(&mut self) -> usize {
+        let before = self.bind_groups.len() + self.textures.len();
+        //bind groups go first as they can be what is holding a texture's view
+        self.bind_groups
+            .retain(|_, bind_group| Arc::strong_count(bind_group) > 1);
+        self.textures
+            .retain(|_, texture| Arc::strong_count(texture) > 1);
+        before - self.bind_groups.len() - self.textures.len()
+    }
+
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn layout_count(&self) -> usize {
+        self.layouts.len()
+    }
+
+    pub fn bind_group_count(&self) -> usize {
+        self.bind_groups.len()
+    }
+
+    //drops every cached object, anything still referenced elsewhere stays alive
+    pub fn clear(&mut self) {
+        self.layouts.clear();
+        self.bind_groups.clear();
+        self.textures.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_write_storage_is_kept_out_of_the_vertex_stage() {
+        let stages = wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT;
+        let read_only = storage_entry(0, stages, StorageAccess::ReadOnly);
+        let read_write = storage_entry(1, stages, StorageAccess::ReadWrite);
+        assert_eq!(read_only.visibility, stages);
+        assert_eq!(read_write.visibility, wgpu::ShaderStages::FRAGMENT);
+        assert!(matches!(
+            read_write.ty,
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                ..
+            }
+        ));
+    }
+}
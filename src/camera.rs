@@ -31,7 +31,7 @@ impl Camera {
             eye: (0.0, 1.0, 2.0).into(),
             target: (0.0, 0.0, 0.0).into(),
             up: cgmath::Vector3::unit_y(),
-            aspect: width as f32 / height as f32,
+            aspect: width / height,
             fovy: 45.0,
             znear: 0.1,
             zfar: 100.0,
@@ -40,7 +40,7 @@ impl Camera {
     pub fn build_view_projection(&self) -> cgmath::Matrix4<f32> {
         let view = cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up);
         let proj = cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar);
-        return OPENGL_TO_WGPU_MATRIX * proj * view;
+        OPENGL_TO_WGPU_MATRIX * proj * view
     }
 }
 
@@ -56,3 +56,9 @@ impl CameraUniform {
         self.view_proj = camera.build_view_projection().into();
     }
 }
+
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
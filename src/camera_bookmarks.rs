@@ -0,0 +1,152 @@
+use crate::camera::Camera;
+use anyhow::Context;
+use cgmath::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+//where the camera is and what it looks at
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraPose {
+    pub eye: [f32; 3],
+    pub target: [f32; 3],
+    //vertical field of view in degrees
+    pub fovy: f32,
+}
+
+impl CameraPose {
+    pub fn of(camera: &Camera) -> Self {
+        Self {
+            eye: camera.eye.into(),
+            target: camera.target.into(),
+            fovy: camera.fovy,
+        }
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.eye = self.eye.into();
+        camera.target = self.target.into();
+        camera.fovy = self.fovy;
+    }
+
+    //the pose a fraction t of the way from self to other, in a straight line
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let lerp3 = |a: [f32; 3], b: [f32; 3]| {
+            cgmath::Vector3::from(a).lerp(cgmath::Vector3::from(b), t).into()
+        };
+        Self {
+            eye: lerp3(self.eye, other.eye),
+            target: lerp3(self.target, other.target),
+            fovy: self.fovy + (other.fovy - self.fovy) * t,
+        }
+    }
+}
+
+//named camera poses, saved as a toml file with one table per name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CameraBookmarks {
+    poses: BTreeMap<String, CameraPose>,
+}
+
+impl CameraBookmarks {
+    //an empty set when the file doesn't exist yet
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading camera bookmarks {}", path.display()))?;
+        toml::from_str(&text)
+            .with_context(|| format!("parsing camera bookmarks {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, toml::to_string(self)?)
+            .with_context(|| format!("writing camera bookmarks {}", path.display()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CameraPose> {
+        self.poses.get(name)
+    }
+
+    //stores the pose under the name, replacing any already there
+    pub fn insert(&mut self, name: &str, pose: CameraPose) {
+        self.poses.insert(name.to_string(), pose);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<CameraPose> {
+        self.poses.remove(name)
+    }
+
+    //in name order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &CameraPose)> {
+        self.poses.iter().map(|(name, pose)| (name.as_str(), pose))
+    }
+}
+
+//an eased move from one pose to another over a fixed time
+#[derive(Debug, Copy, Clone)]
+pub struct CameraFlight {
+    from: CameraPose,
+    to: CameraPose,
+    start: f64,
+    duration: f64,
+}
+
+impl CameraFlight {
+    pub fn new(from: CameraPose, to: CameraPose, start: f64, duration: f64) -> Self {
+        Self {
+            from,
+            to,
+            start,
+            duration,
+        }
+    }
+
+    //the pose at a point in time, and whether the flight has arrived
+    pub fn pose_at(&self, now: f64) -> (CameraPose, bool) {
+        let t = if self.duration > 0.0 {
+            ((now - self.start) / self.duration).clamp(0.0, 1.0) as f32
+        } else {
+            1.0
+        };
+        //smoothstep, so the camera eases out of the old pose and into the new one
+        let eased = t * t * (3.0 - 2.0 * t);
+        (self.from.lerp(&self.to, eased), t >= 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pose(x: f32) -> CameraPose {
+        CameraPose {
+            eye: [x, 1.0, 2.0],
+            target: [0.0; 3],
+            fovy: 45.0 + x,
+        }
+    }
+
+    #[test]
+    fn flights_ease_between_poses() {
+        let flight = CameraFlight::new(pose(0.0), pose(10.0), 5.0, 2.0);
+        assert_eq!(flight.pose_at(4.0), (pose(0.0), false));
+        let (halfway, arrived) = flight.pose_at(6.0);
+        assert!(!arrived);
+        assert!((halfway.eye[0] - 5.0).abs() < 1e-5);
+        assert_eq!(flight.pose_at(7.5), (pose(10.0), true));
+    }
+
+    #[test]
+    fn bookmarks_round_trip_through_toml() {
+        let mut bookmarks = CameraBookmarks::default();
+        bookmarks.insert("overview", pose(3.0));
+        bookmarks.insert("1", pose(-1.0));
+        let text = toml::to_string(&bookmarks).unwrap();
+        let loaded: CameraBookmarks = toml::from_str(&text).unwrap();
+        assert_eq!(loaded, bookmarks);
+        assert_eq!(loaded.get("overview"), Some(&pose(3.0)));
+    }
+}
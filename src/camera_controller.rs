@@ -1,4 +1,5 @@
 use crate::camera::Camera;
+use crate::settings::KeyBindings;
 use winit::event::ElementState;
 use winit::event::KeyEvent;
 use winit::event::WindowEvent;
@@ -8,6 +9,10 @@ use winit::keyboard::PhysicalKey;
 
 pub struct CameraController {
     speed: f32,
+    forward_keys: Vec<KeyCode>,
+    backward_keys: Vec<KeyCode>,
+    left_keys: Vec<KeyCode>,
+    right_keys: Vec<KeyCode>,
     pub is_forward_pressed: bool,
     pub is_backward_pressed: bool,
     pub is_left_pressed: bool,
@@ -18,6 +23,10 @@ impl CameraController {
     pub fn new() -> Self {
         Self {
             speed: 0.02,
+            forward_keys: vec![KeyCode::KeyW, KeyCode::ArrowUp],
+            backward_keys: vec![KeyCode::KeyS, KeyCode::ArrowDown],
+            left_keys: vec![KeyCode::KeyA, KeyCode::ArrowLeft],
+            right_keys: vec![KeyCode::KeyD, KeyCode::ArrowRight],
             is_forward_pressed: false,
             is_backward_pressed: false,
             is_left_pressed: false,
@@ -25,6 +34,29 @@ impl CameraController {
         }
     }
 
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    //whether any movement key is held
+    pub fn is_moving(&self) -> bool {
+        self.is_forward_pressed
+            || self.is_backward_pressed
+            || self.is_left_pressed
+            || self.is_right_pressed
+    }
+
+    pub fn set_bindings(&mut self, bindings: &KeyBindings) {
+        self.forward_keys = bindings.forward.clone();
+        self.backward_keys = bindings.backward.clone();
+        self.left_keys = bindings.left.clone();
+        self.right_keys = bindings.right.clone();
+    }
+
     pub fn process_events(&mut self, event: &WindowEvent) -> bool {
         match event {
             WindowEvent::KeyboardInput {
@@ -37,25 +69,19 @@ impl CameraController {
                 ..
             } => {
                 let is_pressed = *state == ElementState::Pressed;
-                match keycode {
-                    KeyCode::KeyW | KeyCode::ArrowUp => {
-                        self.is_forward_pressed = is_pressed;
-                        true
-                    }
-                    KeyCode::KeyA | KeyCode::ArrowLeft => {
-                        self.is_left_pressed = is_pressed;
-                        true
-                    }
-                    KeyCode::KeyS | KeyCode::ArrowDown => {
-                        self.is_backward_pressed = is_pressed;
-                        true
-                    }
-                    KeyCode::KeyD | KeyCode::ArrowRight => {
-                        self.is_right_pressed = is_pressed;
-                        true
-                    }
-                    _ => false,
-                }
+                let pressed = if self.forward_keys.contains(keycode) {
+                    &mut self.is_forward_pressed
+                } else if self.left_keys.contains(keycode) {
+                    &mut self.is_left_pressed
+                } else if self.backward_keys.contains(keycode) {
+                    &mut self.is_backward_pressed
+                } else if self.right_keys.contains(keycode) {
+                    &mut self.is_right_pressed
+                } else {
+                    return false;
+                };
+                *pressed = is_pressed;
+                true
             }
             _ => false,
         }
@@ -67,8 +93,8 @@ impl CameraController {
         let forward_norm = forward.normalize();
         let forward_mag = forward.magnitude();
 
-        // Prevents glitching when the camera gets too close to the
-        // center of the scene.
+        //Prevents glitching when the camera gets too close to the
+        //center of the scene.
         if self.is_forward_pressed && forward_mag > self.speed {
             camera.eye += forward_norm * self.speed;
         }
@@ -78,14 +104,14 @@ impl CameraController {
 
         let right = forward_norm.cross(camera.up);
 
-        // Redo radius calc in case the forward/backward is pressed.
+        //Redo radius calc in case the forward/backward is pressed.
         let forward = camera.target - camera.eye;
         let forward_mag = forward.magnitude();
 
         if self.is_right_pressed {
-            // Rescale the distance between the target and the eye so
-            // that it doesn't change. The eye, therefore, still
-            // lies on the circle made by the target and eye.
+            //Rescale the distance between the target and the eye so
+            //that it doesn't change. The eye, therefore, still
+            //lies on the circle made by the target and eye.
             camera.eye = camera.target - (forward + right * self.speed).normalize() * forward_mag;
         }
         if self.is_left_pressed {
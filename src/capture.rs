@@ -0,0 +1,184 @@
+use anyhow::*;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+//rows in a texture to buffer copy have to be padded out to a multiple of 256 bytes
+pub fn padded_bytes_per_row(width: u32) -> u32 {
+    let unpadded = width * 4;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    unpadded.div_ceil(align) * align
+}
+
+//creates a buffer big enough to hold a padded copy of a texture of the given size
+pub fn create_readback_buffer(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row(width) * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    })
+}
+
+//records a copy of the whole texture into the readback buffer
+pub fn copy_texture_to_buffer(
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    buffer: &wgpu::Buffer,
+) {
+    let size = texture.size();
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row(size.width)),
+                rows_per_image: Some(size.height),
+            },
+        },
+        wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+//maps a buffer filled by copy_texture_to_buffer, blocking until the gpu is done with it, and
+//turns it into an image
+pub fn read_buffer_to_image(
+    device: &wgpu::Device,
+    buffer: &wgpu::Buffer,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> Result<image::RgbaImage> {
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv()??;
+
+    let image = {
+        let data = slice.get_mapped_range();
+        image_from_padded(&data, width, height, format)
+    };
+    buffer.unmap();
+    image
+}
+
+//read_buffer_to_image without blocking, for the web where the browser maps the buffer and
+//waiting on the device isn't possible
+pub async fn read_buffer_to_image_async(
+    device: &wgpu::Device,
+    buffer: &wgpu::Buffer,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> Result<image::RgbaImage> {
+    let slice = buffer.slice(..);
+    let state = Arc::new(Mutex::new(MapState::default()));
+    let callback_state = state.clone();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let mut state = callback_state.lock().unwrap();
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    });
+    MapFuture { device, state }.await?;
+
+    let image = {
+        let data = slice.get_mapped_range();
+        image_from_padded(&data, width, height, format)
+    };
+    buffer.unmap();
+    image
+}
+
+#[derive(Default)]
+struct MapState {
+    result: Option<Result<(), wgpu::BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+//resolves once the map_async callback has run
+struct MapFuture<'a> {
+    device: &'a wgpu::Device,
+    state: Arc<Mutex<MapState>>,
+}
+
+impl Future for MapFuture<'_> {
+    type Output = Result<(), wgpu::BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.device.poll(wgpu::Maintain::Poll);
+        let mut state = self.state.lock().unwrap();
+        if let Some(result) = state.result.take() {
+            return Poll::Ready(result);
+        }
+        state.waker = Some(cx.waker().clone());
+        //natively the callback only runs inside device.poll, which nothing else calls, so ask to
+        //be polled again rather than waiting on a wake that won't come
+        #[cfg(not(target_arch = "wasm32"))]
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+//strips the row padding from mapped readback data. bgra data is swizzled so the image is always
+//rgba
+pub fn image_from_padded(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> Result<image::RgbaImage> {
+    let is_bgra = is_bgra(format)?;
+    let padded_row = padded_bytes_per_row(width) as usize;
+    let unpadded_row = (width * 4) as usize;
+    let mut pixels = Vec::with_capacity(unpadded_row * height as usize);
+    for row in data.chunks(padded_row).take(height as usize) {
+        pixels.extend_from_slice(&row[..unpadded_row]);
+    }
+    if is_bgra {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    image::RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| anyhow!("readback buffer was smaller than the image"))
+}
+
+//whether readback data in the format needs swizzling, errors for formats that can't be read back
+pub fn is_bgra(format: wgpu::TextureFormat) -> Result<bool> {
+    match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => Ok(false),
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => Ok(true),
+        other => bail!("can't read back texture format {:?}", other),
+    }
+}
+
+pub fn save_png(image: &image::RgbaImage, path: &Path) -> Result<()> {
+    image.save_with_format(path, image::ImageFormat::Png)?;
+    Ok(())
+}
+
+//a file name in the working directory based on the current time, used by the screenshot hotkey
+pub fn timestamped_path(prefix: &str) -> std::path::PathBuf {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or_default();
+    std::path::PathBuf::from(format!("{}_{}.png", prefix, millis))
+}
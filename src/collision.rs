@@ -0,0 +1,552 @@
+use crate::picking::{self, Aabb, BoundingSphere, Ray};
+use cgmath::prelude::*;
+use cgmath::{Point3, Vector3};
+use std::collections::{HashMap, HashSet};
+
+//how far outside a face a point has to be, relative to the size of the shape, to count as
+//outside it. keeps nearly coplanar points from adding slivers to a hull
+const HULL_EPSILON: f32 = 1e-5;
+
+//which shape to build from a model, from the cheapest to test to the closest fit
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColliderKind {
+    Aabb,
+    Sphere,
+    //the smallest convex shape around every vertex
+    ConvexHull,
+    //the triangles themselves, welded into a grid of resolution cells along the longest side
+    //of the bounds so small details merge away. 0 keeps every vertex
+    TriMesh { resolution: u32 },
+}
+
+//a collision shape in model space, generated from a mesh with Collider::generate so it doesn't
+//have to be authored separately. place it with an instance's transform by moving the queries
+//into model space, as picking does
+#[derive(Debug, Clone, PartialEq)]
+pub enum Collider {
+    Aabb(Aabb),
+    Sphere(BoundingSphere),
+    Hull(ConvexHull),
+    TriMesh(TriMesh),
+}
+
+impl Collider {
+    //builds a shape from triangle list positions and indices. None when there is nothing to
+    //build from, or for a hull when the points are all in one plane
+    pub fn generate(kind: ColliderKind, positions: &[[f32; 3]], indices: &[u32]) -> Option<Self> {
+        if positions.is_empty() {
+            return None;
+        }
+        let bounds = Aabb::from_points(positions.iter().copied());
+        Some(match kind {
+            ColliderKind::Aabb => Collider::Aabb(bounds),
+            ColliderKind::Sphere => Collider::Sphere(bounds.bounding_sphere()),
+            ColliderKind::ConvexHull => Collider::Hull(ConvexHull::new(positions)?),
+            ColliderKind::TriMesh { resolution } => {
+                Collider::TriMesh(TriMesh::simplified(positions, indices, resolution))
+            }
+        })
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        match self {
+            Collider::Aabb(bounds) => *bounds,
+            Collider::Sphere(sphere) => {
+                let reach = Vector3::new(1.0, 1.0, 1.0) * sphere.radius;
+                Aabb::from_points([
+                    (sphere.center - reach).into(),
+                    (sphere.center + reach).into(),
+                ])
+            }
+            Collider::Hull(hull) => Aabb::from_points(hull.vertices.iter().map(|&p| p.into())),
+            Collider::TriMesh(mesh) => Aabb::from_points(mesh.positions.iter().copied()),
+        }
+    }
+
+    //whether a point is inside the shape. a trimesh is only solid if it is closed, the test
+    //counts how many of its triangles a ray from the point crosses
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        match self {
+            Collider::Aabb(bounds) => bounds.distance2(point) == 0.0,
+            Collider::Sphere(sphere) => {
+                (point - sphere.center).magnitude2() <= sphere.radius.powi(2)
+            }
+            Collider::Hull(hull) => hull.contains_point(point),
+            Collider::TriMesh(mesh) => {
+                //slightly off axis so the ray doesn't run along shared edges
+                let ray = Ray::new(point, Vector3::new(1.0, 0.000_123, 0.000_371));
+                mesh.triangles()
+                    .filter(|&triangle| picking::intersect_triangle(&ray, triangle).is_some())
+                    .count()
+                    % 2
+                    == 1
+            }
+        }
+    }
+
+    //distance along the ray to where it first meets the surface, 0 when it starts inside a
+    //solid shape
+    pub fn raycast(&self, ray: &Ray) -> Option<f32> {
+        match self {
+            Collider::Aabb(bounds) => bounds.intersect_ray(ray),
+            Collider::Sphere(sphere) => {
+                let offset = ray.origin - sphere.center;
+                let a = ray.direction.magnitude2();
+                let b = offset.dot(ray.direction);
+                let c = offset.magnitude2() - sphere.radius.powi(2);
+                if c <= 0.0 {
+                    return Some(0.0);
+                }
+                let discriminant = b * b - a * c;
+                if discriminant < 0.0 || a == 0.0 {
+                    return None;
+                }
+                let distance = (-b - discriminant.sqrt()) / a;
+                (distance >= 0.0).then_some(distance)
+            }
+            Collider::Hull(hull) => hull.raycast(ray),
+            Collider::TriMesh(mesh) => mesh.raycast(ray),
+        }
+    }
+
+    //the outline of the shape as line segments, for drawing it with debug lines. spheres are
+    //drawn as three circles
+    pub fn edges(&self) -> Vec<[Point3<f32>; 2]> {
+        match self {
+            Collider::Aabb(bounds) => {
+                let corners = bounds.corners();
+                //corners differing in one axis, by the bit of their index that axis sets
+                (0..8)
+                    .flat_map(|i| [1, 2, 4].map(move |bit| (i, i | bit)))
+                    .filter(|(from, to)| from != to)
+                    .map(|(from, to)| [corners[from], corners[to]])
+                    .collect()
+            }
+            Collider::Sphere(sphere) => {
+                const SEGMENTS: usize = 24;
+                let point = |axis: usize, i: usize| {
+                    let angle = std::f32::consts::TAU * i as f32 / SEGMENTS as f32;
+                    let (sin, cos) = angle.sin_cos();
+                    let offset = match axis {
+                        0 => Vector3::new(0.0, cos, sin),
+                        1 => Vector3::new(cos, 0.0, sin),
+                        _ => Vector3::new(cos, sin, 0.0),
+                    };
+                    sphere.center + offset * sphere.radius
+                };
+                (0..3)
+                    .flat_map(|axis| {
+                        (0..SEGMENTS).map(move |i| [point(axis, i), point(axis, i + 1)])
+                    })
+                    .collect()
+            }
+            Collider::Hull(hull) => unique_edges(&hull.faces)
+                .map(|[a, b]| [hull.vertices[a as usize], hull.vertices[b as usize]])
+                .collect(),
+            Collider::TriMesh(mesh) => {
+                let triangles: Vec<[u32; 3]> = mesh
+                    .indices
+                    .chunks_exact(3)
+                    .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                    .collect();
+                unique_edges(&triangles)
+                    .map(|[a, b]| {
+                        [
+                            mesh.positions[a as usize].into(),
+                            mesh.positions[b as usize].into(),
+                        ]
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+//each edge of the triangles once, whichever way round they share it
+fn unique_edges(triangles: &[[u32; 3]]) -> impl Iterator<Item = [u32; 2]> {
+    let edges: HashSet<[u32; 2]> = triangles
+        .iter()
+        .flat_map(|&[a, b, c]| [[a, b], [b, c], [c, a]])
+        .map(|[a, b]| [a.min(b), a.max(b)])
+        .collect();
+    let mut edges: Vec<[u32; 2]> = edges.into_iter().collect();
+    edges.sort_unstable();
+    edges.into_iter()
+}
+
+//a convex polyhedron, with triangle faces wound counter clockwise seen from outside
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvexHull {
+    pub vertices: Vec<Point3<f32>>,
+    pub faces: Vec<[u32; 3]>,
+}
+
+struct HullFace {
+    corners: [usize; 3],
+    normal: Vector3<f32>,
+    offset: f32,
+    //the points in front of this face, waiting to be added
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+impl HullFace {
+    fn new(points: &[Point3<f32>], corners: [usize; 3]) -> Self {
+        let [a, b, c] = corners.map(|corner| points[corner]);
+        let normal = (b - a).cross(c - a).normalize();
+        Self {
+            corners,
+            normal,
+            offset: normal.dot(a.to_vec()),
+            outside: Vec::new(),
+            alive: true,
+        }
+    }
+
+    fn distance(&self, point: Point3<f32>) -> f32 {
+        self.normal.dot(point.to_vec()) - self.offset
+    }
+}
+
+impl ConvexHull {
+    //quickhull: starts from a tetrahedron of extreme points and keeps adding the point furthest
+    //outside any face, replacing the faces it can see. None when the points are all in one
+    //plane, which has no volume to wrap
+    pub fn new(positions: &[[f32; 3]]) -> Option<Self> {
+        let points: Vec<Point3<f32>> = positions.iter().map(|&p| p.into()).collect();
+        let bounds = Aabb::from_points(positions.iter().copied());
+        let epsilon = HULL_EPSILON * bounds.size().magnitude().max(f32::MIN_POSITIVE);
+        let mut faces = Self::initial_tetrahedron(&points, epsilon)?;
+        Self::assign(&points, &mut faces, 0..points.len(), epsilon);
+        while let Some(face) = faces
+            .iter()
+            .position(|face| face.alive && !face.outside.is_empty())
+        {
+            let eye = *faces[face]
+                .outside
+                .iter()
+                .max_by(|&&a, &&b| {
+                    let distance = |point: usize| faces[face].distance(points[point]);
+                    distance(a).total_cmp(&distance(b))
+                })
+                .expect("the face has outside points");
+            let visible: Vec<usize> = (0..faces.len())
+                .filter(|&index| faces[index].alive && faces[index].distance(points[eye]) > epsilon)
+                .collect();
+            //edges of the visible faces that no other visible face shares are the horizon
+            let edges: HashSet<(usize, usize)> = visible
+                .iter()
+                .flat_map(|&index| {
+                    let [a, b, c] = faces[index].corners;
+                    [(a, b), (b, c), (c, a)]
+                })
+                .collect();
+            let horizon: Vec<(usize, usize)> = edges
+                .iter()
+                .filter(|&&(a, b)| !edges.contains(&(b, a)))
+                .copied()
+                .collect();
+            let mut orphans = Vec::new();
+            for &index in &visible {
+                faces[index].alive = false;
+                orphans.append(&mut faces[index].outside);
+            }
+            let first_new = faces.len();
+            faces.extend(
+                horizon
+                    .into_iter()
+                    .map(|(a, b)| HullFace::new(&points, [a, b, eye])),
+            );
+            Self::assign(
+                &points,
+                &mut faces[first_new..],
+                orphans.into_iter().filter(|&point| point != eye),
+                epsilon,
+            );
+        }
+        //keep only the vertices the faces use, numbered in the order they first appear
+        let mut remap = HashMap::new();
+        let mut vertices = Vec::new();
+        let faces = faces
+            .iter()
+            .filter(|face| face.alive)
+            .map(|face| {
+                face.corners.map(|corner| {
+                    *remap.entry(corner).or_insert_with(|| {
+                        vertices.push(points[corner]);
+                        (vertices.len() - 1) as u32
+                    })
+                })
+            })
+            .collect();
+        Some(Self { vertices, faces })
+    }
+
+    fn initial_tetrahedron(points: &[Point3<f32>], epsilon: f32) -> Option<Vec<HullFace>> {
+        //the two points furthest apart along an axis, then the one furthest from their line and
+        //the one furthest from the plane of all three
+        let extremes = (0..3).flat_map(|axis| {
+            let key = move |&&p: &&Point3<f32>| p[axis];
+            let min = points.iter().min_by(|a, b| key(a).total_cmp(&key(b)));
+            let max = points.iter().max_by(|a, b| key(a).total_cmp(&key(b)));
+            [min, max]
+        });
+        let extremes: Vec<usize> = extremes
+            .flatten()
+            .map(|p| points.iter().position(|q| q == p).unwrap_or(0))
+            .collect();
+        let (a, b) = extremes
+            .iter()
+            .flat_map(|&a| extremes.iter().map(move |&b| (a, b)))
+            .max_by(|&(a, b), &(c, d)| {
+                (points[a] - points[b])
+                    .magnitude2()
+                    .total_cmp(&(points[c] - points[d]).magnitude2())
+            })?;
+        let line = points[b] - points[a];
+        if line.magnitude() <= epsilon {
+            return None;
+        }
+        let from_line = |p: usize| line.cross(points[p] - points[a]).magnitude() / line.magnitude();
+        let c = (0..points.len()).max_by(|&p, &q| from_line(p).total_cmp(&from_line(q)))?;
+        if from_line(c) <= epsilon {
+            return None;
+        }
+        let normal = line.cross(points[c] - points[a]).normalize();
+        let from_plane = |p: usize| normal.dot(points[p] - points[a]);
+        let d = (0..points.len())
+            .max_by(|&p, &q| from_plane(p).abs().total_cmp(&from_plane(q).abs()))?;
+        if from_plane(d).abs() <= epsilon {
+            return None;
+        }
+        //wound so every normal points away from the fourth corner
+        let (b, c) = if from_plane(d) > 0.0 { (c, b) } else { (b, c) };
+        Some(
+            [[a, b, c], [a, d, b], [b, d, c], [c, d, a]]
+                .into_iter()
+                .map(|corners| HullFace::new(points, corners))
+                .collect(),
+        )
+    }
+
+    //gives each point to the first face it is outside of, points inside every face are dropped
+    fn assign(
+        points: &[Point3<f32>],
+        faces: &mut [HullFace],
+        candidates: impl IntoIterator<Item = usize>,
+        epsilon: f32,
+    ) {
+        for point in candidates {
+            if let Some(face) = faces
+                .iter_mut()
+                .find(|face| face.alive && face.distance(points[point]) > epsilon)
+            {
+                face.outside.push(point);
+            }
+        }
+    }
+
+    //each face as a unit normal and the plane's distance from the origin along it
+    pub fn planes(&self) -> impl Iterator<Item = (Vector3<f32>, f32)> + '_ {
+        self.faces.iter().map(|&[a, b, c]| {
+            let [a, b, c] = [a, b, c].map(|corner| self.vertices[corner as usize]);
+            let normal = (b - a).cross(c - a).normalize();
+            (normal, normal.dot(a.to_vec()))
+        })
+    }
+
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        let epsilon = HULL_EPSILON * self.scale();
+        self.planes()
+            .all(|(normal, offset)| normal.dot(point.to_vec()) - offset <= epsilon)
+    }
+
+    //clips the ray against every face's plane, what is left of it is inside the hull
+    pub fn raycast(&self, ray: &Ray) -> Option<f32> {
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+        for (normal, offset) in self.planes() {
+            let distance = normal.dot(ray.origin.to_vec()) - offset;
+            let approach = normal.dot(ray.direction);
+            if approach == 0.0 {
+                if distance > 0.0 {
+                    return None;
+                }
+                continue;
+            }
+            let crossing = -distance / approach;
+            if approach < 0.0 {
+                near = near.max(crossing);
+            } else {
+                far = far.min(crossing);
+            }
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+
+    fn scale(&self) -> f32 {
+        Aabb::from_points(self.vertices.iter().map(|&p| p.into()))
+            .size()
+            .magnitude()
+    }
+}
+
+//a triangle list collider, usually a welded down copy of the mesh it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+impl TriMesh {
+    //merges the vertices falling in the same cell of a grid with resolution cells along the
+    //bounds' longest side into their average, then drops the triangles that collapsed or
+    //repeat another. a resolution of 0 only welds vertices at exactly the same position
+    pub fn simplified(positions: &[[f32; 3]], indices: &[u32], resolution: u32) -> Self {
+        let bounds = Aabb::from_points(positions.iter().copied());
+        let size = bounds.size();
+        let longest = size.x.max(size.y).max(size.z);
+        let cell = if resolution == 0 || longest == 0.0 {
+            None
+        } else {
+            Some(longest / resolution as f32)
+        };
+        let key = |position: [f32; 3]| match cell {
+            Some(cell) => {
+                let local = Point3::from(position) - bounds.min;
+                //the far side of the bounds goes in the last cell rather than one of its own
+                [0, 1, 2]
+                    .map(|axis| ((local[axis] / cell).floor() as i64).min(resolution as i64 - 1))
+            }
+            None => position.map(|coordinate| coordinate.to_bits() as i64),
+        };
+        let mut clusters: HashMap<[i64; 3], u32> = HashMap::new();
+        let mut sums: Vec<(Vector3<f32>, f32)> = Vec::new();
+        let remap: Vec<u32> = positions
+            .iter()
+            .map(|&position| {
+                let cluster = *clusters.entry(key(position)).or_insert_with(|| {
+                    sums.push((Vector3::zero(), 0.0));
+                    (sums.len() - 1) as u32
+                });
+                let (sum, count) = &mut sums[cluster as usize];
+                *sum += Vector3::from(position);
+                *count += 1.0;
+                cluster
+            })
+            .collect();
+        let positions = sums
+            .into_iter()
+            .map(|(sum, count)| (sum / count).into())
+            .collect();
+        let mut seen = HashSet::new();
+        let indices = indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|corner| remap[triangle[corner] as usize]))
+            .filter(|&[a, b, c]| a != b && b != c && c != a)
+            .filter(|triangle| {
+                //the same triangle whichever corner it starts from
+                let mut sorted = *triangle;
+                sorted.sort_unstable();
+                seen.insert(sorted)
+            })
+            .flatten()
+            .collect();
+        Self { positions, indices }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = [Point3<f32>; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|triangle| {
+            [0, 1, 2].map(|corner| Point3::from(self.positions[triangle[corner] as usize]))
+        })
+    }
+
+    //the nearest triangle the ray crosses, from either side
+    pub fn raycast(&self, ray: &Ray) -> Option<f32> {
+        self.triangles()
+            .filter_map(|triangle| picking::intersect_triangle(ray, triangle))
+            .min_by(f32::total_cmp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a unit cube split into two triangles per side, wound outwards
+    fn cube() -> (Vec<[f32; 3]>, Vec<u32>) {
+        let positions = (0..8)
+            .map(|i| [(i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32])
+            .collect();
+        let indices = vec![
+            0, 2, 1, 1, 2, 3, // -z
+            4, 5, 6, 5, 7, 6, // +z
+            0, 1, 4, 1, 5, 4, // -y
+            2, 6, 3, 3, 6, 7, // +y
+            0, 4, 2, 2, 4, 6, // -x
+            1, 3, 5, 3, 7, 5, // +x
+        ];
+        (positions, indices)
+    }
+
+    #[test]
+    fn hull_wraps_the_outer_points() {
+        let (mut positions, _) = cube();
+        //points inside and on the surface add nothing
+        positions.extend([[0.5, 0.5, 0.5], [0.25, 0.75, 0.5], [0.5, 0.5, 1.0]]);
+        let hull = ConvexHull::new(&positions).unwrap();
+        assert_eq!(hull.vertices.len(), 8);
+        assert_eq!(hull.faces.len(), 12);
+        assert!(hull.contains_point(Point3::new(0.9, 0.1, 0.5)));
+        assert!(!hull.contains_point(Point3::new(1.1, 0.5, 0.5)));
+        let ray = Ray::new(Point3::new(0.5, 0.5, -2.0), Vector3::unit_z());
+        assert!((hull.raycast(&ray).unwrap() - 2.0).abs() < 1e-5);
+        assert!(
+            ConvexHull::new(&[[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0]])
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn trimesh_welds_nearby_vertices() {
+        let (mut positions, mut indices) = cube();
+        //a sliver triangle hanging off a corner, small enough to fall in the corner's cell
+        positions.extend([[1.01, 1.0, 1.0], [1.0, 1.01, 1.0]]);
+        indices.extend([7, 8, 9]);
+        let mesh = TriMesh::simplified(&positions, &indices, 4);
+        assert_eq!(mesh.positions.len(), 8);
+        assert_eq!(mesh.triangle_count(), 12);
+        let collider = Collider::TriMesh(mesh);
+        assert!(collider.contains_point(Point3::new(0.5, 0.5, 0.5)));
+        assert!(!collider.contains_point(Point3::new(1.5, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn generated_shapes_agree_on_the_bounds() {
+        let (positions, indices) = cube();
+        for kind in [
+            ColliderKind::Aabb,
+            ColliderKind::Sphere,
+            ColliderKind::ConvexHull,
+            ColliderKind::TriMesh { resolution: 0 },
+        ] {
+            let collider = Collider::generate(kind, &positions, &indices).unwrap();
+            assert!(
+                collider.contains_point(Point3::new(0.5, 0.5, 0.5)),
+                "{:?}",
+                kind
+            );
+            let ray = Ray::new(Point3::new(0.5, 0.5, 5.0), -Vector3::unit_z());
+            assert!(collider.raycast(&ray).is_some(), "{:?}", kind);
+            assert!(!collider.edges().is_empty());
+        }
+        assert_eq!(Collider::generate(ColliderKind::Aabb, &[], &[]), None);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::error::ShaderError;
+use crate::gpu_timer::GpuTimer;
+use crate::pipeline_cache;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+
+type Result<T> = std::result::Result<T, ShaderError>;
+
+//a compute pipeline and the layouts of the bind groups it reads, one per group index. the WGSL
+//is validated with naga first so mistakes come back as a ShaderError with a line number
+pub struct ComputeShader {
+    label: String,
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layouts: Vec<wgpu::BindGroupLayout>,
+}
+
+impl ComputeShader {
+    //groups holds the entries of each bind group in order, they have to match the shader
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        entry_point: &str,
+        groups: &[&[wgpu::BindGroupLayoutEntry]],
+    ) -> Result<Self> {
+        pipeline_cache::validate_wgsl(label, source)?;
+        let (pipeline, bind_group_layouts) = pipeline_cache::with_error_scope(device, label, || {
+            let bind_group_layouts: Vec<_> = groups
+                .iter()
+                .enumerate()
+                .map(|(index, entries)| {
+                    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                        label: Some(&format!("{} Bind Group Layout {}", label, index)),
+                        entries,
+                    })
+                })
+                .collect();
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(&format!("{} Pipeline Layout", label)),
+                bind_group_layouts: &bind_group_layouts.iter().collect::<Vec<_>>(),
+                push_constant_ranges: &[],
+            });
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+            let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                module: &module,
+                entry_point,
+                compilation_options: Default::default(),
+            });
+            (pipeline, bind_group_layouts)
+        })?;
+        Ok(Self {
+            label: label.to_string(),
+            pipeline,
+            bind_group_layouts,
+        })
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn bind_group_layout(&self, group: usize) -> Option<&wgpu::BindGroupLayout> {
+        self.bind_group_layouts.get(group)
+    }
+
+    //a bind group for the given group index, None if the shader has no such group
+    pub fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        group: usize,
+        entries: &[wgpu::BindGroupEntry],
+    ) -> Option<wgpu::BindGroup> {
+        let layout = self.bind_group_layout(group)?;
+        Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} Bind Group {}", self.label, group)),
+            layout,
+            entries,
+        }))
+    }
+
+    //records one dispatch in its own compute pass, bind groups are set from group 0 up
+    pub fn dispatch(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        bind_groups: &[&wgpu::BindGroup],
+        workgroups: [u32; 3],
+        timestamp_writes: Option<wgpu::ComputePassTimestampWrites>,
+    ) {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some(&self.label),
+            timestamp_writes,
+        });
+        pass.set_pipeline(&self.pipeline);
+        for (index, bind_group) in bind_groups.iter().enumerate() {
+            pass.set_bind_group(index as u32, bind_group, &[]);
+        }
+        let [x, y, z] = workgroups;
+        pass.dispatch_workgroups(x, y, z);
+    }
+}
+
+//a buffer compute passes write and render passes read. it always has STORAGE and COPY_DST, extra
+//usages such as VERTEX or INDIRECT let the scene draw straight from what a compute pass produced
+pub struct StorageBuffer {
+    buffer: wgpu::Buffer,
+}
+
+impl StorageBuffer {
+    const USAGE: wgpu::BufferUsages = wgpu::BufferUsages::STORAGE
+        .union(wgpu::BufferUsages::COPY_DST)
+        .union(wgpu::BufferUsages::COPY_SRC);
+
+    //zeroed, size is in bytes
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        size: wgpu::BufferAddress,
+        usage: wgpu::BufferUsages,
+    ) -> Self {
+        Self {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: usage | Self::USAGE,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    pub fn with_contents(
+        device: &wgpu::Device,
+        label: &str,
+        contents: &[u8],
+        usage: wgpu::BufferUsages,
+    ) -> Self {
+        Self {
+            buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: usage | Self::USAGE,
+            }),
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn size(&self) -> wgpu::BufferAddress {
+        self.buffer.size()
+    }
+
+    pub fn binding(&self) -> wgpu::BindingResource<'_> {
+        self.buffer.as_entire_binding()
+    }
+
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(..)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ComputePassId(u64);
+
+//a dispatch run every frame
+pub struct ComputePass {
+    //names the pass in gpu timings
+    pub label: &'static str,
+    pub shader: Arc<ComputeShader>,
+    //set from group 0 up
+    pub bind_groups: Vec<Arc<wgpu::BindGroup>>,
+    pub workgroups: [u32; 3],
+    pub enabled: bool,
+}
+
+//the compute passes run at the start of every frame's encoder, after the uniforms are uploaded
+//and before culling and the scene pass, in the order they were added. anything they write is
+//visible to the rest of the frame
+#[derive(Default)]
+pub struct ComputePasses {
+    passes: Vec<(ComputePassId, ComputePass)>,
+    next_id: u64,
+}
+
+impl ComputePasses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, pass: ComputePass) -> ComputePassId {
+        let id = ComputePassId(self.next_id);
+        self.next_id += 1;
+        self.passes.push((id, pass));
+        id
+    }
+
+    pub fn remove(&mut self, id: ComputePassId) -> Option<ComputePass> {
+        let index = self.passes.iter().position(|(pass_id, _)| *pass_id == id)?;
+        Some(self.passes.remove(index).1)
+    }
+
+    pub fn get_mut(&mut self, id: ComputePassId) -> Option<&mut ComputePass> {
+        self.passes
+            .iter_mut()
+            .find(|(pass_id, _)| *pass_id == id)
+            .map(|(_, pass)| pass)
+    }
+
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.passes.clear();
+    }
+
+    pub(crate) fn encode(&self, encoder: &mut wgpu::CommandEncoder, gpu_timer: Option<&GpuTimer>) {
+        for (_, pass) in self.passes.iter().filter(|(_, pass)| pass.enabled) {
+            let bind_groups: Vec<&wgpu::BindGroup> =
+                pass.bind_groups.iter().map(|group| group.as_ref()).collect();
+            pass.shader.dispatch(
+                encoder,
+                &bind_groups,
+                pass.workgroups,
+                gpu_timer.and_then(|timer| timer.compute_pass_writes(pass.label)),
+            );
+        }
+    }
+}
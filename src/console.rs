@@ -0,0 +1,354 @@
+use crate::motion::Motion;
+use crate::scene_control::SceneCommand;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::mpsc;
+
+//lines kept in the scrollback and commands kept for up arrow recall, the oldest go first
+const MAX_LOG_LINES: usize = 500;
+const MAX_HISTORY: usize = 100;
+
+//turns the words after a command's name into the scene commands it sends, or a message saying
+//what was wrong with them
+pub type CommandHandler = Box<dyn Fn(&[&str]) -> Result<Vec<SceneCommand>, String> + Send>;
+
+struct Command {
+    usage: String,
+    handler: CommandHandler,
+}
+
+//a drop down console for typing commands into the running app. each command is parsed by a
+//registered handler into SceneCommands that go through the same channel as commands sent from
+//other threads, so they apply at the start of the next update. the ui layers draw it, without a
+//ui feature commands can still be run with execute
+pub struct Console {
+    commands: BTreeMap<String, Command>,
+    sender: mpsc::Sender<SceneCommand>,
+    open: bool,
+    //the line being typed, edited in place by the ui
+    input: String,
+    history: Vec<String>,
+    //which history entry the input was recalled from, None while typing a new line
+    recalled: Option<usize>,
+    log: VecDeque<String>,
+}
+
+impl Console {
+    //a console with the built in commands, sending on the given channel
+    pub fn new(sender: mpsc::Sender<SceneCommand>) -> Self {
+        let mut console = Self {
+            commands: BTreeMap::new(),
+            sender,
+            open: false,
+            input: String::new(),
+            history: Vec::new(),
+            recalled: None,
+            log: VecDeque::new(),
+        };
+        console.register_builtins();
+        console
+    }
+
+    //adds a command, replacing any already registered under the name. usage is shown by help
+    pub fn register(
+        &mut self,
+        name: &str,
+        usage: &str,
+        handler: impl Fn(&[&str]) -> Result<Vec<SceneCommand>, String> + Send + 'static,
+    ) {
+        self.commands.insert(
+            name.to_string(),
+            Command {
+                usage: usage.to_string(),
+                handler: Box::new(handler),
+            },
+        );
+    }
+
+    pub(crate) fn set_sender(&mut self, sender: mpsc::Sender<SceneCommand>) {
+        self.sender = sender;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn input_mut(&mut self) -> &mut String {
+        &mut self.input
+    }
+
+    //the scrollback, oldest line first
+    pub fn log(&self) -> impl Iterator<Item = &str> {
+        self.log.iter().map(String::as_str)
+    }
+
+    pub fn print(&mut self, line: impl Into<String>) {
+        if self.log.len() == MAX_LOG_LINES {
+            self.log.pop_front();
+        }
+        self.log.push_back(line.into());
+    }
+
+    //runs the typed line and clears it
+    pub fn submit(&mut self) {
+        let line = std::mem::take(&mut self.input);
+        self.execute(&line);
+    }
+
+    //runs a line as if it had been typed, returning false if it didn't parse. blank lines are
+    //ignored, anything else is added to the history and echoed to the log
+    pub fn execute(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((name, args)) = words.split_first() else {
+            return true;
+        };
+        self.recalled = None;
+        if self.history.last().map(String::as_str) != Some(line) {
+            if self.history.len() == MAX_HISTORY {
+                self.history.remove(0);
+            }
+            self.history.push(line.to_string());
+        }
+        self.print(format!("> {}", line));
+        let result = match *name {
+            "help" => {
+                self.print_help();
+                return true;
+            }
+            "clear" => {
+                self.log.clear();
+                return true;
+            }
+            _ => match self.commands.get(*name) {
+                Some(command) => (command.handler)(args)
+                    .map_err(|e| format!("{}\nusage: {}", e, command.usage)),
+                None => Err(format!("unknown command '{}', try help", name)),
+            },
+        };
+        match result {
+            Ok(commands) => {
+                //the channel keeps a receiver of its own, so sending only fails once the state is gone
+                for command in commands {
+                    let _ = self.sender.send(command);
+                }
+                true
+            }
+            Err(e) => {
+                for message in e.lines() {
+                    self.print(message.to_string());
+                }
+                false
+            }
+        }
+    }
+
+    //replaces the input with the previous command in the history, or the next with older
+    //false. stepping past the newest entry leaves an empty line. returns false when there was
+    //nowhere to go
+    pub fn recall(&mut self, older: bool) -> bool {
+        let recalled = match (self.recalled, older) {
+            (None, true) => self.history.len().checked_sub(1),
+            (None, false) => return false,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) => Some(index + 1).filter(|index| *index < self.history.len()),
+        };
+        if recalled == self.recalled {
+            return false;
+        }
+        self.recalled = recalled;
+        self.input = recalled
+            .map(|index| self.history[index].clone())
+            .unwrap_or_default();
+        true
+    }
+
+    fn print_help(&mut self) {
+        let lines: Vec<String> = self
+            .commands
+            .values()
+            .map(|command| command.usage.clone())
+            .chain(["help".to_string(), "clear".to_string()])
+            .collect();
+        for line in lines {
+            self.print(line);
+        }
+    }
+
+    fn register_builtins(&mut self) {
+        self.register("spawn", "spawn [model] <x> <y> <z>", |args| {
+            let (file_name, args) = match args {
+                [file_name, rest @ ..] if rest.len() == 3 => (Some(file_name.to_string()), rest),
+                _ => (None, args),
+            };
+            let position = numbers::<3>(args)?.into();
+            let rotation = cgmath::Quaternion::new(1.0, 0.0, 0.0, 0.0);
+            Ok(vec![match file_name {
+                Some(file_name) => SceneCommand::SpawnNamedModel {
+                    file_name,
+                    position,
+                    rotation,
+                },
+                None => SceneCommand::SpawnModel { position, rotation },
+            }])
+        });
+        self.register(
+            "set",
+            "set fov <degrees> | set light <x> <y> <z> | set light_color <r> <g> <b> | set render_scale <scale>",
+            |args| {
+                let (setting, values) = args.split_first().ok_or("missing a setting")?;
+                Ok(vec![match *setting {
+                    "fov" => {
+                        let [fovy] = numbers::<1>(values)?;
+                        if !(1.0..180.0).contains(&fovy) {
+                            return Err(format!("fov has to be between 1 and 180, not {}", fovy));
+                        }
+                        SceneCommand::SetFov { fovy }
+                    }
+                    "light" => SceneCommand::SetLightPosition {
+                        position: numbers::<3>(values)?,
+                    },
+                    "light_color" => SceneCommand::SetLightColor {
+                        color: numbers::<3>(values)?,
+                    },
+                    "render_scale" => SceneCommand::SetRenderScale {
+                        scale: numbers::<1>(values)?[0],
+                    },
+                    _ => return Err(format!("unknown setting '{}'", setting)),
+                }])
+            },
+        );
+        self.register("toggle", "toggle wireframe | toggle colliders", |args| {
+            Ok(vec![match args {
+                ["wireframe"] => SceneCommand::ToggleWireframe,
+                ["colliders"] => SceneCommand::ToggleColliderDebug,
+                _ => return Err("toggle what?".to_string()),
+            }])
+        });
+        self.register("scale", "scale <instance> <factor>", |args| {
+            let (index, factor) = args.split_first().ok_or("missing an instance")?;
+            let [scale] = numbers::<1>(factor)?;
+            Ok(vec![SceneCommand::SetInstanceScale {
+                index: instance_index(index)?,
+                scale,
+            }])
+        });
+        self.register(
+            "motion",
+            "motion <instance> spin <degrees/s> | orbit <degrees/s> | bob <height> <hertz> | stop",
+            |args| {
+                let (index, args) = args.split_first().ok_or("missing an instance")?;
+                let index = instance_index(index)?;
+                let (kind, values) = args.split_first().ok_or("missing a motion")?;
+                let motion = match *kind {
+                    "spin" => Motion::spin(numbers::<1>(values)?[0]),
+                    "orbit" => Motion::orbit(numbers::<1>(values)?[0]),
+                    "bob" => {
+                        let [height, hertz] = numbers::<2>(values)?;
+                        Motion::bob(height, hertz)
+                    }
+                    "stop" => return Ok(vec![SceneCommand::ClearInstanceMotions { index }]),
+                    _ => return Err(format!("unknown motion '{}'", kind)),
+                };
+                Ok(vec![SceneCommand::AddInstanceMotion { index, motion }])
+            },
+        );
+        self.register("tint", "tint <instance> <r> <g> <b> [a]", |args| {
+            let (index, color) = args.split_first().ok_or("missing an instance")?;
+            let tint = match color.len() {
+                3 => {
+                    let [r, g, b] = numbers::<3>(color)?;
+                    [r, g, b, 1.0]
+                }
+                _ => numbers::<4>(color)?,
+            };
+            Ok(vec![SceneCommand::SetInstanceTint {
+                index: instance_index(index)?,
+                tint,
+            }])
+        });
+    }
+}
+
+//exactly N numbers
+fn numbers<const N: usize>(args: &[&str]) -> Result<[f32; N], String> {
+    if args.len() != N {
+        return Err(format!("expected {} numbers, got {}", N, args.len()));
+    }
+    let mut values = [0.0; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = arg
+            .parse()
+            .map_err(|_| format!("'{}' isn't a number", arg))?;
+    }
+    Ok(values)
+}
+
+fn instance_index(arg: &str) -> Result<usize, String> {
+    arg.parse()
+        .map_err(|_| format!("'{}' isn't an instance index", arg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn console() -> (Console, mpsc::Receiver<SceneCommand>) {
+        let (sender, receiver) = mpsc::channel();
+        (Console::new(sender), receiver)
+    }
+
+    #[test]
+    fn commands_are_sent_down_the_channel() {
+        let (mut console, receiver) = console();
+        assert!(console.execute("set fov 90"));
+        assert!(console.execute("spawn cube.obj 1 2 3"));
+        assert_eq!(receiver.try_recv(), Ok(SceneCommand::SetFov { fovy: 90.0 }));
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(SceneCommand::SpawnNamedModel { file_name, .. }) if file_name == "cube.obj"
+        ));
+        assert!(!console.execute("set fov wide"));
+        assert!(!console.execute("fly"));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn registered_commands_run() {
+        let (mut console, receiver) = console();
+        console.register("night", "night", |_| {
+            Ok(vec![SceneCommand::SetLightColor { color: [0.1; 3] }])
+        });
+        assert!(console.execute("night"));
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(SceneCommand::SetLightColor { color: [0.1; 3] })
+        );
+    }
+
+    #[test]
+    fn history_steps_back_and_forward() {
+        let (mut console, _receiver) = console();
+        console.execute("toggle wireframe");
+        console.execute("set fov 60");
+        assert!(console.recall(true));
+        assert_eq!(console.input(), "set fov 60");
+        assert!(console.recall(true));
+        assert_eq!(console.input(), "toggle wireframe");
+        assert!(!console.recall(true));
+        assert!(console.recall(false));
+        assert!(console.recall(false));
+        assert_eq!(console.input(), "");
+    }
+}
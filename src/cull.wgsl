@@ -0,0 +1,79 @@
+// frustum culls instances against their bounding spheres and copies the survivors into a
+// compacted instance buffer, bumping the instance count of every indirect draw command
+
+struct CullParams {
+    planes: array<vec4<f32>, 6>,
+    radius: f32,
+    instance_count: u32,
+    command_count: u32,
+    // words per instance
+    instance_stride: u32,
+    // non zero for the compact format, a translation and per axis scale then a quaternion,
+    // otherwise instances start with a model matrix
+    compact: u32,
+    _padding0: u32,
+    _padding1: u32,
+    _padding2: u32,
+    // eye position in xyz, w is the max draw distance or zero for no limit
+    draw_distance: vec4<f32>,
+}
+
+// instances are moved around as raw words so integer fields such as the material layer aren't
+// flushed as denormal floats, and bitcast where the maths needs it
+// DrawIndexedCommand is five u32s with the instance count second
+const COMMAND_STRIDE: u32 = 5u;
+
+@group(0) @binding(0)
+var<uniform> params: CullParams;
+@group(0) @binding(1)
+var<storage, read> instances: array<u32>;
+@group(0) @binding(2)
+var<storage, read_write> visible: array<u32>;
+@group(0) @binding(3)
+var<storage, read_write> commands: array<atomic<u32>>;
+
+fn column(start: u32) -> vec3<f32> {
+    return bitcast<vec3<f32>>(vec3<u32>(instances[start], instances[start + 1u], instances[start + 2u]));
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= params.instance_count {
+        return;
+    }
+    let base = index * params.instance_stride;
+    var center: vec3<f32>;
+    var scale: f32;
+    if params.compact != 0u {
+        center = column(base);
+        let axes = abs(column(base + 3u));
+        scale = max(axes.x, max(axes.y, axes.z));
+    } else {
+        // the model matrix is column major, so the translation is the fourth column
+        center = column(base + 12u);
+        let scale_x = length(column(base));
+        let scale_y = length(column(base + 4u));
+        let scale_z = length(column(base + 8u));
+        scale = max(scale_x, max(scale_y, scale_z));
+    }
+    let radius = params.radius * scale;
+    for (var plane = 0u; plane < 6u; plane++) {
+        let p = params.planes[plane];
+        if dot(p.xyz, center) + p.w < -radius {
+            return;
+        }
+    }
+    let max_distance = params.draw_distance.w;
+    if max_distance > 0.0 && length(center - params.draw_distance.xyz) - radius > max_distance {
+        return;
+    }
+    let slot = atomicAdd(&commands[1u], 1u);
+    for (var command = 1u; command < params.command_count; command++) {
+        atomicAdd(&commands[command * COMMAND_STRIDE + 1u], 1u);
+    }
+    let out = slot * params.instance_stride;
+    for (var i = 0u; i < params.instance_stride; i++) {
+        visible[out + i] = instances[base + i];
+    }
+}
@@ -0,0 +1,300 @@
+use crate::bind_cache::{self, StorageAccess};
+use crate::picking::Aabb;
+use crate::{indirect, upload};
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Vector3, Vector4};
+
+const WORKGROUP_SIZE: u32 = 64;
+
+//the six clip planes of a view projection matrix, each normalised with the normal pointing into
+//the frustum so a point is inside when dot(normal, p) + w >= 0. an optional draw distance also
+//drops anything too far from the viewer, however far the projection reaches
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vector4<f32>; 6],
+    pub draw_distance: Option<DrawDistance>,
+}
+
+//a sphere around the viewer, anything entirely outside of it isn't drawn
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DrawDistance {
+    pub eye: Vector3<f32>,
+    pub max: f32,
+}
+
+impl DrawDistance {
+    pub fn contains_sphere(&self, center: Vector3<f32>, radius: f32) -> bool {
+        (center - self.eye).magnitude() - radius <= self.max
+    }
+}
+
+impl Frustum {
+    //assumes wgpu's 0..1 clip space depth, as produced by Camera::build_view_projection
+    pub fn from_view_projection(view_proj: &Matrix4<f32>) -> Self {
+        let row = |i: usize| view_proj.row(i);
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(2),
+            row(3) - row(2),
+        ]
+        .map(|plane| {
+            let length = plane.truncate().magnitude();
+            if length > 0.0 {
+                plane / length
+            } else {
+                plane
+            }
+        });
+        Self {
+            planes,
+            draw_distance: None,
+        }
+    }
+
+    pub fn with_draw_distance(self, draw_distance: Option<DrawDistance>) -> Self {
+        Self {
+            draw_distance,
+            ..self
+        }
+    }
+
+    pub fn contains_sphere(&self, center: cgmath::Vector3<f32>, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+            && self
+                .draw_distance
+                .is_none_or(|distance| distance.contains_sphere(center, radius))
+    }
+
+    //false only for a box entirely behind one of the planes or beyond the draw distance, so a box
+    //near a corner of the frustum can pass without being inside
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            //the corner furthest along the plane's normal
+            let corner = Vector3::new(
+                if plane.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+            plane.truncate().dot(corner) + plane.w >= 0.0
+        }) && self.draw_distance.is_none_or(|distance| {
+            let sphere = aabb.bounding_sphere();
+            distance.contains_sphere(sphere.center.to_vec(), sphere.radius)
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CullParams {
+    planes: [[f32; 4]; 6],
+    radius: f32,
+    instance_count: u32,
+    command_count: u32,
+    instance_stride: u32,
+    compact: u32,
+    _padding: [u32; 3],
+    //eye position and max distance, a distance of zero means unlimited
+    draw_distance: [f32; 4],
+}
+
+//where the cull shader finds the position and scale of an instance
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InstanceLayout {
+    //starts with a column major model matrix, the scale is the longest basis column
+    Matrix,
+    //starts with a translation followed by a per axis scale
+    Compact,
+}
+
+//frustum culling on the gpu. each frame the instance buffer is tested against the camera and the
+//visible instances are compacted into an output vertex buffer, with the instance counts of the
+//indirect commands written by the same pass, so nothing about visibility is rebuilt on the cpu
+pub struct GpuCuller {
+    pipeline: wgpu::ComputePipeline,
+    //in words, as the shader indexes the instances as u32s
+    instance_stride: u32,
+    instance_layout: InstanceLayout,
+    bind_group_layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    visible_buffer: wgpu::Buffer,
+    visible_size: wgpu::BufferAddress,
+    bind_group: Option<wgpu::BindGroup>,
+    bound_instances: Option<wgpu::Id<wgpu::Buffer>>,
+    bound_commands: Option<wgpu::Id<wgpu::Buffer>>,
+}
+
+impl GpuCuller {
+    //instance_stride is the size in bytes of one instance and must be a whole number of words.
+    //it sizes the output until the first prepare
+    pub fn new(
+        device: &wgpu::Device,
+        instance_stride: wgpu::BufferAddress,
+        instance_layout: InstanceLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cull Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("cull.wgsl").into()),
+        });
+        let compute = wgpu::ShaderStages::COMPUTE;
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Cull Bind Group Layout"),
+            entries: &[
+                bind_cache::uniform_entry(0, compute),
+                bind_cache::storage_entry(1, compute, StorageAccess::ReadOnly),
+                bind_cache::storage_entry(2, compute, StorageAccess::ReadWrite),
+                bind_cache::storage_entry(3, compute, StorageAccess::ReadWrite),
+            ],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Cull Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Cull Pipeline"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: "cs_main",
+            compilation_options: Default::default(),
+        });
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cull Params Buffer"),
+            size: std::mem::size_of::<CullParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let visible_buffer = Self::create_visible_buffer(device, instance_stride);
+        Self {
+            pipeline,
+            instance_stride: (instance_stride / 4) as u32,
+            instance_layout,
+            bind_group_layout,
+            params_buffer,
+            visible_buffer,
+            visible_size: instance_stride,
+            bind_group: None,
+            bound_instances: None,
+            bound_commands: None,
+        }
+    }
+
+    fn create_visible_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Visible Instance Buffer"),
+            size,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        })
+    }
+
+    //the compacted instances, bound in place of the full instance buffer when drawing
+    pub fn visible_instances(&self) -> wgpu::BufferSlice<'_> {
+        self.visible_buffer.slice(..)
+    }
+
+    //rebuilds the bind group if either buffer was reallocated since the last call, growing the
+    //output to match. instances must have STORAGE usage
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        instances: &wgpu::Buffer,
+        commands: &indirect::IndirectBuffer,
+    ) {
+        //the output has to be able to hold every instance, so it follows the input's size
+        if instances.size() > self.visible_size {
+            self.visible_buffer = Self::create_visible_buffer(device, instances.size());
+            self.visible_size = instances.size();
+            self.bind_group = None;
+        }
+        if self.bound_instances != Some(instances.global_id())
+            || self.bound_commands != Some(commands.buffer().global_id())
+        {
+            self.bind_group = None;
+        }
+        if self.bind_group.is_some() {
+            return;
+        }
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Cull Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: instances.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.visible_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: commands.buffer().as_entire_binding(),
+                },
+            ],
+        }));
+        self.bound_instances = Some(instances.global_id());
+        self.bound_commands = Some(commands.buffer().global_id());
+    }
+
+    //records the cull into the encoder, overwriting the instance counts of the commands with the
+    //number of visible instances. prepare must have been called with the same buffers
+    #[allow(clippy::too_many_arguments)]
+    pub fn cull(
+        &self,
+        uploader: &upload::Uploader,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+        frustum: &Frustum,
+        radius: f32,
+        instance_count: u32,
+        commands: &indirect::IndirectBuffer,
+        timestamp_writes: Option<wgpu::ComputePassTimestampWrites>,
+    ) {
+        let Some(bind_group) = self.bind_group.as_ref() else {
+            return;
+        };
+        let params = CullParams {
+            planes: frustum.planes.map(Into::into),
+            radius,
+            instance_count,
+            command_count: commands.len() as u32,
+            instance_stride: self.instance_stride,
+            compact: (self.instance_layout == InstanceLayout::Compact) as u32,
+            _padding: [0; 3],
+            draw_distance: frustum
+                .draw_distance
+                .map_or([0.0; 4], |distance| distance.eye.extend(distance.max).into()),
+        };
+        uploader.write(
+            encoder,
+            device,
+            &self.params_buffer,
+            0,
+            bytemuck::cast_slice(&[params]),
+        );
+        //zero the instance counts, the shader counts the survivors back up
+        for index in 0..commands.len() {
+            encoder.clear_buffer(
+                commands.buffer(),
+                indirect::IndirectBuffer::offset(index) + 4,
+                Some(4),
+            );
+        }
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Cull Pass"),
+            timestamp_writes,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.dispatch_workgroups(instance_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
@@ -0,0 +1,375 @@
+use crate::error::ShaderError;
+use crate::model::Vertex;
+use crate::pipeline_cache::{PipelineCache, PipelineKey};
+use crate::shader_preprocessor::ShaderPreprocessor;
+use crate::{model, InstanceFormat};
+use std::collections::HashMap;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+
+type Result<T> = std::result::Result<T, ShaderError>;
+
+//what a downstream crate supplies for a material with its own shading. the snippets are pasted
+//into src/custom_material.wgsl, which documents what each one has to define and what it can use
+pub struct CustomMaterialDescriptor<'a> {
+    //unique among custom materials, creating one with a name in use replaces it
+    pub name: &'a str,
+    //declares struct MaterialParams, laid out like the bytes given for the params
+    pub params: &'a str,
+    //defines fn material_fragment
+    pub fragment: &'a str,
+    //defines fn material_vertex, None keeps the vertices where the instance puts them
+    pub vertex: Option<&'a str>,
+    //None writes the color as is, see through effects want premultiplied alpha blending
+    pub blend: Option<wgpu::BlendState>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CustomMaterialId(usize);
+
+pub struct CustomMaterial {
+    name: String,
+    blend: Option<wgpu::BlendState>,
+    //None when the shader failed, the meshes it is assigned to draw with the error material
+    pipeline: Option<Arc<wgpu::RenderPipeline>>,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+}
+
+impl CustomMaterial {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn pipeline(&self) -> Option<&wgpu::RenderPipeline> {
+        self.pipeline.as_deref()
+    }
+
+    //whether the last attempt to build the material's shader failed
+    pub fn is_broken(&self) -> bool {
+        self.pipeline.is_none()
+    }
+
+    //bound at group 3
+    pub(crate) fn params_bind_group(&self) -> &wgpu::BindGroup {
+        &self.params_bind_group
+    }
+
+    //false if the bytes aren't the size the material was created with
+    pub fn set_params(&self, queue: &wgpu::Queue, params: &[u8]) -> bool {
+        if params.len() as wgpu::BufferAddress != self.params_buffer.size() {
+            return false;
+        }
+        queue.write_buffer(&self.params_buffer, 0, params);
+        true
+    }
+
+    fn shader_name(name: &str, format: InstanceFormat) -> String {
+        match format {
+            InstanceFormat::Full => format!("Custom:{}", name),
+            InstanceFormat::Compact => format!("Custom:{}:Compact", name),
+        }
+    }
+}
+
+//what building a material's pipelines needs from the state
+pub(crate) struct MaterialTargets<'a> {
+    pub device: &'a wgpu::Device,
+    pub preprocessor: &'a mut ShaderPreprocessor,
+    pub pipeline_cache: &'a mut PipelineCache,
+    //the material, camera and light layouts every scene shader shares
+    pub scene_layouts: [&'a wgpu::BindGroupLayout; 3],
+    pub color_format: wgpu::TextureFormat,
+    pub depth_format: wgpu::TextureFormat,
+    pub instance_format: InstanceFormat,
+}
+
+//the custom materials made so far and which of the model's material slots are drawn with them
+#[derive(Default)]
+pub struct CustomMaterials {
+    materials: Vec<CustomMaterial>,
+    assignments: HashMap<usize, CustomMaterialId>,
+}
+
+impl CustomMaterials {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //registers the material's shader for both instance formats and builds the pipeline for the
+    //current one. errors point into whichever snippet caused them. a material whose shader fails
+    //is still kept under its name, broken, so the meshes assigned to it show the error material
+    pub(crate) async fn create(
+        &mut self,
+        targets: MaterialTargets<'_>,
+        descriptor: &CustomMaterialDescriptor<'_>,
+        params: &[u8],
+    ) -> Result<CustomMaterialId> {
+        let name = descriptor.name;
+        let params_size =
+            wgpu::BufferSize::new(params.len() as wgpu::BufferAddress).ok_or_else(|| {
+                ShaderError {
+                    name: name.to_string(),
+                    location: None,
+                    message: "material params can't be empty".to_string(),
+                }
+            })?;
+        let device = targets.device;
+        //the shader's MaterialParams has to fit in the params, checked when the pipeline is built
+        let params_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{} Params Layout", name)),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: Some(params_size),
+                },
+                count: None,
+            }],
+        });
+        let [material_layout, camera_layout, light_layout] = targets.scene_layouts;
+        let layout = Arc::new(
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(&format!("{} Pipeline Layout", name)),
+                bind_group_layouts: &[material_layout, camera_layout, light_layout, &params_layout],
+                push_constant_ranges: &[],
+            }),
+        );
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Params", name)),
+            contents: params,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} Params Bind Group", name)),
+            layout: &params_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+        });
+        let pipeline = Self::build_pipeline(targets, descriptor, layout).await;
+        if let Err(error) = &pipeline {
+            tracing::error!(
+                "custom material {:?} failed, its meshes draw with the error material: {}",
+                name,
+                error
+            );
+        }
+        let (pipeline, result) = match pipeline {
+            Ok(pipeline) => (Some(pipeline), Ok(())),
+            Err(error) => (None, Err(error)),
+        };
+        let material = CustomMaterial {
+            name: name.to_string(),
+            blend: descriptor.blend,
+            pipeline,
+            params_buffer,
+            params_bind_group,
+        };
+        let id = match self.find(name) {
+            Some(id) => {
+                self.materials[id.0] = material;
+                id
+            }
+            None => {
+                self.materials.push(material);
+                CustomMaterialId(self.materials.len() - 1)
+            }
+        };
+        result.map(|()| id)
+    }
+
+    async fn build_pipeline(
+        targets: MaterialTargets<'_>,
+        descriptor: &CustomMaterialDescriptor<'_>,
+        layout: Arc<wgpu::PipelineLayout>,
+    ) -> Result<Arc<wgpu::RenderPipeline>> {
+        let name = descriptor.name;
+        let device = targets.device;
+        let preprocessor = targets.preprocessor;
+        preprocessor.insert_file("custom_material.wgsl", include_str!("custom_material.wgsl"));
+        let snippets = [
+            ("params", Some(descriptor.params)),
+            ("fragment", Some(descriptor.fragment)),
+            ("vertex", descriptor.vertex),
+        ];
+        let mut wrapper_includes = String::new();
+        for (part, snippet) in snippets {
+            if let Some(snippet) = snippet {
+                let file = format!("materials/{}/{}.wgsl", name, part);
+                preprocessor.insert_file(&file, snippet);
+                wrapper_includes.push_str(&format!("#include \"{}\"\n", file));
+            }
+        }
+        for format in [InstanceFormat::Full, InstanceFormat::Compact] {
+            let wrapper = Self::wrapper(format, descriptor.vertex.is_some(), &wrapper_includes);
+            preprocessor
+                .register(
+                    targets.pipeline_cache,
+                    device,
+                    &CustomMaterial::shader_name(name, format),
+                    &format!("{} material", name),
+                    &wrapper,
+                    layout.clone(),
+                )
+                .await?;
+        }
+        targets.pipeline_cache.get(
+            device,
+            &Self::pipeline_key(
+                name,
+                descriptor.blend,
+                targets.color_format,
+                targets.depth_format,
+                targets.instance_format,
+            ),
+        )
+    }
+
+    //the shader registered for a material, the template with the snippets included after it
+    fn wrapper(format: InstanceFormat, custom_vertex: bool, snippet_includes: &str) -> String {
+        let mut wrapper = String::new();
+        if format == InstanceFormat::Compact {
+            wrapper.push_str("#define COMPACT_INSTANCES\n");
+        }
+        if custom_vertex {
+            wrapper.push_str("#define CUSTOM_VERTEX\n");
+        }
+        wrapper.push_str("#include \"custom_material.wgsl\"\n");
+        wrapper.push_str(snippet_includes);
+        wrapper
+    }
+
+    fn pipeline_key(
+        name: &str,
+        blend: Option<wgpu::BlendState>,
+        color_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        instance_format: InstanceFormat,
+    ) -> PipelineKey {
+        let mut key = PipelineKey::opaque(
+            &CustomMaterial::shader_name(name, instance_format),
+            &[model::ModelVertex::desc(), instance_format.desc()],
+            color_format,
+            Some(depth_format),
+        );
+        key.blend = blend;
+        key
+    }
+
+    //switches every material to the pipeline reading the given instance format. broken ones stay
+    //broken, and ones whose pipeline fails become broken
+    pub(crate) fn set_instance_format(
+        &mut self,
+        device: &wgpu::Device,
+        pipeline_cache: &mut PipelineCache,
+        color_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        instance_format: InstanceFormat,
+    ) {
+        for material in self.materials.iter_mut().filter(|material| !material.is_broken()) {
+            let key = Self::pipeline_key(
+                &material.name,
+                material.blend,
+                color_format,
+                depth_format,
+                instance_format,
+            );
+            material.pipeline = match pipeline_cache.get(device, &key) {
+                Ok(pipeline) => Some(pipeline),
+                Err(error) => {
+                    tracing::error!(
+                        "custom material {:?} failed, its meshes draw with the error material: {}",
+                        material.name,
+                        error
+                    );
+                    None
+                }
+            };
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<CustomMaterialId> {
+        self.materials
+            .iter()
+            .position(|material| material.name == name)
+            .map(CustomMaterialId)
+    }
+
+    pub fn get(&self, id: CustomMaterialId) -> Option<&CustomMaterial> {
+        self.materials.get(id.0)
+    }
+
+    //draws the meshes using the model's material at this index with the custom material, or
+    //with their own again for None
+    pub fn assign(&mut self, material_index: usize, material: Option<CustomMaterialId>) {
+        match material {
+            Some(material) => self.assignments.insert(material_index, material),
+            None => self.assignments.remove(&material_index),
+        };
+    }
+
+    //the custom material drawn in place of the model's material at this index
+    pub fn for_material(&self, material_index: usize) -> Option<&CustomMaterial> {
+        self.get(*self.assignments.get(&material_index)?)
+    }
+
+    pub fn has_assignments(&self) -> bool {
+        !self.assignments.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_cache::validate_wgsl;
+
+    const PARAMS: &str = "struct MaterialParams { tint: vec4<f32>, time: f32, }";
+    const FRAGMENT: &str = "\
+fn material_fragment(in: MaterialInput) -> vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * params.tint;
+    return vec4<f32>(lit(in, color.rgb), color.a);
+}";
+    const VERTEX: &str = "\
+fn material_vertex(vertex: MaterialVertex) -> MaterialVertex {
+    var moved = vertex;
+    moved.position.y += sin(params.time + vertex.position.x) * 0.1;
+    return moved;
+}";
+
+    #[test]
+    fn template_validates_for_every_variant() {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.insert_file("common.wgsl", include_str!("../res/shaders/common.wgsl"));
+        preprocessor.insert_file("custom_material.wgsl", include_str!("custom_material.wgsl"));
+        preprocessor.insert_file("params.wgsl", PARAMS);
+        preprocessor.insert_file("fragment.wgsl", FRAGMENT);
+        preprocessor.insert_file("vertex.wgsl", VERTEX);
+        for format in [InstanceFormat::Full, InstanceFormat::Compact] {
+            for custom_vertex in [false, true] {
+                let mut includes =
+                    "#include \"params.wgsl\"\n#include \"fragment.wgsl\"\n".to_string();
+                if custom_vertex {
+                    includes.push_str("#include \"vertex.wgsl\"\n");
+                }
+                let wrapper = CustomMaterials::wrapper(format, custom_vertex, &includes);
+                let shader =
+                    pollster::block_on(preprocessor.process("material.wgsl", &wrapper)).unwrap();
+                if let Err(error) = validate_wgsl("material", &shader.source) {
+                    panic!("{}", shader.map_error(error));
+                }
+            }
+        }
+    }
+}
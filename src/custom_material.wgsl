@@ -0,0 +1,142 @@
+// the scene shader custom materials are built from. the material's snippets are included after
+// this file and provide:
+//
+// - struct MaterialParams, the layout of the parameter uniform
+// - fn material_fragment(in: MaterialInput) -> vec4<f32>, the premultiplied output color
+// - optionally fn material_vertex(vertex: MaterialVertex) -> MaterialVertex, which can move the
+//   world space vertex before it is projected. CUSTOM_VERTEX is defined when it exists
+//
+// the snippets can use t_diffuse and s_diffuse, camera, light, params and lit from here
+#include "common.wgsl"
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(2) @binding(0)
+var<uniform> light: Light;
+
+@group(3) @binding(0)
+var<uniform> params: MaterialParams;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) color: vec3<f32>,
+    @location(4) tex_coords_1: vec2<f32>,
+}
+
+#ifdef COMPACT_INSTANCES
+struct InstanceInput {
+    @location(5) position: vec3<f32>,
+    @location(14) scale: vec3<f32>,
+    @location(6) rotation: vec4<f32>,
+    @location(13) tint: vec4<f32>,
+};
+
+// rotates v by the unit quaternion q, stored as xyz then w
+fn rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    let t = 2.0 * cross(q.xyz, v);
+    return v + q.w * t + cross(q.xyz, t);
+}
+#else
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(9) normal_matrix_0: vec3<f32>,
+    @location(10) normal_matrix_1: vec3<f32>,
+    @location(11) normal_matrix_2: vec3<f32>,
+    @location(13) tint: vec4<f32>,
+};
+#endif
+
+// a vertex in world space, what material_vertex gets and returns
+struct MaterialVertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    tex_coords: vec2<f32>,
+    // the vertex color, white for meshes without any
+    color: vec3<f32>,
+    // the second uv set, for lightmaps and detail textures
+    tex_coords_1: vec2<f32>,
+    // the instance tint, left for the material to apply or ignore
+    tint: vec4<f32>,
+}
+
+// what material_fragment gets, interpolated from the vertices
+struct MaterialInput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) color: vec3<f32>,
+    @location(4) tex_coords_1: vec2<f32>,
+    @location(5) tint: vec4<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput) -> MaterialInput {
+    var vertex: MaterialVertex;
+    vertex.tex_coords = model.tex_coords;
+    vertex.color = model.color;
+    vertex.tex_coords_1 = model.tex_coords_1;
+    vertex.tint = instance.tint;
+#ifdef COMPACT_INSTANCES
+    let s = instance.scale;
+    let normal = model.normal * vec3<f32>(s.y * s.z, s.x * s.z, s.x * s.y);
+    vertex.normal = normalize(rotate(instance.rotation, normal));
+    let scaled = model.position * instance.scale;
+    vertex.position = rotate(instance.rotation, scaled) + instance.position;
+#else
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    let normal_matrix = mat3x3<f32>(
+        instance.normal_matrix_0,
+        instance.normal_matrix_1,
+        instance.normal_matrix_2,
+    );
+    vertex.normal = normalize(normal_matrix * model.normal);
+    vertex.position = (model_matrix * vec4<f32>(model.position, 1.0)).xyz;
+#endif
+#ifdef CUSTOM_VERTEX
+    vertex = material_vertex(vertex);
+#endif
+    var out: MaterialInput;
+    out.tex_coords = vertex.tex_coords;
+    out.world_normal = vertex.normal;
+    out.world_position = vertex.position;
+    out.color = vertex.color;
+    out.tex_coords_1 = vertex.tex_coords_1;
+    out.tint = vertex.tint;
+    out.clip_position = camera.view_proj * vec4<f32>(vertex.position, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: MaterialInput) -> @location(0) vec4<f32> {
+    return material_fragment(in);
+}
+
+// the built in shader's lighting applied to a surface color, for materials that only change the
+// color
+fn lit(in: MaterialInput, color: vec3<f32>) -> vec3<f32> {
+    let normal = normalize(in.world_normal);
+    let ambient_color = light.color * light.ambient;
+    let light_dir = normalize(light.position - in.world_position);
+    let diffuse_color = light.color * max(dot(normal, light_dir), 0.0);
+    let view_dir = normalize(camera.view_pos.xyz - in.world_position);
+    let half_dir = normalize(view_dir + light_dir);
+    let specular_color = pow(max(dot(normal, half_dir), 0.0), 32.0) * light.color;
+    return (ambient_color + diffuse_color + specular_color) * color;
+}
@@ -0,0 +1,177 @@
+use crate::picking::Aabb;
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector3};
+use std::mem;
+
+//the buffer always has room for at least this many vertices so a few more lines don't keep
+//reallocating it
+const MIN_CAPACITY: usize = 1024;
+
+pub const RED: [f32; 3] = [1.0, 0.2, 0.2];
+pub const GREEN: [f32; 3] = [0.2, 1.0, 0.2];
+pub const BLUE: [f32; 3] = [0.3, 0.4, 1.0];
+pub const YELLOW: [f32; 3] = [1.0, 0.9, 0.2];
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl LineVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                    shader_location: 0,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    format: wgpu::VertexFormat::Float32x3,
+                    shader_location: 1,
+                },
+            ],
+        }
+    }
+}
+
+//world space lines gathered over a frame and drawn at the end of the scene pass, depth tested
+//against the scene. everything is cleared once the frame has been rendered, so anything meant to
+//stay on screen is added again every frame
+#[derive(Default)]
+pub struct DebugLines {
+    vertices: Vec<LineVertex>,
+    buffer: Option<wgpu::Buffer>,
+    //how many vertices the last upload wrote, what draw covers
+    uploaded: u32,
+}
+
+impl DebugLines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line(&mut self, from: Point3<f32>, to: Point3<f32>, color: [f32; 3]) {
+        self.vertices.extend([
+            LineVertex {
+                position: from.into(),
+                color,
+            },
+            LineVertex {
+                position: to.into(),
+                color,
+            },
+        ]);
+    }
+
+    //the twelve edges of a box, moved into the world by transform
+    pub fn aabb(&mut self, bounds: &Aabb, transform: &Matrix4<f32>, color: [f32; 3]) {
+        let corner = |index: usize| {
+            let pick = |axis: usize| {
+                if index & (1 << axis) == 0 {
+                    bounds.min[axis]
+                } else {
+                    bounds.max[axis]
+                }
+            };
+            transform.transform_point(Point3::new(pick(0), pick(1), pick(2)))
+        };
+        let corners: [Point3<f32>; 8] = std::array::from_fn(corner);
+        //each edge joins two corners that differ along one axis
+        for index in 0..8 {
+            for axis in 0..3 {
+                let other = index | (1 << axis);
+                if other != index {
+                    self.line(corners[index], corners[other], color);
+                }
+            }
+        }
+    }
+
+    //three short lines crossing at the point, for contact points and other markers
+    pub fn cross(&mut self, point: Point3<f32>, size: f32, color: [f32; 3]) {
+        let half = size * 0.5;
+        for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()] {
+            self.line(point - axis * half, point + axis * half, color);
+        }
+    }
+
+    //a line from origin along vector with a small head at the end, for velocities and forces
+    pub fn arrow(&mut self, origin: Point3<f32>, vector: Vector3<f32>, color: [f32; 3]) {
+        let length = vector.magnitude();
+        if length == 0.0 {
+            return;
+        }
+        let tip = origin + vector;
+        self.line(origin, tip, color);
+        let direction = vector / length;
+        //any axis that isn't parallel to the arrow gives the head its spread
+        let side = if direction.y.abs() < 0.9 {
+            Vector3::unit_y()
+        } else {
+            Vector3::unit_x()
+        };
+        let side = direction.cross(side).normalize() * length * 0.1;
+        let back = tip - direction * length * 0.2;
+        self.line(tip, back + side, color);
+        self.line(tip, back - side, color);
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.vertices.len() / 2
+    }
+
+    //copies the lines into the vertex buffer, growing it when they no longer fit
+    pub(crate) fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.uploaded = self.vertices.len() as u32;
+        if self.vertices.is_empty() {
+            return;
+        }
+        let size = (self.vertices.len() * mem::size_of::<LineVertex>()) as wgpu::BufferAddress;
+        if self
+            .buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.size() < size)
+        {
+            let capacity = self.vertices.len().next_power_of_two().max(MIN_CAPACITY);
+            self.buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Debug Line Buffer"),
+                size: (capacity * mem::size_of::<LineVertex>()) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        if let Some(buffer) = self.buffer.as_ref() {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&self.vertices));
+        }
+    }
+
+    //draws what the last upload wrote. the pipeline takes the camera at group 0
+    pub(crate) fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        pipeline: &'a wgpu::RenderPipeline,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        let Some(buffer) = self.buffer.as_ref().filter(|_| self.uploaded > 0) else {
+            return;
+        };
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, buffer.slice(..));
+        render_pass.draw(0..self.uploaded, 0..1);
+    }
+}
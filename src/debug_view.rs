@@ -0,0 +1,85 @@
+use crate::pipeline_cache::PipelineKey;
+use crate::shader_preprocessor::ShaderFlags;
+
+//what the built in scene shaders show instead of the lit model, for checking imported models and
+//lighting. each view other than Lit is a variant of the scene shaders built with DEBUG_VIEW set
+//to its number, see src/debug_view.wgsl. custom materials keep their own shading
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum DebugView {
+    #[default]
+    Lit,
+    //the texture color without lighting
+    Albedo,
+    //world space normals as colors
+    Normals,
+    //distance from the camera, white up close
+    Depth,
+    //a checker laid out by the texture coordinates
+    UvChecker,
+    //brighter where more fragments land on the same pixel, depth testing is off
+    Overdraw,
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 6] = [
+        DebugView::Lit,
+        DebugView::Albedo,
+        DebugView::Normals,
+        DebugView::Depth,
+        DebugView::UvChecker,
+        DebugView::Overdraw,
+    ];
+
+    //the view after this one, wrapping back to Lit
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|view| *view == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::Lit => "lit",
+            DebugView::Albedo => "albedo",
+            DebugView::Normals => "normals",
+            DebugView::Depth => "depth",
+            DebugView::UvChecker => "uv checker",
+            DebugView::Overdraw => "overdraw",
+        }
+    }
+
+    //the flags the scene shaders are expanded with for this view, none for Lit
+    pub fn flags(self) -> ShaderFlags {
+        let view = match self {
+            DebugView::Lit => return ShaderFlags::new(),
+            DebugView::Albedo => 1,
+            DebugView::Normals => 2,
+            DebugView::Depth => 3,
+            DebugView::UvChecker => 4,
+            DebugView::Overdraw => 5,
+        };
+        ShaderFlags::new().with_value("DEBUG_VIEW", view)
+    }
+
+    //overdraw adds up every fragment instead of keeping the nearest, the rest draw as normal
+    pub fn pipeline_key(self, key: PipelineKey) -> PipelineKey {
+        if self != DebugView::Overdraw {
+            return key;
+        }
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let mut key = key
+            .with_blend(Some(wgpu::BlendState {
+                color: additive,
+                alpha: additive,
+            }))
+            .with_cull_mode(None)
+            .with_depth_write(false);
+        if let Some(depth) = key.depth.as_mut() {
+            depth.depth_compare = wgpu::CompareFunction::Always;
+        }
+        key
+    }
+}
@@ -0,0 +1,39 @@
+// what a scene shader variant built with DEBUG_VIEW outputs instead of the lit color, view is
+// the DebugView the variant was built for, see src/debug_view.rs. distance is from the camera
+fn debug_view(
+    view: u32,
+    color: vec4<f32>,
+    normal: vec3<f32>,
+    tex_coords: vec2<f32>,
+    distance: f32,
+) -> vec4<f32> {
+    switch view {
+        // albedo, the texture without lighting
+        case 1u: {
+            return vec4<f32>(color.rgb * color.a, color.a);
+        }
+        // world space normals mapped from -1..1 to 0..1
+        case 2u: {
+            return vec4<f32>(normalize(normal) * 0.5 + 0.5, 1.0);
+        }
+        // linear distance, white up close fading to black at 50 units
+        case 3u: {
+            let depth = 1.0 - clamp(distance / 50.0, 0.0, 1.0);
+            return vec4<f32>(vec3<f32>(depth), 1.0);
+        }
+        // an 8x8 checker tinted by the coordinates, stretching and seams show up as bent squares
+        case 4u: {
+            let cell = floor(tex_coords * 8.0);
+            let checker = abs(cell.x + cell.y) % 2.0;
+            let tint = vec3<f32>(fract(tex_coords), 0.5);
+            return vec4<f32>(tint * (0.4 + 0.6 * checker), 1.0);
+        }
+        // overdraw, every fragment adds a little heat through additive blending
+        case 5u: {
+            return vec4<f32>(0.1, 0.04, 0.02, 1.0);
+        }
+        default: {
+            return color;
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::model::{Material, Mesh};
+use core::ops::Range;
+
+//how a queued mesh gets its instance range
+#[derive(Debug, Clone)]
+pub enum DrawKind<'a> {
+    Instanced(Range<u32>),
+    Indirect {
+        buffer: &'a wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+    },
+}
+
+pub struct DrawItem<'a> {
+    pub pipeline: &'a wgpu::RenderPipeline,
+    pub material: &'a Material,
+    //a custom material's parameters, bound at group 3 along with its pipeline
+    pub params: Option<&'a wgpu::BindGroup>,
+    pub mesh: &'a Mesh,
+    pub kind: DrawKind<'a>,
+}
+
+impl DrawItem<'_> {
+    fn sort_key(&self) -> (u64, u64, u64) {
+        (
+            self.pipeline.global_id().inner(),
+            self.material.bind_group.global_id().inner(),
+            self.mesh.vertex_buffer.global_id().inner(),
+        )
+    }
+}
+
+//what recording a queue cost in state changes. saved_binds is how many pipeline, material and
+//vertex/index binds were skipped compared to setting everything for every draw
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct DrawStats {
+    pub draws: u32,
+    pub pipeline_binds: u32,
+    pub material_binds: u32,
+    pub mesh_binds: u32,
+    pub saved_binds: u32,
+}
+
+//opaque draws collected for one pass. they are sorted by pipeline, then material, then mesh
+//before being recorded so consecutive draws can reuse whatever state is already bound. the
+//pipelines are expected to use the scene layout: material at group 0, camera at 1 and light at 2
+#[derive(Default)]
+pub struct DrawQueue<'a> {
+    items: Vec<DrawItem<'a>>,
+}
+
+impl<'a> DrawQueue<'a> {
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    pub fn push(&mut self, item: DrawItem<'a>) {
+        self.items.push(item);
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn record<'p>(
+        mut self,
+        render_pass: &mut impl wgpu::util::RenderEncoder<'p>,
+        camera_bind_group: &'p wgpu::BindGroup,
+        light_bind_group: &'p wgpu::BindGroup,
+    ) -> DrawStats
+    where
+        'a: 'p,
+    {
+        self.items.sort_by_key(DrawItem::sort_key);
+        let mut stats = DrawStats::default();
+        let mut bound = (None, None, None);
+        for item in self.items {
+            let (pipeline, material, mesh) = item.sort_key();
+            if bound.0 != Some(pipeline) {
+                render_pass.set_pipeline(item.pipeline);
+                //a new pipeline may not keep the shared groups, so they go back on with it
+                render_pass.set_bind_group(1, camera_bind_group, &[]);
+                render_pass.set_bind_group(2, light_bind_group, &[]);
+                if let Some(params) = item.params {
+                    render_pass.set_bind_group(3, params, &[]);
+                }
+                stats.pipeline_binds += 1;
+                bound = (Some(pipeline), None, bound.2);
+            }
+            if bound.1 != Some(material) {
+                render_pass.set_bind_group(0, &item.material.bind_group, &[]);
+                stats.material_binds += 1;
+                bound.1 = Some(material);
+            }
+            if bound.2 != Some(mesh) {
+                render_pass.set_vertex_buffer(0, item.mesh.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(item.mesh.index_buffer.slice(..), item.mesh.index_format);
+                stats.mesh_binds += 1;
+                bound.2 = Some(mesh);
+            }
+            match item.kind {
+                DrawKind::Instanced(instances) => {
+                    render_pass.draw_indexed(0..item.mesh.num_elements, 0, instances)
+                }
+                DrawKind::Indirect { buffer, offset } => {
+                    render_pass.draw_indexed_indirect(buffer, offset)
+                }
+            }
+            stats.draws += 1;
+        }
+        stats.saved_binds =
+            stats.draws * 3 - stats.pipeline_binds - stats.material_binds - stats.mesh_binds;
+        stats
+    }
+}
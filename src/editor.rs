@@ -0,0 +1,322 @@
+use crate::debug_lines::{self, DebugLines};
+use crate::picking::{InstanceId, Ray};
+use cgmath::prelude::*;
+use cgmath::{Point3, Quaternion, Rad, Vector3};
+
+//gizmos are drawn at this fraction of their distance from the camera, so they stay the same size
+//on screen
+const GIZMO_SCALE: f32 = 0.2;
+//how close to a handle, relative to the gizmo size, the cursor has to be to grab it
+const GRAB_TOLERANCE: f32 = 0.08;
+const RING_SEGMENTS: usize = 48;
+const AXIS_COLORS: [[f32; 3]; 3] = [debug_lines::RED, debug_lines::GREEN, debug_lines::BLUE];
+//scale handles can't drag an axis below this, a zero axis can't be picked or dragged back out
+const MIN_SCALE: f32 = 0.01;
+
+//which gizmo the editor shows on the selected instance
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum GizmoMode {
+    //arrows along the world axes
+    #[default]
+    Translate,
+    //rings around the world axes
+    Rotate,
+    //handles along the instance's own axes
+    Scale,
+}
+
+//the transform a gizmo edits
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GizmoTarget {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl GizmoTarget {
+    fn origin(&self) -> Point3<f32> {
+        Point3::from_vec(self.position)
+    }
+}
+
+struct Drag {
+    axis: usize,
+    start: GizmoTarget,
+    //the cursor's distance along the axis, or its angle around it, when the drag started
+    start_value: f32,
+    size: f32,
+}
+
+//the state of the in game editor: whether it is on, which instance is selected and any handle
+//being dragged. GameState feeds it the cursor ray and applies what it returns
+#[derive(Default)]
+pub struct Editor {
+    enabled: bool,
+    mode: GizmoMode,
+    selected: Option<InstanceId>,
+    hovered_axis: Option<usize>,
+    drag: Option<Drag>,
+}
+
+impl Editor {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.drag = None;
+        self.hovered_axis = None;
+    }
+
+    pub fn mode(&self) -> GizmoMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: GizmoMode) {
+        self.mode = mode;
+        self.drag = None;
+    }
+
+    pub fn selected(&self) -> Option<InstanceId> {
+        self.selected
+    }
+
+    pub fn select(&mut self, instance: Option<InstanceId>) {
+        self.selected = instance;
+        self.drag = None;
+        self.hovered_axis = None;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    //highlights the handle under the cursor
+    pub fn hover(&mut self, ray: &Ray, target: &GizmoTarget) {
+        if self.drag.is_none() {
+            self.hovered_axis = self.grab(ray, target).map(|(axis, _)| axis);
+        }
+    }
+
+    //starts dragging the handle under the cursor, false if there isn't one
+    pub fn begin_drag(&mut self, ray: &Ray, target: &GizmoTarget) -> bool {
+        let Some((axis, start_value)) = self.grab(ray, target) else {
+            return false;
+        };
+        self.drag = Some(Drag {
+            axis,
+            start: *target,
+            start_value,
+            size: gizmo_size(ray.origin, target.origin()),
+        });
+        self.hovered_axis = Some(axis);
+        true
+    }
+
+    //the transform the dragged handle gives with the cursor along this ray, None when nothing is
+    //being dragged or the ray runs parallel to what the handle moves along
+    pub fn drag(&self, ray: &Ray) -> Option<GizmoTarget> {
+        let drag = self.drag.as_ref()?;
+        let start = drag.start;
+        let mut target = start;
+        match self.mode {
+            GizmoMode::Translate => {
+                let axis = world_axis(drag.axis);
+                let along = axis_param(ray, start.origin(), axis)?;
+                target.position = start.position + axis * (along - drag.start_value);
+            }
+            GizmoMode::Rotate => {
+                let axis = world_axis(drag.axis);
+                let angle = plane_angle(ray, start.origin(), axis)?;
+                target.rotation =
+                    Quaternion::from_axis_angle(axis, Rad(angle - drag.start_value)) * start.rotation;
+            }
+            GizmoMode::Scale => {
+                let axis = start.rotation.rotate_vector(world_axis(drag.axis));
+                let along = axis_param(ray, start.origin(), axis)?;
+                let factor = 1.0 + (along - drag.start_value) / drag.size;
+                target.scale[drag.axis] = (start.scale[drag.axis] * factor).max(MIN_SCALE);
+            }
+        }
+        Some(target)
+    }
+
+    //stops dragging, true if a drag was in progress so the edit can be saved
+    pub fn end_drag(&mut self) -> bool {
+        self.drag.take().is_some()
+    }
+
+    //the gizmo for the current mode around the target, seen from the eye
+    pub fn draw(&self, lines: &mut DebugLines, eye: Point3<f32>, target: &GizmoTarget) {
+        let origin = target.origin();
+        let size = gizmo_size(eye, origin);
+        let active = self.drag.as_ref().map(|drag| drag.axis).or(self.hovered_axis);
+        for (axis, color) in AXIS_COLORS.iter().enumerate() {
+            let color = if active == Some(axis) {
+                debug_lines::YELLOW
+            } else {
+                *color
+            };
+            match self.mode {
+                GizmoMode::Translate => lines.arrow(origin, world_axis(axis) * size, color),
+                GizmoMode::Rotate => {
+                    let (u, v) = plane_basis(world_axis(axis));
+                    let point = |segment: usize| {
+                        let angle = segment as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                        origin + (u * angle.cos() + v * angle.sin()) * size
+                    };
+                    for segment in 0..RING_SEGMENTS {
+                        lines.line(point(segment), point(segment + 1), color);
+                    }
+                }
+                GizmoMode::Scale => {
+                    let end = origin + target.rotation.rotate_vector(world_axis(axis)) * size;
+                    lines.line(origin, end, color);
+                    lines.cross(end, size * 0.1, color);
+                }
+            }
+        }
+    }
+
+    //the handle the ray passes closest to within reach, with the drag value at that point
+    fn grab(&self, ray: &Ray, target: &GizmoTarget) -> Option<(usize, f32)> {
+        let origin = target.origin();
+        let size = gizmo_size(ray.origin, origin);
+        let tolerance = size * GRAB_TOLERANCE;
+        (0..3)
+            .filter_map(|axis| {
+                let (miss, value) = match self.mode {
+                    GizmoMode::Translate | GizmoMode::Scale => {
+                        let direction = if self.mode == GizmoMode::Scale {
+                            target.rotation.rotate_vector(world_axis(axis))
+                        } else {
+                            world_axis(axis)
+                        };
+                        let along = axis_param(ray, origin, direction)?;
+                        if !(0.0..=size).contains(&along) {
+                            return None;
+                        }
+                        let point = origin + direction * along;
+                        (distance_to_ray(ray, point), along)
+                    }
+                    GizmoMode::Rotate => {
+                        let normal = world_axis(axis);
+                        let hit = plane_hit(ray, origin, normal)?;
+                        let miss = ((hit - origin).magnitude() - size).abs();
+                        (miss, plane_angle(ray, origin, normal)?)
+                    }
+                };
+                (miss <= tolerance).then_some((axis, value, miss))
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(axis, value, _)| (axis, value))
+    }
+}
+
+fn world_axis(axis: usize) -> Vector3<f32> {
+    match axis {
+        0 => Vector3::unit_x(),
+        1 => Vector3::unit_y(),
+        _ => Vector3::unit_z(),
+    }
+}
+
+fn gizmo_size(eye: Point3<f32>, origin: Point3<f32>) -> f32 {
+    ((origin - eye).magnitude() * GIZMO_SCALE).max(f32::EPSILON)
+}
+
+//how far along the line through origin in direction the point closest to the ray is, in units of
+//direction's length. None when the two are parallel
+fn axis_param(ray: &Ray, origin: Point3<f32>, direction: Vector3<f32>) -> Option<f32> {
+    let w0 = origin - ray.origin;
+    let a = direction.dot(direction);
+    let b = direction.dot(ray.direction);
+    let c = ray.direction.dot(ray.direction);
+    let d = direction.dot(w0);
+    let e = ray.direction.dot(w0);
+    let denominator = a * c - b * b;
+    if denominator.abs() <= f32::EPSILON * a * c {
+        return None;
+    }
+    Some((b * e - c * d) / denominator)
+}
+
+fn distance_to_ray(ray: &Ray, point: Point3<f32>) -> f32 {
+    let to_point = point - ray.origin;
+    let along = (to_point.dot(ray.direction) / ray.direction.magnitude2()).max(0.0);
+    (to_point - ray.direction * along).magnitude()
+}
+
+fn plane_hit(ray: &Ray, origin: Point3<f32>, normal: Vector3<f32>) -> Option<Point3<f32>> {
+    let facing = ray.direction.dot(normal);
+    if facing.abs() <= f32::EPSILON {
+        return None;
+    }
+    let distance = (origin - ray.origin).dot(normal) / facing;
+    (distance >= 0.0).then(|| ray.at(distance))
+}
+
+//two unit vectors spanning the plane with this normal
+fn plane_basis(normal: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let other = if normal.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+    let u = normal.cross(other).normalize();
+    (u, normal.cross(u))
+}
+
+//the angle around normal at which the ray crosses the plane through origin
+fn plane_angle(ray: &Ray, origin: Point3<f32>, normal: Vector3<f32>) -> Option<f32> {
+    let offset = plane_hit(ray, origin, normal)? - origin;
+    let (u, v) = plane_basis(normal);
+    Some(offset.dot(v).atan2(offset.dot(u)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target() -> GizmoTarget {
+        GizmoTarget {
+            position: Vector3::zero(),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn dragging_the_x_arrow_moves_along_x() {
+        let mut editor = Editor::default();
+        //looking straight down at the x arrow from above, a little way along it
+        let grab = Ray::new(Point3::new(0.5, 5.0, 0.0), -Vector3::unit_y());
+        assert!(editor.begin_drag(&grab, &target()));
+        let release = Ray::new(Point3::new(2.0, 5.0, 0.3), -Vector3::unit_y());
+        let moved = editor.drag(&release).unwrap();
+        assert!((moved.position - Vector3::new(1.5, 0.0, 0.0)).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn rotate_rings_turn_around_their_axis() {
+        let mut editor = Editor::default();
+        editor.set_mode(GizmoMode::Rotate);
+        let eye = Point3::new(0.0, 5.0, 0.0);
+        let size = gizmo_size(eye, Point3::origin());
+        let (u, v) = plane_basis(Vector3::unit_y());
+        let through = |point: Vector3<f32>| Ray::new(eye, (Point3::from_vec(point) - eye).normalize());
+        assert!(editor.begin_drag(&through(u * size), &target()));
+        let turned = editor.drag(&through(v * size)).unwrap();
+        let expected = Quaternion::from_axis_angle(Vector3::unit_y(), Rad(std::f32::consts::FRAC_PI_2));
+        assert!(turned.rotation.dot(expected).abs() > 0.9999);
+    }
+
+    #[test]
+    fn misses_leave_the_selection_alone() {
+        let mut editor = Editor::default();
+        let away = Ray::new(Point3::new(10.0, 5.0, 10.0), -Vector3::unit_y());
+        assert!(!editor.begin_drag(&away, &target()));
+        assert!(editor.drag(&away).is_none());
+    }
+}
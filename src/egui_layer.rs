@@ -0,0 +1,484 @@
+use crate::console::Console;
+use crate::frame_stats;
+use crate::ui_layer::{DebugPanel, UiLayer};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{ElementState, Ime, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{Key, NamedKey};
+
+//the egui ui layer. egui-winit 0.28 is built against winit 0.29, and the first release on winit
+//0.30 needs a newer wgpu than ours, so the window events are translated here instead
+pub struct EguiLayer {
+    context: egui::Context,
+    renderer: egui_wgpu::Renderer,
+    //events collected since the last run
+    input: egui::RawInput,
+    pointer: egui::Pos2,
+    modifiers: egui::Modifiers,
+    visible: bool,
+    //the tessellated output of the last run and the texture changes not uploaded yet, both are
+    //consumed by the next paint
+    paint_jobs: Vec<egui::ClippedPrimitive>,
+    textures: egui::TexturesDelta,
+    pixels_per_point: f32,
+    //where the focused text field wants the ime candidate window, in points
+    ime_rect: Option<egui::Rect>,
+    //opened on first copy or paste, None if the platform has no clipboard
+    #[cfg(not(target_arch = "wasm32"))]
+    clipboard: Option<arboard::Clipboard>,
+}
+
+impl EguiLayer {
+    pub fn new(device: &wgpu::Device, color_format: wgpu::TextureFormat) -> Self {
+        Self {
+            context: egui::Context::default(),
+            renderer: egui_wgpu::Renderer::new(device, color_format, None, 1),
+            input: egui::RawInput::default(),
+            pointer: egui::Pos2::ZERO,
+            modifiers: egui::Modifiers::default(),
+            visible: true,
+            paint_jobs: Vec::new(),
+            textures: egui::TexturesDelta::default(),
+            pixels_per_point: 1.0,
+            ime_rect: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            clipboard: None,
+        }
+    }
+
+    //runs any egui ui with the events collected since the last run and tessellates the result
+    //ready for the next paint
+    pub fn run_ui(
+        &mut self,
+        size: winit::dpi::PhysicalSize<u32>,
+        scale_factor: f64,
+        ui: impl FnOnce(&egui::Context),
+    ) {
+        if !self.visible {
+            return;
+        }
+        let pixels_per_point = scale_factor as f32;
+        let mut input = std::mem::take(&mut self.input);
+        input.screen_rect = Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(size.width as f32, size.height as f32) / pixels_per_point,
+        ));
+        input.time = Some(frame_stats::now_seconds());
+        input.modifiers = self.modifiers;
+        input
+            .viewports
+            .entry(egui::ViewportId::ROOT)
+            .or_default()
+            .native_pixels_per_point = Some(pixels_per_point);
+        let output = self.context.run(input, ui);
+        if !output.platform_output.copied_text.is_empty() {
+            self.set_clipboard_text(output.platform_output.copied_text);
+        }
+        self.ime_rect = output.platform_output.ime.map(|ime| ime.cursor_rect);
+        self.textures.append(output.textures_delta);
+        self.paint_jobs = self
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+        self.pixels_per_point = output.pixels_per_point;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn clipboard(&mut self) -> Option<&mut arboard::Clipboard> {
+        if self.clipboard.is_none() {
+            self.clipboard = arboard::Clipboard::new()
+                .map_err(|e| tracing::warn!("clipboard unavailable: {}", e))
+                .ok();
+        }
+        self.clipboard.as_mut()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_clipboard_text(&mut self, text: String) {
+        if let Some(clipboard) = self.clipboard() {
+            if let Err(e) = clipboard.set_text(text) {
+                tracing::warn!("failed to copy to the clipboard: {}", e);
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn clipboard_text(&mut self) -> Option<String> {
+        self.clipboard()?.get_text().ok()
+    }
+
+    //the browser only hands the clipboard over in its own copy and paste events
+    #[cfg(target_arch = "wasm32")]
+    fn set_clipboard_text(&mut self, _text: String) {}
+
+    #[cfg(target_arch = "wasm32")]
+    fn clipboard_text(&mut self) -> Option<String> {
+        None
+    }
+
+    //the copy, cut and paste shortcuts become egui's clipboard events rather than key presses
+    fn clipboard_event(&mut self, key: egui::Key) -> Option<egui::Event> {
+        let shortcut = self.modifiers.command && !self.modifiers.shift;
+        match key {
+            egui::Key::Copy => Some(egui::Event::Copy),
+            egui::Key::Cut => Some(egui::Event::Cut),
+            egui::Key::Paste => self.clipboard_text().map(egui::Event::Paste),
+            egui::Key::C if shortcut => Some(egui::Event::Copy),
+            egui::Key::X if shortcut => Some(egui::Event::Cut),
+            egui::Key::V if shortcut => self.clipboard_text().map(egui::Event::Paste),
+            _ => None,
+        }
+    }
+}
+
+impl UiLayer for EguiLayer {
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        if !visible {
+            self.paint_jobs.clear();
+        }
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    //whether egui wants the pointer or keyboard comes from the previous frame's layout
+    fn on_event(&mut self, event: &WindowEvent, scale_factor: f64) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let scale = scale_factor as f32;
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer = egui::pos2(position.x as f32 / scale, position.y as f32 / scale);
+                self.input.events.push(egui::Event::PointerMoved(self.pointer));
+                self.context.wants_pointer_input()
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.input.events.push(egui::Event::PointerGone);
+                false
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let Some(button) = pointer_button(*button) else {
+                    return false;
+                };
+                self.input.events.push(egui::Event::PointerButton {
+                    pos: self.pointer,
+                    button,
+                    pressed: *state == ElementState::Pressed,
+                    modifiers: self.modifiers,
+                });
+                self.context.wants_pointer_input()
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (unit, delta) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => {
+                        (egui::MouseWheelUnit::Line, egui::vec2(*x, *y))
+                    }
+                    MouseScrollDelta::PixelDelta(delta) => (
+                        egui::MouseWheelUnit::Point,
+                        egui::vec2(delta.x as f32 / scale, delta.y as f32 / scale),
+                    ),
+                };
+                self.input.events.push(egui::Event::MouseWheel {
+                    unit,
+                    delta,
+                    modifiers: self.modifiers,
+                });
+                self.context.wants_pointer_input()
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                let state = modifiers.state();
+                let command = if cfg!(target_os = "macos") {
+                    state.super_key()
+                } else {
+                    state.control_key()
+                };
+                self.modifiers = egui::Modifiers {
+                    alt: state.alt_key(),
+                    ctrl: state.control_key(),
+                    shift: state.shift_key(),
+                    mac_cmd: cfg!(target_os = "macos") && state.super_key(),
+                    command,
+                };
+                false
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                let pressed = event.state == ElementState::Pressed;
+                if let Some(key) = key(&event.logical_key) {
+                    let clipboard_event = pressed.then(|| self.clipboard_event(key)).flatten();
+                    if let Some(clipboard_event) = clipboard_event {
+                        self.input.events.push(clipboard_event);
+                    }
+                    self.input.events.push(egui::Event::Key {
+                        key,
+                        physical_key: None,
+                        pressed,
+                        repeat: event.repeat,
+                        modifiers: self.modifiers,
+                    });
+                }
+                if let Some(text) = event.text.as_ref().filter(|text| {
+                    pressed && !text.is_empty() && !text.chars().any(char::is_control)
+                }) {
+                    self.input.events.push(egui::Event::Text(text.to_string()));
+                }
+                //releases always get through so the camera controller never has a key stuck down
+                //after a text field takes focus
+                pressed && self.context.wants_keyboard_input()
+            }
+            WindowEvent::Ime(ime) => {
+                self.input.events.push(egui::Event::Ime(match ime {
+                    Ime::Enabled => egui::ImeEvent::Enabled,
+                    Ime::Preedit(text, _) => egui::ImeEvent::Preedit(text.clone()),
+                    Ime::Commit(text) => egui::ImeEvent::Commit(text.clone()),
+                    Ime::Disabled => egui::ImeEvent::Disabled,
+                }));
+                self.context.wants_keyboard_input()
+            }
+            WindowEvent::Focused(focused) => {
+                self.input.events.push(egui::Event::WindowFocused(*focused));
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn run(
+        &mut self,
+        size: winit::dpi::PhysicalSize<u32>,
+        scale_factor: f64,
+        panel: &mut DebugPanel<'_>,
+    ) {
+        self.run_ui(size, scale_factor, |context| debug_windows(context, panel));
+    }
+
+    fn ime_area(&self) -> Option<(PhysicalPosition<f64>, PhysicalSize<f64>)> {
+        let rect = self.ime_rect.filter(|_| self.visible)?;
+        let scale = self.pixels_per_point as f64;
+        Some((
+            PhysicalPosition::new(rect.min.x as f64 * scale, rect.min.y as f64 * scale),
+            PhysicalSize::new(rect.width() as f64 * scale, rect.height() as f64 * scale),
+        ))
+    }
+
+    fn paint(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        for (id, delta) in &self.textures.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        self.textures.set.clear();
+        let screen = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [size.width, size.height],
+            pixels_per_point: self.pixels_per_point,
+        };
+        //no paint callbacks are used so there are never extra command buffers to submit
+        self.renderer
+            .update_buffers(device, queue, encoder, &self.paint_jobs, &screen);
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Overlay Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            self.renderer
+                .render(&mut render_pass, &self.paint_jobs, &screen);
+        }
+        for id in self.textures.free.drain(..) {
+            self.renderer.free_texture(&id);
+        }
+    }
+}
+
+fn pointer_button(button: MouseButton) -> Option<egui::PointerButton> {
+    match button {
+        MouseButton::Left => Some(egui::PointerButton::Primary),
+        MouseButton::Right => Some(egui::PointerButton::Secondary),
+        MouseButton::Middle => Some(egui::PointerButton::Middle),
+        MouseButton::Back => Some(egui::PointerButton::Extra1),
+        MouseButton::Forward => Some(egui::PointerButton::Extra2),
+        MouseButton::Other(_) => None,
+    }
+}
+
+//character keys go by the text they type, which egui's names for them match
+fn key(key: &Key) -> Option<egui::Key> {
+    match key {
+        Key::Named(named) => named_key(*named),
+        Key::Character(text) => egui::Key::from_name(text),
+        _ => None,
+    }
+}
+
+fn named_key(key: NamedKey) -> Option<egui::Key> {
+    use egui::Key as K;
+    Some(match key {
+        NamedKey::Enter => K::Enter,
+        NamedKey::Tab => K::Tab,
+        NamedKey::Space => K::Space,
+        NamedKey::ArrowDown => K::ArrowDown,
+        NamedKey::ArrowLeft => K::ArrowLeft,
+        NamedKey::ArrowRight => K::ArrowRight,
+        NamedKey::ArrowUp => K::ArrowUp,
+        NamedKey::End => K::End,
+        NamedKey::Home => K::Home,
+        NamedKey::PageDown => K::PageDown,
+        NamedKey::PageUp => K::PageUp,
+        NamedKey::Backspace => K::Backspace,
+        NamedKey::Delete => K::Delete,
+        NamedKey::Insert => K::Insert,
+        NamedKey::Escape => K::Escape,
+        NamedKey::Cut => K::Cut,
+        NamedKey::Copy => K::Copy,
+        NamedKey::Paste => K::Paste,
+        NamedKey::F1 => K::F1,
+        NamedKey::F2 => K::F2,
+        NamedKey::F3 => K::F3,
+        NamedKey::F4 => K::F4,
+        NamedKey::F5 => K::F5,
+        NamedKey::F6 => K::F6,
+        NamedKey::F7 => K::F7,
+        NamedKey::F8 => K::F8,
+        NamedKey::F9 => K::F9,
+        NamedKey::F10 => K::F10,
+        NamedKey::F11 => K::F11,
+        NamedKey::F12 => K::F12,
+        NamedKey::F13 => K::F13,
+        NamedKey::F14 => K::F14,
+        NamedKey::F15 => K::F15,
+        NamedKey::F16 => K::F16,
+        NamedKey::F17 => K::F17,
+        NamedKey::F18 => K::F18,
+        NamedKey::F19 => K::F19,
+        NamedKey::F20 => K::F20,
+        NamedKey::F21 => K::F21,
+        NamedKey::F22 => K::F22,
+        NamedKey::F23 => K::F23,
+        NamedKey::F24 => K::F24,
+        NamedKey::F25 => K::F25,
+        NamedKey::F26 => K::F26,
+        NamedKey::F27 => K::F27,
+        NamedKey::F28 => K::F28,
+        NamedKey::F29 => K::F29,
+        NamedKey::F30 => K::F30,
+        NamedKey::F31 => K::F31,
+        NamedKey::F32 => K::F32,
+        NamedKey::F33 => K::F33,
+        NamedKey::F34 => K::F34,
+        NamedKey::F35 => K::F35,
+        _ => return None,
+    })
+}
+
+//the built in windows for camera settings, light tweaking and frame stats
+fn debug_windows(context: &egui::Context, panel: &mut DebugPanel<'_>) {
+    console_panel(context, panel.console);
+    egui::Window::new("Camera").show(context, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("eye");
+            ui.add(egui::DragValue::new(&mut panel.camera.eye.x).speed(0.05));
+            ui.add(egui::DragValue::new(&mut panel.camera.eye.y).speed(0.05));
+            ui.add(egui::DragValue::new(&mut panel.camera.eye.z).speed(0.05));
+        });
+        ui.horizontal(|ui| {
+            ui.label("target");
+            ui.add(egui::DragValue::new(&mut panel.camera.target.x).speed(0.05));
+            ui.add(egui::DragValue::new(&mut panel.camera.target.y).speed(0.05));
+            ui.add(egui::DragValue::new(&mut panel.camera.target.z).speed(0.05));
+        });
+        ui.add(egui::Slider::new(&mut panel.camera.fovy, 10.0..=120.0).text("fov"));
+        ui.add(
+            egui::Slider::new(panel.camera_speed, 0.001..=1.0)
+                .logarithmic(true)
+                .text("speed"),
+        );
+        //the far plane can't come in past the near one or the projection flips
+        ui.add(
+            egui::Slider::new(&mut panel.camera.znear, 0.01..=10.0)
+                .logarithmic(true)
+                .text("near"),
+        );
+        let znear = panel.camera.znear;
+        ui.add(
+            egui::Slider::new(&mut panel.camera.zfar, znear + 0.01..=10_000.0)
+                .logarithmic(true)
+                .text("far"),
+        );
+    });
+    egui::Window::new("Light").show(context, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("position");
+            for axis in panel.light_position.iter_mut() {
+                ui.add(egui::DragValue::new(axis).speed(0.05));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("color");
+            ui.color_edit_button_rgb(panel.light_color);
+        });
+    });
+    egui::Window::new("Frame stats").show(context, |ui| {
+        let stats = &panel.frame_stats;
+        ui.label(format!("{:.0} fps", stats.fps));
+        ui.label(format!("{:.2}ms avg, {:.2}ms p95", stats.average_frame_ms, stats.p95_frame_ms));
+        ui.label(format!("{} instances", stats.instance_count));
+        let draws = &panel.draw_stats;
+        ui.label(format!("{} draws", draws.draws));
+        ui.label(format!(
+            "{} pipeline, {} material and {} mesh binds, {} saved",
+            draws.pipeline_binds, draws.material_binds, draws.mesh_binds, draws.saved_binds
+        ));
+    });
+}
+
+//the console as a bar across the top, the input keeps the keyboard while it is open. up and down
+//step through earlier commands
+fn console_panel(context: &egui::Context, console: &mut Console) {
+    if !console.is_open() {
+        return;
+    }
+    egui::TopBottomPanel::top("Console")
+        .resizable(true)
+        .default_height(200.0)
+        .show(context, |ui| {
+            let log_height = ui.available_height() - ui.spacing().interact_size.y * 1.5;
+            egui::ScrollArea::vertical()
+                .max_height(log_height.max(0.0))
+                .auto_shrink([false, true])
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in console.log() {
+                        ui.monospace(line);
+                    }
+                });
+            let response = ui.add(
+                egui::TextEdit::singleline(console.input_mut())
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(f32::INFINITY),
+            );
+            if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                console.submit();
+            }
+            if response.has_focus() {
+                if ui.input(|input| input.key_pressed(egui::Key::ArrowUp)) {
+                    console.recall(true);
+                }
+                if ui.input(|input| input.key_pressed(egui::Key::ArrowDown)) {
+                    console.recall(false);
+                }
+            }
+            response.request_focus();
+        });
+}
@@ -0,0 +1,72 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EngineError {
+    #[error("failed to run the event loop: {0}")]
+    EventLoop(#[from] winit::error::EventLoopError),
+    #[error("failed to create the window: {0}")]
+    Window(#[from] winit::error::OsError),
+    #[error("failed to start the async runtime: {0}")]
+    Runtime(#[from] std::io::Error),
+    #[error("failed to create the surface: {0}")]
+    CreateSurface(#[from] wgpu::CreateSurfaceError),
+    #[error("no suitable graphics adapter was found")]
+    NoAdapter,
+    #[error("failed to get a device from the adapter: {0}")]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+    #[error("the surface isn't supported by the adapter")]
+    UnsupportedSurface,
+    #[error("failed to get the next surface texture: {0}")]
+    Surface(#[from] wgpu::SurfaceError),
+    #[error("failed to load an asset: {0:#}")]
+    Asset(anyhow::Error),
+    #[error("failed to capture frames: {0:#}")]
+    Capture(anyhow::Error),
+    #[error(transparent)]
+    Shader(#[from] ShaderError),
+    #[error("audio failed: {0:#}")]
+    Audio(anyhow::Error),
+    #[error("benchmark failed: {0}")]
+    Benchmark(&'static str),
+}
+
+//a shader that failed to compile or a pipeline the device rejected. line and column point into
+//the WGSL source when the error came from parsing or validating it, the message is the full
+//report with the offending source line underlined
+#[derive(Debug, Error)]
+#[error("shader {name:?}{}: {message}", location_suffix(.location))]
+pub struct ShaderError {
+    pub name: String,
+    pub location: Option<(u32, u32)>,
+    pub message: String,
+}
+
+fn location_suffix(location: &Option<(u32, u32)>) -> String {
+    match location {
+        Some((line, column)) => format!(" at line {line}, column {column}"),
+        None => String::new(),
+    }
+}
+
+pub type Result<T> = std::result::Result<T, EngineError>;
+
+pub type ErrorHandler = Box<dyn FnMut(&EngineError)>;
+
+//passes errors to the handler set on the app, logging them as errors if there isn't one
+#[derive(Default)]
+pub(crate) struct ErrorReporter {
+    handler: Option<ErrorHandler>,
+}
+
+impl ErrorReporter {
+    pub(crate) fn set_handler(&mut self, handler: impl FnMut(&EngineError) + 'static) {
+        self.handler = Some(Box::new(handler));
+    }
+
+    pub(crate) fn report(&mut self, error: EngineError) {
+        match self.handler.as_mut() {
+            Some(handler) => handler(&error),
+            None => tracing::error!("{}", error),
+        }
+    }
+}
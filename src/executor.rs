@@ -0,0 +1,84 @@
+//runs the async init off the event loop and lets the app poll for the result. with the `tokio`
+//feature it is spawned on a tokio runtime, without it a plain thread drives it with pollster so
+//downstream users don't have to pull in tokio. on the web it is handed to the browser
+use std::future::Future;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "tokio"))]
+pub struct InitTask<T> {
+    runtime: tokio::runtime::Runtime,
+    handle: Option<tokio::task::JoinHandle<T>>,
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "tokio"))]
+impl<T: Send + 'static> InitTask<T> {
+    pub fn spawn(future: impl Future<Output = T> + Send + 'static) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Runtime::new()?;
+        let handle = Some(runtime.spawn(future));
+        Ok(Self { runtime, handle })
+    }
+
+    //returns the output once, as soon as the task has finished
+    pub fn try_take(&mut self) -> Option<std::io::Result<T>> {
+        match self.handle.take() {
+            Some(handle) if handle.is_finished() => {
+                Some(self.runtime.block_on(handle).map_err(std::io::Error::other))
+            }
+            handle => {
+                self.handle = handle;
+                None
+            }
+        }
+    }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "tokio")))]
+pub struct InitTask<T> {
+    receiver: std::sync::mpsc::Receiver<T>,
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "tokio")))]
+impl<T: Send + 'static> InitTask<T> {
+    pub fn spawn(future: impl Future<Output = T> + Send + 'static) -> std::io::Result<Self> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("init".to_string())
+            .spawn(move || {
+                let _ = sender.send(pollster::block_on(future));
+            })?;
+        Ok(Self { receiver })
+    }
+
+    //returns the output once, as soon as the task has finished
+    pub fn try_take(&mut self) -> Option<std::io::Result<T>> {
+        match self.receiver.try_recv() {
+            Ok(output) => Some(Ok(output)),
+            Err(std::sync::mpsc::TryRecvError::Empty) => None,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Some(Err(std::io::Error::other(
+                "init thread stopped without a result",
+            ))),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub struct InitTask<T> {
+    output: std::rc::Rc<std::cell::RefCell<Option<T>>>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<T: 'static> InitTask<T> {
+    pub fn spawn(future: impl Future<Output = T> + 'static) -> std::io::Result<Self> {
+        let output = std::rc::Rc::new(std::cell::RefCell::new(None));
+        let slot = output.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = future.await;
+            slot.borrow_mut().replace(result);
+        });
+        Ok(Self { output })
+    }
+
+    //returns the output once, as soon as the task has finished
+    pub fn try_take(&mut self) -> Option<std::io::Result<T>> {
+        self.output.borrow_mut().take().map(Ok)
+    }
+}
@@ -0,0 +1,94 @@
+//optional device features this crate knows how to make use of. each one is only requested if the
+//adapter supports it, anything missing is recorded so the code relying on it can fall back
+pub const REQUESTED_FEATURES: wgpu::Features = wgpu::Features::POLYGON_MODE_LINE
+    .union(wgpu::Features::TIMESTAMP_QUERY)
+    .union(wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_BC)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ETC2)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ASTC)
+    .union(wgpu::Features::DEPTH32FLOAT_STENCIL8)
+    .union(wgpu::Features::MULTI_DRAW_INDIRECT)
+    .union(wgpu::Features::INDIRECT_FIRST_INSTANCE);
+
+#[derive(Debug, Copy, Clone)]
+pub struct DeviceFeatures {
+    //everything that was enabled on the device
+    pub enabled: wgpu::Features,
+    //features that were asked for but the adapter couldn't provide
+    pub missing: wgpu::Features,
+    pub polygon_mode_line: bool,
+    pub timestamp_query: bool,
+    pub texture_compression_bc: bool,
+    pub texture_compression_etc2: bool,
+    pub texture_compression_astc: bool,
+    pub depth32float_stencil8: bool,
+    pub multi_draw_indirect: bool,
+    pub indirect_first_instance: bool,
+    //a downlevel capability rather than a feature, missing on some gl and webgl targets
+    pub anisotropic_filtering: bool,
+}
+
+impl DeviceFeatures {
+    pub fn negotiate(adapter: &wgpu::Adapter, requested: wgpu::Features) -> Self {
+        let enabled = adapter.features() & requested;
+        Self {
+            enabled,
+            missing: requested - enabled,
+            polygon_mode_line: enabled.contains(wgpu::Features::POLYGON_MODE_LINE),
+            timestamp_query: enabled.contains(wgpu::Features::TIMESTAMP_QUERY),
+            texture_compression_bc: enabled.contains(wgpu::Features::TEXTURE_COMPRESSION_BC),
+            texture_compression_etc2: enabled.contains(wgpu::Features::TEXTURE_COMPRESSION_ETC2),
+            texture_compression_astc: enabled.contains(wgpu::Features::TEXTURE_COMPRESSION_ASTC),
+            depth32float_stencil8: enabled.contains(wgpu::Features::DEPTH32FLOAT_STENCIL8),
+            multi_draw_indirect: enabled.contains(wgpu::Features::MULTI_DRAW_INDIRECT),
+            indirect_first_instance: enabled.contains(wgpu::Features::INDIRECT_FIRST_INSTANCE),
+            anisotropic_filtering: adapter
+                .get_downlevel_capabilities()
+                .flags
+                .contains(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING),
+        }
+    }
+
+    pub fn has(&self, feature: wgpu::Features) -> bool {
+        self.enabled.contains(feature)
+    }
+}
+
+//starts from the defaults for the platform and raises them to whatever the adapter can do, so
+//large textures and buffers work on hardware that allows them
+pub fn negotiate_limits(adapter: &wgpu::Adapter) -> wgpu::Limits {
+    let base = if cfg!(target_arch = "wasm32") {
+        wgpu::Limits::downlevel_webgl2_defaults()
+    } else {
+        wgpu::Limits::default()
+    };
+    base.using_resolution(adapter.limits())
+        .using_alignment(adapter.limits())
+}
+
+//picks the depth buffer format. with a stencil wanted it is Depth32FloatStencil8 when the device
+//has the feature, otherwise Depth24PlusStencil8 which every backend can render to. without one
+//Depth32Float is preferred for its precision, falling back to Depth24Plus
+pub fn choose_depth_format(
+    adapter: &wgpu::Adapter,
+    features: &DeviceFeatures,
+    stencil: bool,
+) -> wgpu::TextureFormat {
+    let renderable = |format: wgpu::TextureFormat| {
+        adapter
+            .get_texture_format_features(format)
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+    };
+    if stencil {
+        if features.depth32float_stencil8 && renderable(wgpu::TextureFormat::Depth32FloatStencil8) {
+            wgpu::TextureFormat::Depth32FloatStencil8
+        } else {
+            wgpu::TextureFormat::Depth24PlusStencil8
+        }
+    } else if renderable(wgpu::TextureFormat::Depth32Float) {
+        wgpu::TextureFormat::Depth32Float
+    } else {
+        wgpu::TextureFormat::Depth24Plus
+    }
+}
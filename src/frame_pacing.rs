@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant};
+
+//how close to the deadline we stop sleeping and start spinning, thread::sleep regularly
+//oversleeps by a millisecond or so on most platforms
+const SPIN_MARGIN: Duration = Duration::from_micros(1500);
+
+//caps the frame rate independently of vsync so an uncapped ControlFlow::Poll loop doesn't burn
+//a whole core. sleeps for most of the remaining frame time then spins for the last bit
+#[derive(Debug, Default)]
+pub struct FrameLimiter {
+    target_frame_time: Option<Duration>,
+    next_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+    pub fn new(target_fps: Option<f32>) -> Self {
+        let mut limiter = Self::default();
+        limiter.set_target_fps(target_fps);
+        limiter
+    }
+
+    //None or a non positive fps removes the cap
+    pub fn set_target_fps(&mut self, target_fps: Option<f32>) {
+        self.target_frame_time = target_fps
+            .filter(|fps| *fps > 0.0)
+            .map(|fps| Duration::from_secs_f32(1.0 / fps));
+        self.next_frame = None;
+    }
+
+    pub fn target_fps(&self) -> Option<f32> {
+        self.target_frame_time
+            .map(|frame_time| 1.0 / frame_time.as_secs_f32())
+    }
+
+    //blocks until it is time to start the next frame. the browser paces frames itself so this
+    //does nothing on the web
+    pub fn wait(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let Some(frame_time) = self.target_frame_time else {
+                return;
+            };
+            let now = Instant::now();
+            let deadline = self.next_frame.unwrap_or(now);
+            if deadline > now {
+                let remaining = deadline - now;
+                if remaining > SPIN_MARGIN {
+                    std::thread::sleep(remaining - SPIN_MARGIN);
+                }
+                while Instant::now() < deadline {
+                    std::hint::spin_loop();
+                }
+            }
+            //if we fell more than a frame behind don't try to catch up with a burst of frames
+            let now = Instant::now();
+            let next = deadline + frame_time;
+            self.next_frame = Some(if next < now { now + frame_time } else { next });
+        }
+    }
+}
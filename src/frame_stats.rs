@@ -0,0 +1,93 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+//frame times kept for the averages, about two seconds at 60fps
+const HISTORY: usize = 120;
+
+//a snapshot of how the last couple of seconds of frames went
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct FrameStats {
+    pub fps: f32,
+    pub average_frame_ms: f32,
+    pub p95_frame_ms: f32,
+    pub draw_calls: u32,
+    pub instance_count: u32,
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.0} fps | {:.2}ms avg | {:.2}ms p95 | {} draws | {} instances",
+            self.fps, self.average_frame_ms, self.p95_frame_ms, self.draw_calls, self.instance_count
+        )
+    }
+}
+
+//seconds since some fixed point, Instant isn't available in the browser
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now_seconds() -> f64 {
+    use std::sync::OnceLock;
+    static START: OnceLock<std::time::Instant> = OnceLock::new();
+    START.get_or_init(std::time::Instant::now).elapsed().as_secs_f64()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now_seconds() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
+//collects frame times as frames are presented and turns them into FrameStats
+#[derive(Debug, Default)]
+pub struct FrameStatsTracker {
+    last_frame: Option<f64>,
+    frame_times: VecDeque<f32>,
+    stats: FrameStats,
+}
+
+impl FrameStatsTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //call once per presented frame with what it drew
+    pub fn record_frame(&mut self, draw_calls: u32, instance_count: u32) {
+        let now = now_seconds();
+        if let Some(last) = self.last_frame {
+            if self.frame_times.len() == HISTORY {
+                self.frame_times.pop_front();
+            }
+            self.frame_times.push_back(((now - last) * 1000.0) as f32);
+        }
+        self.last_frame = Some(now);
+
+        let mut sorted: Vec<f32> = self.frame_times.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let average = if sorted.is_empty() {
+            0.0
+        } else {
+            sorted.iter().sum::<f32>() / sorted.len() as f32
+        };
+        let p95 = sorted
+            .get(((sorted.len() as f32 * 0.95).ceil() as usize).saturating_sub(1))
+            .copied()
+            .unwrap_or_default();
+        self.stats = FrameStats {
+            fps: if average > 0.0 { 1000.0 / average } else { 0.0 },
+            average_frame_ms: average,
+            p95_frame_ms: p95,
+            draw_calls,
+            instance_count,
+        };
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    //forget the history, e.g. after a pause so the gap doesn't skew the numbers
+    pub fn reset(&mut self) {
+        self.last_frame = None;
+        self.frame_times.clear();
+    }
+}
@@ -0,0 +1,109 @@
+//frame captures through renderdoc's in-application api, behind the renderdoc feature. the api
+//only attaches when the app was launched from renderdoc or has it injected, so a build with the
+//feature runs as normal everywhere else and every capture request is turned down
+pub struct GpuCapture {
+    #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+    api: Option<renderdoc::RenderDoc<renderdoc::V141>>,
+    //asked for and waiting for the next frame to start
+    pending: bool,
+    //between starting and ending a capture
+    capturing: bool,
+}
+
+impl GpuCapture {
+    //attaches to renderdoc if it is loaded into the process
+    pub fn new() -> Self {
+        #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+        let api = match renderdoc::RenderDoc::new() {
+            Ok(api) => {
+                tracing::info!("renderdoc attached, frame captures are available");
+                Some(api)
+            }
+            Err(e) => {
+                tracing::debug!("renderdoc not attached: {}", e);
+                None
+            }
+        };
+        Self {
+            #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+            api,
+            pending: false,
+            capturing: false,
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+        return self.api.is_some();
+        #[cfg(not(all(feature = "renderdoc", not(target_arch = "wasm32"))))]
+        false
+    }
+
+    //captures the next frame. returns false if renderdoc isn't attached
+    pub fn trigger(&mut self) -> bool {
+        self.pending = self.is_available();
+        self.pending
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    //how many captures renderdoc has saved this session, including ones taken from its own ui
+    pub fn capture_count(&self) -> u32 {
+        #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+        if let Some(api) = self.api.as_ref() {
+            return api.get_num_captures();
+        }
+        0
+    }
+
+    //called before any of a frame's work is recorded
+    pub(crate) fn begin_frame(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+        if let (true, Some(api)) = (pending, self.api.as_mut()) {
+            //null device and window capture whichever renderdoc considers active, the app only
+            //ever has the one
+            api.start_frame_capture(std::ptr::null(), std::ptr::null());
+            self.capturing = true;
+        }
+    }
+
+    //called once the frame has been submitted and presented
+    pub(crate) fn end_frame(&mut self) {
+        let capturing = std::mem::take(&mut self.capturing);
+        #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+        if let (true, Some(api)) = (capturing, self.api.as_mut()) {
+            api.end_frame_capture(std::ptr::null(), std::ptr::null());
+            let latest = api.get_num_captures().checked_sub(1);
+            match latest.and_then(|index| api.get_capture(index)) {
+                Some((path, _)) => tracing::info!("saved renderdoc capture {}", path.display()),
+                None => tracing::warn!("renderdoc didn't save the capture"),
+            }
+        }
+    }
+}
+
+impl Default for GpuCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_turned_down_without_renderdoc() {
+        //tests never run under renderdoc
+        let mut capture = GpuCapture::new();
+        assert!(!capture.is_available());
+        assert!(!capture.trigger());
+        assert!(!capture.is_pending());
+        capture.begin_frame();
+        capture.end_frame();
+        assert_eq!(capture.capture_count(), 0);
+    }
+}
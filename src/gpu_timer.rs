@@ -0,0 +1,327 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+//timestamp pairs that can be written in one frame
+const MAX_SCOPES: u32 = 16;
+//readback buffers cycled through so mapping never stalls the frame
+const RING_SIZE: usize = 3;
+//samples kept for each pass's rolling average
+const AVERAGE_WINDOW: usize = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassTiming {
+    pub label: &'static str,
+    pub last_ms: f32,
+    pub average_ms: f32,
+}
+
+struct RollingAverage {
+    samples: VecDeque<f32>,
+    sum: f32,
+}
+
+impl RollingAverage {
+    fn new() -> Self {
+        Self {
+            samples: VecDeque::with_capacity(AVERAGE_WINDOW),
+            sum: 0.0,
+        }
+    }
+
+    fn push(&mut self, sample: f32) {
+        if self.samples.len() == AVERAGE_WINDOW {
+            self.sum -= self.samples.pop_front().unwrap_or_default();
+        }
+        self.samples.push_back(sample);
+        self.sum += sample;
+    }
+
+    fn last(&self) -> f32 {
+        self.samples.back().copied().unwrap_or_default()
+    }
+
+    fn average(&self) -> f32 {
+        if self.samples.is_empty() {
+            0.0
+        } else {
+            self.sum / self.samples.len() as f32
+        }
+    }
+}
+
+struct ReadbackSlot {
+    buffer: wgpu::Buffer,
+    //the scopes resolved into this buffer, in query order
+    scopes: Vec<&'static str>,
+    //set once the buffer has been copied into and is waiting to be mapped
+    copied: bool,
+    //filled in by the map callback
+    pending: Option<MapResult>,
+}
+
+type MapResult = Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>;
+
+struct TimerState {
+    scopes: Vec<&'static str>,
+    slots: Vec<ReadbackSlot>,
+    next_slot: usize,
+    averages: Vec<(&'static str, RollingAverage)>,
+    //readbacks whose buffer failed to map, their frames are missing from the averages
+    failed_readbacks: usize,
+    //tracy's context for the queue, made when the first timestamps come back
+    #[cfg(feature = "profile-tracy")]
+    tracy: Option<tracy_client::GpuContext>,
+}
+
+//measures how long each pass takes on the gpu with timestamp queries. passes ask for timestamp
+//writes by label, the queries are resolved at the end of the frame and read back a few frames
+//later, feeding a rolling average per label
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    //nanoseconds per timestamp tick
+    period: f32,
+    state: RefCell<TimerState>,
+}
+
+impl GpuTimer {
+    //None if the device wasn't created with TIMESTAMP_QUERY
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Timestamp Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: MAX_SCOPES * 2,
+        });
+        let size = Self::buffer_size(MAX_SCOPES);
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let slots = (0..RING_SIZE)
+            .map(|_| ReadbackSlot {
+                buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Timestamp Readback Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                scopes: Vec::new(),
+                copied: false,
+                pending: None,
+            })
+            .collect();
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            period: queue.get_timestamp_period(),
+            state: RefCell::new(TimerState {
+                scopes: Vec::new(),
+                slots,
+                next_slot: 0,
+                averages: Vec::new(),
+                failed_readbacks: 0,
+                #[cfg(feature = "profile-tracy")]
+                tracy: None,
+            }),
+        })
+    }
+
+    fn buffer_size(scopes: u32) -> wgpu::BufferAddress {
+        (scopes * 2) as wgpu::BufferAddress * std::mem::size_of::<u64>() as wgpu::BufferAddress
+    }
+
+    //claims a pair of queries for the label, None once the frame's queries are used up
+    fn begin_scope(&self, label: &'static str) -> Option<u32> {
+        let mut state = self.state.borrow_mut();
+        if state.scopes.len() as u32 >= MAX_SCOPES {
+            return None;
+        }
+        state.scopes.push(label);
+        Some((state.scopes.len() as u32 - 1) * 2)
+    }
+
+    pub fn render_pass_writes(&self, label: &'static str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        let index = self.begin_scope(label)?;
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    pub fn compute_pass_writes(&self, label: &'static str) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        let index = self.begin_scope(label)?;
+        Some(wgpu::ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    //records the resolve of this frame's queries into the next free readback buffer. if every
+    //buffer is still waiting on the gpu the frame's timings are dropped instead of stalling
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut state = self.state.borrow_mut();
+        let scopes = std::mem::take(&mut state.scopes);
+        if scopes.is_empty() {
+            return;
+        }
+        let next = state.next_slot;
+        let slot = &mut state.slots[next];
+        if slot.copied || slot.pending.is_some() {
+            return;
+        }
+        let count = scopes.len() as u32;
+        encoder.resolve_query_set(&self.query_set, 0..count * 2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &slot.buffer,
+            0,
+            Self::buffer_size(count),
+        );
+        slot.scopes = scopes;
+        slot.copied = true;
+    }
+
+    //call after the encoder holding resolve was submitted. starts mapping what was just copied
+    //and folds any finished readbacks into the averages
+    pub fn after_submit(&self, device: &wgpu::Device) {
+        let mut state = self.state.borrow_mut();
+        let next = state.next_slot;
+        let slot = &mut state.slots[next];
+        if slot.copied {
+            slot.copied = false;
+            let map_result = MapResult::default();
+            let callback_result = map_result.clone();
+            slot.buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    if let Ok(mut slot) = callback_result.lock() {
+                        *slot = Some(result);
+                    }
+                });
+            slot.pending = Some(map_result);
+            state.next_slot = (next + 1) % RING_SIZE;
+        }
+        device.poll(wgpu::Maintain::Poll);
+        let TimerState {
+            slots,
+            averages,
+            failed_readbacks,
+            #[cfg(feature = "profile-tracy")]
+            tracy,
+            ..
+        } = &mut *state;
+        for slot in slots.iter_mut() {
+            let result = slot
+                .pending
+                .as_ref()
+                .and_then(|pending| pending.lock().ok()?.take());
+            let Some(result) = result else {
+                continue;
+            };
+            slot.pending = None;
+            if let Err(e) = result {
+                *failed_readbacks += 1;
+                tracing::warn!("gpu timestamp readback failed: {}", e);
+                //resets the buffer's map state so it can be mapped again next time round. the
+                //core may complain it was never mapped, which isn't worth reporting
+                device.push_error_scope(wgpu::ErrorFilter::Validation);
+                slot.buffer.unmap();
+                drop(device.pop_error_scope());
+                continue;
+            }
+            {
+                let data = slot.buffer.slice(..).get_mapped_range();
+                let timestamps: Vec<u64> = data
+                    .chunks_exact(8)
+                    .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap_or_default()))
+                    .collect();
+                #[cfg(feature = "profile-tracy")]
+                Self::emit_tracy_zones(tracy, self.period, &slot.scopes, &timestamps);
+                for (scope, pair) in slot.scopes.iter().zip(timestamps.chunks_exact(2)) {
+                    let ms = pair[1].saturating_sub(pair[0]) as f32 * self.period / 1_000_000.0;
+                    match averages.iter_mut().find(|(label, _)| *label == *scope) {
+                        Some((_, average)) => average.push(ms),
+                        None => {
+                            let mut average = RollingAverage::new();
+                            average.push(ms);
+                            averages.push((*scope, average));
+                        }
+                    }
+                }
+            }
+            slot.buffer.unmap();
+        }
+    }
+
+    //hands a frame's timestamps to tracy as gpu zones. they are only known a few frames after the
+    //passes ran, so each zone is opened and closed here and placed by its timestamps alone
+    #[cfg(feature = "profile-tracy")]
+    fn emit_tracy_zones(
+        context: &mut Option<tracy_client::GpuContext>,
+        period: f32,
+        scopes: &[&'static str],
+        timestamps: &[u64],
+    ) {
+        let (Some(client), Some(first)) = (tracy_client::Client::running(), timestamps.first()) else {
+            return;
+        };
+        if context.is_none() {
+            //the first timestamp stands in for the gpu clock now, off by the readback latency
+            *context = client
+                .new_gpu_context(
+                    Some("wgpu"),
+                    tracy_client::GpuContextType::Invalid,
+                    *first as i64,
+                    period,
+                )
+                .ok();
+        }
+        let Some(context) = context.as_ref() else {
+            return;
+        };
+        for (scope, pair) in scopes.iter().zip(timestamps.chunks_exact(2)) {
+            let Ok(mut span) = context.span_alloc(scope, "", file!(), line!()) else {
+                return;
+            };
+            span.end_zone();
+            span.upload_timestamp_start(pair[0] as i64);
+            span.upload_timestamp_end(pair[1] as i64);
+        }
+    }
+
+    //the latest and averaged time of every pass seen so far, in the order they first ran
+    pub fn timings(&self) -> Vec<PassTiming> {
+        self.state
+            .borrow()
+            .averages
+            .iter()
+            .map(|(label, average)| PassTiming {
+                label,
+                last_ms: average.last(),
+                average_ms: average.average(),
+            })
+            .collect()
+    }
+
+    pub fn failed_readbacks(&self) -> usize {
+        self.state.borrow().failed_readbacks
+    }
+
+    //one line summary, e.g. "gpu: cull 0.02ms main 0.41ms"
+    pub fn summary(&self) -> String {
+        let mut summary = String::from("gpu:");
+        for timing in self.timings() {
+            summary.push_str(&format!(" {} {:.2}ms", timing.label, timing.average_ms));
+        }
+        summary
+    }
+}
@@ -0,0 +1,53 @@
+// draws dear imgui's triangles. positions are in logical pixels and are mapped to clip space by
+// the scale and translate worked out from the display rect
+
+struct Projection {
+    scale: vec2<f32>,
+    translate: vec2<f32>,
+};
+@group(0) @binding(0)
+var<uniform> projection: Projection;
+@group(0) @binding(1)
+var t_font: texture_2d<f32>;
+@group(0) @binding(2)
+var s_font: sampler;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(model.position * projection.scale + projection.translate, 0.0, 1.0);
+    out.uv = model.uv;
+    out.color = model.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color * textureSample(t_font, s_font, in.uv);
+}
+
+// imgui colours are srgb values, an srgb target encodes on write so they are decoded first
+@fragment
+fn fs_main_srgb(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = vec4<f32>(srgb_to_linear(in.color.rgb), in.color.a);
+    return color * textureSample(t_font, s_font, in.uv);
+}
+
+fn srgb_to_linear(srgb: vec3<f32>) -> vec3<f32> {
+    let cutoff = srgb <= vec3<f32>(0.04045);
+    let lower = srgb / 12.92;
+    let higher = pow((srgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(higher, lower, cutoff);
+}
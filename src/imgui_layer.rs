@@ -0,0 +1,623 @@
+use crate::console::Console;
+use crate::frame_stats;
+use crate::ui_layer::{DebugPanel, UiLayer};
+use wgpu::util::DeviceExt;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{Key, NamedKey};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ImguiVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [u8; 4],
+}
+
+impl ImguiVertex {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ImguiVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Unorm8x4,
+                },
+            ],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct Projection {
+    scale: [f32; 2],
+    translate: [f32; 2],
+}
+
+//one imgui draw command, already offset into the combined vertex and index buffers
+struct ImguiDraw {
+    //in framebuffer pixels, x y width height
+    scissor: [u32; 4],
+    indices: std::ops::Range<u32>,
+    base_vertex: i32,
+}
+
+//what the last run drew, copied out of imgui so it can be painted later in the frame
+#[derive(Default)]
+struct ImguiFrame {
+    vertices: Vec<ImguiVertex>,
+    indices: Vec<u16>,
+    draws: Vec<ImguiDraw>,
+    projection: Projection,
+}
+
+//the dear imgui ui layer. there is no imgui renderer or winit support for these versions so both
+//the wgpu drawing and the window event translation live here. only the font atlas is bound, draws
+//using any other texture id are skipped
+pub struct ImguiLayer {
+    context: imgui::Context,
+    pipeline: wgpu::RenderPipeline,
+    projection_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    //grown as needed, never shrunk
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    frame: ImguiFrame,
+    last_frame: f64,
+    visible: bool,
+}
+
+//SAFETY: imgui::Context isn't Send as it points into imgui's global state and holds an Rc to the
+//font atlas. the layer only ever moves between threads whole, from the init task to the event
+//loop, and nothing else holds the context or a clone of that Rc, so it is never touched from two
+//threads at once
+unsafe impl Send for ImguiLayer {}
+
+const FONT_TEXTURE_ID: usize = 0;
+
+impl ImguiLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color_format: wgpu::TextureFormat,
+    ) -> Self {
+        let mut context = imgui::Context::create();
+        //don't leave an imgui.ini in the working directory
+        context.set_ini_filename(None);
+        let font_texture = {
+            let atlas = context.fonts().build_rgba32_texture();
+            device.create_texture_with_data(
+                queue,
+                &wgpu::TextureDescriptor {
+                    label: Some("imgui_font_texture"),
+                    size: wgpu::Extent3d {
+                        width: atlas.width,
+                        height: atlas.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                },
+                wgpu::util::TextureDataOrder::LayerMajor,
+                atlas.data,
+            )
+        };
+        context.fonts().tex_id = imgui::TextureId::new(FONT_TEXTURE_ID);
+        let font_view = font_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let font_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("imgui_font_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let projection_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("imgui_projection_buffer"),
+            size: std::mem::size_of::<Projection>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("imgui_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("imgui_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: projection_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&font_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&font_sampler),
+                },
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Imgui Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("imgui.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Imgui Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Imgui Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[ImguiVertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: if color_format.is_srgb() {
+                    "fs_main_srgb"
+                } else {
+                    "fs_main"
+                },
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            //imgui doesn't keep a consistent winding
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        Self {
+            context,
+            pipeline,
+            projection_buffer,
+            bind_group,
+            vertex_buffer: Self::create_buffer(device, "imgui_vertex_buffer", 0, wgpu::BufferUsages::VERTEX),
+            index_buffer: Self::create_buffer(device, "imgui_index_buffer", 0, wgpu::BufferUsages::INDEX),
+            frame: ImguiFrame::default(),
+            last_frame: frame_stats::now_seconds(),
+            visible: true,
+        }
+    }
+
+    fn create_buffer(
+        device: &wgpu::Device,
+        label: &str,
+        size: wgpu::BufferAddress,
+        usage: wgpu::BufferUsages,
+    ) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            //room for a few windows before the first grow
+            size: size.max(64 * 1024).next_power_of_two(),
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    //copies imgui's draw lists into one vertex and index buffer worth of data, turning the clip
+    //rects into scissors in framebuffer pixels
+    fn copy_draw_data(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        let draw_data = self.context.render();
+        let frame = &mut self.frame;
+        frame.vertices.clear();
+        frame.indices.clear();
+        frame.draws.clear();
+        let [x, y] = draw_data.display_pos;
+        let [width, height] = draw_data.display_size;
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        let scale = [2.0 / width, -2.0 / height];
+        frame.projection = Projection {
+            scale,
+            translate: [-1.0 - x * scale[0], 1.0 - y * scale[1]],
+        };
+        let [scale_x, scale_y] = draw_data.framebuffer_scale;
+        for draw_list in draw_data.draw_lists() {
+            let base_vertex = frame.vertices.len();
+            let base_index = frame.indices.len();
+            frame
+                .vertices
+                .extend(draw_list.vtx_buffer().iter().map(|vertex| ImguiVertex {
+                    position: vertex.pos,
+                    uv: vertex.uv,
+                    color: vertex.col,
+                }));
+            frame.indices.extend_from_slice(draw_list.idx_buffer());
+            for command in draw_list.commands() {
+                let imgui::DrawCmd::Elements { count, cmd_params } = command else {
+                    continue;
+                };
+                if cmd_params.texture_id.id() != FONT_TEXTURE_ID {
+                    continue;
+                }
+                let [left, top, right, bottom] = cmd_params.clip_rect;
+                let left = ((left - x) * scale_x).clamp(0.0, size.width as f32) as u32;
+                let top = ((top - y) * scale_y).clamp(0.0, size.height as f32) as u32;
+                let right = ((right - x) * scale_x).clamp(0.0, size.width as f32) as u32;
+                let bottom = ((bottom - y) * scale_y).clamp(0.0, size.height as f32) as u32;
+                if right <= left || bottom <= top {
+                    continue;
+                }
+                let start = (base_index + cmd_params.idx_offset) as u32;
+                frame.draws.push(ImguiDraw {
+                    scissor: [left, top, right - left, bottom - top],
+                    indices: start..start + count as u32,
+                    base_vertex: (base_vertex + cmd_params.vtx_offset) as i32,
+                });
+            }
+        }
+    }
+}
+
+impl UiLayer for ImguiLayer {
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        if !visible {
+            self.frame.draws.clear();
+        }
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    //whether imgui wants the mouse or keyboard comes from the previous frame
+    fn on_event(&mut self, event: &WindowEvent, scale_factor: f64) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let io = self.context.io_mut();
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = position.to_logical::<f32>(scale_factor);
+                io.add_mouse_pos_event([position.x, position.y]);
+                io.want_capture_mouse
+            }
+            WindowEvent::CursorLeft { .. } => {
+                io.add_mouse_pos_event([f32::MAX, f32::MAX]);
+                false
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let Some(button) = mouse_button(*button) else {
+                    return false;
+                };
+                io.add_mouse_button_event(button, *state == ElementState::Pressed);
+                io.want_capture_mouse
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                //imgui scrolls in lines, trackpads report pixels so only their direction is kept
+                let wheel = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => [*x, *y],
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        [delta.x.signum() as f32, delta.y.signum() as f32]
+                    }
+                };
+                io.add_mouse_wheel_event(wheel);
+                io.want_capture_mouse
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                let state = modifiers.state();
+                io.add_key_event(imgui::Key::ModCtrl, state.control_key());
+                io.add_key_event(imgui::Key::ModShift, state.shift_key());
+                io.add_key_event(imgui::Key::ModAlt, state.alt_key());
+                io.add_key_event(imgui::Key::ModSuper, state.super_key());
+                false
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                let pressed = event.state == ElementState::Pressed;
+                if let Some(key) = key(&event.logical_key) {
+                    io.add_key_event(key, pressed);
+                }
+                if pressed {
+                    if let Some(text) = event.text.as_ref() {
+                        for character in text.chars().filter(|c| !c.is_control()) {
+                            io.add_input_character(character);
+                        }
+                    }
+                }
+                pressed && io.want_capture_keyboard
+            }
+            _ => false,
+        }
+    }
+
+    fn run(
+        &mut self,
+        size: winit::dpi::PhysicalSize<u32>,
+        scale_factor: f64,
+        panel: &mut DebugPanel<'_>,
+    ) {
+        if !self.visible {
+            return;
+        }
+        let now = frame_stats::now_seconds();
+        let io = self.context.io_mut();
+        let scale = scale_factor as f32;
+        io.display_size = [size.width as f32 / scale, size.height as f32 / scale];
+        io.display_framebuffer_scale = [scale, scale];
+        //imgui asserts on a zero delta
+        io.update_delta_time(std::time::Duration::from_secs_f64(
+            (now - self.last_frame).max(1e-4),
+        ));
+        self.last_frame = now;
+        let ui = self.context.new_frame();
+        debug_windows(ui, panel);
+        self.copy_draw_data(size);
+    }
+
+    fn paint(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        _size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        let frame = &self.frame;
+        if frame.draws.is_empty() {
+            return;
+        }
+        let vertices: &[u8] = bytemuck::cast_slice(&frame.vertices);
+        //writes have to be a multiple of four bytes, an odd index count gets one unused index
+        let mut indices = bytemuck::cast_slice::<u16, u8>(&frame.indices).to_vec();
+        indices.resize(indices.len().next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT as usize), 0);
+        if self.vertex_buffer.size() < vertices.len() as wgpu::BufferAddress {
+            self.vertex_buffer = Self::create_buffer(
+                device,
+                "imgui_vertex_buffer",
+                vertices.len() as wgpu::BufferAddress,
+                wgpu::BufferUsages::VERTEX,
+            );
+        }
+        if self.index_buffer.size() < indices.len() as wgpu::BufferAddress {
+            self.index_buffer = Self::create_buffer(
+                device,
+                "imgui_index_buffer",
+                indices.len() as wgpu::BufferAddress,
+                wgpu::BufferUsages::INDEX,
+            );
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, vertices);
+        queue.write_buffer(&self.index_buffer, 0, &indices);
+        queue.write_buffer(
+            &self.projection_buffer,
+            0,
+            bytemuck::cast_slice(&[frame.projection]),
+        );
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overlay Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        for draw in &frame.draws {
+            let [x, y, width, height] = draw.scissor;
+            render_pass.set_scissor_rect(x, y, width, height);
+            render_pass.draw_indexed(draw.indices.clone(), draw.base_vertex, 0..1);
+        }
+    }
+}
+
+fn mouse_button(button: MouseButton) -> Option<imgui::MouseButton> {
+    match button {
+        MouseButton::Left => Some(imgui::MouseButton::Left),
+        MouseButton::Right => Some(imgui::MouseButton::Right),
+        MouseButton::Middle => Some(imgui::MouseButton::Middle),
+        MouseButton::Back => Some(imgui::MouseButton::Extra1),
+        MouseButton::Forward => Some(imgui::MouseButton::Extra2),
+        MouseButton::Other(_) => None,
+    }
+}
+
+//the keys imgui's widgets use for navigation and editing, plus letters and digits for shortcuts
+fn key(key: &Key) -> Option<imgui::Key> {
+    let named = match key {
+        Key::Named(named) => named,
+        Key::Character(text) => {
+            let mut chars = text.chars();
+            let character = chars.next().filter(|_| chars.next().is_none())?;
+            let name = match character.to_ascii_uppercase() {
+                letter @ 'A'..='Z' => letter.to_string(),
+                digit @ '0'..='9' => format!("Alpha{}", digit),
+                _ => return None,
+            };
+            return imgui::Key::VARIANTS
+                .into_iter()
+                .find(|key| format!("{:?}", key) == name);
+        }
+        _ => return None,
+    };
+    Some(match named {
+        NamedKey::Tab => imgui::Key::Tab,
+        NamedKey::ArrowLeft => imgui::Key::LeftArrow,
+        NamedKey::ArrowRight => imgui::Key::RightArrow,
+        NamedKey::ArrowUp => imgui::Key::UpArrow,
+        NamedKey::ArrowDown => imgui::Key::DownArrow,
+        NamedKey::PageUp => imgui::Key::PageUp,
+        NamedKey::PageDown => imgui::Key::PageDown,
+        NamedKey::Home => imgui::Key::Home,
+        NamedKey::End => imgui::Key::End,
+        NamedKey::Insert => imgui::Key::Insert,
+        NamedKey::Delete => imgui::Key::Delete,
+        NamedKey::Backspace => imgui::Key::Backspace,
+        NamedKey::Space => imgui::Key::Space,
+        NamedKey::Enter => imgui::Key::Enter,
+        NamedKey::Escape => imgui::Key::Escape,
+        _ => return None,
+    })
+}
+
+//the same camera, light and frame stat windows as the egui layer
+fn debug_windows(ui: &imgui::Ui, panel: &mut DebugPanel<'_>) {
+    console_window(ui, panel.console);
+    ui.window("Camera")
+        .position([10.0, 10.0], imgui::Condition::FirstUseEver)
+        .always_auto_resize(true)
+        .build(|| {
+            let mut eye = [panel.camera.eye.x, panel.camera.eye.y, panel.camera.eye.z];
+            if imgui::Drag::new("eye").speed(0.05).build_array(ui, &mut eye) {
+                panel.camera.eye = eye.into();
+            }
+            let mut target = [panel.camera.target.x, panel.camera.target.y, panel.camera.target.z];
+            if imgui::Drag::new("target").speed(0.05).build_array(ui, &mut target) {
+                panel.camera.target = target.into();
+            }
+            ui.slider("fov", 10.0, 120.0, &mut panel.camera.fovy);
+            ui.slider_config("speed", 0.001, 1.0)
+                .flags(imgui::SliderFlags::LOGARITHMIC)
+                .build(panel.camera_speed);
+            //the far plane can't come in past the near one or the projection flips
+            ui.slider_config("near", 0.01, 10.0)
+                .flags(imgui::SliderFlags::LOGARITHMIC)
+                .build(&mut panel.camera.znear);
+            let znear = panel.camera.znear;
+            ui.slider_config("far", znear + 0.01, 10_000.0)
+                .flags(imgui::SliderFlags::LOGARITHMIC)
+                .build(&mut panel.camera.zfar);
+        });
+    ui.window("Light")
+        .position([10.0, 200.0], imgui::Condition::FirstUseEver)
+        .always_auto_resize(true)
+        .build(|| {
+            imgui::Drag::new("position")
+                .speed(0.05)
+                .build_array(ui, panel.light_position);
+            ui.color_edit3("color", panel.light_color);
+        });
+    ui.window("Frame stats")
+        .position([10.0, 300.0], imgui::Condition::FirstUseEver)
+        .always_auto_resize(true)
+        .build(|| {
+            let stats = &panel.frame_stats;
+            ui.text(format!("{:.0} fps", stats.fps));
+            ui.text(format!("{:.2}ms avg, {:.2}ms p95", stats.average_frame_ms, stats.p95_frame_ms));
+            ui.text(format!("{} instances", stats.instance_count));
+            let draws = &panel.draw_stats;
+            ui.text(format!("{} draws", draws.draws));
+            ui.text(format!(
+                "{} pipeline, {} material and {} mesh binds, {} saved",
+                draws.pipeline_binds, draws.material_binds, draws.mesh_binds, draws.saved_binds
+            ));
+        });
+}
+
+//the console as a window pinned across the top, the same as the egui one
+fn console_window(ui: &imgui::Ui, console: &mut Console) {
+    if !console.is_open() {
+        return;
+    }
+    let [width, _] = ui.io().display_size;
+    ui.window("Console")
+        .position([0.0, 0.0], imgui::Condition::Always)
+        .size([width, 200.0], imgui::Condition::Always)
+        .movable(false)
+        .resizable(false)
+        .collapsible(false)
+        .build(|| {
+            ui.child_window("log")
+                .size([0.0, -ui.frame_height_with_spacing()])
+                .build(|| {
+                    for line in console.log() {
+                        ui.text(line);
+                    }
+                    if ui.scroll_y() >= ui.scroll_max_y() {
+                        ui.set_scroll_here_y_with_ratio(1.0);
+                    }
+                });
+            //the callback recalls history into imgui's own copy of the text, so the console's
+            //input is taken out for the widget to edit
+            let mut input = std::mem::take(console.input_mut());
+            ui.set_next_item_width(-1.0);
+            let entered = ui
+                .input_text("##console", &mut input)
+                .enter_returns_true(true)
+                .callback(imgui::InputTextCallback::HISTORY, ConsoleHistory(console))
+                .build();
+            *console.input_mut() = input;
+            if entered {
+                console.submit();
+            }
+            if !ui.is_item_active() {
+                ui.set_keyboard_focus_here_with_offset(imgui::FocusedWidget::Previous);
+            }
+        });
+}
+
+struct ConsoleHistory<'a>(&'a mut Console);
+
+impl imgui::InputTextCallbackHandler for ConsoleHistory<'_> {
+    fn on_history(&mut self, direction: imgui::HistoryDirection, mut data: imgui::TextCallbackData) {
+        if self.0.recall(direction == imgui::HistoryDirection::Up) {
+            data.clear();
+            data.push_str(self.0.input());
+        }
+    }
+}
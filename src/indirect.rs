@@ -0,0 +1,268 @@
+use crate::model::{Mesh, ModelVertex};
+use crate::upload;
+use core::ops::Range;
+use std::cell::Cell;
+
+//the layout wgpu expects for draw_indexed_indirect arguments. it is Pod so commands can be built
+//on the cpu and uploaded, or written straight into the buffer by a compute shader
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DrawIndexedCommand {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub first_instance: u32,
+}
+
+impl DrawIndexedCommand {
+    pub const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as wgpu::BufferAddress;
+}
+
+//a buffer of indexed indirect draw commands. instance counts can be changed without re-recording
+//the draws that read them, and with MULTI_DRAW_INDIRECT several commands go out in one call
+pub struct IndirectBuffer {
+    buffer: wgpu::Buffer,
+    commands: Vec<DrawIndexedCommand>,
+    dirty: Cell<bool>,
+}
+
+impl IndirectBuffer {
+    pub fn new(device: &wgpu::Device, label: &str, commands: Vec<DrawIndexedCommand>) -> Self {
+        let buffer = Self::create_buffer(device, label, commands.len());
+        Self {
+            buffer,
+            commands,
+            dirty: Cell::new(true),
+        }
+    }
+
+    //STORAGE is included so gpu culling passes can write the commands themselves
+    fn create_buffer(device: &wgpu::Device, label: &str, count: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: DrawIndexedCommand::SIZE * count.max(1) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn commands(&self) -> &[DrawIndexedCommand] {
+        &self.commands
+    }
+
+    //byte offset of a command, as passed to draw_indexed_indirect
+    pub fn offset(index: usize) -> wgpu::BufferAddress {
+        DrawIndexedCommand::SIZE * index as wgpu::BufferAddress
+    }
+
+    pub fn set(&mut self, index: usize, command: DrawIndexedCommand) {
+        if let Some(slot) = self.commands.get_mut(index) {
+            if *slot != command {
+                *slot = command;
+                self.dirty.set(true);
+            }
+        }
+    }
+
+    pub fn set_instance_count(&mut self, index: usize, instance_count: u32) {
+        if let Some(command) = self.commands.get(index).copied() {
+            self.set(
+                index,
+                DrawIndexedCommand {
+                    instance_count,
+                    ..command
+                },
+            );
+        }
+    }
+
+    //stages the cpu built commands if they changed since the last upload
+    pub fn upload(
+        &self,
+        uploader: &upload::Uploader,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+    ) {
+        if self.dirty.replace(false) {
+            uploader.write(
+                encoder,
+                device,
+                &self.buffer,
+                0,
+                bytemuck::cast_slice(&self.commands),
+            );
+        }
+    }
+}
+
+//encoders that can issue a run of indirect commands. a render pass sends them as one multi draw
+//when the device supports it, bundles have no multi draw and always go one command at a time
+pub trait IndirectEncoder<'a>: wgpu::util::RenderEncoder<'a> {
+    //issues count commands starting at first. the vertex, index and bind group state must
+    //already be set and shared by all of them
+    fn draw_indexed_indirect_range(
+        &mut self,
+        indirect: &'a wgpu::Buffer,
+        first: usize,
+        count: u32,
+        multi_draw_supported: bool,
+    );
+}
+
+impl<'a> IndirectEncoder<'a> for wgpu::RenderPass<'a> {
+    fn draw_indexed_indirect_range(
+        &mut self,
+        indirect: &'a wgpu::Buffer,
+        first: usize,
+        count: u32,
+        multi_draw_supported: bool,
+    ) {
+        if multi_draw_supported {
+            self.multi_draw_indexed_indirect(indirect, IndirectBuffer::offset(first), count);
+        } else {
+            draw_each(self, indirect, first, count);
+        }
+    }
+}
+
+impl<'a> IndirectEncoder<'a> for wgpu::RenderBundleEncoder<'a> {
+    fn draw_indexed_indirect_range(
+        &mut self,
+        indirect: &'a wgpu::Buffer,
+        first: usize,
+        count: u32,
+        _multi_draw_supported: bool,
+    ) {
+        draw_each(self, indirect, first, count);
+    }
+}
+
+fn draw_each<'a>(
+    encoder: &mut impl wgpu::util::RenderEncoder<'a>,
+    indirect: &'a wgpu::Buffer,
+    first: usize,
+    count: u32,
+) {
+    for index in first..first + count as usize {
+        encoder.draw_indexed_indirect(indirect, IndirectBuffer::offset(index));
+    }
+}
+
+//every mesh of a model copied into one vertex and one index buffer so their commands share the
+//same bound buffers. commands are ordered so meshes using the same material sit next to each
+//other, and each of those runs can go out as a single multi draw
+pub(crate) struct MergedMeshes {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_format: wgpu::IndexFormat,
+    //one command per mesh in the order they are drawn, without instance counts
+    commands: Vec<DrawIndexedCommand>,
+    //the material index and command range of each run
+    runs: Vec<(usize, Range<usize>)>,
+}
+
+impl MergedMeshes {
+    //copies the meshes with the encoder. None for a model without meshes or one mixing index
+    //formats, which can't share an index buffer
+    pub fn new(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        meshes: &[Mesh],
+    ) -> Option<Self> {
+        let index_format = meshes.first()?.index_format;
+        if meshes.iter().any(|mesh| mesh.index_format != index_format) {
+            return None;
+        }
+        let index_size = match index_format {
+            wgpu::IndexFormat::Uint16 => 2,
+            wgpu::IndexFormat::Uint32 => 4,
+        };
+        let vertex_size = std::mem::size_of::<ModelVertex>() as wgpu::BufferAddress;
+        let mut order: Vec<usize> = (0..meshes.len()).collect();
+        order.sort_by_key(|&index| meshes[index].material);
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Merged Vertex Buffer"),
+            size: meshes.iter().map(|mesh| mesh.vertex_buffer.size()).sum(),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Merged Index Buffer"),
+            size: meshes.iter().map(|mesh| mesh.index_buffer.size()).sum(),
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        //mesh buffers are padded to whole words, the padding is copied along and never drawn
+        let (mut vertex_offset, mut index_offset) = (0, 0);
+        let mut commands = Vec::with_capacity(meshes.len());
+        let mut runs: Vec<(usize, Range<usize>)> = Vec::new();
+        for (command, &index) in order.iter().enumerate() {
+            let mesh = &meshes[index];
+            encoder.copy_buffer_to_buffer(
+                &mesh.vertex_buffer,
+                0,
+                &vertex_buffer,
+                vertex_offset,
+                mesh.vertex_buffer.size(),
+            );
+            encoder.copy_buffer_to_buffer(
+                &mesh.index_buffer,
+                0,
+                &index_buffer,
+                index_offset,
+                mesh.index_buffer.size(),
+            );
+            commands.push(DrawIndexedCommand {
+                index_count: mesh.num_elements,
+                instance_count: 0,
+                first_index: (index_offset / index_size) as u32,
+                base_vertex: (vertex_offset / vertex_size) as i32,
+                first_instance: 0,
+            });
+            match runs.last_mut() {
+                Some((material, range)) if *material == mesh.material => range.end = command + 1,
+                _ => runs.push((mesh.material, command..command + 1)),
+            }
+            vertex_offset += mesh.vertex_buffer.size();
+            index_offset += mesh.index_buffer.size();
+        }
+        Some(Self {
+            vertex_buffer,
+            index_buffer,
+            index_format,
+            commands,
+            runs,
+        })
+    }
+
+    //the commands for an IndirectBuffer, every one drawing instance_count instances
+    pub fn commands(&self, instance_count: u32) -> Vec<DrawIndexedCommand> {
+        self.commands
+            .iter()
+            .map(|command| DrawIndexedCommand {
+                instance_count,
+                ..*command
+            })
+            .collect()
+    }
+
+    //the material index of each run of commands that can be drawn with the same state
+    pub fn runs(&self) -> &[(usize, Range<usize>)] {
+        &self.runs
+    }
+}
@@ -0,0 +1,203 @@
+use crate::upload;
+use std::cell::Cell;
+use std::ops::Range;
+use wgpu::util::DeviceExt;
+
+//dirty ranges closer together than this many bytes are uploaded as one write, copying a few
+//unchanged instances is cheaper than another copy command
+const MERGE_GAP_BYTES: usize = 256;
+//past this many separate ranges everything between the first and last is uploaded in one go
+const MAX_DIRTY_RANGES: usize = 64;
+
+//a vertex buffer of per instance data that can change every frame. edits are made to a cpu copy
+//and only the changed ranges are uploaded when the frame is encoded, so moving a handful of
+//instances in a big crowd doesn't rewrite the whole buffer. the gpu buffer grows by
+//reallocating when instances are added past its capacity. it is also bound as storage so compute
+//passes such as culling can read it
+pub struct InstanceBuffer<T: bytemuck::Pod> {
+    label: String,
+    buffer: wgpu::Buffer,
+    capacity: usize,
+    data: Vec<T>,
+    //instances changed since the last upload, sorted with overlapping or touching ranges merged
+    dirty: Cell<Vec<Range<usize>>>,
+}
+
+impl<T: bytemuck::Pod> InstanceBuffer<T> {
+    pub fn new(device: &wgpu::Device, label: &str, data: Vec<T>) -> Self {
+        let capacity = data.len().max(1);
+        let buffer = Self::create_buffer(device, label, capacity, &data);
+        Self {
+            label: label.to_string(),
+            buffer,
+            capacity,
+            data,
+            dirty: Cell::new(Vec::new()),
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, label: &str, capacity: usize, data: &[T]) -> wgpu::Buffer {
+        let size = (capacity * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+        if data.len() == capacity {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::cast_slice(data),
+                usage: wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST,
+            })
+        } else {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.data.get(index)
+    }
+
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    //the bound part of the buffer, covering only the live instances
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        let end = (self.data.len().max(1) * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+        self.buffer.slice(..end)
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    //flags a range of instances to be uploaded with the next frame, for changes made some other
+    //way than set or modify. the range is clamped to the live instances
+    pub fn mark_dirty(&self, range: Range<usize>) {
+        let range = range.start.min(self.data.len())..range.end.min(self.data.len());
+        if range.is_empty() {
+            return;
+        }
+        let mut dirty = self.dirty.take();
+        //everything that overlaps or touches the new range is folded into it
+        let first = dirty.partition_point(|existing| existing.end < range.start);
+        let last = dirty.partition_point(|existing| existing.start <= range.end);
+        let merged = if first < last {
+            dirty[first].start.min(range.start)..dirty[last - 1].end.max(range.end)
+        } else {
+            range
+        };
+        dirty.splice(first..last, std::iter::once(merged));
+        self.dirty.set(dirty);
+    }
+
+    //number of separate ranges waiting to be uploaded
+    pub fn dirty_ranges(&self) -> usize {
+        let dirty = self.dirty.take();
+        let count = dirty.len();
+        self.dirty.set(dirty);
+        count
+    }
+
+    //edits an instance in place and flags it for upload. returns false if the index is out of
+    //range
+    pub fn modify(&mut self, index: usize, edit: impl FnOnce(&mut T)) -> bool {
+        let Some(slot) = self.data.get_mut(index) else {
+            return false;
+        };
+        edit(slot);
+        self.mark_dirty(index..index + 1);
+        true
+    }
+
+    //returns false if the index is out of range
+    pub fn set(&mut self, index: usize, value: T) -> bool {
+        let Some(slot) = self.data.get_mut(index) else {
+            return false;
+        };
+        *slot = value;
+        self.mark_dirty(index..index + 1);
+        true
+    }
+
+    //appends an instance, doubling the gpu buffer if it is full. returns the new index
+    pub fn push(&mut self, device: &wgpu::Device, value: T) -> usize {
+        let index = self.data.len();
+        self.data.push(value);
+        if self.data.len() > self.capacity {
+            self.capacity = (self.capacity * 2).max(self.data.len());
+            self.buffer = Self::create_buffer(device, &self.label, self.capacity, &[]);
+            //the new buffer starts empty so everything has to go up again
+            self.dirty.take();
+            self.mark_dirty(0..self.data.len());
+        } else {
+            self.mark_dirty(index..index + 1);
+        }
+        index
+    }
+
+    //swaps out every instance at once, for buffers rebuilt from scratch each frame. grows the
+    //gpu buffer the same way push does
+    pub fn replace(&mut self, device: &wgpu::Device, data: Vec<T>) {
+        self.data = data;
+        if self.data.len() > self.capacity {
+            self.capacity = (self.capacity * 2).max(self.data.len());
+            self.buffer = Self::create_buffer(device, &self.label, self.capacity, &[]);
+        }
+        self.dirty.take();
+        self.mark_dirty(0..self.data.len());
+    }
+
+    //stages the dirty ranges into the encoder, a no-op if nothing changed. ranges separated by
+    //small gaps go up together
+    pub fn upload(
+        &self,
+        uploader: &upload::Uploader,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+    ) {
+        let dirty = self.dirty.take();
+        if dirty.is_empty() {
+            return;
+        }
+        let max_gap = if dirty.len() > MAX_DIRTY_RANGES {
+            usize::MAX
+        } else {
+            MERGE_GAP_BYTES / std::mem::size_of::<T>().max(1)
+        };
+        let mut ranges: Vec<Range<usize>> = Vec::with_capacity(dirty.len());
+        for range in dirty {
+            match ranges.last_mut() {
+                Some(previous) if range.start - previous.end <= max_gap => previous.end = range.end,
+                _ => ranges.push(range),
+            }
+        }
+        for range in ranges {
+            let offset = (range.start * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+            uploader.write(
+                encoder,
+                device,
+                &self.buffer,
+                offset,
+                bytemuck::cast_slice(&self.data[range]),
+            );
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use crate::transform::Transform;
+use cgmath::prelude::*;
+use cgmath::{Deg, Quaternion, Rad, Vector3};
+
+//where the instances go, before the offset and jitter are added
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Layout {
+    //counts along x, y and z, spacing apart. rows start half the row's length before the
+    //origin, so an even count has one more instance on the negative side. a spacing of 0 stacks
+    //that axis' instances on top of each other
+    Grid { counts: [u32; 3], spacing: [f32; 3] },
+    //evenly spaced around a circle in the xz plane, starting on +x
+    Ring { count: u32, radius: f32 },
+    //spread evenly over a sphere's surface along a golden angle spiral, from the top down
+    Sphere { count: u32, radius: f32 },
+}
+
+impl Layout {
+    pub fn len(&self) -> usize {
+        match *self {
+            Layout::Grid { counts, .. } => counts.iter().map(|&count| count as usize).product(),
+            Layout::Ring { count, .. } | Layout::Sphere { count, .. } => count as usize,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn positions(&self) -> Vec<Vector3<f32>> {
+        match *self {
+            Layout::Grid { counts, spacing } => {
+                let [nx, ny, nz] = counts;
+                let along =
+                    |axis: usize, i: u32| spacing[axis] * (i as f32 - counts[axis] as f32 / 2.0);
+                //z outermost and y innermost, the order the demo scene has always had
+                (0..nz)
+                    .flat_map(|z| (0..nx).flat_map(move |x| (0..ny).map(move |y| (x, y, z))))
+                    .map(|(x, y, z)| Vector3::new(along(0, x), along(1, y), along(2, z)))
+                    .collect()
+            }
+            Layout::Ring { count, radius } => (0..count)
+                .map(|i| {
+                    let angle = Deg(360.0 * i as f32 / count as f32);
+                    Vector3::new(angle.cos(), 0.0, angle.sin()) * radius
+                })
+                .collect(),
+            Layout::Sphere { count, radius } => {
+                let golden_angle = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+                (0..count)
+                    .map(|i| {
+                        //heights evenly spaced from just under the top to just over the bottom
+                        let y = 1.0 - (i as f32 + 0.5) / count as f32 * 2.0;
+                        let ring = (1.0 - y * y).sqrt();
+                        let angle = golden_angle * i as f32;
+                        Vector3::new(angle.cos() * ring, y, angle.sin() * ring) * radius
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+//how each instance is turned
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RotationRule {
+    //every instance the same way
+    Fixed(Quaternion<f32>),
+    //turned about y so the model's +z faces the layout's center
+    FaceCenter,
+    //tipped by an angle about the direction out from the center, instances at the center stay
+    //upright
+    TiltOutward(Deg<f32>),
+    //a random turn about y for each instance, from the seed
+    RandomYaw,
+}
+
+//generates instance transforms for a scene, e.g. the demo grid or a stress test
+//
+//    let ring = InstanceGrid::ring(64, 20.0).rotation(RotationRule::FaceCenter).jitter(0.5);
+//
+//jitter and random rotations come from the seed, so the same settings always give the same scene
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InstanceGrid {
+    pub layout: Layout,
+    //added to every position, the layout is centered on the origin
+    pub offset: Vector3<f32>,
+    pub rotation: RotationRule,
+    //the most each position is moved along each axis at random
+    pub jitter: f32,
+    pub seed: u64,
+    //at most this many instances, the layout's first ones
+    pub limit: Option<usize>,
+}
+
+impl Default for InstanceGrid {
+    //the demo scene: ten columns of ten stacked instances in each of ten rows, turned to face
+    //the camera
+    fn default() -> Self {
+        Self::grid([10, 10, 10], [3.0, 0.0, 3.0])
+            .offset(Vector3::new(0.0, 0.5, 0.0))
+            .rotation(RotationRule::Fixed(Quaternion::from_axis_angle(
+                Vector3::unit_y(),
+                Deg(180.0),
+            )))
+    }
+}
+
+impl InstanceGrid {
+    pub fn new(layout: Layout) -> Self {
+        Self {
+            layout,
+            offset: Vector3::zero(),
+            rotation: RotationRule::Fixed(Quaternion::one()),
+            jitter: 0.0,
+            seed: 0,
+            limit: None,
+        }
+    }
+
+    pub fn grid(counts: [u32; 3], spacing: [f32; 3]) -> Self {
+        Self::new(Layout::Grid { counts, spacing })
+    }
+
+    //exactly count instances in the most nearly cubic grid that holds them, centered on the
+    //origin. the last layer is left partly filled
+    pub fn cube(count: u32, spacing: f32) -> Self {
+        let side = (count as f32).cbrt().ceil().max(1.0) as u32;
+        Self::grid([side; 3], [spacing; 3])
+            .offset(Vector3::new(spacing, spacing, spacing) * 0.5)
+            .limit(count as usize)
+    }
+
+    pub fn ring(count: u32, radius: f32) -> Self {
+        Self::new(Layout::Ring { count, radius })
+    }
+
+    pub fn sphere(count: u32, radius: f32) -> Self {
+        Self::new(Layout::Sphere { count, radius })
+    }
+
+    pub fn offset(mut self, offset: Vector3<f32>) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: RotationRule) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: f32) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        let len = self.layout.len();
+        self.limit.map_or(len, |limit| len.min(limit))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    //one transform per instance, at unit scale
+    pub fn transforms(&self) -> Vec<Transform> {
+        self.layout
+            .positions()
+            .into_iter()
+            .take(self.len())
+            .enumerate()
+            .map(|(i, local)| {
+                let jitter = Vector3::new(self.random(i, 0), self.random(i, 1), self.random(i, 2))
+                    * self.jitter;
+                let rotation = match self.rotation {
+                    RotationRule::Fixed(rotation) => rotation,
+                    RotationRule::FaceCenter => {
+                        let yaw = Rad::atan2(-local.x, -local.z);
+                        Quaternion::from_angle_y(yaw)
+                    }
+                    RotationRule::TiltOutward(angle) if local.magnitude2() > 0.0 => {
+                        Quaternion::from_axis_angle(local.normalize(), angle)
+                    }
+                    RotationRule::TiltOutward(_) => Quaternion::one(),
+                    RotationRule::RandomYaw => {
+                        Quaternion::from_angle_y(Deg(180.0 * self.random(i, 3)))
+                    }
+                };
+                Transform::from_position_rotation(self.offset + local + jitter, rotation)
+            })
+            .collect()
+    }
+
+    //a repeatable number from -1 to 1 for an instance, salt picks one of several per instance
+    fn random(&self, index: usize, salt: u64) -> f32 {
+        //splitmix64's finalizer over the seed, index and salt
+        let mut x = self
+            .seed
+            .wrapping_add((index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .wrapping_add(salt.wrapping_mul(0xbf58_476d_1ce4_e5b9));
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^= x >> 31;
+        (x >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_grid_matches_the_demo_scene() {
+        let transforms = InstanceGrid::default().transforms();
+        assert_eq!(transforms.len(), 1000);
+        assert_eq!(transforms[0].position(), Vector3::new(-15.0, 0.5, -15.0));
+        //y is the innermost loop and has no spacing, so the first ten share a spot
+        assert_eq!(transforms[9].position(), transforms[0].position());
+        assert_eq!(transforms[10].position(), Vector3::new(-12.0, 0.5, -15.0));
+    }
+
+    #[test]
+    fn rings_and_spheres_sit_at_their_radius() {
+        let ring = InstanceGrid::ring(8, 4.0)
+            .rotation(RotationRule::FaceCenter)
+            .transforms();
+        for transform in &ring {
+            assert!((transform.position().magnitude() - 4.0).abs() < 1e-4);
+            let facing = transform.rotation().rotate_vector(Vector3::unit_z());
+            assert!((facing + transform.position() / 4.0).magnitude() < 1e-4);
+        }
+        let sphere = InstanceGrid::sphere(50, 2.0).transforms();
+        assert!(sphere
+            .iter()
+            .all(|transform| (transform.position().magnitude() - 2.0).abs() < 1e-4));
+    }
+
+    #[test]
+    fn jitter_is_bounded_and_repeatable() {
+        let grid = InstanceGrid::grid([4, 1, 4], [1.0; 3]).jitter(0.25).seed(7);
+        let a = grid.transforms();
+        assert_eq!(a, grid.transforms());
+        let plain = InstanceGrid::grid([4, 1, 4], [1.0; 3]).transforms();
+        for (jittered, plain) in a.iter().zip(&plain) {
+            let moved = jittered.position() - plain.position();
+            assert!(moved.x.abs() <= 0.25 && moved.y.abs() <= 0.25 && moved.z.abs() <= 0.25);
+        }
+        assert_ne!(a, grid.seed(8).transforms());
+    }
+
+    #[test]
+    fn cubes_hold_exactly_their_count() {
+        let cube = InstanceGrid::cube(10, 2.0);
+        assert_eq!(cube.len(), 10);
+        let transforms = cube.transforms();
+        assert_eq!(transforms.len(), 10);
+        //a full cube is centered, so its corners mirror each other
+        let full = InstanceGrid::cube(27, 2.0).transforms();
+        assert_eq!(full[0].position(), Vector3::new(-2.0, -2.0, -2.0));
+        assert_eq!(full[26].position(), Vector3::new(2.0, 2.0, 2.0));
+    }
+}
@@ -8,524 +8,4503 @@ use model::{DrawModel, Vertex};
 use std::borrow::Cow;
 use std::mem;
 use std::sync::Arc;
-use tokio::runtime::Runtime;
 use wgpu::util::DeviceExt;
 use winit::application::ApplicationHandler;
-use winit::event::WindowEvent;
+use winit::event::{ElementState, KeyEvent, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::keyboard::KeyCode;
-use winit::window::{Window, WindowId};
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
+use winit::window::{Icon, Window, WindowId};
 use crate::model::DrawLight;
-mod camera;
+pub use error::{EngineError, ShaderError};
+pub use resources::watcher::{FileWatcher, WatchId};
+pub use picking::InstanceId;
+pub use resources::ModelLoadOptions;
+pub use scene_control::SceneCommand;
+pub mod animation;
+pub mod camera;
+pub mod camera_bookmarks;
+#[cfg(feature = "accesskit")]
+pub mod accessibility;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod benchmark;
+pub mod bind_cache;
+pub mod capture;
+pub mod collision;
+pub mod compute;
+pub mod console;
+pub mod culling;
+pub mod custom_material;
+pub mod debug_lines;
+pub mod debug_view;
+pub mod draw_queue;
+pub mod editor;
+#[cfg(feature = "egui")]
+pub mod egui_layer;
+pub mod error;
+mod executor;
+pub mod features;
+pub mod frame_pacing;
+pub mod frame_stats;
+pub mod gpu_capture;
+pub mod gpu_timer;
+#[cfg(feature = "imgui")]
+pub mod imgui_layer;
+pub mod indirect;
+pub mod instance_grid;
+pub mod instance_buffer;
+pub mod logging;
+pub mod motion;
+pub mod pipeline_cache;
+pub mod profiler;
 mod camera_controller;
 mod model;
+pub mod object_uniforms;
+pub mod parallel_encode;
+pub mod picking;
+pub mod recorder;
+pub mod render_scale;
+pub mod render_target;
+pub mod scene_bundle;
+pub mod scene_control;
+pub mod scene_file;
+pub mod settings;
+pub mod shader_preprocessor;
+pub mod spatial_index;
+pub mod stencil;
+#[cfg(feature = "scripting")]
+pub mod scripting;
 mod resources;
-mod texture;
+pub mod texture;
+pub mod time_of_day;
+pub mod transform;
+pub mod ui_layer;
+pub mod uniform;
+mod upload;
+pub mod windowing;
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 struct LightUniform {
     position: [f32; 3],
-    _padding: u32,
+    //how much of the light's color reaches surfaces facing away from it, packed into the vec3's
+    //padding
+    ambient: f32,
     color: [f32; 3],
     _padding2: u32,
 }
 
 struct Instances {
-    position: cgmath::Vector3<f32>,
-    rotation: cgmath::Quaternion<f32>,
+    transform: transform::Transform,
+    //layer of the packed material array to sample, ignored unless materials are packed
+    layer: u32,
+    //rgba multiplied into the shaded color, white leaves the material as is
+    tint: [f32; 4],
 }
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 struct InstanceRaw {
     model: [[f32; 4]; 4],
     normal:[[f32; 3]; 3],
+    layer: u32,
+    tint: [f32; 4],
+}
+
+//the compact instance format, 48 bytes against InstanceRaw's 120. the vertex shader rebuilds the
+//transform from the quaternion, so it suits large crowds where upload bandwidth matters more
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CompactInstanceRaw {
+    position: [f32; 3],
+    scale: [f32; 3],
+    //quaternion as xyz then w
+    rotation: [f32; 4],
+    layer: u32,
+    //the tint packed to 8 bits a channel, so it can't go above one
+    tint: [u8; 4],
+}
+
+//how instances are laid out in the instance buffer
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum InstanceFormat {
+    //a model and normal matrix per instance
+    #[default]
+    Full,
+    //translation, scale and rotation, expanded in the vertex shader
+    Compact,
+}
+
+impl InstanceFormat {
+    fn desc(self) -> wgpu::VertexBufferLayout<'static> {
+        match self {
+            InstanceFormat::Full => InstanceRaw::desc(),
+            InstanceFormat::Compact => CompactInstanceRaw::desc(),
+        }
+    }
+}
+
+//the gpu copy of the instances in whichever format they are drawn with
+enum InstanceData {
+    Full(instance_buffer::InstanceBuffer<InstanceRaw>),
+    Compact(instance_buffer::InstanceBuffer<CompactInstanceRaw>),
+}
+
+impl InstanceData {
+    fn new(device: &wgpu::Device, format: InstanceFormat, instances: &[Instances]) -> Self {
+        profiling::scope!("build instances");
+        match format {
+            InstanceFormat::Full => InstanceData::Full(instance_buffer::InstanceBuffer::new(
+                device,
+                "Instance Buffer",
+                instances.iter().map(Instances::to_raw).collect(),
+            )),
+            InstanceFormat::Compact => InstanceData::Compact(instance_buffer::InstanceBuffer::new(
+                device,
+                "Compact Instance Buffer",
+                instances.iter().map(Instances::to_compact).collect(),
+            )),
+        }
+    }
+
+    fn format(&self) -> InstanceFormat {
+        match self {
+            InstanceData::Full(_) => InstanceFormat::Full,
+            InstanceData::Compact(_) => InstanceFormat::Compact,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            InstanceData::Full(buffer) => buffer.len(),
+            InstanceData::Compact(buffer) => buffer.len(),
+        }
+    }
+
+    fn stride(&self) -> wgpu::BufferAddress {
+        match self {
+            InstanceData::Full(_) => mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            InstanceData::Compact(_) => mem::size_of::<CompactInstanceRaw>() as wgpu::BufferAddress,
+        }
+    }
+
+    fn set(&mut self, index: usize, instance: &Instances) -> bool {
+        match self {
+            InstanceData::Full(buffer) => buffer.set(index, instance.to_raw()),
+            InstanceData::Compact(buffer) => buffer.set(index, instance.to_compact()),
+        }
+    }
+
+    fn push(&mut self, device: &wgpu::Device, instance: &Instances) {
+        match self {
+            InstanceData::Full(buffer) => {
+                buffer.push(device, instance.to_raw());
+            }
+            InstanceData::Compact(buffer) => {
+                buffer.push(device, instance.to_compact());
+            }
+        }
+    }
+
+    //swaps in a new set of instances, keeping the format
+    fn replace<'i>(&mut self, device: &wgpu::Device, instances: impl Iterator<Item = &'i Instances>) {
+        match self {
+            InstanceData::Full(buffer) => {
+                buffer.replace(device, instances.map(Instances::to_raw).collect())
+            }
+            InstanceData::Compact(buffer) => {
+                buffer.replace(device, instances.map(Instances::to_compact).collect())
+            }
+        }
+    }
+
+    fn slice(&self) -> wgpu::BufferSlice<'_> {
+        match self {
+            InstanceData::Full(buffer) => buffer.slice(),
+            InstanceData::Compact(buffer) => buffer.slice(),
+        }
+    }
+
+    fn buffer(&self) -> &wgpu::Buffer {
+        match self {
+            InstanceData::Full(buffer) => buffer.buffer(),
+            InstanceData::Compact(buffer) => buffer.buffer(),
+        }
+    }
+
+    fn upload(
+        &self,
+        uploader: &upload::Uploader,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+    ) {
+        match self {
+            InstanceData::Full(buffer) => buffer.upload(uploader, encoder, device),
+            InstanceData::Compact(buffer) => buffer.upload(uploader, encoder, device),
+        }
+    }
 }
 
+//frames between printed gpu timing summaries while profiling
+const GPU_REPORT_INTERVAL: u32 = 120;
+//frames between title bar refreshes when showing frame stats, setting the title every frame is
+//slow on some platforms
+const TITLE_STATS_INTERVAL: u32 = 30;
+//how long the number key hotkeys take to fly the camera to a bookmark
+const BOOKMARK_FLIGHT_SECONDS: f32 = 0.75;
+//how far past its warmup and measured time a headless benchmark may run before giving up
+const BENCHMARK_GRACE_SECONDS: f64 = 30.0;
+//render errors in a row that end a headless benchmark
+const BENCHMARK_MAX_FAILED_FRAMES: u32 = 30;
+//the longest step animations and motions take in one update, a stalled frame such as a window
+//drag would otherwise jump them forward
+const MAX_FRAME_DELTA: f32 = 0.1;
+const WINDOW_TITLE: &str = "wgpu winit 0.30";
+//the model drawn at startup unless App::set_model picks another
+const DEFAULT_MODEL: &str = "cube.obj";
+const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+
 #[derive(Default)]
 pub struct App<'a> {
     window: Option<Arc<Window>>,
     state: Option<GameState<'a>>,
+    modifiers: ModifiersState,
+    window_icon: Option<Icon>,
+    pending_cursor: Option<windowing::CursorRequest>,
+    //init runs as a task so the event loop isn't frozen while the gpu and assets load, the
+    //finished state is picked up in about_to_wait
+    init_task: Option<executor::InitTask<error::Result<GameState<'static>>>>,
+    errors: error::ErrorReporter,
+    exit_handler: Option<ExitHandler>,
+    exit_requested: bool,
+    monitor_selection: Option<windowing::MonitorSelection>,
+    frames_since_gpu_report: u32,
+    cpu_profiler: Option<profiler::CpuProfiler>,
+    stats_in_title: bool,
+    frames_since_title_update: u32,
+    transparent: bool,
+    //whether the depth buffer gets a stencil aspect
+    stencil: bool,
+    //ime input is only turned on while a text field in the overlay has focus
+    ime_allowed: bool,
+    //opened on first use, paused while suspended and closed on exit
+    #[cfg(feature = "audio")]
+    audio: Option<audio::Audio>,
+    //set once opening the audio device has failed so it isn't retried every call
+    #[cfg(feature = "audio")]
+    audio_unavailable: bool,
+    //handed to the state once init finishes so senders taken before then keep working
+    scene_channel: scene_control::SceneChannel,
+    //created with the window, before it is shown
+    #[cfg(feature = "accesskit")]
+    accessibility: Option<accessibility::Accessibility>,
+    //window size, fullscreen, vsync and key bindings, defaults unless load_settings was called
+    settings: settings::SettingsFile,
+    //loaded into the state once it exists
+    bookmarks_path: Option<std::path::PathBuf>,
+    //the instances the state is built with
+    instance_grid: instance_grid::InstanceGrid,
+    //started once the state exists, the app exits when it finishes
+    benchmark: Option<benchmark::BenchmarkConfig>,
+    //the model the state is built with, cube.obj unless set
+    model: Option<String>,
+    //the graphics apis to pick an adapter from, the platform's primary ones unless set
+    backends: Option<wgpu::Backends>,
+    //used over the settings file's without being saved to it
+    window_size: Option<winit::dpi::LogicalSize<f64>>,
+    vsync: Option<bool>,
+}
+
+//run once with the state right before it is torn down on exit
+pub type ExitHandler = Box<dyn for<'s> FnOnce(&mut GameState<'s>)>;
+
+impl App<'_> {
+    //sets the hook run on exit, whether from the window closing or request_exit, while the state
+    //and gpu resources are all still alive
+    pub fn on_exit(&mut self, handler: impl for<'s> FnOnce(&mut GameState<'s>) + 'static) {
+        self.exit_handler = Some(Box::new(handler));
+    }
+
+    //reads window and graphics settings from a toml file, writing the defaults there if it doesn't
+    //exist. changes made while running, like resizing or going fullscreen, are saved back to it.
+    //must be called before the app starts for the window settings to apply
+    pub fn load_settings(&mut self, path: impl Into<std::path::PathBuf>) -> anyhow::Result<()> {
+        self.settings = settings::SettingsFile::load(path)?;
+        if let Some(state) = self.state.as_mut() {
+            Self::apply_settings(state, &self.settings.settings, self.vsync);
+        }
+        Ok(())
+    }
+
+    pub fn settings(&self) -> &settings::Settings {
+        &self.settings.settings
+    }
+
+    //where camera bookmarks are read from and saved to, see GameState::load_camera_bookmarks
+    pub fn set_camera_bookmarks_path(&mut self, path: impl Into<std::path::PathBuf>) {
+        let path = path.into();
+        if let Some(state) = self.state.as_mut() {
+            if let Err(e) = state.load_camera_bookmarks(&path) {
+                tracing::error!("{:#}", e);
+            }
+        }
+        self.bookmarks_path = Some(path);
+    }
+
+    //the instances the scene starts with, the demo grid unless set before the app starts. a
+    //running scene is regenerated from it
+    pub fn set_instance_grid(&mut self, grid: instance_grid::InstanceGrid) {
+        if let Some(state) = self.state.as_mut() {
+            state.set_instance_grid(&grid);
+        }
+        self.instance_grid = grid;
+    }
+
+    //the model drawn at startup, in place of cube.obj. relative paths are looked up in the res
+    //folder, absolute ones are loaded from where they are. must be set before the app starts,
+    //GameState::load_model swaps the model of a running app
+    pub fn set_model(&mut self, path: impl Into<String>) {
+        self.model = Some(path.into());
+    }
+
+    //restricts the adapter to these graphics apis, e.g. wgpu::Backends::VULKAN. must be set
+    //before the app starts
+    pub fn set_backends(&mut self, backends: wgpu::Backends) {
+        self.backends = Some(backends);
+    }
+
+    //opens the window at this logical size instead of the one in the settings file, which is left
+    //alone. must be set before the app starts
+    pub fn set_window_size(&mut self, width: f64, height: f64) {
+        self.window_size = Some(winit::dpi::LogicalSize::new(width, height));
+    }
+
+    //turns vsync on or off over the settings file's choice, without saving it
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.vsync = Some(vsync);
+        if let Some(state) = self.state.as_mut() {
+            Self::apply_settings(state, &self.settings.settings, self.vsync);
+        }
+    }
+
+    //runs a benchmark as soon as the state exists, or straight away if it already does, and
+    //exits once the report is written. see GameState::start_benchmark
+    pub fn set_benchmark(&mut self, config: benchmark::BenchmarkConfig) {
+        match self.state.as_mut() {
+            Some(state) => state.start_benchmark(config),
+            None => self.benchmark = Some(config),
+        }
+    }
+
+    //the settings that live in the state, applied once it exists
+    fn apply_settings(state: &mut GameState, settings: &settings::Settings, vsync: Option<bool>) {
+        if !state.set_vsync(vsync.unwrap_or(settings.graphics.vsync)) {
+            tracing::warn!("the surface can't present without vsync");
+        }
+        state.set_fps_limit(settings.graphics.fps_limit);
+        state.set_render_scale(settings.graphics.render_scale);
+        state.set_key_bindings(&settings.bindings);
+    }
+
+    //asks the app to shut down at the end of the current pass through the event loop
+    pub fn request_exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    //runs the exit hook, flushes any recording and drops the state before the window so gpu
+    //resources are destroyed in a known order
+    fn shutdown(&mut self) {
+        if let Some(mut state) = self.state.take() {
+            if let Some(handler) = self.exit_handler.take() {
+                handler(&mut state);
+            }
+            if let Err(e) = state.stop_recording() {
+                self.errors.report(e);
+            }
+            drop(state);
+        }
+        if let Err(e) = self.settings.flush() {
+            tracing::error!("{:#}", e);
+        }
+        #[cfg(feature = "audio")]
+        {
+            self.audio = None;
+        }
+        if let Some(profiler) = self.cpu_profiler.take() {
+            match profiler.finish() {
+                Ok(Some(path)) => tracing::info!("cpu profile written to {}", path.display()),
+                Ok(None) => (),
+                Err(e) => tracing::error!("failed to write cpu profile: {}", e),
+            }
+        }
+        self.window = None;
+    }
+
+    //one frame, always in the same order: wait out the frame limiter, step the simulation once,
+    //render what it produced, then report on the finished frame. nothing is simulated after the
+    //render, so camera movement and buffer writes happen exactly once per presented frame
+    fn redraw(&mut self, event_loop: &ActiveEventLoop, window: &Window) {
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+        //nothing to draw to until resumed hands us a new surface, resumed will request the next
+        //redraw. the same goes for a minimized window, restoring it requests one
+        if state.is_suspended() || state.is_minimized() {
+            return;
+        }
+        state.frame_limiter.wait();
+        state.update();
+        let ime_area = state.overlay_ime_area();
+        if ime_area.is_some() != self.ime_allowed {
+            self.ime_allowed = ime_area.is_some();
+            window.set_ime_allowed(self.ime_allowed);
+        }
+        if let Some((position, size)) = ime_area {
+            window.set_ime_cursor_area(position, size);
+        }
+        #[cfg(feature = "accesskit")]
+        if let Some(accessibility) = self.accessibility.as_mut() {
+            state.update_accessibility(accessibility);
+        }
+        #[cfg(feature = "audio")]
+        if let Some(audio) = self.audio.as_mut() {
+            state.update_audio(audio);
+        }
+        if let Some(profiler) = self.cpu_profiler.as_ref() {
+            profiler.finish_frame();
+        }
+        match state.render() {
+            Ok(_) => self.after_frame(window),
+            //the surface needs reconfiguring, outdated is common mid resize on some platforms
+            Err(e @ (wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated)) => {
+                tracing::debug!("reconfiguring the surface: {}", e);
+                let size = state.size;
+                state.resize(size);
+            }
+            //the gpu took too long to hand us a texture, just skip this frame
+            Err(wgpu::SurfaceError::Timeout) => tracing::debug!("surface texture timed out"),
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                self.errors.report(wgpu::SurfaceError::OutOfMemory.into());
+                event_loop.exit();
+            }
+            Err(e) => self.errors.report(e.into()),
+        }
+        window.request_redraw();
+    }
+
+    //periodic reporting once a frame has been presented
+    fn after_frame(&mut self, window: &Window) {
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+        if state.take_benchmark_report().is_some() {
+            self.exit_requested = true;
+        }
+        self.frames_since_gpu_report += 1;
+        if self.frames_since_gpu_report >= GPU_REPORT_INTERVAL {
+            self.frames_since_gpu_report = 0;
+            if let Some(summary) = state.gpu_timing_summary() {
+                tracing::info!("{}", summary);
+            }
+        }
+        if self.stats_in_title {
+            self.frames_since_title_update += 1;
+            if self.frames_since_title_update >= TITLE_STATS_INTERVAL {
+                self.frames_since_title_update = 0;
+                window.set_title(&format!("{} | {}", WINDOW_TITLE, state.frame_stats()));
+            }
+        }
+    }
+
+    //the audio subsystem, opening the output device the first time. None if there is no usable
+    //device, which is reported once
+    #[cfg(feature = "audio")]
+    pub fn audio(&mut self) -> Option<&mut audio::Audio> {
+        if self.audio.is_none() && !self.audio_unavailable {
+            match audio::Audio::new() {
+                Ok(audio) => self.audio = Some(audio),
+                Err(e) => {
+                    self.audio_unavailable = true;
+                    self.errors.report(EngineError::Audio(e));
+                }
+            }
+        }
+        self.audio.as_mut()
+    }
+
+    //creates the window with a transparent background and composites the scene over the desktop
+    //through a non opaque surface alpha mode. must be set before the app starts
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.transparent = transparent;
+    }
+
+    //gives the depth buffer a stencil aspect for masking effects, Depth32FloatStencil8 where the
+    //device supports it and Depth24PlusStencil8 otherwise. must be set before the app starts, the
+    //graphics.stencil setting turns it on too
+    pub fn set_stencil(&mut self, stencil: bool) {
+        self.stencil = stencil;
+    }
+
+    //appends the frame stats to the window title, refreshed every few frames
+    pub fn show_stats_in_title(&mut self, enabled: bool) {
+        self.stats_in_title = enabled;
+        if !enabled {
+            if let Some(window) = self.window.as_ref() {
+                window.set_title(WINDOW_TITLE);
+            }
+        }
+    }
+
+    //records cpu profiling scopes until the app exits, when they are written next to output.
+    //needs one of the profile-puffin, profile-tracing or profile-tracy features
+    pub fn start_cpu_profiling(&mut self, output: impl Into<std::path::PathBuf>) -> anyhow::Result<()> {
+        self.cpu_profiler = Some(profiler::CpuProfiler::start(output)?);
+        Ok(())
+    }
+
+    //a sender for driving the scene from other threads, see SceneCommand. it can be taken before
+    //the state exists, commands sent early are applied in the first update
+    pub fn scene_sender(&self) -> std::sync::mpsc::Sender<SceneCommand> {
+        match self.state.as_ref() {
+            Some(state) => state.scene_sender(),
+            None => self.scene_channel.sender(),
+        }
+    }
+
+    //errors that would otherwise crash the app are passed here, without a handler they are
+    //logged through tracing
+    pub fn set_error_handler(&mut self, handler: impl FnMut(&EngineError) + 'static) {
+        self.errors.set_handler(handler);
+    }
+
+    //sets the window icon, applied straight away if the window already exists
+    pub fn set_window_icon(&mut self, icon: Option<Icon>) {
+        if let Some(window) = self.window.as_ref() {
+            window.set_window_icon(icon.clone());
+        }
+        self.window_icon = icon;
+    }
+
+    //lists the connected monitors and their video modes, empty until the window exists
+    pub fn monitors(&self) -> Vec<windowing::MonitorInfo> {
+        self.window
+            .as_ref()
+            .map(|window| windowing::list_monitors(window))
+            .unwrap_or_default()
+    }
+
+    //puts the window on the chosen monitor with the chosen mode. applied straight away if the
+    //window exists, otherwise when it is created. the video mode is also used by Alt+Enter
+    pub fn select_monitor(&mut self, selection: windowing::MonitorSelection) {
+        if let Some(window) = self.window.as_ref() {
+            //the surface follows in the Resized event the mode change sends
+            if !windowing::apply_monitor_selection(window, &selection) {
+                tracing::warn!("no monitor at index {}", selection.monitor_index);
+            }
+        }
+        self.monitor_selection = Some(selection);
+    }
+
+    //queues a cursor change which is applied on the next pass through the event loop
+    pub fn set_cursor(&mut self, cursor: impl Into<windowing::CursorRequest>) {
+        self.pending_cursor = Some(cursor.into());
+    }
+
+    //moves the state built by the init task into the app once it has finished
+    fn poll_pending_state(&mut self, event_loop: &ActiveEventLoop) {
+        if self.state.is_some() {
+            return;
+        }
+        let pending = self
+            .init_task
+            .as_mut()
+            .and_then(|task| task.try_take())
+            .map(|result| result.map_err(EngineError::from).and_then(|state| state));
+        if pending.is_some() {
+            self.init_task = None;
+        }
+        match pending {
+            Some(Ok(mut state)) => {
+                state.scene_channel = std::mem::take(&mut self.scene_channel);
+                //the console was given a sender for the channel the state was built with
+                state.console.set_sender(state.scene_channel.sender());
+                Self::apply_settings(&mut state, &self.settings.settings, self.vsync);
+                if let Some(path) = self.bookmarks_path.as_ref() {
+                    if let Err(e) = state.load_camera_bookmarks(path) {
+                        tracing::error!("{:#}", e);
+                    }
+                }
+                //after the settings, it turns vsync and the fps limit off
+                if let Some(config) = self.benchmark.take() {
+                    state.start_benchmark(config);
+                }
+                self.state = Some(state);
+                if let Some(window) = self.window.as_ref() {
+                    window.request_redraw();
+                }
+            }
+            Some(Err(e)) => {
+                self.errors.report(e);
+                event_loop.exit();
+            }
+            None => (),
+        }
+    }
+
+    fn apply_pending_cursor(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(window) = self.window.as_ref() {
+            if let Some(request) = self.pending_cursor.take() {
+                window.set_cursor(request.into_cursor(event_loop));
+            }
+        }
+    }
 }
 
-struct GameState<'a> {
-    surface: wgpu::Surface<'a>,
+pub struct GameState<'a> {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    //the surface is dropped while suspended as platforms like android destroy the native window
+    surface: Option<wgpu::Surface<'a>>,
+    //set for headless states, rendered into instead of a surface texture
+    offscreen_target: Option<texture::Texture>,
+    //render textures kept at the surface size, e.g. for post processing
+    surface_textures: Vec<texture::RenderTexture>,
     device: wgpu::Device,
     queue: wgpu::Queue,
+    //which of the optional features the device ended up with
+    device_features: features::DeviceFeatures,
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
-    render_pipeline: wgpu::RenderPipeline,
-    light_render_pipeline: wgpu::RenderPipeline,
+    render_pipeline: Arc<wgpu::RenderPipeline>,
+    light_render_pipeline: Arc<wgpu::RenderPipeline>,
+    debug_line_pipeline: Arc<wgpu::RenderPipeline>,
     depth_texture: texture::Texture,
+    //the scene is drawn at the window size times this, see set_render_scale
+    render_scale: f32,
+    //where the scene is drawn when the scale isn't 1, filtered onto the surface afterwards
+    render_scaler: Option<render_scale::RenderScaler>,
+    depth_format: wgpu::TextureFormat,
+    //the regions the scene is clipped to and the pass that marks them, see set_scene_mask
+    scene_mask: Option<(stencil::SceneMask, stencil::StencilMasker)>,
     camera: camera::Camera,
     camera_uniform: camera::CameraUniform,
-    camera_buffer: wgpu::Buffer,
-    camera_bind_group: wgpu::BindGroup,
+    camera_buffer: uniform::UniformBuffer<camera::CameraUniform>,
     camera_controller: camera_controller::CameraController,
     light_uniform: LightUniform,
-    light_buffer: wgpu::Buffer,
-    light_bind_group: wgpu::BindGroup,
+    light_buffer: uniform::UniformBuffer<LightUniform>,
     instances: Vec<Instances>,
-    instance_buffer: wgpu::Buffer,
+    //instances whose transform changed since the last frame, uploaded before it is drawn
+    dirty_instances: Vec<usize>,
+    //each instance's world space box, for culling, picking and gameplay queries
+    spatial_index: spatial_index::SpatialIndex,
+    //the model's collision shape and the kind it was generated as, rebuilt with the model
+    collider: Option<(collision::ColliderKind, collision::Collider)>,
+    instance_buffer: InstanceData,
+    //instances beyond this distance from the camera aren't drawn
+    max_draw_distance: Option<f32>,
+    //the instances within the draw distance, rebuilt every update. only used when a distance is
+    //set and gpu culling, which applies the distance itself, is off
+    distance_culled: Option<InstanceData>,
     obj_model: model::Model,
+    default_material: model::Material,
+    //the magenta checker drawn in place of a custom material whose shader failed
+    error_material: model::Material,
+    recorder: Option<recorder::FrameRecorder>,
+    exit_requested: bool,
+    frame_limiter: frame_pacing::FrameLimiter,
+    //the window has no area, e.g. while minimized, so there is no surface size to render at
+    minimized: bool,
+    //the compositor reported the window as fully hidden
+    occluded: bool,
+    //ratio of physical to logical pixels for the monitor the window is on, for ui and text
+    scale_factor: f64,
+    uploader: upload::Uploader,
+    //one command per mesh of the model, set when drawing through the indirect path
+    indirect_draws: Option<indirect::IndirectBuffer>,
+    //the model's meshes in shared buffers, made alongside the commands when the device can multi
+    //draw so each run of meshes sharing a material is one call
+    merged_meshes: Option<indirect::MergedMeshes>,
+    //compacts the visible instances on the gpu each frame, only used with indirect drawing
+    culler: Option<culling::GpuCuller>,
+    //user compute dispatches, run at the start of every frame
+    compute_passes: compute::ComputePasses,
+    //materials with their own shaders, drawn in place of some of the model's materials
+    custom_materials: custom_material::CustomMaterials,
+    //what the scene shaders output, the lit model unless a debug view is picked
+    debug_view: debug_view::DebugView,
+    //layouts, bind groups and textures shared between materials
+    binding_cache: bind_cache::BindingCache,
+    texture_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    pipeline_cache: pipeline_cache::PipelineCache,
+    //holds the includes the built in shaders loaded, for shaders registered later
+    shader_preprocessor: shader_preprocessor::ShaderPreprocessor,
+    objects: object_uniforms::ObjectUniforms,
+    object_pipeline: Arc<wgpu::RenderPipeline>,
+    //state changes made by the last scene pass
+    draw_stats: std::cell::Cell<draw_queue::DrawStats>,
+    //per pass gpu timings, only present while profiling
+    gpu_timer: Option<gpu_timer::GpuTimer>,
+    //the run in progress and the report of the last one to finish
+    benchmark: Option<benchmark::Benchmark>,
+    benchmark_report: Option<benchmark::BenchmarkReport>,
+    //renderdoc frame captures, does nothing unless renderdoc is attached
+    gpu_capture: gpu_capture::GpuCapture,
+    frame_stats: frame_stats::FrameStatsTracker,
+    material_array_layout: Arc<wgpu::BindGroupLayout>,
+    //every material's texture packed into layers, with the pipeline that samples it
+    material_array: Option<(model::Material, Arc<wgpu::RenderPipeline>)>,
+    //the light and model draws recorded once and replayed each frame
+    scene_bundles: std::cell::RefCell<scene_bundle::SceneBundleCache>,
+    //debug ui painted over the scene, only present with the egui or imgui feature. borrowed
+    //mutably while a frame is encoded
+    overlay: std::cell::RefCell<Option<Box<dyn ui_layer::UiLayer>>>,
+    #[cfg(feature = "scripting")]
+    scripts: scripting::ScriptHost,
+    //commands from other threads, applied at the start of each update
+    scene_channel: scene_control::SceneChannel,
+    //shared by anything reloading from disk, polled at the start of update
+    file_watcher: resources::watcher::FileWatcher,
+    //uploaded while the scene pass is encoded and cleared once the frame is rendered
+    debug_lines: std::cell::RefCell<debug_lines::DebugLines>,
+    //draws every instance's collision bounds as wireframe boxes each update
+    collider_debug: bool,
+    //pauses the simulation and lets instances be picked and moved with gizmos
+    editor: editor::Editor,
+    //the last cursor position, what editor clicks cast their ray through
+    cursor: winit::dpi::PhysicalPosition<f64>,
+    //where edits made in the editor are saved, set by load_scene or set_scene_path
+    scene_path: Option<std::path::PathBuf>,
+    //the drop down console, its commands are sent through scene_channel
+    console: console::Console,
+    //draws the scene pipelines with line polygons, only possible with POLYGON_MODE_LINE
+    wireframe: bool,
+    //the file the drawn model was loaded from, the only one console spawns can name
+    model_name: String,
+    //what opens and closes the console, from the key bindings
+    console_keys: Vec<KeyCode>,
+    //named camera poses, written to bookmarks_path whenever one is saved
+    camera_bookmarks: camera_bookmarks::CameraBookmarks,
+    bookmarks_path: Option<std::path::PathBuf>,
+    //a move to a bookmark in progress, it is dropped as soon as the camera is steered by hand
+    camera_flight: Option<camera_bookmarks::CameraFlight>,
+    //animates the light and sky through a day when set
+    time_of_day: Option<time_of_day::TimeOfDay>,
+    sky_color: wgpu::Color,
+    //tweens started with animate, ticked each update while the simulation runs
+    animator: animation::Animator,
+    //spins, orbits, bobs and callbacks attached to instances, run each update after animations
+    motions: motion::Motions,
+    //when the last update ran and the seconds since the one before it
+    last_update: Option<f64>,
+    frame_delta: f32,
+}
+
+//the gpu objects created before the rest of the state, handed to GameState::build
+struct GpuContext {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    device_features: features::DeviceFeatures,
+}
+
+//the cofactor of a rotation and scale, which keeps normals perpendicular to the stretched
+//surface. it is the inverse transpose times the determinant, so it stays finite when an axis is
+//flattened to zero, and the shaders normalize away the length it leaves
+fn normal_matrix(
+    rotation: cgmath::Quaternion<f32>,
+    scale: cgmath::Vector3<f32>,
+) -> cgmath::Matrix3<f32> {
+    let s = scale;
+    cgmath::Matrix3::from(rotation)
+        * cgmath::Matrix3::from_diagonal(cgmath::vec3(s.y * s.z, s.x * s.z, s.x * s.y))
 }
 
 impl Instances {
+    //an instance whose transform goes into the buffer it is created for, so it starts clean
+    fn new(mut transform: transform::Transform, layer: u32, tint: [f32; 4]) -> Self {
+        transform.take_dirty();
+        Self {
+            transform,
+            layer,
+            tint,
+        }
+    }
+
     fn to_raw(&self) -> InstanceRaw {
         InstanceRaw {
-            model: (cgmath::Matrix4::from_translation(self.position)
-                * cgmath::Matrix4::from(self.rotation))
-            .into(),
-            normal: cgmath::Matrix3::from(self.rotation).into(),
+            model: self.transform.matrix().into(),
+            normal: self.transform.normal_matrix().into(),
+            layer: self.layer,
+            tint: self.tint,
+        }
+    }
+
+    fn to_compact(&self) -> CompactInstanceRaw {
+        let rotation = self.transform.rotation();
+        CompactInstanceRaw {
+            position: self.transform.position().into(),
+            scale: self.transform.scale().into(),
+            rotation: [rotation.v.x, rotation.v.y, rotation.v.z, rotation.s],
+            layer: self.layer,
+            tint: self.tint.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8),
         }
     }
 }
 impl<'a> GameState<'a> {
-    async fn new(window: Arc<Window>) -> GameState<'a> {
+    //None picks from the platform's usual backends
+    fn create_instance(backends: Option<wgpu::Backends>) -> wgpu::Instance {
+        //browsers without webgpu only give us webgl2 so the web build uses the GL backend
+        #[cfg(not(target_arch = "wasm32"))]
+        let default = wgpu::Backends::PRIMARY;
+        #[cfg(target_arch = "wasm32")]
+        let default = wgpu::Backends::GL;
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: backends.unwrap_or(default),
+            ..Default::default()
+        })
+    }
+
+    //asks for every requested feature the adapter has, the returned flags say which made it
+    async fn request_device(
+        adapter: &wgpu::Adapter,
+        requested_features: wgpu::Features,
+    ) -> error::Result<(wgpu::Device, wgpu::Queue, features::DeviceFeatures)> {
+        let device_features = features::DeviceFeatures::negotiate(adapter, requested_features);
+        if !device_features.missing.is_empty() {
+            tracing::warn!("adapter is missing optional features: {:?}", device_features.missing);
+        }
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("Device"),
+                    required_features: device_features.enabled,
+                    required_limits: features::negotiate_limits(adapter),
+                },
+                None,
+            )
+            .await?;
+        Ok((device, queue, device_features))
+    }
+
+    #[tracing::instrument(name = "init", skip_all)]
+    async fn new(
+        window: Arc<Window>,
+        transparent: bool,
+        stencil: bool,
+        grid: instance_grid::InstanceGrid,
+        model: String,
+        backends: Option<wgpu::Backends>,
+    ) -> error::Result<GameState<'a>> {
         //define window size
         let size = window.inner_size();
         //create a WGPU instance
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
-            ..Default::default()
-        });
+        let instance = Self::create_instance(backends);
         //use our instance to create a surface for wgpu to display to
-        let surface = instance
-            .create_surface(Arc::clone(&window))
-            .expect("Failed to initialised surface");
+        let surface = instance.create_surface(Arc::clone(&window))?;
         //create an adapter to the physical graphics device
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 ..Default::default()
             })
             .await
-            .expect("Failed to get adapter");
+            .ok_or(EngineError::NoAdapter)?;
+        let info = adapter.get_info();
+        tracing::info!(adapter = %info.name, backend = ?info.backend, "picked a graphics adapter");
         //return the graphics device and command queue for the device.
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default(), None)
-            .await
-            .expect("Failed to load device");
+        let (device, queue, device_features) =
+            Self::request_device(&adapter, features::REQUESTED_FEATURES).await?;
         //returns the config for the adaptor in interact with the surface
-        let config = surface
+        let mut config = surface
             .get_default_config(&adapter, size.width, size.height)
-            .unwrap();
+            .ok_or(EngineError::UnsupportedSurface)?;
+        let capabilities = surface.get_capabilities(&adapter);
+        //the default isn't always srgb, which leaves the scene washed out on some machines. every
+        //pipeline and pass is built from config.format so they all agree on the choice
+        config.format = Self::preferred_surface_format(&capabilities.formats, config.format);
+        if transparent {
+            config.alpha_mode = Self::transparent_alpha_mode(&capabilities.alpha_modes);
+        }
+        //allow copying out of the surface texture for screenshots where the platform supports it
+        if capabilities.usages.contains(wgpu::TextureUsages::COPY_SRC) {
+            config.usage |= wgpu::TextureUsages::COPY_SRC;
+        }
         //initializes the surface for configuration
         surface.configure(&device, &config);
+        //put something on screen straight away, it stays up while the assets load
+        Self::present_splash(&surface, &device, &queue);
+
+        let gpu = GpuContext {
+            instance,
+            adapter,
+            device,
+            queue,
+            device_features,
+        };
+        let mut state =
+            Self::build(gpu, Some(surface), None, config, stencil, &grid, &model).await?;
+        if transparent && state.config.alpha_mode == wgpu::CompositeAlphaMode::Opaque {
+            tracing::warn!("the surface doesn't support transparency, the window will be opaque");
+        }
+        state.scale_factor = window.scale_factor();
+        Ok(state)
+    }
+
+    //the first srgb format the surface supports, the shaders output linear colour and rely on
+    //the target to encode it. falls back to the default when there isn't one, e.g. on webgl
+    fn preferred_surface_format(
+        formats: &[wgpu::TextureFormat],
+        fallback: wgpu::TextureFormat,
+    ) -> wgpu::TextureFormat {
+        match formats.iter().copied().find(wgpu::TextureFormat::is_srgb) {
+            Some(format) => format,
+            None => {
+                tracing::warn!(
+                    "no srgb surface format available, colours will look washed out in {:?}",
+                    fallback
+                );
+                fallback
+            }
+        }
+    }
+
+    //the first mode that lets the desktop show through, the shaders write premultiplied colour so
+    //that mode is preferred
+    fn transparent_alpha_mode(modes: &[wgpu::CompositeAlphaMode]) -> wgpu::CompositeAlphaMode {
+        [
+            wgpu::CompositeAlphaMode::PreMultiplied,
+            wgpu::CompositeAlphaMode::PostMultiplied,
+            wgpu::CompositeAlphaMode::Inherit,
+        ]
+        .into_iter()
+        .find(|mode| modes.contains(mode))
+        .unwrap_or(wgpu::CompositeAlphaMode::Opaque)
+    }
+
+    //switches how the surface is composited with whatever is behind the window. returns false,
+    //leaving the mode alone, if the surface doesn't support it
+    pub fn set_alpha_mode(&mut self, alpha_mode: wgpu::CompositeAlphaMode) -> bool {
+        let Some(surface) = self.surface.as_ref() else {
+            return false;
+        };
+        if !surface
+            .get_capabilities(&self.adapter)
+            .alpha_modes
+            .contains(&alpha_mode)
+        {
+            return false;
+        }
+        self.config.alpha_mode = alpha_mode;
+        surface.configure(&self.device, &self.config);
+        true
+    }
+
+    pub fn alpha_mode(&self) -> wgpu::CompositeAlphaMode {
+        self.config.alpha_mode
+    }
+
+    //vsync presents with fifo, without it mailbox or immediate are used where the surface has
+    //them. returns false, leaving the present mode alone, if neither is supported
+    pub fn set_vsync(&mut self, vsync: bool) -> bool {
+        let Some(surface) = self.surface.as_ref() else {
+            return false;
+        };
+        let present_mode = if vsync {
+            Some(wgpu::PresentMode::Fifo)
+        } else {
+            let modes = surface.get_capabilities(&self.adapter).present_modes;
+            [wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate]
+                .into_iter()
+                .find(|mode| modes.contains(mode))
+        };
+        let Some(present_mode) = present_mode else {
+            return false;
+        };
+        if present_mode != self.config.present_mode {
+            self.config.present_mode = present_mode;
+            surface.configure(&self.device, &self.config);
+        }
+        true
+    }
+
+    pub fn is_vsync(&self) -> bool {
+        matches!(
+            self.config.present_mode,
+            wgpu::PresentMode::Fifo | wgpu::PresentMode::FifoRelaxed | wgpu::PresentMode::AutoVsync
+        )
+    }
+
+    //the keys for moving the camera and opening the console, App handles the rest of the bindings
+    pub fn set_key_bindings(&mut self, bindings: &settings::KeyBindings) {
+        self.camera_controller.set_bindings(bindings);
+        self.console_keys = bindings.console.clone();
+    }
+
+    //the scene is cleared to a solid background unless the window is see through
+    fn clear_color(&self) -> wgpu::Color {
+        match self.config.alpha_mode {
+            wgpu::CompositeAlphaMode::Opaque | wgpu::CompositeAlphaMode::Auto => self.sky_color,
+            _ => wgpu::Color::TRANSPARENT,
+        }
+    }
+
+    //the depth format every scene pipeline and pass uses, picked from what the adapter supports
+    pub fn depth_format(&self) -> wgpu::TextureFormat {
+        self.depth_format
+    }
+
+    pub fn has_stencil(&self) -> bool {
+        self.depth_format.has_stencil_aspect()
+    }
+
+    //clips the light, model and per object draws to rectangles of the window, or cuts the
+    //rectangles out of them, through the stencil buffer, e.g. a portal's window or a ui panel's
+    //clip region. debug lines draw over the mask. returns false, leaving the scene as it was,
+    //without a stencil aspect or if the masked pipelines fail to build
+    pub fn set_scene_mask(&mut self, mask: Option<stencil::SceneMask>) -> bool {
+        if mask.is_some() && !self.has_stencil() {
+            return false;
+        }
+        let mode = mask.as_ref().map(|mask| mask.mode);
+        let previous = self.scene_mask.take();
+        let previous_mode = previous.as_ref().map(|(mask, _)| mask.mode);
+        self.scene_mask = match (mask, previous) {
+            (Some(mask), Some((_, mut masker))) => {
+                masker.set_rects(&self.device, &mask.rects);
+                Some((mask, masker))
+            }
+            (Some(mask), None) => {
+                let masker = stencil::StencilMasker::new(
+                    &self.device,
+                    self.config.format,
+                    self.depth_format,
+                    &mask.rects,
+                );
+                Some((mask, masker))
+            }
+            (None, _) => None,
+        };
+        //moving the rectangles only changes what the masker draws, the pipelines' stencil test
+        //depends on the mode alone
+        if mode != previous_mode {
+            if let Err(e) = self.set_scene_pipelines(self.instance_buffer.format()) {
+                tracing::error!("{}", e);
+                self.scene_mask = None;
+                if let Err(e) = self.set_scene_pipelines(self.instance_buffer.format()) {
+                    tracing::error!("{}", e);
+                }
+                return false;
+            }
+            self.scene_bundles.get_mut().invalidate();
+        }
+        true
+    }
+
+    pub fn scene_mask(&self) -> Option<&stencil::SceneMask> {
+        self.scene_mask.as_ref().map(|(mask, _)| mask)
+    }
+
+    //the colour format every scene pipeline and pass targets
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    //presents a single frame cleared to the background colour
+    fn present_splash(surface: &wgpu::Surface<'_>, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let Ok(output) = surface.get_current_texture() else {
+            return;
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Splash Encoder"),
+        });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Splash Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(CLEAR_COLOR),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        queue.submit(Some(encoder.finish()));
+        output.present();
+    }
+
+    //creates a state with no window or surface, frames are rendered into an offscreen texture
+    //which can be read back, e.g. for thumbnail generation or automated tests
+    pub async fn new_headless(width: u32, height: u32) -> error::Result<GameState<'static>> {
+        Self::new_headless_with_stencil(width, height, false).await
+    }
+
+    //new_headless with a choice of a stencil aspect on the depth buffer, see App::set_stencil
+    pub async fn new_headless_with_stencil(
+        width: u32,
+        height: u32,
+        stencil: bool,
+    ) -> error::Result<GameState<'static>> {
+        let instance = Self::create_instance(None);
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                ..Default::default()
+            })
+            .await
+            .ok_or(EngineError::NoAdapter)?;
+        let (device, queue, device_features) =
+            GameState::request_device(&adapter, features::REQUESTED_FEATURES).await?;
+        //there is no surface to ask for a config so describe the offscreen target ourselves
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+        let offscreen_target =
+            texture::Texture::create_offscreen_texture(&device, &config, "offscreen_target");
+
+        let gpu = GpuContext {
+            instance,
+            adapter,
+            device,
+            queue,
+            device_features,
+        };
+        GameState::build(
+            gpu,
+            None,
+            Some(offscreen_target),
+            config,
+            stencil,
+            &instance_grid::InstanceGrid::default(),
+            DEFAULT_MODEL,
+        )
+        .await
+    }
+
+    //everything past device and surface creation is shared between windowed and headless states
+    async fn build(
+        gpu: GpuContext,
+        surface: Option<wgpu::Surface<'a>>,
+        offscreen_target: Option<texture::Texture>,
+        config: wgpu::SurfaceConfiguration,
+        stencil: bool,
+        grid: &instance_grid::InstanceGrid,
+        model: &str,
+    ) -> error::Result<GameState<'a>> {
+        let GpuContext {
+            instance,
+            adapter,
+            device,
+            queue,
+            device_features,
+        } = gpu;
+        let size = winit::dpi::PhysicalSize::new(config.width, config.height);
+
+        //the grid of instances the scene starts with, see instance_grid::InstanceGrid
+        let instances = grid
+            .transforms()
+            .into_iter()
+            .map(|transform| Instances::new(transform, 0, [1.0; 4]))
+            .collect::<Vec<_>>();
+        //takes our instance position and rotation to turn into a matrix4X4 so it can be read by the shader
+        //puts the instance into the buffer, which can be updated and grown after creation
+        let instance_buffer = InstanceData::new(&device, InstanceFormat::Full, &instances);
+        //define the layout of our bind group for our textures
+        let mut binding_cache = bind_cache::BindingCache::new();
+        let texture_bind_group_layout = binding_cache.layout(
+            &device,
+            "texture_bind_group_layout",
+            &texture::Texture::layout_entries(
+                wgpu::ShaderStages::FRAGMENT,
+                wgpu::TextureViewDimension::D2,
+            ),
+        );
+        //the best depth format the adapter can render to, everything drawn with depth uses it
+        let depth_format = features::choose_depth_format(&adapter, &device_features, stencil);
+        //create our depth texture which will amend texel displayed based on depth rather than CW or CCW
+        let depth_texture =
+            texture::Texture::create_depth_texture(&device, &config, depth_format, "depth_texture");
+        //loading in our model and the associated texture
+        let obj_model =
+            resources::load_model(
+                model,
+                &device,
+                &queue,
+                &texture_bind_group_layout,
+                &mut binding_cache,
+                &resources::ModelLoadOptions::default(),
+            )
+                .await
+                .map_err(EngineError::Asset)?;
+        //drawn with when a mesh's material index doesn't exist in its model
+        let default_material = resources::default_material(
+            &device,
+            &queue,
+            &texture_bind_group_layout,
+            &mut binding_cache,
+        )
+        .map_err(EngineError::Asset)?;
+        let error_material = resources::error_material(
+            &device,
+            &queue,
+            &texture_bind_group_layout,
+            &mut binding_cache,
+        )
+        .map_err(EngineError::Asset)?;
+
+        //create our camera controller and send it to the buffer
+        let camera_controller = camera_controller::CameraController::new();
+        let mut camera = camera::Camera::new(size.width as f32, size.height as f32);
+        let mut camera_uniform = camera::CameraUniform::new();
+        //the camera and light uniforms, each with its own bind group
+        let camera_buffer = uniform::UniformBuffer::new(
+            &device,
+            "Camera Buffer",
+            &camera_uniform,
+            wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+        );
+        let camera_bind_group_layout = camera_buffer.layout();
+        let light_uniform = LightUniform {
+            position: [2.0, 2.0, 2.0],
+            ambient: 0.1,
+            color: [1.0, 1.0, 1.0],
+            _padding2: 0,
+        };
+        let light_buffer = uniform::UniformBuffer::new(
+            &device,
+            "Light Buffer",
+            &light_uniform,
+            wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+        );
+        let light_bind_group_layout = light_buffer.layout();
+
+        //define the render pipeline layout. which will need our bind group layouts that are needed to be
+        //rendered
+        //objects drawn with their own uniforms instead of instance data, picked by dynamic offset
+        let objects = object_uniforms::ObjectUniforms::new(&device);
+        let object_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Object Pipeline Layout"),
+            bind_group_layouts: &[
+                &texture_bind_group_layout,
+                camera_bind_group_layout,
+                light_bind_group_layout,
+                objects.layout(),
+            ],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline_layout =
+            Arc::new(device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[&texture_bind_group_layout, camera_bind_group_layout, light_bind_group_layout,],
+                push_constant_ranges: &[],
+            }));
+        //pipelines come from the cache so variants of the same shaders are only built once
+        let mut pipeline_cache = pipeline_cache::PipelineCache::new();
+        //the built in shaders share their structs through res/shaders/common.wgsl
+        let mut shader_preprocessor = shader_preprocessor::ShaderPreprocessor::new();
+        //kept in so debug view variants are expanded without waiting on a load
+        shader_preprocessor.insert_file("debug_view.wgsl", include_str!("debug_view.wgsl"));
+        shader_preprocessor
+            .register(
+                &mut pipeline_cache,
+                &device,
+                "Normal",
+                "shader.wgsl",
+                include_str!("shader.wgsl"),
+                render_pipeline_layout.clone(),
+            )
+            .await?;
+        shader_preprocessor
+            .register(
+                &mut pipeline_cache,
+                &device,
+                "NormalCompact",
+                "shader_compact.wgsl",
+                include_str!("shader_compact.wgsl"),
+                render_pipeline_layout,
+            )
+            .await?;
+        shader_preprocessor
+            .register(
+                &mut pipeline_cache,
+                &device,
+                "Light",
+                "light.wgsl",
+                include_str!("light.wgsl"),
+                Arc::new(device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Light Pipeline Layout"),
+                    bind_group_layouts: &[camera_bind_group_layout, light_bind_group_layout],
+                    push_constant_ranges: &[],
+                })),
+            )
+            .await?;
+        //the packed material variant samples one texture array instead of a texture per material
+        let material_array_layout = binding_cache.layout(
+            &device,
+            "material_array_bind_group_layout",
+            &texture::Texture::array_layout_entries(wgpu::ShaderStages::FRAGMENT),
+        );
+        let material_array_pipeline_layout =
+            Arc::new(device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Material Array Pipeline Layout"),
+                bind_group_layouts: &[
+                    &material_array_layout,
+                    camera_bind_group_layout,
+                    light_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }));
+        shader_preprocessor
+            .register(
+                &mut pipeline_cache,
+                &device,
+                "MaterialArray",
+                "shader_array.wgsl",
+                include_str!("shader_array.wgsl"),
+                material_array_pipeline_layout.clone(),
+            )
+            .await?;
+        shader_preprocessor
+            .register(
+                &mut pipeline_cache,
+                &device,
+                "MaterialArrayCompact",
+                "shader_array_compact.wgsl",
+                include_str!("shader_array_compact.wgsl"),
+                material_array_pipeline_layout,
+            )
+            .await?;
+        shader_preprocessor
+            .register(
+                &mut pipeline_cache,
+                &device,
+                "Object",
+                "object.wgsl",
+                include_str!("object.wgsl"),
+                Arc::new(object_pipeline_layout),
+            )
+            .await?;
+        let object_pipeline = pipeline_cache
+            .get(&device, &Self::object_pipeline_key(config.format, depth_format))?;
+        let render_pipeline = pipeline_cache
+            .get(
+                &device,
+                &Self::scene_pipeline_key(config.format, depth_format, InstanceFormat::Full),
+            )?;
+        let light_render_pipeline = pipeline_cache
+            .get(&device, &Self::light_pipeline_key(config.format, depth_format))?;
+        shader_preprocessor
+            .register(
+                &mut pipeline_cache,
+                &device,
+                "DebugLines",
+                "debug_lines.wgsl",
+                include_str!("debug_lines.wgsl"),
+                Arc::new(device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Debug Line Pipeline Layout"),
+                    bind_group_layouts: &[camera_bind_group_layout],
+                    push_constant_ranges: &[],
+                })),
+            )
+            .await?;
+        let debug_line_pipeline = pipeline_cache
+            .get(&device, &Self::debug_line_pipeline_key(config.format, depth_format))?;
+        let overlay = std::cell::RefCell::new(ui_layer::create(&device, &queue, config.format));
+
+        let scene_channel = scene_control::SceneChannel::default();
+        let console = console::Console::new(scene_channel.sender());
+        let spatial_index = spatial_index::SpatialIndex::from_bounds(
+            instances
+                .iter()
+                .map(|instance| obj_model.world_aabb(&instance.transform.matrix())),
+        );
+        Ok(Self {
+            instance,
+            adapter,
+            surface,
+            offscreen_target,
+            surface_textures: Vec::new(),
+            device,
+            queue,
+            device_features,
+            config,
+            size,
+            render_pipeline,
+            depth_texture,
+            render_scale: 1.0,
+            render_scaler: None,
+            depth_format,
+            scene_mask: None,
+            camera,
+            camera_uniform,
+            camera_buffer,
+            camera_controller,
+            instances,
+            dirty_instances: Vec::new(),
+            spatial_index,
+            collider: None,
+            instance_buffer,
+            max_draw_distance: None,
+            distance_culled: None,
+            light_buffer,
+            light_uniform,
+            light_render_pipeline,
+            debug_line_pipeline,
+            obj_model,
+            default_material,
+            error_material,
+            recorder: None,
+            exit_requested: false,
+            frame_limiter: frame_pacing::FrameLimiter::default(),
+            minimized: false,
+            occluded: false,
+            scale_factor: 1.0,
+            uploader: upload::Uploader::new(),
+            indirect_draws: None,
+            merged_meshes: None,
+            culler: None,
+            compute_passes: compute::ComputePasses::new(),
+            custom_materials: custom_material::CustomMaterials::new(),
+            debug_view: debug_view::DebugView::Lit,
+            binding_cache,
+            texture_bind_group_layout,
+            pipeline_cache,
+            shader_preprocessor,
+            objects,
+            object_pipeline,
+            draw_stats: Default::default(),
+            gpu_timer: None,
+            benchmark: None,
+            benchmark_report: None,
+            gpu_capture: gpu_capture::GpuCapture::new(),
+            frame_stats: frame_stats::FrameStatsTracker::new(),
+            material_array_layout,
+            material_array: None,
+            scene_bundles: Default::default(),
+            overlay,
+            #[cfg(feature = "scripting")]
+            scripts: scripting::ScriptHost::new(),
+            scene_channel,
+            file_watcher: resources::watcher::FileWatcher::new(),
+            debug_lines: Default::default(),
+            collider_debug: false,
+            editor: Default::default(),
+            cursor: Default::default(),
+            scene_path: None,
+            console,
+            wireframe: false,
+            model_name: model.to_string(),
+            console_keys: vec![KeyCode::Backquote],
+            camera_bookmarks: Default::default(),
+            bookmarks_path: None,
+            camera_flight: None,
+            time_of_day: None,
+            sky_color: CLEAR_COLOR,
+            animator: Default::default(),
+            motions: Default::default(),
+            last_update: None,
+            frame_delta: 0.0,
+        })
+    }
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        //some platforms report a zero size when minimizing, the old surface config is kept and
+        //nothing is drawn until a real size comes back
+        self.minimized = new_size.width == 0 || new_size.height == 0;
+        if self.minimized {
+            //no updates run while minimized, the time spent that way isn't simulated afterwards
+            self.last_update = None;
+        }
+        tracing::debug!(width = new_size.width, height = new_size.height, "resize");
+        if !self.minimized {
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            self.size = new_size;
+            self.camera.aspect = self.config.width as f32 / self.config.height as f32;
+            if let Some(surface) = self.surface.as_ref() {
+                surface.configure(&self.device, &self.config);
+            }
+            if self.offscreen_target.is_some() {
+                self.offscreen_target = Some(texture::Texture::create_offscreen_texture(
+                    &self.device,
+                    &self.config,
+                    "offscreen_target",
+                ));
+            }
+            for target in &mut self.surface_textures {
+                target.resize(&self.device, (self.config.width, self.config.height));
+            }
+            self.resize_scene_targets();
+        }
+    }
+
+    //renders the scene at this multiple of the window's resolution and filters it onto the
+    //surface, e.g. 0.5 for a quarter of the pixels on a weak gpu or 2.0 to supersample a
+    //screenshot. the overlay is always drawn at full resolution. returns the scale used, which is
+    //kept between render_scale::MIN_SCALE and MAX_SCALE
+    pub fn set_render_scale(&mut self, scale: f32) -> f32 {
+        let scale = if scale.is_finite() { scale } else { 1.0 };
+        self.render_scale = scale.clamp(render_scale::MIN_SCALE, render_scale::MAX_SCALE);
+        self.resize_scene_targets();
+        self.render_scale
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    //the size the scene is rendered at, the window size unless a render scale is set
+    pub fn render_size(&self) -> (u32, u32) {
+        self.render_scaler
+            .as_ref()
+            .map_or((self.config.width, self.config.height), |scaler| scaler.size())
+    }
+
+    //the depth buffer and the scaled scene texture follow the window size and render scale
+    fn resize_scene_targets(&mut self) {
+        let window = (self.config.width, self.config.height);
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        let size = render_scale::scaled_size(window, self.render_scale, max_dimension);
+        if size == window {
+            self.render_scaler = None;
+        } else {
+            match self.render_scaler.as_mut() {
+                Some(scaler) if scaler.format() == self.config.format => {
+                    scaler.resize(&self.device, size)
+                }
+                _ => {
+                    self.render_scaler = Some(render_scale::RenderScaler::new(
+                        &self.device,
+                        self.config.format,
+                        size,
+                    ))
+                }
+            }
+        }
+        self.depth_texture = texture::Texture::create_render_target(
+            &self.device,
+            size,
+            self.depth_format,
+            wgpu::TextureUsages::empty(),
+            "depth_texture",
+        );
+    }
+    fn is_suspended(&self) -> bool {
+        self.surface.is_none() && self.offscreen_target.is_none()
+    }
+
+    pub fn set_occluded(&mut self, occluded: bool) {
+        self.occluded = occluded;
+        if occluded {
+            self.last_update = None;
+        }
+    }
+
+    //true while there is nothing visible to draw to, update and render are skipped until the
+    //window is restored
+    pub fn is_minimized(&self) -> bool {
+        self.minimized || self.occluded
+    }
+
+    //lets game code ask the app to shut down, the exit hook still runs first
+    pub fn request_exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    //caps the frame rate regardless of the present mode, None renders as fast as possible
+    pub fn set_fps_limit(&mut self, fps: Option<f32>) {
+        self.frame_limiter.set_target_fps(fps);
+    }
+
+    pub fn fps_limit(&self) -> Option<f32> {
+        self.frame_limiter.target_fps()
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    //called when the window moves to a monitor with a different dpi, the surface and depth
+    //texture are resized to the new physical size
+    pub fn set_scale_factor(&mut self, scale_factor: f64, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.scale_factor = scale_factor;
+        self.resize(new_size);
+    }
+
+    //the state the model is drawn with, variants can be made from it with the PipelineKey builders
+    pub fn scene_pipeline_key(
+        color_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        instance_format: InstanceFormat,
+    ) -> pipeline_cache::PipelineKey {
+        let shader = match instance_format {
+            InstanceFormat::Full => "Normal",
+            InstanceFormat::Compact => "NormalCompact",
+        };
+        pipeline_cache::PipelineKey::opaque(
+            shader,
+            &[model::ModelVertex::desc(), instance_format.desc()],
+            color_format,
+            Some(depth_format),
+        )
+    }
+
+    fn object_pipeline_key(
+        color_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+    ) -> pipeline_cache::PipelineKey {
+        pipeline_cache::PipelineKey::opaque(
+            "Object",
+            &[model::ModelVertex::desc()],
+            color_format,
+            Some(depth_format),
+        )
+    }
+
+    fn material_array_pipeline_key(
+        color_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        instance_format: InstanceFormat,
+    ) -> pipeline_cache::PipelineKey {
+        let shader = match instance_format {
+            InstanceFormat::Full => "MaterialArray",
+            InstanceFormat::Compact => "MaterialArrayCompact",
+        };
+        pipeline_cache::PipelineKey::opaque(
+            shader,
+            &[model::ModelVertex::desc(), instance_format.desc()],
+            color_format,
+            Some(depth_format),
+        )
+    }
+
+    fn light_pipeline_key(
+        color_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+    ) -> pipeline_cache::PipelineKey {
+        pipeline_cache::PipelineKey::opaque(
+            "Light",
+            &[model::ModelVertex::desc()],
+            color_format,
+            Some(depth_format),
+        )
+    }
+
+    //lines are tested against the scene's depth without writing it, so they never hide each other
+    fn debug_line_pipeline_key(
+        color_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+    ) -> pipeline_cache::PipelineKey {
+        pipeline_cache::PipelineKey::opaque(
+            "DebugLines",
+            &[debug_lines::LineVertex::desc()],
+            color_format,
+            Some(depth_format),
+        )
+        .with_topology(wgpu::PrimitiveTopology::LineList)
+        .with_cull_mode(None)
+        .with_depth_write(false)
+    }
+
+    //lines drawn over the scene for the next frame only, e.g. contact points with cross and
+    //velocities with arrow from a physics step
+    pub fn debug_lines(&mut self) -> &mut debug_lines::DebugLines {
+        self.debug_lines.get_mut()
+    }
+
+    //switches the built in scene shaders to a debug view, or back to Lit. each view's shaders are
+    //built the first time it is picked, if that fails the current view is kept
+    pub fn set_debug_view(&mut self, view: debug_view::DebugView) -> error::Result<()> {
+        let previous = std::mem::replace(&mut self.debug_view, view);
+        if let Err(e) = self.set_scene_pipelines(self.instance_buffer.format()) {
+            self.debug_view = previous;
+            return Err(e.into());
+        }
+        self.scene_bundles.get_mut().invalidate();
+        Ok(())
+    }
+
+    pub fn debug_view(&self) -> debug_view::DebugView {
+        self.debug_view
+    }
+
+    //points the model and packed material pipelines at the shaders for the instance format and
+    //debug view, and every scene pipeline at the scene mask's stencil test
+    fn set_scene_pipelines(&mut self, format: InstanceFormat) -> Result<(), ShaderError> {
+        let light_pipeline = self.pipeline_cache.get(
+            &self.device,
+            &self.masked(Self::light_pipeline_key(self.config.format, self.depth_format)),
+        )?;
+        let object_pipeline = self.pipeline_cache.get(
+            &self.device,
+            &self.masked(Self::object_pipeline_key(self.config.format, self.depth_format)),
+        )?;
+        let render_pipeline = self.debug_view_pipeline(Self::scene_pipeline_key(
+            self.config.format,
+            self.depth_format,
+            format,
+        ))?;
+        if self.material_array.is_some() {
+            let pipeline = self.debug_view_pipeline(Self::material_array_pipeline_key(
+                self.config.format,
+                self.depth_format,
+                format,
+            ))?;
+            if let Some((_, material_pipeline)) = self.material_array.as_mut() {
+                *material_pipeline = pipeline;
+            }
+        }
+        self.render_pipeline = render_pipeline;
+        self.light_render_pipeline = light_pipeline;
+        self.object_pipeline = object_pipeline;
+        Ok(())
+    }
+
+    //a scene pipeline's key with the scene mask's stencil test, if there is a mask
+    fn masked(&self, key: pipeline_cache::PipelineKey) -> pipeline_cache::PipelineKey {
+        match self.scene_mask.as_ref() {
+            Some((mask, _)) => key.with_stencil(mask.test_state()),
+            None => key,
+        }
+    }
+
+    //the pipeline for a built in scene shader's key under the current debug view
+    fn debug_view_pipeline(
+        &mut self,
+        key: pipeline_cache::PipelineKey,
+    ) -> Result<Arc<wgpu::RenderPipeline>, ShaderError> {
+        let flags = self.debug_view.flags();
+        //every include is already loaded, so expanding finishes without waiting
+        let variant = pipeline_cache::poll_once(self.shader_preprocessor.register_variant(
+            &mut self.pipeline_cache,
+            &self.device,
+            &key.shader,
+            &flags,
+        ))
+        .unwrap_or_else(|| {
+            Err(ShaderError {
+                name: key.shader.clone(),
+                location: None,
+                message: "variant includes a file that hasn't been loaded".to_string(),
+            })
+        })?;
+        let mut key = self.masked(self.debug_view.pipeline_key(pipeline_cache::PipelineKey {
+            shader: variant,
+            ..key
+        }));
+        if self.wireframe {
+            key = key.with_polygon_mode(wgpu::PolygonMode::Line);
+        }
+        self.pipeline_cache.get(&self.device, &key)
+    }
+
+    //draws the built in scene shaders as lines. returns false, leaving the scene filled, if the
+    //device can't draw lines or the pipelines fail to build
+    pub fn set_wireframe(&mut self, enabled: bool) -> bool {
+        if enabled && !self.device_features.polygon_mode_line {
+            return false;
+        }
+        let previous = std::mem::replace(&mut self.wireframe, enabled);
+        if let Err(e) = self.set_scene_pipelines(self.instance_buffer.format()) {
+            tracing::error!("{}", e);
+            self.wireframe = previous;
+            return false;
+        }
+        self.scene_bundles.get_mut().invalidate();
+        true
+    }
+
+    pub fn is_wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    //shows the bounds picking and culling use for each instance as wireframe boxes
+    pub fn set_collider_debug(&mut self, enabled: bool) {
+        self.collider_debug = enabled;
+    }
+
+    pub fn is_collider_debug(&self) -> bool {
+        self.collider_debug
+    }
+
+    fn add_collider_lines(&mut self) {
+        profiling::scope!("add_collider_lines");
+        let lines = self.debug_lines.get_mut();
+        //the generated collider where there is one, the mesh boxes otherwise
+        let edges = self.collider.as_ref().map(|(_, collider)| collider.edges());
+        for instance in &self.instances {
+            let transform = instance.transform.matrix();
+            match edges.as_ref() {
+                Some(edges) => {
+                    for [from, to] in edges {
+                        lines.line(
+                            transform.transform_point(*from),
+                            transform.transform_point(*to),
+                            debug_lines::GREEN,
+                        );
+                    }
+                }
+                None => {
+                    for mesh in &self.obj_model.meshes {
+                        lines.aabb(&mesh.bounds, &transform, debug_lines::GREEN);
+                    }
+                }
+            }
+        }
+    }
+
+    //times each pass on the gpu. returns false if the device doesn't support timestamp queries
+    pub fn set_gpu_profiling(&mut self, enabled: bool) -> bool {
+        self.gpu_timer = if enabled {
+            gpu_timer::GpuTimer::new(&self.device, &self.queue)
+        } else {
+            None
+        };
+        self.gpu_timer.is_some() == enabled
+    }
+
+    pub fn is_gpu_profiling(&self) -> bool {
+        self.gpu_timer.is_some()
+    }
+
+    //wraps the next rendered frame in a renderdoc capture. returns false unless the app was built
+    //with the renderdoc feature and launched from renderdoc
+    pub fn trigger_capture(&mut self) -> bool {
+        self.gpu_capture.trigger()
+    }
+
+    pub fn gpu_capture(&self) -> &gpu_capture::GpuCapture {
+        &self.gpu_capture
+    }
+
+    //rolling averages of each pass's gpu time, empty unless profiling
+    pub fn gpu_timings(&self) -> Vec<gpu_timer::PassTiming> {
+        self.gpu_timer
+            .as_ref()
+            .map(gpu_timer::GpuTimer::timings)
+            .unwrap_or_default()
+    }
+
+    pub fn gpu_timing_summary(&self) -> Option<String> {
+        self.gpu_timer.as_ref().map(gpu_timer::GpuTimer::summary)
+    }
+
+    //fps, frame times and what the last frame drew, updated every presented frame
+    pub fn frame_stats(&self) -> frame_stats::FrameStats {
+        self.frame_stats.stats()
+    }
+
+    //draws and binds recorded by the most recent scene pass
+    pub fn draw_stats(&self) -> draw_queue::DrawStats {
+        self.draw_stats.get()
+    }
+
+    pub fn pipeline_cache(&mut self) -> &mut pipeline_cache::PipelineCache {
+        &mut self.pipeline_cache
+    }
+
+    //expands #include and #define in WGSL before it is registered with the pipeline cache.
+    //common.wgsl is already loaded
+    pub fn shader_preprocessor(&mut self) -> &mut shader_preprocessor::ShaderPreprocessor {
+        &mut self.shader_preprocessor
+    }
+
+    //builds a material from WGSL snippets, see custom_material::CustomMaterialDescriptor. params
+    //are the initial contents of its MaterialParams uniform. it isn't drawn until assigned
+    pub async fn create_custom_material(
+        &mut self,
+        descriptor: &custom_material::CustomMaterialDescriptor<'_>,
+        params: &[u8],
+    ) -> error::Result<custom_material::CustomMaterialId> {
+        let targets = custom_material::MaterialTargets {
+            device: &self.device,
+            preprocessor: &mut self.shader_preprocessor,
+            pipeline_cache: &mut self.pipeline_cache,
+            scene_layouts: [
+                &self.texture_bind_group_layout,
+                self.camera_buffer.layout(),
+                self.light_buffer.layout(),
+            ],
+            color_format: self.config.format,
+            depth_format: self.depth_format,
+            instance_format: self.instance_buffer.format(),
+        };
+        let id = self
+            .custom_materials
+            .create(targets, descriptor, params)
+            .await?;
+        self.scene_bundles.get_mut().invalidate();
+        Ok(id)
+    }
+
+    //draws the meshes using the model's material at material_index with a custom material, or
+    //with their own material again for None
+    pub fn assign_custom_material(
+        &mut self,
+        material_index: usize,
+        material: Option<custom_material::CustomMaterialId>,
+    ) {
+        self.custom_materials.assign(material_index, material);
+        self.scene_bundles.get_mut().invalidate();
+    }
+
+    //false if the material doesn't exist or params aren't the size it was created with
+    pub fn set_custom_material_params(
+        &self,
+        material: custom_material::CustomMaterialId,
+        params: &[u8],
+    ) -> bool {
+        self.custom_materials
+            .get(material)
+            .is_some_and(|material| material.set_params(&self.queue, params))
+    }
+
+    pub fn custom_materials(&self) -> &custom_material::CustomMaterials {
+        &self.custom_materials
+    }
+
+    //the compute passes dispatched at the start of every frame, before culling and the scene
+    pub fn compute_passes(&mut self) -> &mut compute::ComputePasses {
+        &mut self.compute_passes
+    }
+
+    //groups lists the entries of each bind group the shader reads, in group order
+    pub fn create_compute_shader(
+        &self,
+        label: &str,
+        source: &str,
+        entry_point: &str,
+        groups: &[&[wgpu::BindGroupLayoutEntry]],
+    ) -> error::Result<Arc<compute::ComputeShader>> {
+        let shader = compute::ComputeShader::new(&self.device, label, source, entry_point, groups)?;
+        Ok(Arc::new(shader))
+    }
+
+    //None if the shader has no bind group at that index
+    pub fn create_compute_bind_group(
+        &self,
+        shader: &compute::ComputeShader,
+        group: usize,
+        entries: &[wgpu::BindGroupEntry],
+    ) -> Option<Arc<wgpu::BindGroup>> {
+        shader
+            .create_bind_group(&self.device, group, entries)
+            .map(Arc::new)
+    }
+
+    //a uniform holding value with its own layout and bind group, update it with set
+    pub fn create_uniform<T: bytemuck::Pod>(
+        &self,
+        label: &str,
+        value: &T,
+        visibility: wgpu::ShaderStages,
+    ) -> uniform::UniformBuffer<T> {
+        uniform::UniformBuffer::new(&self.device, label, value, visibility)
+    }
+
+    //usage is added to STORAGE and the copy usages, e.g. VERTEX to draw from what a compute pass
+    //wrote
+    pub fn create_storage_buffer(
+        &self,
+        label: &str,
+        size: wgpu::BufferAddress,
+        usage: wgpu::BufferUsages,
+    ) -> compute::StorageBuffer {
+        compute::StorageBuffer::new(&self.device, label, size, usage)
+    }
+
+    pub fn create_storage_buffer_init(
+        &self,
+        label: &str,
+        contents: &[u8],
+        usage: wgpu::BufferUsages,
+    ) -> compute::StorageBuffer {
+        compute::StorageBuffer::with_contents(&self.device, label, contents, usage)
+    }
+
+    //lands before the next frame's compute passes run
+    pub fn write_storage_buffer(
+        &self,
+        buffer: &compute::StorageBuffer,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        self.queue.write_buffer(buffer.buffer(), offset, data);
+    }
+
+    //expands a custom shader and registers it with the pipeline cache, errors point at the file
+    //and line they came from rather than the expanded source
+    pub async fn register_shader(
+        &mut self,
+        name: &str,
+        file_name: &str,
+        source: &str,
+        layout: Arc<wgpu::PipelineLayout>,
+    ) -> error::Result<()> {
+        self.shader_preprocessor
+            .register(&mut self.pipeline_cache, &self.device, name, file_name, source, layout)
+            .await?;
+        Ok(())
+    }
+
+    //the variant of a registered shader built with the given flags, e.g. the built in "Normal"
+    //with ALPHA_TEST for cutout foliage. returns the name to use in a PipelineKey, each set of
+    //flags is only expanded and compiled once
+    pub async fn shader_variant(
+        &mut self,
+        name: &str,
+        flags: &shader_preprocessor::ShaderFlags,
+    ) -> error::Result<String> {
+        Ok(self
+            .shader_preprocessor
+            .register_variant(&mut self.pipeline_cache, &self.device, name, flags)
+            .await?)
+    }
+
+    //a pipeline for the given key, built on first use
+    pub fn pipeline(
+        &mut self,
+        key: &pipeline_cache::PipelineKey,
+    ) -> std::result::Result<Arc<wgpu::RenderPipeline>, ShaderError> {
+        self.pipeline_cache.get(&self.device, key)
+    }
+
+    //the stages storage buffers can be bound in. empty on WebGL2, and without VERTEX where the
+    //backend can't read them from vertex shaders
+    pub fn storage_stages(&self) -> wgpu::ShaderStages {
+        if self.device.limits().max_storage_buffers_per_shader_stage == 0 {
+            return wgpu::ShaderStages::empty();
+        }
+        let mut stages = wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE;
+        if self
+            .adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::VERTEX_STORAGE)
+        {
+            stages |= wgpu::ShaderStages::VERTEX;
+        }
+        stages
+    }
+
+    //a layout shared with any other asking for the same entries, see the entry helpers in
+    //bind_cache
+    pub fn bind_group_layout(
+        &mut self,
+        label: &str,
+        entries: &[wgpu::BindGroupLayoutEntry],
+    ) -> Arc<wgpu::BindGroupLayout> {
+        self.binding_cache.layout(&self.device, label, entries)
+    }
+
+    pub fn bind_group(
+        &mut self,
+        label: Option<&str>,
+        layout: &wgpu::BindGroupLayout,
+        entries: &[wgpu::BindGroupEntry],
+    ) -> Arc<wgpu::BindGroup> {
+        self.binding_cache
+            .bind_group(&self.device, label, layout, entries)
+    }
+
+    pub fn binding_cache(&mut self) -> &mut bind_cache::BindingCache {
+        &mut self.binding_cache
+    }
+
+    pub fn device_features(&self) -> &features::DeviceFeatures {
+        &self.device_features
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.offscreen_target.is_some()
+    }
+
+    //the texture headless frames are rendered into
+    pub fn offscreen_texture(&self) -> Option<&wgpu::Texture> {
+        self.offscreen_target.as_ref().map(|target| &target.texture)
+    }
+
+    //drops the surface, anything rendering should check is_suspended first
+    fn suspend(&mut self) {
+        self.surface = None;
+        self.frame_stats.reset();
+        //the first update after resuming starts from zero rather than the whole time away
+        self.last_update = None;
+    }
+
+    //recreates the surface for the window after a suspend and configures it at the current size
+    fn resume(&mut self, window: Arc<Window>) -> error::Result<()> {
+        if self.surface.is_some() {
+            return Ok(());
+        }
+        let surface = self.instance.create_surface(window.clone())?;
+        self.surface = Some(surface);
+        self.resize(window.inner_size());
+        Ok(())
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        #[cfg(feature = "scripting")]
+        self.script_input(event);
+        if let WindowEvent::CursorMoved { position, .. } = event {
+            self.cursor = *position;
+        }
+        if self.editor_input(event) {
+            return true;
+        }
+        self.camera_controller.process_events(event)
+    }
+
+    //clicks pick and drag gizmo handles, 1, 2 and 3 pick translate, rotate and scale and escape
+    //clears the selection. the camera keeps moving with the keyboard
+    fn editor_input(&mut self, event: &WindowEvent) -> bool {
+        if !self.editor.is_enabled() {
+            return false;
+        }
+        let ray = picking::Ray::from_screen(&self.camera, self.cursor, self.size);
+        match event {
+            WindowEvent::CursorMoved { .. } => {
+                let Some(ray) = ray else {
+                    return false;
+                };
+                if let Some(target) = self.editor.drag(&ray) {
+                    if let Some(index) = self.editor.selected() {
+                        self.set_gizmo_target(index, &target);
+                    }
+                } else if let Some(target) = self.selected_gizmo_target() {
+                    self.editor.hover(&ray, &target);
+                }
+                false
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: winit::event::MouseButton::Left,
+                ..
+            } => {
+                let Some(ray) = ray else {
+                    return false;
+                };
+                if *state == ElementState::Released {
+                    if self.editor.end_drag() {
+                        self.save_edited_scene();
+                    }
+                    return true;
+                }
+                let grabbed = self
+                    .selected_gizmo_target()
+                    .is_some_and(|target| self.editor.begin_drag(&ray, &target));
+                if !grabbed {
+                    let picked = self.pick_ray(&ray).map(|(instance, _)| instance);
+                    self.editor.select(picked);
+                }
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(keycode),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                let mode = match keycode {
+                    KeyCode::Digit1 => editor::GizmoMode::Translate,
+                    KeyCode::Digit2 => editor::GizmoMode::Rotate,
+                    KeyCode::Digit3 => editor::GizmoMode::Scale,
+                    KeyCode::Escape => {
+                        self.editor.select(None);
+                        return true;
+                    }
+                    _ => return false,
+                };
+                self.editor.set_mode(mode);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn selected_gizmo_target(&self) -> Option<editor::GizmoTarget> {
+        let transform = &self.instances.get(self.editor.selected()?)?.transform;
+        Some(editor::GizmoTarget {
+            position: transform.position(),
+            rotation: transform.rotation(),
+            scale: transform.scale(),
+        })
+    }
+
+    fn set_gizmo_target(&mut self, index: usize, target: &editor::GizmoTarget) {
+        self.modify_instance_transform(index, |transform| {
+            transform.set_position(target.position);
+            transform.set_rotation(target.rotation);
+            transform.set_scale(target.scale);
+        });
+    }
+
+    //the selection box and gizmo, added to the debug lines each frame the editor is on
+    fn add_editor_lines(&mut self) {
+        let Some(instance) = self.editor.selected().and_then(|index| self.instances.get(index)) else {
+            return;
+        };
+        let transform = instance.transform.matrix();
+        let target = editor::GizmoTarget {
+            position: instance.transform.position(),
+            rotation: instance.transform.rotation(),
+            scale: instance.transform.scale(),
+        };
+        let lines = self.debug_lines.get_mut();
+        for mesh in &self.obj_model.meshes {
+            lines.aabb(&mesh.bounds, &transform, debug_lines::YELLOW);
+        }
+        self.editor.draw(lines, self.camera.eye, &target);
+    }
+
+    //writes the scene out once a gizmo drag ends, if there is a file to write to
+    fn save_edited_scene(&mut self) {
+        let Some(path) = self.scene_path.clone() else {
+            return;
+        };
+        if let Err(e) = self.save_scene(&path) {
+            tracing::error!("{:#}", e);
+        }
+    }
+
+    //turns the editor on or off. while it is on the simulation is paused: scene commands wait in
+    //their channel, scripts aren't run and the light stops moving
+    pub fn set_editor_enabled(&mut self, enabled: bool) {
+        self.editor.set_enabled(enabled);
+    }
+
+    pub fn is_editor_enabled(&self) -> bool {
+        self.editor.is_enabled()
+    }
+
+    pub fn editor(&mut self) -> &mut editor::Editor {
+        &mut self.editor
+    }
+
+    //every instance as a scene file, ready to be saved
+    pub fn scene_file(&self) -> scene_file::SceneFile {
+        scene_file::SceneFile {
+            instances: self
+                .instances
+                .iter()
+                .map(|instance| {
+                    let rotation = instance.transform.rotation();
+                    scene_file::SceneInstance {
+                        position: instance.transform.position().into(),
+                        rotation: [rotation.v.x, rotation.v.y, rotation.v.z, rotation.s],
+                        scale: instance.transform.scale().into(),
+                        tint: instance.tint,
+                        layer: instance.layer,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    //replaces every instance with the ones in the scene file
+    pub fn apply_scene_file(&mut self, scene: &scene_file::SceneFile) {
+        let instances = scene
+            .instances
+            .iter()
+            .map(|instance| {
+                let [x, y, z, w] = instance.rotation;
+                Instances::new(
+                    transform::Transform::new(
+                        instance.position.into(),
+                        cgmath::Quaternion::new(w, x, y, z).normalize(),
+                        instance.scale.into(),
+                    ),
+                    instance.layer,
+                    instance.tint,
+                )
+            })
+            .collect();
+        self.replace_instances(instances);
+    }
+
+    //replaces every instance with ones generated by the grid, untinted and on the first layer
+    pub fn set_instance_grid(&mut self, grid: &instance_grid::InstanceGrid) {
+        let instances = grid
+            .transforms()
+            .into_iter()
+            .map(|transform| Instances::new(transform, 0, [1.0; 4]))
+            .collect();
+        self.replace_instances(instances);
+    }
+
+    fn replace_instances(&mut self, instances: Vec<Instances>) {
+        self.instances = instances;
+        self.rebuild_spatial_index();
+        self.instance_buffer
+            .replace(&self.device, self.instances.iter());
+        self.editor.select(None);
+        //the motions were attached to the old instances
+        self.motions.clear();
+        self.sync_indirect_instance_counts();
+        self.prepare_culler();
+        self.apply_draw_distance();
+    }
+
+    //loads the instances from a toml scene file, which the editor then saves its edits back to
+    pub fn load_scene(&mut self, path: impl Into<std::path::PathBuf>) -> anyhow::Result<()> {
+        let path = path.into();
+        let scene = scene_file::SceneFile::load(&path)?;
+        self.apply_scene_file(&scene);
+        self.scene_path = Some(path);
+        Ok(())
+    }
+
+    pub fn save_scene(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        self.scene_file().save(path)
+    }
+
+    //where the editor saves edits, None keeps them in memory only
+    pub fn set_scene_path(&mut self, path: Option<std::path::PathBuf>) {
+        self.scene_path = path;
+    }
+
+    //scripts see every key and cursor move, even the ones the camera uses
+    #[cfg(feature = "scripting")]
+    fn script_input(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(keycode),
+                        state,
+                        ..
+                    },
+                ..
+            } => self
+                .scripts
+                .set_key_down(&format!("{:?}", keycode), *state == ElementState::Pressed),
+            WindowEvent::CursorMoved { position, .. } => {
+                self.scripts.set_cursor(position.x as f32, position.y as f32)
+            }
+            _ => (),
+        }
+    }
+
+    //runs the script file now and reruns it with fresh state whenever it changes on disk
+    #[cfg(feature = "scripting")]
+    pub fn load_script(&mut self, path: impl Into<std::path::PathBuf>) -> anyhow::Result<()> {
+        let result = self.scripts.load(path, self.instances.len(), &mut self.file_watcher);
+        self.run_scripts();
+        result
+    }
+
+    //calls the script's update and applies what it asked for. errors are printed, the script
+    //stops being called until it is fixed and saved
+    #[cfg(feature = "scripting")]
+    fn run_scripts(&mut self) {
+        if !self.scripts.is_loaded() {
+            return;
+        }
+        let commands = match self.scripts.update(self.instances.len()) {
+            Ok(commands) => commands,
+            Err(e) => {
+                tracing::error!("{:#}", e);
+                return;
+            }
+        };
+        for command in commands {
+            match command {
+                scripting::ScriptCommand::Spawn { position } => {
+                    self.add_instance(
+                        position.into(),
+                        cgmath::Quaternion::from_axis_angle(
+                            cgmath::Vector3::unit_y(),
+                            cgmath::Deg(0.0),
+                        ),
+                    );
+                }
+                scripting::ScriptCommand::MoveInstance { index, position } => {
+                    self.modify_instance_transform(index, |transform| {
+                        transform.set_position(position.into())
+                    });
+                }
+                scripting::ScriptCommand::ScaleInstance { index, scale } => {
+                    self.set_instance_scale(index, scale);
+                }
+                scripting::ScriptCommand::SetLightPosition(position) => {
+                    self.light_uniform.position = position;
+                }
+                scripting::ScriptCommand::SetLightColor(color) => {
+                    self.light_uniform.color = color;
+                }
+            }
+        }
+    }
+
+    //a sender other threads can drive the scene through, see SceneCommand
+    pub fn scene_sender(&self) -> std::sync::mpsc::Sender<SceneCommand> {
+        self.scene_channel.sender()
+    }
+
+    //subscribe here to have something reloaded when its file changes, callbacks run during update
+    pub fn file_watcher(&mut self) -> &mut FileWatcher {
+        &mut self.file_watcher
+    }
+
+    //applies one command straight away, the same as sending it and waiting for the next update
+    pub fn apply_scene_command(&mut self, command: SceneCommand) {
+        match command {
+            SceneCommand::SetInstanceTransform {
+                index,
+                position,
+                rotation,
+            } => {
+                self.set_instance_transform(index, position, rotation);
+            }
+            SceneCommand::SetInstanceScale { index, scale } => {
+                self.set_instance_scale(index, scale);
+            }
+            SceneCommand::SetInstanceTint { index, tint } => {
+                self.set_instance_tint(index, tint);
+            }
+            SceneCommand::AddInstanceMotion { index, motion } => {
+                self.add_instance_motion(index, motion);
+            }
+            SceneCommand::ClearInstanceMotions { index } => {
+                self.clear_instance_motions(index);
+            }
+            SceneCommand::SpawnModel { position, rotation } => {
+                self.add_instance(position, rotation);
+            }
+            SceneCommand::SpawnNamedModel {
+                file_name,
+                position,
+                rotation,
+            } => {
+                if file_name == self.model_name {
+                    self.add_instance(position, rotation);
+                } else {
+                    let message = format!(
+                        "can't spawn {}, only the loaded model {} can be",
+                        file_name, self.model_name
+                    );
+                    tracing::warn!("{}", message);
+                    self.console.print(message);
+                }
+            }
+            SceneCommand::SetCameraPose { eye, target } => {
+                self.camera.eye = eye;
+                self.camera.target = target;
+            }
+            SceneCommand::SetLight { position, color } => {
+                self.light_uniform.position = position;
+                self.light_uniform.color = color;
+            }
+            SceneCommand::SetLightPosition { position } => {
+                self.light_uniform.position = position;
+            }
+            SceneCommand::SetLightColor { color } => {
+                self.light_uniform.color = color;
+            }
+            SceneCommand::SetFov { fovy } => {
+                self.camera.fovy = fovy;
+            }
+            SceneCommand::SetRenderScale { scale } => {
+                self.set_render_scale(scale);
+            }
+            SceneCommand::ToggleWireframe => {
+                if !self.set_wireframe(!self.wireframe) {
+                    self.console.print("wireframe isn't supported on this device");
+                }
+            }
+            SceneCommand::ToggleColliderDebug => {
+                self.collider_debug = !self.collider_debug;
+            }
+        }
+    }
+
+    //the console key, backquote by default, opens and closes the console, ahead of the overlay so it can close a
+    //console that has the keyboard. opening it shows the overlay it is drawn in
+    fn console_input(&mut self, event: &WindowEvent) -> bool {
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(keycode),
+                    state,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return false;
+        };
+        if !self.console_keys.contains(keycode) {
+            return false;
+        }
+        if *state == ElementState::Pressed {
+            self.console.toggle();
+            if self.console.is_open() {
+                self.set_overlay_visible(true);
+            }
+        }
+        true
+    }
+
+    //register commands here, or run one with execute. it is drawn by the egui and imgui overlays
+    pub fn console(&mut self) -> &mut console::Console {
+        &mut self.console
+    }
+
+    fn apply_scene_commands(&mut self) {
+        for command in self.scene_channel.drain() {
+            self.apply_scene_command(command);
+        }
+    }
+
+    //gives the overlay first look at an event, true when the ui is using it
+    fn overlay_input(&mut self, event: &WindowEvent) -> bool {
+        let scale_factor = self.scale_factor;
+        self.overlay
+            .get_mut()
+            .as_mut()
+            .is_some_and(|overlay| overlay.on_event(event, scale_factor))
+    }
+
+    //does nothing when built without a ui feature
+    pub fn set_overlay_visible(&mut self, visible: bool) {
+        if let Some(overlay) = self.overlay.get_mut().as_mut() {
+            overlay.set_visible(visible);
+        }
+    }
+
+    pub fn is_overlay_visible(&self) -> bool {
+        self.overlay
+            .borrow()
+            .as_ref()
+            .is_some_and(|overlay| overlay.is_visible())
+    }
+
+    //where the overlay wants the ime candidate window, None when nothing in it is being typed into
+    fn overlay_ime_area(
+        &self,
+    ) -> Option<(winit::dpi::PhysicalPosition<f64>, winit::dpi::PhysicalSize<f64>)> {
+        self.overlay.borrow().as_ref()?.ime_area()
+    }
+
+    //places the listener at the camera and the emitters at their instances, as this frame draws them
+    #[cfg(feature = "audio")]
+    fn update_audio(&self, audio: &mut audio::Audio) {
+        audio.update_spatial(&self.camera, |instance| {
+            self.instances.get(instance).map(|instance| instance.transform.position())
+        });
+    }
+
+    //carries out what assistive technology asked for and hands it the window's current tree
+    #[cfg(feature = "accesskit")]
+    fn update_accessibility(&mut self, accessibility: &mut accessibility::Accessibility) {
+        for request in accessibility.take_requests() {
+            match request {
+                accessibility::AccessibilityRequest::ToggleOverlay => {
+                    let visible = !self.is_overlay_visible();
+                    self.set_overlay_visible(visible);
+                }
+            }
+        }
+        let has_overlay = self.overlay.get_mut().is_some();
+        let mut camera_speed = self.camera_controller.speed();
+        let panel = ui_layer::DebugPanel {
+            camera: &mut self.camera,
+            camera_speed: &mut camera_speed,
+            light_position: &mut self.light_uniform.position,
+            light_color: &mut self.light_uniform.color,
+            frame_stats: self.frame_stats.stats(),
+            draw_stats: self.draw_stats.get(),
+            console: &mut self.console,
+        };
+        let visible = self
+            .overlay
+            .get_mut()
+            .as_ref()
+            .is_some_and(|overlay| overlay.is_visible());
+        accessibility.update(WINDOW_TITLE, has_overlay, visible.then_some(&panel));
+    }
+
+    //builds this frame's debug windows, changes made through them apply in this update
+    fn run_overlay(&mut self) {
+        let Some(overlay) = self.overlay.get_mut().as_mut() else {
+            return;
+        };
+        if !overlay.is_visible() {
+            return;
+        }
+        let frame_stats = self.frame_stats.stats();
+        let draw_stats = self.draw_stats.get();
+        let mut camera_speed = self.camera_controller.speed();
+        let mut panel = ui_layer::DebugPanel {
+            camera: &mut self.camera,
+            camera_speed: &mut camera_speed,
+            light_position: &mut self.light_uniform.position,
+            light_color: &mut self.light_uniform.color,
+            frame_stats,
+            draw_stats,
+            console: &mut self.console,
+        };
+        overlay.run(self.size, self.scale_factor, &mut panel);
+        self.camera_controller.set_speed(camera_speed);
+    }
+
+    //only touches the cpu side copies, the uniform buffers are written when the frame is encoded
+    #[profiling::function]
+    pub fn update(&mut self) {
+        profiler::tracy_zone!("update");
+        let now = frame_stats::now_seconds();
+        self.frame_delta = self
+            .last_update
+            .replace(now)
+            .map_or(0.0, |last| ((now - last) as f32).min(MAX_FRAME_DELTA));
+        self.file_watcher.poll();
+        //the editor pauses everything that moves the scene on its own
+        let paused = self.editor.is_enabled();
+        if !paused {
+            self.apply_scene_commands();
+        }
+        self.run_overlay();
+        #[cfg(feature = "scripting")]
+        if !paused {
+            self.run_scripts();
+        }
+        if !paused {
+            self.update_lighting();
+            self.run_animations();
+            self.run_motions();
+        }
+        self.fly_camera();
+        self.camera_controller.update_camera(&mut self.camera);
+        //the benchmark path overrides any input so every run sees the same frames
+        if let Some(benchmark) = self.benchmark.as_ref() {
+            benchmark
+                .pose(frame_stats::now_seconds())
+                .apply(&mut self.camera);
+        }
+        self.camera_uniform.update_view_proj(&self.camera);
+        self.spatial_index.refresh();
+        self.apply_draw_distance();
+        if self.collider_debug {
+            self.add_collider_lines();
+        }
+        if paused {
+            self.add_editor_lines();
+        }
+    }
+
+    //the animator is taken out while it runs so it can write to the rest of the state
+    fn run_animations(&mut self) {
+        let mut animator = std::mem::take(&mut self.animator);
+        animator.tick(self.frame_delta, self);
+        self.animator = animator;
+    }
+
+    fn run_motions(&mut self) {
+        let mut motions = std::mem::take(&mut self.motions);
+        motions.tick(self.frame_delta, |index, edit| {
+            self.modify_instance_transform(index, |transform| edit(transform))
+        });
+        self.motions = motions;
+    }
+
+    //keeps an instance moving from the next update until its motions are cleared. returns false
+    //if there is no instance at that index
+    pub fn add_instance_motion(&mut self, index: usize, motion: motion::Motion) -> bool {
+        if index >= self.instances.len() {
+            return false;
+        }
+        self.motions.add(index, motion);
+        true
+    }
+
+    //like add_instance_motion with a callback of its own, see motion::MotionFn
+    pub fn add_instance_motion_fn(
+        &mut self,
+        index: usize,
+        motion: impl FnMut(&mut transform::Transform, f32, f32) + Send + 'static,
+    ) -> bool {
+        if index >= self.instances.len() {
+            return false;
+        }
+        self.motions.add_fn(index, Box::new(motion));
+        true
+    }
+
+    //leaves the instance wherever its motions got it. returns false if it had none
+    pub fn clear_instance_motions(&mut self, index: usize) -> bool {
+        self.motions.remove(index)
+    }
+
+    fn rebuild_spatial_index(&mut self) {
+        self.spatial_index = spatial_index::SpatialIndex::from_bounds(
+            self.instances
+                .iter()
+                .map(|instance| self.obj_model.world_aabb(&instance.transform.matrix())),
+        );
+    }
+
+    //builds a collision shape from the model's meshes, used by the collider debug view and the
+    //collider queries and rebuilt whenever the model is replaced. returns false when the model
+    //can't give one, e.g. a hull from a model loaded without keep_triangles
+    pub fn generate_collider(&mut self, kind: collision::ColliderKind) -> bool {
+        self.collider = self
+            .obj_model
+            .collider(kind)
+            .map(|collider| (kind, collider));
+        self.collider.is_some()
+    }
+
+    pub fn clear_collider(&mut self) {
+        self.collider = None;
+    }
+
+    //the model space shape every instance shares
+    pub fn collider(&self) -> Option<&collision::Collider> {
+        self.collider.as_ref().map(|(_, collider)| collider)
+    }
+
+    //the instances whose collider holds a world space point, in id order. empty without a
+    //collider
+    pub fn instances_containing(&self, point: cgmath::Point3<f32>) -> Vec<InstanceId> {
+        let Some(collider) = self.collider() else {
+            return Vec::new();
+        };
+        self.spatial_index
+            .query_sphere(point, 0.0)
+            .into_iter()
+            .filter(|&index| {
+                let transform = &self.instances[index].transform;
+                transform.scale().product() != 0.0
+                    && collider.contains_point(transform.to_model_space(point))
+            })
+            .collect()
+    }
+
+    //the nearest instance whose collider a world space ray hits and the distance to it
+    pub fn collider_raycast(&self, ray: &picking::Ray) -> Option<(InstanceId, f32)> {
+        let collider = self.collider()?;
+        self.spatial_index.cast_ray(ray, |index| {
+            let local = Self::model_space_ray(&self.instances.get(index)?.transform, ray)?;
+            collider.raycast(&local)
+        })
+    }
+
+    //the world space boxes of every instance, keyed by InstanceId, for queries of your own.
+    //changes show up in queries straight away
+    pub fn spatial_index(&self) -> &spatial_index::SpatialIndex {
+        &self.spatial_index
+    }
+
+    //the instances whose bounds reach within radius of a point, e.g. what is near the player
+    pub fn instances_near(&self, center: cgmath::Point3<f32>, radius: f32) -> Vec<InstanceId> {
+        self.spatial_index.query_sphere(center, radius)
+    }
+
+    //the instances whose bounds overlap a world space box
+    pub fn instances_in_box(&self, area: &picking::Aabb) -> Vec<InstanceId> {
+        self.spatial_index.query_box(area)
+    }
+
+    //the instances that may be in the camera's view and within the draw distance
+    pub fn instances_in_view(&self) -> Vec<InstanceId> {
+        let frustum = culling::Frustum::from_view_projection(&self.camera.build_view_projection())
+            .with_draw_distance(self.draw_distance());
+        self.spatial_index.query_frustum(&frustum)
+    }
+
+    //starts an animation, it runs from the next update. see animation::Animation
+    pub fn animate(&mut self, animation: animation::Animation) -> animation::AnimationId {
+        self.animator.play(animation)
+    }
+
+    //leaves whatever it was animating where it is. returns false if it had already finished
+    pub fn stop_animation(&mut self, id: animation::AnimationId) -> bool {
+        self.animator.stop(id)
+    }
+
+    pub fn is_animating(&self, id: animation::AnimationId) -> bool {
+        self.animator.is_playing(id)
+    }
+
+    //seconds between the start of the last update and the one before it, at most MAX_FRAME_DELTA
+    //and zero for the first update after a suspend or minimize
+    pub fn frame_delta(&self) -> f32 {
+        self.frame_delta
+    }
+
+    //the time of day drives the light and sky when it is set, otherwise the light orbits
+    fn update_lighting(&mut self) {
+        let Some(time_of_day) = self.time_of_day.as_mut() else {
+            let old_position:  cgmath::Vector3<_> = self.light_uniform.position.into();
+            self. light_uniform.position = ( cgmath::Quaternion::from_axis_angle((0.0, 1.0, 0.0).into(), cgmath::Deg(0.1)) * old_position).into();
+            return;
+        };
+        time_of_day.tick(frame_stats::now_seconds());
+        let lighting = time_of_day.lighting();
+        self.light_uniform.position = lighting.sun_position;
+        self.light_uniform.color = lighting.sun_color;
+        self.light_uniform.ambient = lighting.ambient;
+        let [r, g, b] = lighting.sky_color.map(f64::from);
+        self.sky_color = wgpu::Color { r, g, b, a: 1.0 };
+    }
+
+    //hands the light and background over to a day and night cycle, None goes back to the orbiting
+    //light and the default background
+    pub fn set_time_of_day(&mut self, time_of_day: Option<time_of_day::TimeOfDay>) {
+        if time_of_day.is_none() {
+            self.sky_color = CLEAR_COLOR;
+            self.light_uniform.ambient = 0.1;
+        }
+        self.time_of_day = time_of_day;
+    }
+
+    pub fn time_of_day(&mut self) -> Option<&mut time_of_day::TimeOfDay> {
+        self.time_of_day.as_mut()
+    }
+
+    //the background the scene is cleared to when the window is opaque
+    pub fn set_sky_color(&mut self, color: wgpu::Color) {
+        self.sky_color = color;
+    }
+
+    //how much of the light color reaches surfaces facing away from it, 0.1 by default
+    pub fn set_ambient(&mut self, ambient: f32) {
+        self.light_uniform.ambient = ambient;
+    }
+
+    fn fly_camera(&mut self) {
+        let Some(flight) = self.camera_flight else {
+            return;
+        };
+        if self.camera_controller.is_moving() {
+            self.camera_flight = None;
+            return;
+        }
+        let (pose, arrived) = flight.pose_at(frame_stats::now_seconds());
+        pose.apply(&mut self.camera);
+        if arrived {
+            self.camera_flight = None;
+        }
+    }
+
+    pub fn camera_pose(&self) -> camera_bookmarks::CameraPose {
+        camera_bookmarks::CameraPose::of(&self.camera)
+    }
+
+    //jumps straight to the pose, cancelling any flight
+    pub fn set_camera_pose(&mut self, pose: &camera_bookmarks::CameraPose) {
+        self.camera_flight = None;
+        pose.apply(&mut self.camera);
+    }
+
+    //reads bookmarks from a toml file, which saved bookmarks are then written back to. a file
+    //that doesn't exist yet starts an empty set
+    pub fn load_camera_bookmarks(
+        &mut self,
+        path: impl Into<std::path::PathBuf>,
+    ) -> anyhow::Result<()> {
+        let path = path.into();
+        self.camera_bookmarks = camera_bookmarks::CameraBookmarks::load(&path)?;
+        self.bookmarks_path = Some(path);
+        Ok(())
+    }
+
+    pub fn camera_bookmarks(&self) -> &camera_bookmarks::CameraBookmarks {
+        &self.camera_bookmarks
+    }
+
+    //stores the current camera pose under the name and saves the bookmarks file, if there is one
+    pub fn save_camera_bookmark(&mut self, name: &str) -> anyhow::Result<()> {
+        self.camera_bookmarks.insert(name, self.camera_pose());
+        match self.bookmarks_path.as_ref() {
+            Some(path) => self.camera_bookmarks.save(path),
+            None => Ok(()),
+        }
+    }
+
+    //moves the camera to a bookmark, easing there over the given seconds or jumping with zero.
+    //returns false if there is no bookmark with that name
+    pub fn go_to_camera_bookmark(&mut self, name: &str, seconds: f32) -> bool {
+        let Some(pose) = self.camera_bookmarks.get(name).copied() else {
+            return false;
+        };
+        self.camera_flight = Some(camera_bookmarks::CameraFlight::new(
+            self.camera_pose(),
+            pose,
+            frame_stats::now_seconds(),
+            seconds as f64,
+        ));
+        self.fly_camera();
+        true
+    }
+
+    //instances further than this from the camera are skipped entirely, a cheap way to cut the
+    //instance count before reaching for gpu culling. None draws everything
+    pub fn set_max_draw_distance(&mut self, distance: Option<f32>) {
+        self.max_draw_distance = distance.filter(|distance| *distance > 0.0);
+        self.apply_draw_distance();
+    }
+
+    pub fn max_draw_distance(&self) -> Option<f32> {
+        self.max_draw_distance
+    }
+
+    fn draw_distance(&self) -> Option<culling::DrawDistance> {
+        self.max_draw_distance.map(|max| culling::DrawDistance {
+            eye: self.camera.eye.to_vec(),
+            max,
+        })
+    }
+
+    //rebuilds the buffer of instances in range of the camera. the gpu culler tests the distance
+    //itself so this only runs without it
+    fn apply_draw_distance(&mut self) {
+        let distance = self.draw_distance().filter(|_| self.culler.is_none());
+        let Some(distance) = distance else {
+            if self.distance_culled.take().is_some() {
+                self.sync_indirect_instance_counts();
+            }
+            return;
+        };
+        profiling::scope!("apply_draw_distance");
+        let radius = self.obj_model.bounding_radius();
+        //the index narrows it down to the instances whose boxes are in range
+        let nearby = self
+            .spatial_index
+            .query_sphere(cgmath::Point3::from_vec(distance.eye), distance.max);
+        let visible = nearby.into_iter().map(|index| &self.instances[index]).filter(|instance| {
+            let transform = &instance.transform;
+            distance.contains_sphere(transform.position(), radius * transform.max_scale())
+        });
+        let format = self.instance_buffer.format();
+        match self.distance_culled.as_mut() {
+            Some(culled) if culled.format() == format => culled.replace(&self.device, visible),
+            _ => {
+                let mut culled = InstanceData::new(&self.device, format, &[]);
+                culled.replace(&self.device, visible);
+                self.distance_culled = Some(culled);
+            }
+        }
+        self.sync_indirect_instance_counts();
+    }
+
+    //the instances bound for drawing this frame
+    fn drawn_instances(&self) -> &InstanceData {
+        self.distance_culled.as_ref().unwrap_or(&self.instance_buffer)
+    }
+
+    fn drawn_instance_count(&self) -> u32 {
+        self.drawn_instances().len() as u32
+    }
+
+    pub fn instance_count(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn instance_transform(&self, index: usize) -> Option<&transform::Transform> {
+        self.instances.get(index).map(|instance| &instance.transform)
+    }
+
+    //edits an instance's transform in place. instances whose transform changed are rebuilt and
+    //uploaded once, when the next frame is drawn, however many edits they had. returns false if
+    //there is no instance at that index
+    pub fn modify_instance_transform(
+        &mut self,
+        index: usize,
+        edit: impl FnOnce(&mut transform::Transform),
+    ) -> bool {
+        let Some(instance) = self.instances.get_mut(index) else {
+            return false;
+        };
+        let was_dirty = instance.transform.is_dirty();
+        edit(&mut instance.transform);
+        if instance.transform.is_dirty() {
+            if !was_dirty {
+                self.dirty_instances.push(index);
+            }
+            let bounds = self.obj_model.world_aabb(&instance.transform.matrix());
+            self.spatial_index.update(index, bounds);
+        }
+        true
+    }
+
+    //moves an existing instance, the change is uploaded with the next frame. returns false if
+    //there is no instance at that index
+    pub fn set_instance_transform(
+        &mut self,
+        index: usize,
+        position: cgmath::Vector3<f32>,
+        rotation: cgmath::Quaternion<f32>,
+    ) -> bool {
+        self.modify_instance_transform(index, |transform| {
+            transform.set_position(position);
+            transform.set_rotation(rotation);
+        })
+    }
+
+    //writes the instances edited since the last frame into the instance buffer
+    fn upload_dirty_transforms(&mut self) {
+        for index in self.dirty_instances.drain(..) {
+            //indices can outlive their instance when the scene is replaced
+            let Some(instance) = self.instances.get_mut(index) else {
+                continue;
+            };
+            if instance.transform.take_dirty() {
+                self.instance_buffer.set(index, instance);
+            }
+        }
+    }
+
+    //scales an instance evenly on every axis. returns false if there is no instance at that index
+    pub fn set_instance_scale(&mut self, index: usize, scale: f32) -> bool {
+        self.set_instance_scale_xyz(index, cgmath::vec3(scale, scale, scale))
+    }
+
+    //stretches or squashes an instance along its own axes, before it is rotated. returns false if
+    //there is no instance at that index
+    pub fn set_instance_scale_xyz(&mut self, index: usize, scale: cgmath::Vector3<f32>) -> bool {
+        self.modify_instance_transform(index, |transform| transform.set_scale(scale))
+    }
+
+    pub fn instance_scale(&self, index: usize) -> Option<cgmath::Vector3<f32>> {
+        self.instances.get(index).map(|instance| instance.transform.scale())
+    }
+
+    //rgba multiplied into everything the instance draws, e.g. a selection highlight or team
+    //color without a material of its own. the compact format clamps it to 0..1. returns false
+    //if there is no instance at that index
+    pub fn set_instance_tint(&mut self, index: usize, tint: [f32; 4]) -> bool {
+        let Some(instance) = self.instances.get_mut(index) else {
+            return false;
+        };
+        instance.tint = tint;
+        self.instance_buffer.set(index, instance)
+    }
+
+    pub fn instance_tint(&self, index: usize) -> Option<[f32; 4]> {
+        self.instances.get(index).map(|instance| instance.tint)
+    }
+
+    //the instance under a point on screen, in physical pixels from the top left as given by
+    //CursorMoved. load the model with keep_triangles to test the actual shape instead of the
+    //bounding boxes
+    pub fn pick(&self, cursor: winit::dpi::PhysicalPosition<f64>) -> Option<InstanceId> {
+        let ray = picking::Ray::from_screen(&self.camera, cursor, self.size)?;
+        self.pick_ray(&ray).map(|(instance, _)| instance)
+    }
+
+    //the box around the model in model space, e.g. to size a spawn area
+    pub fn model_bounds(&self) -> picking::Aabb {
+        self.obj_model.aabb()
+    }
+
+    //the world space box around one instance, None if there is no such instance
+    pub fn instance_bounds(&self, instance: InstanceId) -> Option<picking::Aabb> {
+        let instance = self.instances.get(instance)?;
+        Some(self.obj_model.world_aabb(&instance.transform.matrix()))
+    }
+
+    //the world space box around every visible instance, None when there are none. its bounding_sphere
+    //gives the distance a camera needs to frame the whole scene
+    pub fn scene_bounds(&self) -> Option<picking::Aabb> {
+        self.instances
+            .iter()
+            .filter(|instance| instance.transform.max_scale() != 0.0)
+            .map(|instance| self.obj_model.world_aabb(&instance.transform.matrix()))
+            .reduce(|all, bounds| all.union(&bounds))
+    }
+
+    //the nearest instance a world space ray hits and the distance along it to the hit
+    pub fn pick_ray(&self, ray: &picking::Ray) -> Option<(InstanceId, f32)> {
+        profiling::scope!("pick_ray");
+        //the index only hands over instances whose world box the ray enters, nearest first
+        self.spatial_index.cast_ray(ray, |index| {
+            let local = Self::model_space_ray(&self.instances.get(index)?.transform, ray)?;
+            self.obj_model.intersect_ray(&local)
+        })
+    }
+
+    //the nearest triangle a world space ray hits across every instance, with the hit point and
+    //normals in world space. unlike pick_ray it only sees models loaded with keep_triangles, for
+    //traces that need to know where and at what angle they hit
+    pub fn raycast(&self, ray: &picking::Ray) -> Option<(InstanceId, picking::Hit)> {
+        profiling::scope!("raycast");
+        let model_space_hit = |index: usize| {
+            let local = Self::model_space_ray(&self.instances.get(index)?.transform, ray)?;
+            self.obj_model.raycast(&local)
+        };
+        let (index, _) = self
+            .spatial_index
+            .cast_ray(ray, |index| model_space_hit(index).map(|hit| hit.distance))?;
+        let hit = model_space_hit(index)?;
+        let normal_matrix = self.instances[index].transform.normal_matrix();
+        Some((
+            index,
+            picking::Hit {
+                point: ray.at(hit.distance),
+                normal: (normal_matrix * hit.normal).normalize(),
+                face_normal: (normal_matrix * hit.face_normal).normalize(),
+                ..hit
+            },
+        ))
+    }
+
+    //a world space ray in an instance's model space. distances along it match the world space
+    //ones since the same linear map is applied to the origin and direction. None for an instance
+    //flattened on any axis, which can't be unscaled
+    fn model_space_ray(
+        transform: &transform::Transform,
+        ray: &picking::Ray,
+    ) -> Option<picking::Ray> {
+        if transform.scale().product() == 0.0 {
+            return None;
+        }
+        let unrotate = transform.rotation().invert();
+        Some(picking::Ray::new(
+            cgmath::Point3::from_vec(
+                unrotate
+                    .rotate_vector(ray.origin.to_vec() - transform.position())
+                    .div_element_wise(transform.scale()),
+            ),
+            unrotate.rotate_vector(ray.direction).div_element_wise(transform.scale()),
+        ))
+    }
+
+    //adds a new instance of the model, growing the instance buffer if needed. returns its index
+    pub fn add_instance(
+        &mut self,
+        position: cgmath::Vector3<f32>,
+        rotation: cgmath::Quaternion<f32>,
+    ) -> usize {
+        profiling::scope!("add_instance");
+        let instance = Instances::new(
+            transform::Transform::from_position_rotation(position, rotation),
+            0,
+            [1.0; 4],
+        );
+        self.instance_buffer.push(&self.device, &instance);
+        let index = self.instances.len();
+        self.spatial_index
+            .insert(index, self.obj_model.world_aabb(&instance.transform.matrix()));
+        self.instances.push(instance);
+        self.sync_indirect_instance_counts();
+        self.prepare_culler();
+        self.instances.len() - 1
+    }
+
+    //replaces the drawn model with one loaded from the res folder. indirect commands and culling
+    //are rebuilt for the new meshes
+    pub async fn load_model(
+        &mut self,
+        file_name: &str,
+        mut options: ModelLoadOptions,
+    ) -> anyhow::Result<()> {
+        options.sampler = options.sampler.supported_by(&self.device_features);
+        self.obj_model = resources::load_model(
+            file_name,
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            &mut self.binding_cache,
+            &options,
+        )
+        .await?;
+        self.model_name = file_name.to_string();
+        //every instance's box depends on the model's
+        self.rebuild_spatial_index();
+        if let Some((kind, _)) = self.collider {
+            if !self.generate_collider(kind) {
+                tracing::warn!("{} can't give a {:?} collider", file_name, kind);
+            }
+        }
+        //the packed array holds the old model's textures
+        self.material_array = None;
+        self.binding_cache.trim();
+        self.scene_bundles.get_mut().invalidate();
+        if self.indirect_draws.is_some() {
+            self.set_indirect_drawing(true);
+        }
+        Ok(())
+    }
+
+    //adds a single copy of the model drawn with its own uniform state rather than as an instance,
+    //for objects that need a unique tint or shininess. returns its index
+    pub fn add_object(&mut self, object: object_uniforms::ObjectUniform) -> usize {
+        self.objects.push(&self.device, object)
+    }
+
+    //returns false if there is no object at that index
+    pub fn set_object(&mut self, index: usize, object: object_uniforms::ObjectUniform) -> bool {
+        self.objects.set(index, object)
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.objects.len()
+    }
+
+    //packs the diffuse texture of every material into one texture array so each instance can
+    //pick its texture by layer and every instance of a mesh still goes out in a single draw. the
+    //textures must all be the same size. returns the number of layers
+    pub fn pack_materials(&mut self) -> anyhow::Result<u32> {
+        let textures: Vec<&texture::Texture> = self
+            .obj_model
+            .materials
+            .iter()
+            .map(|material| material.diffuse_texture.as_ref())
+            .collect();
+        let array = texture::Texture::array_from_textures(
+            &self.device,
+            &self.queue,
+            &textures,
+            "Material Array",
+        )?;
+        let layers = textures.len() as u32;
+        let bind_group = self.binding_cache.bind_group(
+            &self.device,
+            Some("Material Array Bind Group"),
+            &self.material_array_layout,
+            &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&array.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&array.sampler),
+                },
+            ],
+        );
+        let pipeline = self.debug_view_pipeline(Self::material_array_pipeline_key(
+            self.config.format,
+            self.depth_format,
+            self.instance_buffer.format(),
+        ))?;
+        let material = model::Material {
+            name: "Material Array".to_string(),
+            diffuse_texture: Arc::new(array),
+            bind_group,
+        };
+        self.material_array = Some((material, pipeline));
+        //the previous array's bind group is only held by the cache now
+        self.binding_cache.trim();
+        self.scene_bundles.get_mut().invalidate();
+        Ok(layers)
+    }
+
+    //goes back to drawing each mesh with its own material
+    pub fn unpack_materials(&mut self) {
+        self.material_array = None;
+        self.binding_cache.trim();
+        self.scene_bundles.get_mut().invalidate();
+    }
+
+    //picks which packed material layer an instance samples. returns false if there is no
+    //instance at that index
+    pub fn set_instance_layer(&mut self, index: usize, layer: u32) -> bool {
+        let Some(instance) = self.instances.get_mut(index) else {
+            return false;
+        };
+        instance.layer = layer;
+        self.instance_buffer.set(index, instance)
+    }
+
+    //switches the model draws over to draw_indexed_indirect, the commands are kept in step with
+    //the instance count so culling can later vary it without re-recording the draws
+    pub fn set_indirect_drawing(&mut self, enabled: bool) {
+        self.scene_bundles.get_mut().invalidate();
+        if !enabled {
+            self.indirect_draws = None;
+            self.merged_meshes = None;
+            self.culler = None;
+            return;
+        }
+        self.merged_meshes = None;
+        if self.device_features.multi_draw_indirect {
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Merge Meshes Encoder"),
+                });
+            self.merged_meshes =
+                indirect::MergedMeshes::new(&self.device, &mut encoder, &self.obj_model.meshes);
+            self.queue.submit(Some(encoder.finish()));
+        }
+        let instance_count = self.drawn_instance_count();
+        let commands = match self.merged_meshes.as_ref() {
+            Some(merged) => merged.commands(instance_count),
+            None => self
+                .obj_model
+                .meshes
+                .iter()
+                .map(|mesh| indirect::DrawIndexedCommand {
+                    index_count: mesh.num_elements,
+                    instance_count,
+                    ..Default::default()
+                })
+                .collect(),
+        };
+        self.indirect_draws = Some(indirect::IndirectBuffer::new(
+            &self.device,
+            "Indirect Draw Buffer",
+            commands,
+        ));
+        self.prepare_culler();
+    }
+
+    pub fn is_indirect_drawing(&self) -> bool {
+        self.indirect_draws.is_some()
+    }
+
+    //frustum culls the instances in a compute pass before every scene pass and draws only the
+    //survivors. turns on indirect drawing as the visible count never comes back to the cpu
+    pub fn set_gpu_culling(&mut self, enabled: bool) {
+        self.scene_bundles.get_mut().invalidate();
+        if !enabled {
+            self.culler = None;
+            return;
+        }
+        if self.indirect_draws.is_none() {
+            self.set_indirect_drawing(true);
+        }
+        let layout = match self.instance_buffer.format() {
+            InstanceFormat::Full => culling::InstanceLayout::Matrix,
+            InstanceFormat::Compact => culling::InstanceLayout::Compact,
+        };
+        self.culler = Some(culling::GpuCuller::new(
+            &self.device,
+            self.instance_buffer.stride(),
+            layout,
+        ));
+        self.prepare_culler();
+    }
+
+    pub fn is_gpu_culling(&self) -> bool {
+        self.culler.is_some()
+    }
+
+    //re-uploads every instance in the given format and switches the scene pipelines over to the
+    //matching shaders. the compact format cuts the instance data to about a third
+    pub fn set_instance_format(&mut self, format: InstanceFormat) -> anyhow::Result<()> {
+        if format == self.instance_buffer.format() {
+            return Ok(());
+        }
+        self.set_scene_pipelines(format)?;
+        self.custom_materials.set_instance_format(
+            &self.device,
+            &mut self.pipeline_cache,
+            self.config.format,
+            self.depth_format,
+            format,
+        );
+        self.instance_buffer = InstanceData::new(&self.device, format, &self.instances);
+        //the culler reads the instances so it is rebuilt for the new layout
+        if self.culler.is_some() {
+            self.set_gpu_culling(true);
+        }
+        self.apply_draw_distance();
+        self.scene_bundles.get_mut().invalidate();
+        Ok(())
+    }
+
+    pub fn instance_format(&self) -> InstanceFormat {
+        self.instance_buffer.format()
+    }
+
+    //replays the light and model draws from a cached render bundle instead of encoding them
+    //every frame. on by default
+    pub fn set_scene_bundles(&mut self, enabled: bool) {
+        self.scene_bundles.get_mut().set_enabled(enabled);
+    }
+
+    pub fn is_scene_bundles(&self) -> bool {
+        self.scene_bundles.borrow().is_enabled()
+    }
+
+    //how many times the scene bundle has been recorded
+    pub fn scene_bundle_recordings(&self) -> u32 {
+        self.scene_bundles.borrow().recordings()
+    }
+
+    //the visible buffer may be reallocated, which the recorded scene bundle binds
+    fn prepare_culler(&mut self) {
+        self.scene_bundles.get_mut().invalidate();
+        if let (Some(culler), Some(indirect_draws)) =
+            (self.culler.as_mut(), self.indirect_draws.as_ref())
+        {
+            culler.prepare(&self.device, self.instance_buffer.buffer(), indirect_draws);
+        }
+    }
+
+    //records the cull pass for the given camera, a no-op unless gpu culling is on
+    #[profiling::function]
+    fn encode_cull(&self, encoder: &mut wgpu::CommandEncoder, camera: &camera::Camera) {
+        if let (Some(culler), Some(indirect_draws)) =
+            (self.culler.as_ref(), self.indirect_draws.as_ref())
+        {
+            culler.cull(
+                &self.uploader,
+                encoder,
+                &self.device,
+                &culling::Frustum::from_view_projection(&camera.build_view_projection())
+                    .with_draw_distance(self.draw_distance()),
+                self.obj_model.bounding_radius(),
+                self.instances.len() as u32,
+                indirect_draws,
+                self.gpu_timer
+                    .as_ref()
+                    .and_then(|timer| timer.compute_pass_writes("cull")),
+            );
+        }
+    }
+
+    fn sync_indirect_instance_counts(&mut self) {
+        let instance_count = self.drawn_instance_count();
+        if let Some(indirect_draws) = self.indirect_draws.as_mut() {
+            for index in 0..indirect_draws.len() {
+                indirect_draws.set_instance_count(index, instance_count);
+            }
+        }
+    }
+
+    //stages this frame's uniform values into the encoder ahead of the passes that read them
+    #[profiling::function]
+    fn upload_uniforms(&self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(indirect_draws) = self.indirect_draws.as_ref() {
+            indirect_draws.upload(&self.uploader, encoder, &self.device);
+        }
+        self.instance_buffer
+            .upload(&self.uploader, encoder, &self.device);
+        if let Some(culled) = self.distance_culled.as_ref() {
+            culled.upload(&self.uploader, encoder, &self.device);
+        }
+        self.objects.upload(&self.uploader, encoder, &self.device);
+        self.light_buffer
+            .stage(&self.uploader, encoder, &self.device, &self.light_uniform);
+        self.camera_buffer
+            .stage(&self.uploader, encoder, &self.device, &self.camera_uniform);
+    }
+
+    //finishes the staged uploads, submits the encoder and hands the staging chunks back
+    fn submit(&self, mut encoder: wgpu::CommandEncoder) {
+        if let Some(timer) = self.gpu_timer.as_ref() {
+            timer.resolve(&mut encoder);
+        }
+        self.uploader.finish();
+        self.queue.submit(Some(encoder.finish()));
+        self.uploader.recall();
+        if let Some(timer) = self.gpu_timer.as_ref() {
+            timer.after_submit(&self.device);
+        }
+    }
+
+    #[profiling::function]
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        profiler::tracy_zone!("render");
+        self.upload_dirty_transforms();
+        if let Some(target) = self.offscreen_target.as_ref() {
+            self.gpu_capture.begin_frame();
+            self.render_to_view(&target.view);
+            Self::record_frame(&self.device, &self.queue, &mut self.recorder, &target.texture);
+            self.gpu_capture.end_frame();
+            self.frame_stats
+                .record_frame(self.draw_stats.get().draws, self.drawn_instance_count());
+            self.record_benchmark_frame();
+            self.debug_lines.get_mut().clear();
+            return Ok(());
+        }
+        let Some(surface) = self.surface.as_ref() else {
+            return Ok(());
+        };
+        let output = surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.gpu_capture.begin_frame();
+        self.render_to_view(&view);
+        Self::record_frame(&self.device, &self.queue, &mut self.recorder, &output.texture);
+        output.present();
+        self.gpu_capture.end_frame();
+        self.frame_stats
+            .record_frame(self.draw_stats.get().draws, self.drawn_instance_count());
+        self.record_benchmark_frame();
+        self.debug_lines.get_mut().clear();
+        Ok(())
+    }
+
+    //replaces the scene with the benchmark's instances, turns vsync and the fps limit off and
+    //flies the camera along its path, measuring every frame. when it finishes the report is
+    //logged, written to the config's report path and kept for take_benchmark_report
+    pub fn start_benchmark(&mut self, config: benchmark::BenchmarkConfig) {
+        let instances = config
+            .transforms()
+            .into_iter()
+            .map(|transform| Instances::new(transform, 0, [1.0; 4]))
+            .collect();
+        self.replace_instances(instances);
+        if !self.set_vsync(false) {
+            tracing::warn!("the surface can't present without vsync, frame times will be capped");
+        }
+        self.set_fps_limit(None);
+        self.camera_flight = None;
+        tracing::info!(
+            "benchmarking {} instances for {}s",
+            config.instances,
+            config.seconds
+        );
+        self.benchmark_report = None;
+        self.benchmark = Some(benchmark::Benchmark::new(config, frame_stats::now_seconds()));
+    }
+
+    pub fn is_benchmarking(&self) -> bool {
+        self.benchmark.is_some()
+    }
+
+    //the report of a benchmark that has finished since the last call
+    pub fn take_benchmark_report(&mut self) -> Option<benchmark::BenchmarkReport> {
+        self.benchmark_report.take()
+    }
 
-        // This is to instancing of our object to display multiple copys of the same object, This will map
-        // 10 in x,y,z direction and rotate the object up to 45 degree as it gets further away
-        let num_instances_per_row = 10;
-        let instances = (0..num_instances_per_row)
-            .flat_map(|z| {
-                (0..num_instances_per_row)
-                    .flat_map(move |x| {
-                        (0..num_instances_per_row).map(move |y| {
-                            let space_between = 3.0;
-                            let position = cgmath::Vector3 {
-                                x: space_between * (x as f32 - num_instances_per_row as f32 / 2.0),
-                                y: 0.5,
-                                z: space_between * (z as f32 - num_instances_per_row as f32 / 2.0),
-                            };
-
-                            let rotation = 
-                                cgmath::Quaternion::from_axis_angle((0.0,1.0,0.0).into(),
-                                    cgmath::Deg(180.0));
-//                            } else {
-//                                cgmath::Quaternion::from_axis_angle(
-//                                    position.normalize(),
-//                                    cgmath::Deg(45.0),
-//                                )
-//                            };
-
-                            Instances { position, rotation }
-                        })
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-        //takes our instance position and rotation to turn into a matrix4X4 so it can be read by the shader
-        let instance_data: Vec<InstanceRaw> = instances.iter().map(Instances::to_raw).collect();
-        //puts the instance into the buffer
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Instance Buffer"),
-            contents: bytemuck::cast_slice(&instance_data),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-        //define the layout of our bind group for our textures
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
-                label: Some("texture_bind_group_layout"),
-            });
-        //create our depth texture which will amend texel displayed based on depth rather than CW or CCW
-        let depth_texture =
-            texture::Texture::create_depth_texture(&device, &config, "depth_texture");
-        //loading in our model and the associated texture
-        let obj_model =
-            resources::load_model("cube.obj", &device, &queue, &texture_bind_group_layout)
-                .await
-                .unwrap();
+    //starts a benchmark and renders until it finishes, for headless states. a windowed app
+    //should use App::set_benchmark, which leaves the event loop running. gives up when frames
+    //keep failing or when nothing gets rendered, e.g. while suspended, well past the run's length
+    pub fn run_benchmark(
+        &mut self,
+        config: benchmark::BenchmarkConfig,
+    ) -> error::Result<benchmark::BenchmarkReport> {
+        let deadline = frame_stats::now_seconds()
+            + (config.warmup_seconds + config.seconds) as f64
+            + BENCHMARK_GRACE_SECONDS;
+        self.start_benchmark(config);
+        let mut failed_frames = 0;
+        loop {
+            self.update();
+            match self.render() {
+                Ok(()) => failed_frames = 0,
+                Err(e) => {
+                    tracing::debug!("benchmark frame skipped: {}", e);
+                    failed_frames += 1;
+                    if failed_frames >= BENCHMARK_MAX_FAILED_FRAMES {
+                        self.benchmark = None;
+                        return Err(e.into());
+                    }
+                }
+            }
+            if let Some(report) = self.take_benchmark_report() {
+                return Ok(report);
+            }
+            if frame_stats::now_seconds() > deadline {
+                self.benchmark = None;
+                return Err(EngineError::Benchmark(
+                    "it ran past its length without finishing, frames aren't being rendered",
+                ));
+            }
+        }
+    }
 
-        //create our camera controller and send it to the buffer
-        let camera_controller = camera_controller::CameraController::new();
-        let mut camera = camera::Camera::new(size.width as f32, size.height as f32);
-        let mut camera_uniform = camera::CameraUniform::new();
-        //adds our camera into a buffer
-        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("camera buffer"),
-            contents: bytemuck::cast_slice(&[camera_uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-        //define the layout of the camera bind group
-        let camera_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("camera_bind_group_layout"),
-            });
-        //create the camera bind group using our layout and load the buffer into it
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &camera_bind_group_layout,
-            label: Some("camera_bind_group"),
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
+    fn record_benchmark_frame(&mut self) {
+        let (draws, drawn) = (self.draw_stats.get().draws, self.drawn_instance_count());
+        let finished = self.benchmark.as_mut().is_some_and(|benchmark| {
+            benchmark.record_frame(frame_stats::now_seconds(), draws, drawn)
         });
+        let Some(benchmark) = self.benchmark.take_if(|_| finished) else {
+            return;
+        };
+        let report = benchmark.report(&self.adapter.get_info());
+        tracing::info!("benchmark: {}", report);
+        if let Some(path) = benchmark.config().report.as_ref() {
+            match report.save(path) {
+                Ok(()) => tracing::info!("benchmark report written to {}", path.display()),
+                Err(e) => tracing::error!("{:#}", e),
+            }
+        }
+        self.benchmark_report = Some(report);
+    }
 
-let light_uniform = LightUniform {
-    position: [2.0,2.0,2.0],
-    _padding: 0, 
-    color: [1.0, 1.0, 1.0],
-    _padding2:0,
-};
-let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
-    label: Some("Light Buffer"),
-    contents: bytemuck::cast_slice(&[light_uniform]),
-    usage:wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-    });
-        //define where the shader is and load it into the program
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
-    let light_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{
-        entries: &[wgpu::BindGroupLayoutEntry{
-            binding: 0,
-            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
-        label: None,
-    });
-let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor{
-    layout: &light_bind_group_layout,
-    label: Some("Light Bind Group"),
-    entries: &[wgpu::BindGroupEntry{
-    binding: 0,
-    resource: light_buffer.as_entire_binding(),
-    }],
-});
+    //starts capturing every nth frame to the sink, replacing any recording already running
+    pub fn start_recording(
+        &mut self,
+        sink: recorder::RecordingSink,
+        every_nth: u32,
+    ) -> error::Result<()> {
+        if self.offscreen_target.is_none()
+            && !self.config.usage.contains(wgpu::TextureUsages::COPY_SRC)
+        {
+            return Err(EngineError::Capture(anyhow::anyhow!(
+                "the surface doesn't support copying frames out of it"
+            )));
+        }
+        self.stop_recording()?;
+        self.recorder = Some(recorder::FrameRecorder::new(sink, every_nth)?);
+        Ok(())
+    }
 
-        //define the render pipeline layout. which will need our bind group layouts that are needed to be
-        //rendered
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&texture_bind_group_layout, &camera_bind_group_layout, &light_bind_group_layout,],
-                push_constant_ranges: &[],
-            });
-let render_pipeline = {
-    let shader = wgpu::ShaderModuleDescriptor {
-        label: Some("Normal Shader"),
-        source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-    };
-    create_render_pipeline(
-        &device,
-        &render_pipeline_layout,
-        config.format,
-        Some(texture::Texture::DEPTH_FORMAT),
-        &[model::ModelVertex::desc(), InstanceRaw::desc()],
-        shader,
-    )
-};
-let light_render_pipeline = {
-    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-        label: Some("Light Pipeline Layout"),
-        bind_group_layouts: &[&camera_bind_group_layout, &light_bind_group_layout],
-        push_constant_ranges: &[],
-    });
-    let shader = wgpu::ShaderModuleDescriptor {
-        label: Some("Light Shader"),
-        source: wgpu::ShaderSource::Wgsl(include_str!("light.wgsl").into()),
-    };
-    create_render_pipeline(
-        &device,
-        &layout,
-        config.format,
-        Some(texture::Texture::DEPTH_FORMAT),
-        &[model::ModelVertex::desc()],
-        shader,
-    )
-};
- 
-        Self {
-            surface,
-            device,
-            queue,
-            config,
-            size,
-            render_pipeline,
-            depth_texture,
-            camera,
-            camera_uniform,
-            camera_buffer,
-            camera_bind_group,
-            camera_controller,
-            instances,
-            instance_buffer,
-            light_buffer,
-            light_uniform,
-            light_bind_group,
-            light_render_pipeline,
-            obj_model,
+    //flushes all frames still in flight and returns how many were captured
+    pub fn stop_recording(&mut self) -> error::Result<u64> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(&self.device),
+            None => Ok(0),
         }
     }
-    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.size = new_size;
-            self.camera.aspect = self.config.width as f32 / self.config.height as f32;
-            self.surface.configure(&self.device, &self.config);
-            self.depth_texture =
-                texture::Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    //copies the rendered frame into the recorder, a failing recorder is stopped rather than
+    //taking the render loop down with it
+    fn record_frame(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        recorder: &mut Option<recorder::FrameRecorder>,
+        texture: &wgpu::Texture,
+    ) {
+        let Some(active) = recorder.as_mut() else {
+            return;
+        };
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let result = match active.record(device, &mut encoder, texture) {
+            Ok(true) => {
+                queue.submit(Some(encoder.finish()));
+                active.after_submit(device)
+            }
+            Ok(false) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::error!("stopping recording: {}", e);
+            if let Some(Err(e)) = recorder.take().map(|recorder| recorder.finish(device)) {
+                tracing::error!("{}", e);
+            }
         }
     }
-    fn input(&mut self, event: &WindowEvent) -> bool {
-        self.camera_controller.process_events(event)
+
+    //renders a frame and saves it as a png. in headless mode the offscreen texture is copied,
+    //otherwise the surface texture is copied before it is presented
+    pub fn capture_frame(&mut self, path: &std::path::Path) -> anyhow::Result<()> {
+        self.upload_dirty_transforms();
+        let (width, height) = (self.config.width, self.config.height);
+        let buffer = capture::create_readback_buffer(&self.device, width, height);
+        if let Some(target) = self.offscreen_target.as_ref() {
+            self.render_to_view(&target.view);
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            capture::copy_texture_to_buffer(&mut encoder, &target.texture, &buffer);
+            self.queue.submit(Some(encoder.finish()));
+        } else {
+            if !self.config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+                anyhow::bail!("the surface doesn't support copying frames out of it");
+            }
+            let surface = self
+                .surface
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("can't capture while suspended"))?;
+            let output = surface.get_current_texture()?;
+            let view = output
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+            self.render_to_view(&view);
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            capture::copy_texture_to_buffer(&mut encoder, &output.texture, &buffer);
+            self.queue.submit(Some(encoder.finish()));
+            output.present();
+        }
+        let image =
+            capture::read_buffer_to_image(&self.device, &buffer, width, height, self.config.format)?;
+        capture::save_png(&image, path)
     }
 
-    fn update(&mut self) {
-        let old_position:  cgmath::Vector3<_> = self.light_uniform.position.into();
-        self. light_uniform.position = ( cgmath::Quaternion::from_axis_angle((0.0, 1.0, 0.0).into(), cgmath::Deg(0.1)) * old_position).into();
-        self.queue.write_buffer(&self.light_buffer, 0, bytemuck::cast_slice(&[self.light_uniform]));
-        self.camera_controller.update_camera(&mut self.camera);
-        self.camera_uniform.update_view_proj(&self.camera);
-        self.queue.write_buffer(
+    //adds a render texture that is resized along with the surface, with a depth buffer in the
+    //scene's depth format if asked for. returns its index for surface_texture. the textures are
+    //replaced on resize, so bind groups made from them need making again when the size changes
+    pub fn add_surface_texture(
+        &mut self,
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsages,
+        depth: bool,
+    ) -> usize {
+        let index = self.surface_textures.len();
+        self.surface_textures.push(texture::RenderTexture::new(
+            &self.device,
+            (self.config.width, self.config.height),
+            format,
+            usage,
+            depth.then_some(self.depth_format),
+            &format!("surface_texture_{}", index),
+        ));
+        index
+    }
+
+    pub fn surface_texture(&self, index: usize) -> Option<&texture::RenderTexture> {
+        self.surface_textures.get(index)
+    }
+
+    //the scene's depth buffer, for passes that read depth such as fog or soft particles. it is
+    //replaced on resize
+    pub fn depth_texture(&self) -> &texture::Texture {
+        &self.depth_texture
+    }
+
+    //creates an offscreen colour + depth target in the surface format, looking at the scene from
+    //the given camera
+    pub fn create_render_target(
+        &self,
+        width: u32,
+        height: u32,
+        camera: camera::Camera,
+    ) -> render_target::RenderTarget {
+        let config = wgpu::SurfaceConfiguration {
+            width: width.max(1),
+            height: height.max(1),
+            ..self.config.clone()
+        };
+        render_target::RenderTarget::new(
+            &self.device,
             &self.camera_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera_uniform]),
+            &config,
+            self.depth_format,
+            camera,
+        )
+    }
+
+    //runs the normal scene pass into the render target using the target's camera. the colour
+    //texture can then be bound like any other texture in a later pass
+    #[profiling::function]
+    pub fn render_to_target(&self, target: &mut render_target::RenderTarget) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        self.upload_uniforms(&mut encoder);
+        target.upload_camera(&self.uploader, &mut encoder, &self.device);
+        self.encode_cull(&mut encoder, &target.camera);
+        self.encode_scene_pass(
+            &mut encoder,
+            &target.color.view,
+            &target.depth.view,
+            target.camera_bind_group(),
+            "target",
         );
+        self.submit(encoder);
     }
 
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture().ok().unwrap();
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    //records and submits the scene pass into the given colour target
+    #[profiling::function]
+    fn render_to_view(&self, view: &wgpu::TextureView) {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        self.upload_uniforms(&mut encoder);
+        self.compute_passes
+            .encode(&mut encoder, self.gpu_timer.as_ref());
+        self.encode_cull(&mut encoder, &self.camera);
+        let scene_view = self
+            .render_scaler
+            .as_ref()
+            .map_or(view, render_scale::RenderScaler::view);
+        self.encode_scene_pass(
+            &mut encoder,
+            scene_view,
+            &self.depth_texture.view,
+            self.camera_buffer.bind_group(),
+            "main",
+        );
+        if let Some(scaler) = self.render_scaler.as_ref() {
+            scaler.blit(&mut encoder, view);
+        }
+        if let Some(overlay) = self
+            .overlay
+            .borrow_mut()
+            .as_mut()
+            .filter(|overlay| overlay.is_visible())
+        {
+            overlay.paint(&self.device, &self.queue, &mut encoder, view, self.size);
+        }
+        self.submit(encoder);
+    }
+
+    #[profiling::function]
+    fn encode_scene_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        camera_bind_group: &wgpu::BindGroup,
+        //names the pass in gpu timings
+        timing_label: &'static str,
+    ) {
+        let object_draws = parallel_encode::ObjectDraws {
+            pipeline: &self.object_pipeline,
+            camera_bind_group,
+            light_bind_group: self.light_buffer.bind_group(),
+            object_bind_group: self.objects.bind_group(),
+            object_stride: self.objects.stride(),
+            object_count: self.objects.len(),
+            model: &self.obj_model,
+            default_material: &self.default_material,
+        };
+        //thousands of unique draws are split up and recorded on several threads rather than
+        //serially into the pass
+        let object_bundles = if self.objects.len() > parallel_encode::OBJECTS_PER_BUNDLE {
+            object_draws.encode_bundles(
+                &self.device,
+                parallel_encode::BundleTargets {
+                    color_format: self.config.format,
+                    depth_format: Some(self.depth_format),
+                    sample_count: 1,
+                },
+            )
+        } else {
+            Vec::new()
+        };
+        //the light and mesh draws only change with the scene, so they are replayed from a bundle
+        //recorded the first time they are needed
+        let bundle_key = scene_bundle::SceneBundleKey {
+            camera_bind_group: camera_bind_group.global_id().inner(),
+            instance_buffer: self.drawn_instances().buffer().global_id().inner(),
+            instance_count: self.drawn_instance_count(),
+            color_format: self.config.format,
+        };
+        self.scene_bundles.borrow_mut().prepare(bundle_key, || {
+            self.record_scene_bundle(camera_bind_group)
+        });
+        let scene_bundles = self.scene_bundles.borrow();
+        let mut debug_lines = self.debug_lines.borrow_mut();
+        debug_lines.upload(&self.device, &self.queue);
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[
-                    // This is what @location(0) in the fragment shader targets
+                    //This is what @location(0) in the fragment shader targets
                     Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
+                        view,
                         resolve_target: None,
                         ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.1,
-                                g: 0.2,
-                                b: 0.3,
-                                a: 1.0,
-                            }),
+                            load: wgpu::LoadOp::Clear(self.clear_color()),
                             store: wgpu::StoreOp::Store,
                         },
                     }),
                 ],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture.view,
+                    view: depth_view,
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
                         store: wgpu::StoreOp::Store,
                     }),
-                    stencil_ops: None,
+                    //masks are drawn again in every pass, so the stencil starts clear
+                    stencil_ops: self.has_stencil().then_some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0),
+                        store: wgpu::StoreOp::Store,
+                    }),
                 }),
+                timestamp_writes: self
+                    .gpu_timer
+                    .as_ref()
+                    .and_then(|timer| timer.render_pass_writes(timing_label)),
                 ..Default::default()
             });
-            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-            render_pass.set_pipeline(&self.light_render_pipeline);
-            render_pass.draw_light_model(
-                &self.obj_model, 
-                &self.camera_bind_group, 
-                &self.light_bind_group
-                );
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.draw_mesh_instanced(
-                &self.obj_model.meshes[0],
-                &self.obj_model.materials[0],
-                0..self.instances.len() as u32,
-                &self.camera_bind_group,
-                &self.light_bind_group,
-            )
+            //marks the mask before anything that tests against it is drawn
+            if let Some((_, masker)) = self.scene_mask.as_ref() {
+                masker.draw(&mut render_pass);
+            }
+            let mut stats = match scene_bundles.get(&bundle_key) {
+                Some((bundle, stats)) => {
+                    render_pass.execute_bundles(std::iter::once(bundle));
+                    stats
+                }
+                None => self.record_static_scene(&mut render_pass, camera_bind_group),
+            };
+            //the per object draws bypass the queue but still count as draws
+            stats.draws += self.obj_model.meshes.len() as u32 * self.objects.len() as u32;
+            self.draw_stats.set(stats);
+            //per object draws share one bind group, only the dynamic offset changes between them.
+            //large counts were recorded into bundles up front
+            if !object_bundles.is_empty() {
+                render_pass.execute_bundles(object_bundles.iter());
+            } else if !self.objects.is_empty() {
+                object_draws.record(&mut render_pass, 0..self.objects.len());
+            }
+            debug_lines.draw(&mut render_pass, &self.debug_line_pipeline, camera_bind_group);
         }
+    }
 
-        self.queue.submit(Some(encoder.finish()));
-        output.present();
-        Ok(())
+    //records the light and every model mesh into a render bundle compatible with the scene pass
+    #[profiling::function]
+    fn record_scene_bundle(
+        &self,
+        camera_bind_group: &wgpu::BindGroup,
+    ) -> (wgpu::RenderBundle, draw_queue::DrawStats) {
+        let mut encoder =
+            self.device
+                .create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                    label: Some("Scene Bundle Encoder"),
+                    color_formats: &[Some(self.config.format)],
+                    depth_stencil: Some(wgpu::RenderBundleDepthStencil {
+                        format: self.depth_format,
+                        depth_read_only: false,
+                        stencil_read_only: true,
+                    }),
+                    sample_count: 1,
+                    multiview: None,
+                });
+        let stats = self.record_static_scene(&mut encoder, camera_bind_group);
+        let bundle = encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some("Scene Bundle"),
+        });
+        (bundle, stats)
+    }
+
+    //the light and instanced model draws, recorded into either the pass or a bundle
+    fn record_static_scene<'p>(
+        &'p self,
+        render_pass: &mut impl indirect::IndirectEncoder<'p>,
+        camera_bind_group: &'p wgpu::BindGroup,
+    ) -> draw_queue::DrawStats {
+        render_pass.set_vertex_buffer(1, self.drawn_instances().slice());
+        render_pass.set_pipeline(&self.light_render_pipeline);
+        render_pass.draw_light_model(
+            &self.obj_model, 
+            camera_bind_group, 
+            self.light_buffer.bind_group()
+            );
+        if let Some(culler) = self.culler.as_ref() {
+            render_pass.set_vertex_buffer(1, culler.visible_instances());
+        }
+        if let (Some(indirect_draws), Some(merged)) =
+            (self.indirect_draws.as_ref(), self.merged_meshes.as_ref())
+        {
+            let mut stats = self.record_merged_meshes(
+                render_pass,
+                camera_bind_group,
+                indirect_draws,
+                merged,
+            );
+            stats.draws += self.obj_model.meshes.len() as u32;
+            return stats;
+        }
+        //every mesh goes through the queue with its own material, sorted so shared state
+        //is only bound once
+        let mut queue = draw_queue::DrawQueue::new();
+        for (index, mesh) in self.obj_model.meshes.iter().enumerate() {
+            let kind = match self.indirect_draws.as_ref() {
+                Some(indirect_draws) => draw_queue::DrawKind::Indirect {
+                    buffer: indirect_draws.buffer(),
+                    offset: indirect::IndirectBuffer::offset(index),
+                },
+                None => draw_queue::DrawKind::Instanced(0..self.drawn_instance_count()),
+            };
+            let (pipeline, material, params) = self.mesh_shading(mesh.material);
+            queue.push(draw_queue::DrawItem {
+                pipeline,
+                material,
+                params,
+                mesh,
+                kind,
+            });
+        }
+        let mut stats = queue.record(render_pass, camera_bind_group, self.light_buffer.bind_group());
+        //the light bypasses the queue but still counts as draws
+        stats.draws += self.obj_model.meshes.len() as u32;
+        stats
+    }
+
+    //the pipeline, material and custom material params a mesh using the model's material at
+    //material_index is drawn with. a custom material takes over the mesh's own material, and
+    //packed materials override the rest with the instance picking the layer
+    fn mesh_shading(
+        &self,
+        material_index: usize,
+    ) -> (
+        &wgpu::RenderPipeline,
+        &model::Material,
+        Option<&wgpu::BindGroup>,
+    ) {
+        let own_material = || {
+            self.obj_model
+                .materials
+                .get(material_index)
+                .unwrap_or(&self.default_material)
+        };
+        if let Some(custom) = self.custom_materials.for_material(material_index) {
+            return match custom.pipeline() {
+                Some(pipeline) => (pipeline, own_material(), Some(custom.params_bind_group())),
+                None => (self.render_pipeline.as_ref(), &self.error_material, None),
+            };
+        }
+        match self.material_array.as_ref() {
+            Some((material, pipeline)) => (pipeline.as_ref(), material, None),
+            None => (self.render_pipeline.as_ref(), own_material(), None),
+        }
+    }
+
+    //the model draws when its meshes share buffers, one indirect run per material. packed
+    //materials are one material, so then everything goes out together
+    fn record_merged_meshes<'p>(
+        &'p self,
+        render_pass: &mut impl indirect::IndirectEncoder<'p>,
+        camera_bind_group: &'p wgpu::BindGroup,
+        indirect_draws: &'p indirect::IndirectBuffer,
+        merged: &'p indirect::MergedMeshes,
+    ) -> draw_queue::DrawStats {
+        render_pass.set_vertex_buffer(0, merged.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(merged.index_buffer.slice(..), merged.index_format);
+        //custom materials still need their runs drawn apart from the packed ones
+        let packed_run = [(0, 0..indirect_draws.len())];
+        let runs = match self.material_array.as_ref() {
+            Some(_) if !self.custom_materials.has_assignments() => &packed_run[..],
+            _ => merged.runs(),
+        };
+        for (material, commands) in runs {
+            let (pipeline, material, params) = self.mesh_shading(*material);
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &material.bind_group, &[]);
+            render_pass.set_bind_group(1, camera_bind_group, &[]);
+            render_pass.set_bind_group(2, self.light_buffer.bind_group(), &[]);
+            if let Some(params) = params {
+                render_pass.set_bind_group(3, params, &[]);
+            }
+            render_pass.draw_indexed_indirect_range(
+                indirect_draws.buffer(),
+                commands.start,
+                commands.len() as u32,
+                self.device_features.multi_draw_indirect,
+            );
+        }
+        let runs = runs.len() as u32;
+        let draws = indirect_draws.len() as u32;
+        draw_queue::DrawStats {
+            draws,
+            pipeline_binds: runs,
+            material_binds: runs,
+            mesh_binds: 1,
+            saved_binds: (draws * 3).saturating_sub(runs * 2 + 1),
+        }
     }
 }
 
 impl ApplicationHandler for App<'_> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         let window_attributes = Window::default_attributes()
-            .with_title("wgpu winit 0.30")
-            .with_inner_size(winit::dpi::LogicalSize::new(1280.0, 720.0));
+            .with_title(WINDOW_TITLE)
+            .with_inner_size(self.window_size.unwrap_or(winit::dpi::LogicalSize::new(
+                self.settings.settings.window.width,
+                self.settings.settings.window.height,
+            )))
+            .with_window_icon(self.window_icon.clone())
+            .with_transparent(self.transparent);
+        //accesskit has to hook the window up before it is first shown
+        #[cfg(feature = "accesskit")]
+        let window_attributes = window_attributes.with_visible(false);
+        //on the web the window is a canvas which gets appended to the page body
+        #[cfg(target_arch = "wasm32")]
+        let window_attributes = {
+            use winit::platform::web::WindowAttributesExtWebSys;
+            window_attributes.with_append(true)
+        };
         if self.window.is_none() {
-            let window = Arc::new(
-                event_loop
-                    .create_window(window_attributes)
-                    .expect("failed to get window attributes"),
-            );
+            logging::init();
+            let window = match event_loop.create_window(window_attributes) {
+                Ok(window) => Arc::new(window),
+                Err(e) => {
+                    self.errors.report(e.into());
+                    event_loop.exit();
+                    return;
+                }
+            };
+            if let Some(selection) = self.monitor_selection.as_ref() {
+                if !windowing::apply_monitor_selection(&window, selection) {
+                    tracing::warn!("no monitor at index {}", selection.monitor_index);
+                }
+            }
+            //a monitor selection made in code wins over the settings file
+            let selected_fullscreen = self
+                .monitor_selection
+                .as_ref()
+                .is_some_and(|selection| selection.fullscreen.is_some());
+            if let (Some(mode), false) = (self.settings.settings.window.fullscreen, selected_fullscreen) {
+                windowing::toggle_fullscreen(&window, mode, None);
+            }
+            #[cfg(feature = "accesskit")]
+            {
+                self.accessibility = Some(accessibility::Accessibility::new(&window));
+                window.set_visible(true);
+            }
             self.window = Some(window.clone());
-            let rt = Runtime::new().expect("Failed to get runtime");
-            let state = GameState::new(window);
-            let state = rt.block_on(state);
-            self.state = Some(state);
+            match executor::InitTask::spawn(GameState::new(
+                window,
+                self.transparent,
+                self.stencil || self.settings.settings.graphics.stencil,
+                self.instance_grid,
+                self.model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+                self.backends,
+            )) {
+                Ok(task) => self.init_task = Some(task),
+                Err(e) => {
+                    self.errors.report(e.into());
+                    event_loop.exit();
+                    return;
+                }
+            }
+        } else if let (Some(window), Some(state)) = (self.window.clone(), self.state.as_mut()) {
+            if let Err(e) = state.resume(window.clone()) {
+                self.errors.report(e);
+            }
+            #[cfg(feature = "audio")]
+            if let Some(audio) = self.audio.as_mut() {
+                audio.resume();
+            }
+            window.request_redraw();
+        }
+        self.apply_pending_cursor(event_loop);
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(state) = self.state.as_mut() {
+            state.suspend();
+        }
+        #[cfg(feature = "audio")]
+        if let Some(audio) = self.audio.as_mut() {
+            audio.pause();
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let state_requested_exit = self
+            .state
+            .as_ref()
+            .is_some_and(|state| state.exit_requested);
+        if self.exit_requested || state_requested_exit {
+            self.shutdown();
+            event_loop.exit();
+            return;
         }
+        self.poll_pending_state(event_loop);
+        self.apply_pending_cursor(event_loop);
+        if let Err(e) = self.settings.poll() {
+            tracing::error!("{:#}", e);
+        }
+        //nothing is redrawn while minimized so there is no point polling, sleep until an event
+        let minimized = self.state.as_ref().is_some_and(GameState::is_minimized);
+        event_loop.set_control_flow(if minimized {
+            ControlFlow::Wait
+        } else {
+            ControlFlow::Poll
+        });
+    }
+
+    //covers the event loop ending without going through request_exit, e.g. on platform quit
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.shutdown();
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
-        if id != self.window.as_ref().unwrap().id() {
+        let Some(window) = self.window.clone() else {
+            return;
+        };
+        if id != window.id() || self.exit_requested {
             return;
         }
-        if !self
-            .state
-            .as_mut()
-            .expect("failed to get input")
-            .input(&event)
-        {
-            match event {
-                WindowEvent::CloseRequested => {
-                    event_loop.exit();
+        #[cfg(feature = "accesskit")]
+        if let Some(accessibility) = self.accessibility.as_mut() {
+            accessibility.process_event(&window, &event);
+        }
+        self.poll_pending_state(event_loop);
+        //events can arrive before the state exists while init is still running
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+        if state.console_input(&event) || state.overlay_input(&event) {
+            return;
+        }
+        if state.input(&event) {
+            return;
+        }
+        match event {
+            //the actual shutdown happens in about_to_wait once this event has been handled
+            WindowEvent::CloseRequested => {
+                self.exit_requested = true;
+            }
+            WindowEvent::Resized(physical_size) => {
+                let was_minimized = state.is_minimized();
+                state.resize(physical_size);
+                //the windowed size is what gets saved, fullscreen and minimized sizes are skipped
+                //as is the size set with set_window_size, only a resize away from it is saved
+                if window.fullscreen().is_none() && !state.is_minimized() {
+                    let size = physical_size.to_logical::<f64>(window.scale_factor());
+                    let overridden = self.window_size.is_some_and(|overridden| {
+                        (overridden.width - size.width).abs() < 1.0
+                            && (overridden.height - size.height).abs() < 1.0
+                    });
+                    if !overridden {
+                        self.settings.update(|settings| {
+                            settings.window.width = size.width;
+                            settings.window.height = size.height;
+                        });
+                    }
                 }
-                WindowEvent::Resized(physical_size) => {
-                    self.state.as_mut().unwrap().resize(physical_size);
+                if was_minimized && !state.is_minimized() {
+                    window.request_redraw();
                 }
-                WindowEvent::RedrawRequested => {
-                    self.state.as_mut().unwrap().update();
-                    match self.state.as_mut().unwrap().render() {
-                        Ok(_) => {
-                            self.state.as_mut().unwrap().update();
-                        }
-                        Err(wgpu::SurfaceError::Lost) => {
-                            let size = self.state.as_mut().unwrap().size;
-                            self.state.as_mut().unwrap().resize(size);
+            }
+            WindowEvent::Occluded(occluded) => {
+                let was_minimized = state.is_minimized();
+                state.set_occluded(occluded);
+                if was_minimized && !state.is_minimized() {
+                    window.request_redraw();
+                }
+            }
+            //winit has already picked the new physical size, follow it so nothing is rendered at
+            //the old resolution and stretched
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                state.set_scale_factor(scale_factor, window.inner_size());
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(keycode),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                let bindings = self.settings.settings.bindings.clone();
+                //F11 toggles borderless fullscreen and Alt+Enter toggles exclusive fullscreen
+                let mode = if bindings.fullscreen.contains(&keycode) {
+                    Some(windowing::FullscreenMode::Borderless)
+                } else if keycode == KeyCode::Enter && self.modifiers.alt_key() {
+                    Some(windowing::FullscreenMode::Exclusive)
+                } else {
+                    None
+                };
+                if let Some(mode) = mode {
+                    let video_mode = self
+                        .monitor_selection
+                        .as_ref()
+                        .and_then(|selection| selection.video_mode.as_ref());
+                    let fullscreen = windowing::toggle_fullscreen(&window, mode, video_mode);
+                    self.settings
+                        .update(|settings| settings.window.fullscreen = fullscreen);
+                }
+                //1 to 9 fly the camera to a bookmark and Ctrl with the number saves one there
+                if let Some(slot) = bookmark_slot(keycode) {
+                    if self.modifiers.control_key() {
+                        match state.save_camera_bookmark(slot) {
+                            Ok(()) => tracing::info!("saved camera bookmark {}", slot),
+                            Err(e) => tracing::error!("{:#}", e),
                         }
-                        Err(wgpu::SurfaceError::OutOfMemory) => event_loop.exit(),
-                        Err(e) => eprintln!("{:?}", e),
+                    } else if !state.go_to_camera_bookmark(slot, BOOKMARK_FLIGHT_SECONDS) {
+                        tracing::info!("no camera bookmark {}, Ctrl+{} saves one", slot, slot);
+                    }
+                }
+                //F1 shows and hides the debug overlay
+                if bindings.overlay.contains(&keycode) {
+                    let visible = !state.is_overlay_visible();
+                    state.set_overlay_visible(visible);
+                }
+                //F6 turns the editor on and off
+                if bindings.editor.contains(&keycode) {
+                    let enabled = !state.is_editor_enabled();
+                    state.set_editor_enabled(enabled);
+                    tracing::info!(enabled, "editor");
+                }
+                //F7 cycles the debug views, the lit scene, albedo, normals, depth, uv checker
+                //and overdraw
+                if bindings.debug_view.contains(&keycode) {
+                    let view = state.debug_view().next();
+                    match state.set_debug_view(view) {
+                        Ok(()) => tracing::info!("debug view: {}", view.name()),
+                        Err(e) => self.errors.report(e),
+                    }
+                }
+                //F8 shows and hides the collision bounds of every instance
+                if bindings.colliders.contains(&keycode) {
+                    let enabled = !state.is_collider_debug();
+                    state.set_collider_debug(enabled);
+                }
+                //F9 turns gpu pass timings on and off, they are printed every couple of seconds
+                if bindings.gpu_profiling.contains(&keycode) {
+                    let enable = !state.is_gpu_profiling();
+                    if !state.set_gpu_profiling(enable) {
+                        tracing::warn!("gpu profiling needs timestamp query support");
+                    }
+                }
+                //F10 starts and stops recording every other frame to a png sequence
+                if bindings.recording.contains(&keycode) {
+                    let result = if state.is_recording() {
+                        state.stop_recording().map(|frames| {
+                            tracing::info!("recorded {} frames", frames);
+                        })
+                    } else {
+                        let directory = capture::timestamped_path("recording").with_extension("");
+                        state.start_recording(recorder::RecordingSink::PngSequence(directory), 2)
+                    };
+                    if let Err(e) = result {
+                        self.errors.report(e);
+                    }
+                }
+                //F2 captures the next frame in renderdoc
+                if bindings.gpu_capture.contains(&keycode) && !state.trigger_capture() {
+                    tracing::warn!(
+                        "gpu captures need the renderdoc feature and the app launched from renderdoc"
+                    );
+                }
+                //F12 saves a screenshot of the next frame to the working directory
+                if bindings.screenshot.contains(&keycode) {
+                    let path = capture::timestamped_path("screenshot");
+                    if let Err(e) = state.capture_frame(&path) {
+                        self.errors.report(EngineError::Capture(e));
                     }
-                    self.window
-                        .as_mut()
-                        .expect("failed to get window")
-                        .request_redraw();
                 }
-                _ => (),
             }
+            WindowEvent::RedrawRequested => self.redraw(event_loop, &window),
+            _ => (),
         }
     }
 }
 
-        fn create_render_pipeline(    
-            device: &wgpu::Device,
-    layout: &wgpu::PipelineLayout,
-    color_format: wgpu::TextureFormat,
-    depth_format: Option<wgpu::TextureFormat>,
-    vertex_layouts: &[wgpu::VertexBufferLayout],
-    shader: wgpu::ShaderModuleDescriptor,) -> wgpu::RenderPipeline{
-            let shader = device.create_shader_module(shader);
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main", // 1.
-                buffers: &[
-                    model::ModelVertex::desc(),
-                    InstanceRaw::desc(),
-                ], // 2.
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                // 3.
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    // 4.
-                    format: color_format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList, // 1.
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw, // 2.
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: texture::Texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }), // 1.
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
+impl animation::Animatable for GameState<'_> {
+    fn get(&self, property: animation::Property) -> Option<animation::Value> {
+        use animation::Property;
+        let instance = |index: usize| self.instances.get(index);
+        Some(match property {
+            Property::InstancePosition(index) => instance(index)?.transform.position().into(),
+            Property::InstanceRotation(index) => instance(index)?.transform.rotation().into(),
+            Property::InstanceScale(index) => instance(index)?.transform.scale().into(),
+            Property::InstanceTint(index) => instance(index)?.tint.into(),
+            Property::LightPosition => self.light_uniform.position.into(),
+            Property::LightColor => self.light_uniform.color.into(),
+            Property::Ambient => self.light_uniform.ambient.into(),
+            Property::CameraEye => self.camera.eye.to_vec().into(),
+            Property::CameraTarget => self.camera.target.to_vec().into(),
+            Property::CameraFov => self.camera.fovy.into(),
         })
+    }
+
+    //values of the wrong kind for the property are ignored
+    fn set(&mut self, property: animation::Property, value: animation::Value) {
+        use animation::{Property, Value};
+        match (property, value) {
+            (Property::InstancePosition(index), Value::Vec3(position)) => {
+                self.modify_instance_transform(index, |transform| transform.set_position(position));
+            }
+            (Property::InstanceRotation(index), Value::Quat(rotation)) => {
+                self.modify_instance_transform(index, |transform| {
+                    transform.set_rotation(rotation.normalize())
+                });
+            }
+            (Property::InstanceScale(index), Value::Vec3(scale)) => {
+                self.set_instance_scale_xyz(index, scale);
+            }
+            (Property::InstanceTint(index), Value::Vec4(tint)) => {
+                self.set_instance_tint(index, tint.into());
+            }
+            (Property::LightPosition, Value::Vec3(position)) => {
+                self.light_uniform.position = position.into();
+            }
+            (Property::LightColor, Value::Vec3(color)) => self.light_uniform.color = color.into(),
+            (Property::Ambient, Value::Float(ambient)) => self.light_uniform.ambient = ambient,
+            (Property::CameraEye, Value::Vec3(eye)) => self.camera.eye = cgmath::Point3::from_vec(eye),
+            (Property::CameraTarget, Value::Vec3(target)) => {
+                self.camera.target = cgmath::Point3::from_vec(target);
+            }
+            (Property::CameraFov, Value::Float(fovy)) => self.camera.fovy = fovy,
+            _ => (),
+        }
+    }
+}
+
+//the bookmark a number key stands for
+fn bookmark_slot(keycode: KeyCode) -> Option<&'static str> {
+    Some(match keycode {
+        KeyCode::Digit1 => "1",
+        KeyCode::Digit2 => "2",
+        KeyCode::Digit3 => "3",
+        KeyCode::Digit4 => "4",
+        KeyCode::Digit5 => "5",
+        KeyCode::Digit6 => "6",
+        KeyCode::Digit7 => "7",
+        KeyCode::Digit8 => "8",
+        KeyCode::Digit9 => "9",
+        _ => return None,
+    })
+}
 
+//entry point for the web build, the winit event loop is handed to the browser rather than run
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen(start)]
+pub fn run_web() {
+    use winit::platform::web::EventLoopExtWebSys;
+    console_error_panic_hook::set_once();
+    //there is no stderr in the browser, so the error goes to the console
+    let event_loop = match EventLoop::new() {
+        Ok(event_loop) => event_loop,
+        Err(e) => {
+            web_sys::console::error_1(&EngineError::from(e).to_string().into());
+            return;
+        }
+    };
+    event_loop.set_control_flow(ControlFlow::Poll);
+    event_loop.spawn_app(App::default());
 }
 
+impl CompactInstanceRaw {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<CompactInstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                //scale, after the matrix locations the full format uses
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 14,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                //material array layer, only read by the packed material shader
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 10]>() as wgpu::BufferAddress,
+                    shader_location: 12,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                //tint, unpacked to 0..1 floats
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
+                    shader_location: 13,
+                    format: wgpu::VertexFormat::Unorm8x4,
+                },
+            ],
+        }
+    }
+}
 
 impl InstanceRaw {
     fn desc() -> wgpu::VertexBufferLayout<'static> {
@@ -571,6 +4550,17 @@ impl InstanceRaw {
                     shader_location: 11,
                     format: wgpu::VertexFormat::Float32x3,
                 },
+                //material array layer, only read by the packed material shader
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 25]>() as wgpu::BufferAddress,
+                    shader_location: 12,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 26]>() as wgpu::BufferAddress,
+                    shader_location: 13,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
@@ -1,14 +1,8 @@
+#include "common.wgsl"
 
-struct Camera {
-    view_proj: mat4x4<f32>,
-}
 @group(0) @binding(0)
-var<uniform> camera: Camera;
+var<uniform> camera: CameraUniform;
 
-struct Light {
-    position: vec3<f32>,
-    color: vec3<f32>,
-}
 @group(1) @binding(0)
 var<uniform> light: Light;
 
@@ -0,0 +1,26 @@
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+//what is logged when RUST_LOG isn't set: warnings from everything and progress from this crate
+const DEFAULT_FILTER: &str = "warn,wgpu_winit_0_30=info";
+
+//human readable events on stderr, filtered by RUST_LOG. the filter only applies to this layer so
+//a profiling layer sharing the subscriber still sees every span
+pub(crate) fn fmt_layer<S>() -> impl Layer<S>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(filter)
+}
+
+//installs the stderr subscriber. does nothing if one is already set, either by the application
+//embedding the app or by the profile-tracing cpu profiler, which includes the same layer. the
+//app calls it when it creates its window, call it earlier to see anything logged before that
+pub fn init() {
+    let _ = tracing_subscriber::registry().with(fmt_layer()).try_init();
+}
@@ -1,9 +1,167 @@
-use wgpu_winit_0_30::App;
+use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use wgpu_winit_0_30::benchmark::BenchmarkConfig;
+use wgpu_winit_0_30::instance_grid::InstanceGrid;
+use wgpu_winit_0_30::{App, EngineError};
 use winit::event_loop::{ControlFlow, EventLoop};
 
-fn main() {
-    let event_loop = EventLoop::new().expect("failed to get event loop");
-    event_loop.set_control_flow(ControlFlow::Poll);
+//the user's config folder rather than the working directory, so resizing the window of a build
+//run from a checkout doesn't leave a file in it. the working directory where there's no such folder
+fn settings_path() -> PathBuf {
+    match dirs::config_dir() {
+        Some(folder) => folder.join("wgpu_winit_0_30").join("settings.toml"),
+        None => PathBuf::from("settings.toml"),
+    }
+}
+
+fn main() -> ExitCode {
+    //parsed first so --help and mistakes exit before anything starts up
+    let args = Args::parse();
     let mut app = App::default();
-    let _ = event_loop.run_app(&mut app);
+    //profiling builds capture from startup and write the profile on exit
+    #[cfg(any(
+        feature = "profile-puffin",
+        feature = "profile-tracing",
+        feature = "profile-tracy"
+    ))]
+    let profiling = app.start_cpu_profiling("profile");
+    //after the profiler, which installs its own subscriber writing the same output
+    wgpu_winit_0_30::logging::init();
+    #[cfg(any(
+        feature = "profile-puffin",
+        feature = "profile-tracing",
+        feature = "profile-tracy"
+    ))]
+    if let Err(e) = profiling {
+        tracing::warn!("cpu profiling unavailable: {}", e);
+    }
+    //a settings file that can't be read leaves the defaults in place and is never overwritten
+    if let Err(e) = app.load_settings(settings_path()) {
+        tracing::warn!("{:#}", e);
+    }
+    app.set_camera_bookmarks_path("bookmarks.toml");
+    if let Err(message) = args.apply(&mut app) {
+        tracing::error!("{}", message);
+        return ExitCode::FAILURE;
+    }
+    match run(&mut app) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            tracing::error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+//the instances in a benchmark run unless --instances says otherwise
+const BENCHMARK_INSTANCES: u32 = 10_000;
+
+#[derive(Parser)]
+#[command(version, about = "Views an .obj model with the wgpu renderer")]
+struct Args {
+    #[arg(help = "The .obj model to view, cube.obj from the res folder if left out")]
+    model: Option<PathBuf>,
+    #[arg(
+        long,
+        value_parser = parse_backends,
+        help = "Graphics APIs to pick an adapter from, comma separated: vulkan, metal, dx12, gl or webgpu"
+    )]
+    backend: Option<wgpu::Backends>,
+    #[arg(
+        long,
+        value_name = "WIDTHxHEIGHT",
+        value_parser = parse_size,
+        help = "Window size in logical pixels, over the settings file"
+    )]
+    size: Option<(f64, f64)>,
+    #[arg(long, help = "Turn vsync on or off, over the settings file")]
+    vsync: Option<bool>,
+    #[arg(long, help = "How many instances of the model to draw, in a cube")]
+    instances: Option<u32>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Run the benchmark for this long and exit, with 10000 instances unless --instances is given"
+    )]
+    benchmark: Option<f32>,
+    #[arg(
+        long,
+        default_value = "benchmark.csv",
+        requires = "benchmark",
+        help = "Where the benchmark report goes, a .json path gets json and anything else gets a csv row appended"
+    )]
+    report: PathBuf,
+    #[arg(
+        long,
+        requires = "benchmark",
+        help = "Stored with the benchmark results, e.g. a commit hash"
+    )]
+    label: Option<String>,
+}
+
+impl Args {
+    fn apply(self, app: &mut App) -> Result<(), String> {
+        if let Some(model) = self.model {
+            //the app looks relative paths up in the res folder, the command line means the
+            //working directory. not canonicalize, that gives \\?\ paths on windows
+            std::fs::metadata(&model)
+                .map_err(|e| format!("can't open {}: {}", model.display(), e))?;
+            let path = std::path::absolute(&model)
+                .map_err(|e| format!("can't open {}: {}", model.display(), e))?;
+            app.set_model(path.to_string_lossy());
+        }
+        if let Some(backends) = self.backend {
+            app.set_backends(backends);
+        }
+        if let Some((width, height)) = self.size {
+            app.set_window_size(width, height);
+        }
+        if let Some(vsync) = self.vsync {
+            app.set_vsync(vsync);
+        }
+        match self.benchmark {
+            Some(seconds) => {
+                let instances = self.instances.unwrap_or(BENCHMARK_INSTANCES);
+                let config = BenchmarkConfig::new(instances, seconds)
+                    .report(self.report)
+                    .label(self.label.unwrap_or_default());
+                app.set_benchmark(config);
+            }
+            None => {
+                if let Some(count) = self.instances {
+                    //turned to face the camera like the demo grid
+                    let rotation = InstanceGrid::default().rotation;
+                    app.set_instance_grid(InstanceGrid::cube(count, 3.0).rotation(rotation));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_backends(text: &str) -> Result<wgpu::Backends, String> {
+    let backends = wgpu::util::parse_backends_from_comma_list(&text.to_lowercase());
+    if backends.is_empty() {
+        return Err(format!("no known backend in {}", text));
+    }
+    Ok(backends)
+}
+
+fn parse_size(text: &str) -> Result<(f64, f64), String> {
+    let (width, height) = text
+        .split_once('x')
+        .ok_or_else(|| format!("{} isn't WIDTHxHEIGHT", text))?;
+    let parse = |side: &str| match side.trim().parse::<f64>() {
+        Ok(side) if side >= 1.0 => Ok(side),
+        _ => Err(format!("{} isn't a size", side)),
+    };
+    Ok((parse(width)?, parse(height)?))
+}
+
+fn run(app: &mut App) -> Result<(), EngineError> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Poll);
+    event_loop.run_app(app)?;
+    Ok(())
 }
@@ -1,6 +1,7 @@
-use crate::texture;
+use crate::{collision, picking, texture};
 use core::ops::Range;
 use std::mem;
+use std::sync::Arc;
 
 pub trait DrawModel<'a> {
     fn draw_mesh(
@@ -18,10 +19,32 @@ pub trait DrawModel<'a> {
         camera_bind_group: &'a wgpu::BindGroup,
         light_bind_group: &'a wgpu::BindGroup,
     );
+
+    //every mesh of the model, each with the material its material index points at. meshes whose
+    //index is out of range are drawn with fallback
+    fn draw_model(
+        &mut self,
+        model: &'a Model,
+        fallback: &'a Material,
+        camera_bind_group: &'a wgpu::BindGroup,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
+    fn draw_model_instanced(
+        &mut self,
+        model: &'a Model,
+        fallback: &'a Material,
+        instances: Range<u32>,
+        camera_bind_group: &'a wgpu::BindGroup,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
 }
-impl<'a, 'b> DrawModel<'b> for wgpu::RenderPass<'a>
+
+//implemented for anything that records render commands, like DrawLight, so models can be drawn
+//into a pass or a render bundle
+impl<'a, 'b, T> DrawModel<'b> for T
 where
     'b: 'a,
+    T: wgpu::util::RenderEncoder<'a>,
 {
     fn draw_mesh(
         &mut self,
@@ -41,12 +64,41 @@ where
         light_bind_group: &'b wgpu::BindGroup,
     ) {
         self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-        self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        self.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
         self.set_bind_group(0, &material.bind_group, &[]);
         self.set_bind_group(1, camera_bind_group, &[]);
         self.set_bind_group(2, light_bind_group, &[]);
         self.draw_indexed(0..mesh.num_elements, 0, instances);
     }
+
+    fn draw_model(
+        &mut self,
+        model: &'b Model,
+        fallback: &'b Material,
+        camera_bind_group: &'b wgpu::BindGroup,
+        light_bind_group: &'b wgpu::BindGroup,
+    ) {
+        self.draw_model_instanced(model, fallback, 0..1, camera_bind_group, light_bind_group);
+    }
+
+    fn draw_model_instanced(
+        &mut self,
+        model: &'b Model,
+        fallback: &'b Material,
+        instances: Range<u32>,
+        camera_bind_group: &'b wgpu::BindGroup,
+        light_bind_group: &'b wgpu::BindGroup,
+    ) {
+        for mesh in &model.meshes {
+            self.draw_mesh_instanced(
+                mesh,
+                model.material_or(mesh, fallback),
+                instances.clone(),
+                camera_bind_group,
+                light_bind_group,
+            );
+        }
+    }
 }
 pub trait Vertex {
     fn desc() -> wgpu::VertexBufferLayout<'static>;
@@ -57,18 +109,180 @@ pub struct Model {
     pub materials: Vec<Material>,
 }
 
+impl Model {
+    //a sphere around the model origin that contains every mesh
+    pub fn bounding_radius(&self) -> f32 {
+        self.meshes
+            .iter()
+            .map(|mesh| mesh.bounding_radius)
+            .fold(0.0, f32::max)
+    }
+
+    //the box around every mesh in model space
+    pub fn aabb(&self) -> picking::Aabb {
+        let mut bounds = self.meshes.iter().map(|mesh| mesh.bounds);
+        let first = bounds.next().unwrap_or(picking::Aabb::from_points([]));
+        bounds.fold(first, |all, mesh| all.union(&mesh))
+    }
+
+    //the box around the model placed by transform, from each mesh's box so it stays tighter than
+    //transforming aabb when meshes are spread out
+    pub fn world_aabb(&self, transform: &cgmath::Matrix4<f32>) -> picking::Aabb {
+        let mut bounds = self.meshes.iter().map(|mesh| mesh.bounds.transformed(transform));
+        let first = bounds
+            .next()
+            .unwrap_or_else(|| picking::Aabb::from_points([]).transformed(transform));
+        bounds.fold(first, |all, mesh| all.union(&mesh))
+    }
+
+    //the nearest hit of a model space ray, against the triangles where the meshes kept them and
+    //the bounding boxes otherwise
+    pub fn intersect_ray(&self, ray: &picking::Ray) -> Option<f32> {
+        self.meshes
+            .iter()
+            .filter_map(|mesh| {
+                let box_hit = mesh.bounds.intersect_ray(ray)?;
+                match mesh.triangles {
+                    Some(_) => mesh.raycast(ray).map(|hit| hit.distance),
+                    None => Some(box_hit),
+                }
+            })
+            .min_by(f32::total_cmp)
+    }
+
+    //the nearest triangle a model space ray hits across every mesh, None when it misses or no
+    //mesh kept its triangles
+    pub fn raycast(&self, ray: &picking::Ray) -> Option<picking::Hit> {
+        self.meshes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, mesh)| {
+                let hit = mesh.raycast(ray)?;
+                Some(picking::Hit { mesh: index, ..hit })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    //a collision shape around every mesh, see collision::Collider::generate. hulls and trimeshes
+    //are built from the triangles kept with ModelLoadOptions::keep_triangles and are None
+    //without them, boxes and spheres fall back to the mesh bounds
+    pub fn collider(&self, kind: collision::ColliderKind) -> Option<collision::Collider> {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for triangles in self.meshes.iter().filter_map(|mesh| mesh.triangles.as_ref()) {
+            let base = positions.len() as u32;
+            positions.extend_from_slice(&triangles.positions);
+            indices.extend(triangles.indices.iter().map(|index| index + base));
+        }
+        match kind {
+            collision::ColliderKind::Aabb | collision::ColliderKind::Sphere
+                if positions.is_empty() && !self.meshes.is_empty() =>
+            {
+                let bounds = self.aabb();
+                collision::Collider::generate(kind, &[bounds.min.into(), bounds.max.into()], &[])
+            }
+            _ => collision::Collider::generate(kind, &positions, &indices),
+        }
+    }
+
+    //the material a mesh refers to, None if the index is out of range so the caller can fall
+    //back to a default instead of panicking on a hand built or empty model
+    pub fn material(&self, mesh: &Mesh) -> Option<&Material> {
+        self.materials.get(mesh.material)
+    }
+
+    //the mesh's material, or fallback when its index is out of range
+    pub fn material_or<'m>(&'m self, mesh: &Mesh, fallback: &'m Material) -> &'m Material {
+        self.material(mesh).unwrap_or(fallback)
+    }
+}
+
 pub struct Material {
     pub name: String,
-    pub diffuse_texture: texture::Texture,
-    pub bind_group: wgpu::BindGroup,
+    pub diffuse_texture: Arc<texture::Texture>,
+    pub bind_group: Arc<wgpu::BindGroup>,
 }
 
 pub struct Mesh {
     pub name: String,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
+    //Uint16 for meshes with few enough vertices, halving the index memory
+    pub index_format: wgpu::IndexFormat,
     pub num_elements: u32,
     pub material: usize,
+    //distance from the mesh origin to its furthest vertex, used as a bounding sphere for culling
+    pub bounding_radius: f32,
+    //the box around the mesh in model space, the first test when picking
+    pub bounds: picking::Aabb,
+    //a cpu copy of the triangles, only kept when loaded with ModelLoadOptions::keep_triangles
+    pub triangles: Option<MeshTriangles>,
+}
+
+impl Mesh {
+    //the sphere around the mesh's bounding box in model space, centered on the mesh rather than
+    //its origin like bounding_radius
+    pub fn bounding_sphere(&self) -> picking::BoundingSphere {
+        self.bounds.bounding_sphere()
+    }
+
+    //the nearest triangle a model space ray hits, with its mesh left as 0. needs the triangles
+    //kept with ModelLoadOptions::keep_triangles, None without them
+    pub fn raycast(&self, ray: &picking::Ray) -> Option<picking::Hit> {
+        self.bounds.intersect_ray(ray)?;
+        self.triangles.as_ref()?.raycast(ray)
+    }
+}
+
+//triangle list positions and normals kept on the cpu for picking against the actual shape
+pub struct MeshTriangles {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshTriangles {
+    pub fn iter(&self) -> impl Iterator<Item = [cgmath::Point3<f32>; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|triangle| {
+            [0, 1, 2].map(|corner| cgmath::Point3::from(self.positions[triangle[corner] as usize]))
+        })
+    }
+
+    //tests every triangle, the nearest hit wins
+    pub fn raycast(&self, ray: &picking::Ray) -> Option<picking::Hit> {
+        use cgmath::InnerSpace;
+        let (triangle, corners, distance, barycentric) = self
+            .iter()
+            .enumerate()
+            .filter_map(|(index, corners)| {
+                let (distance, barycentric) = picking::intersect_triangle_barycentric(ray, corners)?;
+                Some((index, corners, distance, barycentric))
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))?;
+        let [a, b, c] = corners;
+        let face_normal = (b - a).cross(c - a).normalize();
+        let indices = &self.indices[triangle * 3..triangle * 3 + 3];
+        let blended: cgmath::Vector3<f32> = indices
+            .iter()
+            .zip(barycentric)
+            .map(|(&index, weight)| cgmath::Vector3::from(self.normals[index as usize]) * weight)
+            .sum();
+        //vertices without normals leave the flat one
+        let normal = if blended.magnitude2() > 0.0 {
+            blended.normalize()
+        } else {
+            face_normal
+        };
+        Some(picking::Hit {
+            distance,
+            point: ray.at(distance),
+            barycentric,
+            normal,
+            face_normal,
+            mesh: 0,
+            triangle,
+        })
+    }
 }
 
 #[repr(C)]
@@ -77,6 +291,11 @@ pub struct ModelVertex {
     pub position: [f32; 3],
     pub tex_coords: [f32; 2],
     pub normal: [f32; 3],
+    //multiplied into the shaded color, white for meshes without vertex colors
+    pub color: [f32; 3],
+    //a second uv set for lightmaps and detail textures, a copy of tex_coords when the file has
+    //only one
+    pub tex_coords_1: [f32; 2],
 }
 
 impl Vertex for ModelVertex {
@@ -100,6 +319,16 @@ impl Vertex for ModelVertex {
                     format: wgpu::VertexFormat::Float32x3,
                     shader_location: 2,
                 },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    format: wgpu::VertexFormat::Float32x3,
+                    shader_location: 3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
+                    format: wgpu::VertexFormat::Float32x2,
+                    shader_location: 4,
+                },
             ],
         }
     }
@@ -135,9 +364,12 @@ pub trait DrawLight<'a> {
     );
 }
 
-impl<'a, 'b> DrawLight<'b> for wgpu::RenderPass<'a>
+//implemented for anything that records render commands so the light can be drawn straight into
+//a pass or into a render bundle
+impl<'a, 'b, T> DrawLight<'b> for T
 where
     'b: 'a,
+    T: wgpu::util::RenderEncoder<'a>,
 {
     fn draw_light_mesh(
         &mut self,
@@ -156,7 +388,7 @@ where
         light_bind_group: &'b wgpu::BindGroup,
     ) {
         self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-        self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        self.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
         self.set_bind_group(0, camera_bind_group, &[]);
         self.set_bind_group(1, light_bind_group, &[]);
         self.draw_indexed(0..mesh.num_elements, 0, instances);
@@ -183,3 +415,27 @@ where
     }
 }
  
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{InnerSpace, Point3, Vector3};
+
+    #[test]
+    fn raycasts_blend_the_vertex_normals() {
+        //a unit quad facing +z whose right edge leans its normals towards +x
+        let triangles = MeshTriangles {
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+            normals: vec![[0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 0.0, 1.0], [0.0, 0.0, 1.0]],
+            indices: vec![0, 1, 2, 0, 2, 3],
+        };
+        let ray = picking::Ray::new(Point3::new(0.25, 0.75, 2.0), -Vector3::unit_z());
+        let hit = triangles.raycast(&ray).unwrap();
+        assert_eq!(hit.triangle, 1);
+        assert_eq!(hit.distance, 2.0);
+        assert_eq!(hit.point, Point3::new(0.25, 0.75, 0.0));
+        assert_eq!(hit.face_normal, Vector3::unit_z());
+        //a quarter of the way across, between the straight and leaning normals
+        assert!((hit.normal - Vector3::new(0.25, 0.0, 1.0).normalize()).magnitude() < 1e-5);
+    }
+}
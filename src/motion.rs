@@ -0,0 +1,217 @@
+use crate::transform::Transform;
+use cgmath::prelude::*;
+use cgmath::{Deg, Quaternion, Vector3};
+
+//a callback moving one instance each update, given its transform, the seconds since the motion
+//was added and the seconds since the last update
+pub type MotionFn = Box<dyn FnMut(&mut Transform, f32, f32) + Send>;
+
+//the built in motions. each is applied as a change from the last update rather than a fixed
+//pose, so they stack with each other and with anything else moving the instance
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Motion {
+    //turns in place about an axis through the instance
+    Spin {
+        axis: Vector3<f32>,
+        degrees_per_second: f32,
+    },
+    //circles a point, turning about an axis through it. the radius is wherever the instance is
+    //when it starts, its rotation is left alone
+    Orbit {
+        center: Vector3<f32>,
+        axis: Vector3<f32>,
+        degrees_per_second: f32,
+    },
+    //swings back and forth along an offset, reaching it and its opposite once each cycle
+    Bob {
+        offset: Vector3<f32>,
+        hertz: f32,
+    },
+}
+
+impl Motion {
+    //turning about y
+    pub fn spin(degrees_per_second: f32) -> Self {
+        Motion::Spin {
+            axis: Vector3::unit_y(),
+            degrees_per_second,
+        }
+    }
+
+    //circling the origin about y
+    pub fn orbit(degrees_per_second: f32) -> Self {
+        Motion::Orbit {
+            center: Vector3::zero(),
+            axis: Vector3::unit_y(),
+            degrees_per_second,
+        }
+    }
+
+    //up and down by height
+    pub fn bob(height: f32, hertz: f32) -> Self {
+        Motion::Bob {
+            offset: Vector3::unit_y() * height,
+            hertz,
+        }
+    }
+
+    //moves a transform on from elapsed seconds in by another delta seconds
+    pub fn apply(&self, transform: &mut Transform, elapsed: f32, delta: f32) {
+        match *self {
+            Motion::Spin {
+                axis,
+                degrees_per_second,
+            } => {
+                let turn =
+                    Quaternion::from_axis_angle(axis.normalize(), Deg(degrees_per_second * delta));
+                transform.rotate(turn);
+            }
+            Motion::Orbit {
+                center,
+                axis,
+                degrees_per_second,
+            } => {
+                let turn =
+                    Quaternion::from_axis_angle(axis.normalize(), Deg(degrees_per_second * delta));
+                transform.set_position(center + turn.rotate_vector(transform.position() - center));
+            }
+            Motion::Bob { offset, hertz } => {
+                let phase = |seconds: f32| (std::f32::consts::TAU * hertz * seconds).sin();
+                transform.translate(offset * (phase(elapsed + delta) - phase(elapsed)));
+            }
+        }
+    }
+}
+
+enum Kind {
+    Builtin(Motion),
+    Custom(MotionFn),
+}
+
+struct Entry {
+    instance: usize,
+    kind: Kind,
+    //seconds since the motion was added
+    elapsed: f32,
+}
+
+//the motions attached to instances, ticked once per update. an instance can have any number,
+//they run in the order they were added
+#[derive(Default)]
+pub struct Motions {
+    entries: Vec<Entry>,
+}
+
+impl Motions {
+    pub fn add(&mut self, instance: usize, motion: Motion) {
+        self.push(instance, Kind::Builtin(motion));
+    }
+
+    pub fn add_fn(&mut self, instance: usize, motion: MotionFn) {
+        self.push(instance, Kind::Custom(motion));
+    }
+
+    fn push(&mut self, instance: usize, kind: Kind) {
+        self.entries.push(Entry {
+            instance,
+            kind,
+            elapsed: 0.0,
+        });
+    }
+
+    //stops every motion on an instance where it is, returns false if it had none
+    pub fn remove(&mut self, instance: usize) -> bool {
+        let count = self.entries.len();
+        self.entries.retain(|entry| entry.instance != instance);
+        self.entries.len() != count
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    //moves every motion on by seconds. edit is handed each instance index with the change to
+    //make to its transform, and returns false when there is no such instance, which drops the
+    //motion
+    pub fn tick(
+        &mut self,
+        seconds: f32,
+        mut edit: impl FnMut(usize, &mut dyn FnMut(&mut Transform)) -> bool,
+    ) {
+        self.entries.retain_mut(|entry| {
+            let elapsed = entry.elapsed;
+            entry.elapsed += seconds;
+            edit(entry.instance, &mut |transform| match &mut entry.kind {
+                Kind::Builtin(motion) => motion.apply(transform, elapsed, seconds),
+                Kind::Custom(motion) => motion(transform, elapsed, seconds),
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32) -> Transform {
+        Transform::from_position_rotation(Vector3::new(x, 0.0, 0.0), Quaternion::one())
+    }
+
+    #[test]
+    fn builtin_motions_follow_their_rates() {
+        let mut spun = at(0.0);
+        Motion::spin(90.0).apply(&mut spun, 0.0, 1.0);
+        let facing = spun.rotation().rotate_vector(Vector3::unit_x());
+        assert!((facing - -Vector3::unit_z()).magnitude() < 1e-5);
+
+        let mut orbiting = at(2.0);
+        Motion::orbit(90.0).apply(&mut orbiting, 0.0, 1.0);
+        assert!((orbiting.position() - Vector3::new(0.0, 0.0, -2.0)).magnitude() < 1e-5);
+
+        //a whole cycle in small steps ends back where it began
+        let mut bobbing = at(1.0);
+        let bob = Motion::bob(0.5, 2.0);
+        for step in 0..50 {
+            bob.apply(&mut bobbing, step as f32 * 0.01, 0.01);
+        }
+        assert!((bobbing.position() - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn motions_stack_and_drop_with_their_instance() {
+        let mut transforms = [at(0.0), at(5.0)];
+        let mut motions = Motions::default();
+        motions.add(
+            0,
+            Motion::Bob {
+                offset: Vector3::unit_x(),
+                hertz: 0.25,
+            },
+        );
+        motions.add_fn(
+            0,
+            Box::new(|transform, _, delta| transform.translate(Vector3::unit_y() * delta)),
+        );
+        motions.add(7, Motion::spin(10.0));
+        motions.tick(1.0, |index, edit| match transforms.get_mut(index) {
+            Some(transform) => {
+                edit(transform);
+                true
+            }
+            None => false,
+        });
+        assert!((transforms[0].position() - Vector3::new(1.0, 1.0, 0.0)).magnitude() < 1e-5);
+        assert_eq!(transforms[1].position(), Vector3::new(5.0, 0.0, 0.0));
+        assert_eq!(motions.len(), 2);
+        assert!(motions.remove(0));
+        assert!(motions.is_empty());
+    }
+}
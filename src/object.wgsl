@@ -0,0 +1,69 @@
+#include "common.wgsl"
+
+// Vertex shader
+@group(1) @binding(0) 
+var<uniform> camera: CameraUniform;
+
+@group(2) @binding(0)
+var<uniform> light: Light;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) color: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) color: vec3<f32>,
+}
+// per object state read through a dynamic offset instead of per instance vertex data
+struct ObjectUniform {
+    model_matrix: mat4x4<f32>,
+    normal_matrix: mat3x3<f32>,
+    tint: vec4<f32>,
+    shininess: f32,
+}
+@group(3) @binding(0)
+var<uniform> object: ObjectUniform;
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.color = model.color;
+    out.world_normal = normalize(object.normal_matrix * model.normal);
+    var world_position: vec4<f32> = object.model_matrix * vec4<f32>(model.position, 1.0);
+    out.world_position = world_position.xyz;
+    out.clip_position = camera.view_proj * world_position;
+    return out;
+}
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords) * object.tint
+        * vec4<f32>(in.color, 1.0);
+    let ambient_color = light.color * light.ambient;
+    let light_dir = normalize(light.position - in.world_position);
+    let diffuse_strength = max(dot(in.world_normal, light_dir), 0.0);
+    let diffuse_color = light.color * diffuse_strength;
+    let view_dir = normalize(camera.view_pos.xyz - in.world_position);
+    let half_dir = normalize(view_dir + light_dir);
+    let specular_strength = pow(max(dot(in.world_normal, half_dir), 0.0), object.shininess);
+    let specular_color = specular_strength * light.color;
+
+    let result = (ambient_color + diffuse_color + specular_color) * object_color.xyz;
+    // premultiplied so a transparent window composites correctly, opaque texels are unchanged
+    return vec4<f32>(result * object_color.a, object_color.a);
+}
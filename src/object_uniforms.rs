@@ -0,0 +1,200 @@
+use crate::upload;
+use cgmath::prelude::*;
+use std::cell::Cell;
+
+//matches ObjectUniform in object.wgsl. the mat3 columns are padded out to vec4s
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ObjectUniform {
+    pub model: [[f32; 4]; 4],
+    pub normal: [[f32; 4]; 3],
+    pub tint: [f32; 4],
+    pub shininess: f32,
+    pub _padding: [f32; 3],
+}
+
+impl ObjectUniform {
+    pub fn new(position: cgmath::Vector3<f32>, rotation: cgmath::Quaternion<f32>) -> Self {
+        Self::with_transform(position, rotation, cgmath::vec3(1.0, 1.0, 1.0))
+    }
+
+    //like new with a per axis scale applied before the rotation, the normal matrix is built to
+    //match so stretched objects still light correctly
+    pub fn with_transform(
+        position: cgmath::Vector3<f32>,
+        rotation: cgmath::Quaternion<f32>,
+        scale: cgmath::Vector3<f32>,
+    ) -> Self {
+        let model = cgmath::Matrix4::from_translation(position)
+            * cgmath::Matrix4::from(rotation)
+            * cgmath::Matrix4::from_nonuniform_scale(scale.x, scale.y, scale.z);
+        let normal = crate::normal_matrix(rotation, scale);
+        Self {
+            model: model.into(),
+            normal: [normal.x.extend(0.0).into(), normal.y.extend(0.0).into(), normal.z.extend(0.0).into()],
+            tint: [1.0; 4],
+            shininess: 32.0,
+            _padding: [0.0; 3],
+        }
+    }
+
+    pub fn with_tint(mut self, tint: [f32; 4]) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn with_shininess(mut self, shininess: f32) -> Self {
+        self.shininess = shininess;
+        self
+    }
+}
+
+//one uniform buffer holding the state of every object, each at its own aligned offset. a single
+//bind group with a dynamic offset covers all of them, so objects with unique transforms or
+//material params don't need their own buffers or bind groups
+pub struct ObjectUniforms {
+    layout: wgpu::BindGroupLayout,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    //bytes between objects, the uniform size rounded up to the device's offset alignment
+    stride: wgpu::BufferAddress,
+    capacity: usize,
+    objects: Vec<ObjectUniform>,
+    dirty: Cell<bool>,
+}
+
+impl ObjectUniforms {
+    const SIZE: wgpu::BufferAddress = std::mem::size_of::<ObjectUniform>() as wgpu::BufferAddress;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Object Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(Self::SIZE),
+                },
+                count: None,
+            }],
+        });
+        let alignment =
+            device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let stride = Self::SIZE.div_ceil(alignment) * alignment;
+        let capacity = 16;
+        let (buffer, bind_group) = Self::create_buffer(device, &layout, stride, capacity);
+        Self {
+            layout,
+            buffer,
+            bind_group,
+            stride,
+            capacity,
+            objects: Vec::new(),
+            dirty: Cell::new(false),
+        }
+    }
+
+    fn create_buffer(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        stride: wgpu::BufferAddress,
+        capacity: usize,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Object Uniform Buffer"),
+            size: stride * capacity as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        //the binding only covers one object, the dynamic offset picks which
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Object Bind Group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(Self::SIZE),
+                }),
+            }],
+        });
+        (buffer, bind_group)
+    }
+
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&ObjectUniform> {
+        self.objects.get(index)
+    }
+
+    //the dynamic offset to bind the object at
+    pub fn offset(&self, index: usize) -> u32 {
+        (self.stride * index as wgpu::BufferAddress) as u32
+    }
+
+    //bytes between consecutive objects' offsets
+    pub fn stride(&self) -> u32 {
+        self.stride as u32
+    }
+
+    //adds an object, doubling the buffer when full. returns its index
+    pub fn push(&mut self, device: &wgpu::Device, object: ObjectUniform) -> usize {
+        self.objects.push(object);
+        if self.objects.len() > self.capacity {
+            self.capacity *= 2;
+            (self.buffer, self.bind_group) =
+                Self::create_buffer(device, &self.layout, self.stride, self.capacity);
+        }
+        self.dirty.set(true);
+        self.objects.len() - 1
+    }
+
+    //returns false if the index is out of range
+    pub fn set(&mut self, index: usize, object: ObjectUniform) -> bool {
+        let Some(slot) = self.objects.get_mut(index) else {
+            return false;
+        };
+        *slot = object;
+        self.dirty.set(true);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.objects.clear();
+    }
+
+    //writes every object at its aligned offset if anything changed
+    pub fn upload(
+        &self,
+        uploader: &upload::Uploader,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+    ) {
+        if !self.dirty.replace(false) || self.objects.is_empty() {
+            return;
+        }
+        let stride = self.stride as usize;
+        let mut data = vec![0u8; stride * self.objects.len()];
+        for (object, chunk) in self.objects.iter().zip(data.chunks_exact_mut(stride)) {
+            chunk[..Self::SIZE as usize].copy_from_slice(bytemuck::bytes_of(object));
+        }
+        uploader.write(encoder, device, &self.buffer, 0, &data);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::model::{Material, Model};
+use std::ops::Range;
+use wgpu::util::RenderEncoder;
+
+//objects recorded into each bundle. small enough to spread across threads, big enough that the
+//per bundle overhead doesn't matter
+pub const OBJECTS_PER_BUNDLE: usize = 256;
+
+//what the bundles will be executed in, they must match the render pass exactly
+#[derive(Debug, Copy, Clone)]
+pub struct BundleTargets {
+    pub color_format: wgpu::TextureFormat,
+    pub depth_format: Option<wgpu::TextureFormat>,
+    pub sample_count: u32,
+}
+
+//everything needed to record per object draws
+pub struct ObjectDraws<'a> {
+    pub pipeline: &'a wgpu::RenderPipeline,
+    pub camera_bind_group: &'a wgpu::BindGroup,
+    pub light_bind_group: &'a wgpu::BindGroup,
+    //the shared object bind group and the dynamic offset step between objects
+    pub object_bind_group: &'a wgpu::BindGroup,
+    pub object_stride: u32,
+    pub object_count: usize,
+    pub model: &'a Model,
+    //drawn with for meshes whose material index is out of range
+    pub default_material: &'a Material,
+}
+
+impl<'a> ObjectDraws<'a> {
+    //records the draws for a range of objects into a render pass or bundle encoder
+    pub fn record(&self, encoder: &mut impl RenderEncoder<'a>, objects: Range<usize>) {
+        encoder.set_pipeline(self.pipeline);
+        encoder.set_bind_group(1, self.camera_bind_group, &[]);
+        encoder.set_bind_group(2, self.light_bind_group, &[]);
+        for index in objects {
+            let offset = self.object_stride * index as u32;
+            encoder.set_bind_group(3, self.object_bind_group, &[offset]);
+            for mesh in &self.model.meshes {
+                let material = self.model.material_or(mesh, self.default_material);
+                encoder.set_bind_group(0, &material.bind_group, &[]);
+                encoder.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                encoder.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
+                encoder.draw_indexed(0..mesh.num_elements, 0, 0..1);
+            }
+        }
+    }
+
+    fn encode_bundle(
+        &self,
+        device: &wgpu::Device,
+        targets: BundleTargets,
+        objects: Range<usize>,
+    ) -> wgpu::RenderBundle {
+        profiling::scope!("encode object bundle");
+        let mut encoder = device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+            label: Some("Object Bundle"),
+            color_formats: &[Some(targets.color_format)],
+            depth_stencil: targets.depth_format.map(|format| wgpu::RenderBundleDepthStencil {
+                format,
+                depth_read_only: false,
+                stencil_read_only: true,
+            }),
+            sample_count: targets.sample_count,
+            multiview: None,
+        });
+        self.record(&mut encoder, objects);
+        encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some("Object Bundle"),
+        })
+    }
+
+    //splits the objects into bundles and records them, on the rayon pool when the parallel
+    //feature is on. the bundles are executed together in a single render pass afterwards
+    pub fn encode_bundles(&self, device: &wgpu::Device, targets: BundleTargets) -> Vec<wgpu::RenderBundle> {
+        let chunks: Vec<Range<usize>> = (0..self.object_count)
+            .step_by(OBJECTS_PER_BUNDLE)
+            .map(|start| start..(start + OBJECTS_PER_BUNDLE).min(self.object_count))
+            .collect();
+        #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+        {
+            use rayon::prelude::*;
+            chunks
+                .into_par_iter()
+                .map(|chunk| self.encode_bundle(device, targets, chunk))
+                .collect()
+        }
+        #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+        {
+            chunks
+                .into_iter()
+                .map(|chunk| self.encode_bundle(device, targets, chunk))
+                .collect()
+        }
+    }
+}
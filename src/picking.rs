@@ -0,0 +1,274 @@
+use crate::camera::Camera;
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector3, Vector4};
+
+//rays are tested against triangles edge on up to this much, anything flatter counts as a miss
+const PARALLEL_EPSILON: f32 = 1e-7;
+
+//an instance's index, as returned by add_instance and taken by set_instance_transform
+pub type InstanceId = usize;
+
+//a half line from origin, direction doesn't have to be normalised. hit distances are measured in
+//multiples of it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Self {
+        Self { origin, direction }
+    }
+
+    //the ray from the camera through a point on screen, in physical pixels from the top left. it
+    //starts at the eye and has unit length, so hit distances are in world units. None for an
+    //empty viewport or a camera whose projection can't be inverted
+    pub fn from_screen(
+        camera: &Camera,
+        cursor: winit::dpi::PhysicalPosition<f64>,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Option<Self> {
+        if size.width == 0 || size.height == 0 {
+            return None;
+        }
+        let x = (2.0 * cursor.x / size.width as f64 - 1.0) as f32;
+        let y = (1.0 - 2.0 * cursor.y / size.height as f64) as f32;
+        let inverse = camera.build_view_projection().invert()?;
+        //any point under the cursor gives the direction, measuring it from the eye keeps it
+        //pointing into the scene whatever depth range the projection maps to
+        let point = Point3::from_homogeneous(inverse * Vector4::new(x, y, 0.0, 1.0));
+        let direction = point - camera.eye;
+        if direction.magnitude2() == 0.0 {
+            return None;
+        }
+        Some(Self::new(camera.eye, direction.normalize()))
+    }
+
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+}
+
+//an axis aligned box, used as the quick first test before any triangles
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    //the smallest box holding every point, a zero sized box at the origin when there are none
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]>) -> Self {
+        let mut points = points.into_iter().map(Point3::from);
+        let Some(first) = points.next() else {
+            return Self {
+                min: Point3::origin(),
+                max: Point3::origin(),
+            };
+        };
+        points.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |bounds, point| Self {
+                min: Point3::new(
+                    bounds.min.x.min(point.x),
+                    bounds.min.y.min(point.y),
+                    bounds.min.z.min(point.z),
+                ),
+                max: Point3::new(
+                    bounds.max.x.max(point.x),
+                    bounds.max.y.max(point.y),
+                    bounds.max.z.max(point.z),
+                ),
+            },
+        )
+    }
+
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::from_points([self.min, self.max, other.min, other.max].map(Into::into))
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    pub fn size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    //the eight corners, min first and max last
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    //the box around this one once transformed, e.g. a mesh's model space bounds placed by an
+    //instance. it can be looser than the shape inside when rotated
+    pub fn transformed(&self, transform: &Matrix4<f32>) -> Self {
+        Self::from_points(
+            self.corners()
+                .map(|corner| transform.transform_point(corner).into()),
+        )
+    }
+
+    //whether the boxes share any space, touching counts
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|axis| self.min[axis] <= other.max[axis] && other.min[axis] <= self.max[axis])
+    }
+
+    //the square of the distance from a point to the nearest point in the box, 0 inside it
+    pub fn distance2(&self, point: Point3<f32>) -> f32 {
+        (0..3)
+            .map(|axis| {
+                let outside = (self.min[axis] - point[axis]).max(point[axis] - self.max[axis]);
+                outside.max(0.0).powi(2)
+            })
+            .sum()
+    }
+
+    //the sphere through the corners
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere {
+            center: self.center(),
+            radius: self.size().magnitude() * 0.5,
+        }
+    }
+
+    //distance along the ray to where it enters the box, 0 when it starts inside
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<f32> {
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+        for axis in 0..3 {
+            let origin = ray.origin[axis];
+            let direction = ray.direction[axis];
+            if direction == 0.0 {
+                if origin < self.min[axis] || origin > self.max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let a = (self.min[axis] - origin) / direction;
+            let b = (self.max[axis] - origin) / direction;
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+}
+
+//a sphere around something, for framing it with the camera or quick overlap tests
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+//where a ray meets the triangles of a mesh, see Mesh::raycast
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Hit {
+    //along the ray, in multiples of its direction
+    pub distance: f32,
+    pub point: Point3<f32>,
+    //how much of each of the triangle's corners is in the hit point, they add up to 1. blends
+    //anything stored per vertex, like uvs or colors
+    pub barycentric: [f32; 3],
+    //the vertex normals blended at the hit point, unit length
+    pub normal: Vector3<f32>,
+    //the flat normal of the triangle, on the side its corners wind counter clockwise around
+    pub face_normal: Vector3<f32>,
+    //the mesh's index in its model
+    pub mesh: usize,
+    //the triangle's index in the mesh, its corners are indices 3 * triangle to 3 * triangle + 2
+    pub triangle: usize,
+}
+
+//distance along the ray to where it crosses the triangle, from either side. moller-trumbore
+pub fn intersect_triangle(ray: &Ray, triangle: [Point3<f32>; 3]) -> Option<f32> {
+    intersect_triangle_barycentric(ray, triangle).map(|(distance, _)| distance)
+}
+
+//intersect_triangle along with the barycentric weights of the three corners at the hit
+pub fn intersect_triangle_barycentric(
+    ray: &Ray,
+    triangle: [Point3<f32>; 3],
+) -> Option<(f32, [f32; 3])> {
+    let [a, b, c] = triangle;
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = ray.direction.cross(edge2);
+    let determinant = edge1.dot(p);
+    if determinant.abs() < PARALLEL_EPSILON {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let offset = ray.origin - a;
+    let u = offset.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = offset.cross(edge1);
+    let v = ray.direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = edge2.dot(q) * inverse;
+    (distance >= 0.0).then_some((distance, [1.0 - u - v, u, v]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Quaternion};
+
+    #[test]
+    fn transformed_box_holds_the_rotated_corners() {
+        let unit = Aabb::from_points([[-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]]);
+        let transform = Matrix4::from_translation(Vector3::new(10.0, 0.0, 0.0))
+            * Matrix4::from(Quaternion::from_angle_y(Deg(45.0)))
+            * Matrix4::from_scale(2.0);
+        let bounds = unit.transformed(&transform);
+        let half = 2.0 * 2.0f32.sqrt();
+        assert!((bounds.min.x - (10.0 - half)).abs() < 1e-5);
+        assert!((bounds.max.x - (10.0 + half)).abs() < 1e-5);
+        assert!((bounds.max.y - 2.0).abs() < 1e-5);
+        assert!((bounds.center() - Point3::new(10.0, 0.0, 0.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn triangle_hits_carry_barycentric_weights() {
+        let triangle = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(4.0, 0.0, 0.0),
+            Point3::new(0.0, 4.0, 0.0),
+        ];
+        let ray = Ray::new(Point3::new(1.0, 2.0, 5.0), -Vector3::unit_z());
+        let (distance, weights) = intersect_triangle_barycentric(&ray, triangle).unwrap();
+        assert_eq!(distance, 5.0);
+        assert_eq!(weights, [0.25, 0.25, 0.5]);
+        let missed = Ray::new(Point3::new(3.0, 3.0, 5.0), -Vector3::unit_z());
+        assert_eq!(intersect_triangle(&missed, triangle), None);
+    }
+
+    #[test]
+    fn bounding_sphere_reaches_the_corners() {
+        let bounds = Aabb::from_points([[0.0, 0.0, 0.0], [2.0, 4.0, 4.0]]);
+        let sphere = bounds.bounding_sphere();
+        assert_eq!(sphere.center, Point3::new(1.0, 2.0, 2.0));
+        assert!((sphere.radius - 3.0).abs() < 1e-5);
+    }
+}
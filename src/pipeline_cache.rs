@@ -0,0 +1,255 @@
+use crate::error::ShaderError;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+type Result<T> = std::result::Result<T, ShaderError>;
+
+//everything that distinguishes one render pipeline from another. two keys that compare equal
+//share a pipeline
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    //name the shader was registered under
+    pub shader: String,
+    pub vertex_layouts: Vec<wgpu::VertexBufferLayout<'static>>,
+    pub color_format: wgpu::TextureFormat,
+    pub topology: wgpu::PrimitiveTopology,
+    pub blend: Option<wgpu::BlendState>,
+    pub cull_mode: Option<wgpu::Face>,
+    pub polygon_mode: wgpu::PolygonMode,
+    pub depth: Option<wgpu::DepthStencilState>,
+}
+
+impl PipelineKey {
+    //an opaque, back face culled, depth tested pipeline, the state the scene shaders use
+    pub fn opaque(
+        shader: &str,
+        vertex_layouts: &[wgpu::VertexBufferLayout<'static>],
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Self {
+        Self {
+            shader: shader.to_string(),
+            vertex_layouts: vertex_layouts.to_vec(),
+            color_format,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            blend: Some(wgpu::BlendState::REPLACE),
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            depth: depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+        }
+    }
+
+    //strips need no index format here, they are only drawn without an index buffer
+    pub fn with_topology(mut self, topology: wgpu::PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn with_blend(mut self, blend: Option<wgpu::BlendState>) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn with_cull_mode(mut self, cull_mode: Option<wgpu::Face>) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    //Line and Point need the matching device features
+    pub fn with_polygon_mode(mut self, polygon_mode: wgpu::PolygonMode) -> Self {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    //transparent passes usually test against depth without writing it
+    pub fn with_depth_write(mut self, enabled: bool) -> Self {
+        if let Some(depth) = self.depth.as_mut() {
+            depth.depth_write_enabled = enabled;
+        }
+        self
+    }
+
+    pub fn with_depth(mut self, depth: Option<wgpu::DepthStencilState>) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    //left out for depth formats without a stencil aspect, see the stencil module for the states
+    pub fn with_stencil(mut self, stencil: wgpu::StencilState) -> Self {
+        if let Some(depth) = self.depth.as_mut().filter(|depth| depth.format.has_stencil_aspect()) {
+            depth.stencil = stencil;
+        }
+        self
+    }
+}
+
+struct ShaderEntry {
+    module: wgpu::ShaderModule,
+    layout: Arc<wgpu::PipelineLayout>,
+}
+
+//creates render pipelines the first time a combination of state is asked for and hands back the
+//same pipeline afterwards. shaders are registered once with the pipeline layout they expect and
+//must have vs_main and fs_main entry points
+#[derive(Default)]
+pub struct PipelineCache {
+    shaders: HashMap<String, ShaderEntry>,
+    pipelines: HashMap<PipelineKey, Arc<wgpu::RenderPipeline>>,
+}
+
+impl PipelineCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //registering a name again replaces the shader and drops the pipelines built from the old one.
+    //WGSL is parsed and validated with naga first so a broken shader comes back as an error
+    //pointing at the line instead of a validation panic inside wgpu, the old shader is kept if
+    //the new one fails
+    pub fn register_shader(
+        &mut self,
+        device: &wgpu::Device,
+        name: &str,
+        source: wgpu::ShaderModuleDescriptor,
+        layout: Arc<wgpu::PipelineLayout>,
+    ) -> Result<()> {
+        if let wgpu::ShaderSource::Wgsl(wgsl) = &source.source {
+            validate_wgsl(name, wgsl)?;
+        }
+        let module = with_error_scope(device, name, || device.create_shader_module(source))?;
+        self.pipelines.retain(|key, _| key.shader != name);
+        self.shaders
+            .insert(name.to_string(), ShaderEntry { module, layout });
+        Ok(())
+    }
+
+    pub fn has_shader(&self, name: &str) -> bool {
+        self.shaders.contains_key(name)
+    }
+
+    pub fn get(&mut self, device: &wgpu::Device, key: &PipelineKey) -> Result<Arc<wgpu::RenderPipeline>> {
+        if let Some(pipeline) = self.pipelines.get(key) {
+            return Ok(pipeline.clone());
+        }
+        let shader = self.shaders.get(&key.shader).ok_or_else(|| ShaderError {
+            name: key.shader.clone(),
+            location: None,
+            message: "no shader registered under this name".to_string(),
+        })?;
+        //entry points and vertex layouts are only checked against the shader here
+        let pipeline = Arc::new(with_error_scope(device, &key.shader, || {
+            Self::create(device, shader, key)
+        })?);
+        self.pipelines.insert(key.clone(), pipeline.clone());
+        Ok(pipeline)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.is_empty()
+    }
+
+    //drops every cached pipeline, e.g. after the surface format changes. shaders stay registered
+    pub fn clear(&mut self) {
+        self.pipelines.clear();
+    }
+
+    fn create(device: &wgpu::Device, shader: &ShaderEntry, key: &PipelineKey) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("{} Pipeline", key.shader)),
+            layout: Some(&shader.layout),
+            vertex: wgpu::VertexState {
+                module: &shader.module,
+                entry_point: "vs_main",
+                buffers: &key.vertex_layouts,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader.module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: key.color_format,
+                    blend: key.blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: key.topology,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: key.cull_mode,
+                polygon_mode: key.polygon_mode,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: key.depth.clone(),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+}
+
+//parses and validates WGSL the same way wgpu would, reporting the first error with its location
+pub fn validate_wgsl(name: &str, source: &str) -> Result<()> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|error| ShaderError {
+        name: name.to_string(),
+        location: error
+            .location(source)
+            .map(|location| (location.line_number, location.line_position)),
+        message: error.emit_to_string(source),
+    })?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|error| ShaderError {
+        name: name.to_string(),
+        location: error
+            .location(source)
+            .map(|location| (location.line_number, location.line_position)),
+        message: error.emit_to_string(source),
+    })?;
+    Ok(())
+}
+
+//runs create inside a validation error scope so whatever the device rejects comes back as an
+//error instead of going to the uncaptured error handler, which panics by default
+pub(crate) fn with_error_scope<T>(device: &wgpu::Device, name: &str, create: impl FnOnce() -> T) -> Result<T> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = create();
+    match poll_once(device.pop_error_scope()) {
+        Some(Some(error)) => Err(ShaderError {
+            name: name.to_string(),
+            location: None,
+            message: error.to_string(),
+        }),
+        _ => Ok(value),
+    }
+}
+
+//native backends resolve the popped scope straight away. on the web it resolves later and is
+//treated as no error, the browser still logs the validation message to the console
+pub(crate) fn poll_once<F: Future>(future: F) -> Option<F::Output> {
+    let mut future = std::pin::pin!(future);
+    match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::*;
+use std::path::PathBuf;
+
+//cpu profiling. the scopes placed around update, instance building, asset loading and render
+//recording come from the profiling crate and compile to nothing unless one of the profile-*
+//features picks a backend. this owns whatever the chosen backend needs to get the data out
+//
+//tracy is the exception, it streams to a running tracy profiler instead of writing a file. its
+//zones come from tracy_zone! rather than the profiling crate, which can't share a lockfile
+//between its puffin and tracy backends
+pub struct CpuProfiler {
+    output: PathBuf,
+    #[cfg(feature = "profile-puffin")]
+    frames: puffin::GlobalFrameView,
+    //the chrome trace is flushed to disk when this is dropped
+    #[cfg(feature = "profile-tracing")]
+    _trace_guard: tracing_chrome::FlushGuard,
+    #[cfg(feature = "profile-tracy")]
+    tracy: tracy_client::Client,
+}
+
+//a tracy zone covering the rest of the enclosing block. compiles to nothing without the
+//profile-tracy feature and records nothing until the profiler has been started
+macro_rules! tracy_zone {
+    ($name:literal) => {
+        #[cfg(feature = "profile-tracy")]
+        let _tracy_zone = tracy_client::Client::running()
+            .map(|client| client.span(tracy_client::span_location!($name), 0));
+    };
+}
+pub(crate) use tracy_zone;
+
+impl CpuProfiler {
+    //starts collecting scopes. output is the path the capture is written to without an
+    //extension, puffin frames are saved as .puffin and tracing spans as a chrome .json trace.
+    //tracy needs no output, connect the profiler to the running app instead
+    pub fn start(output: impl Into<PathBuf>) -> Result<Self> {
+        let output = output.into();
+        if cfg!(not(any(
+            feature = "profile-puffin",
+            feature = "profile-tracing",
+            feature = "profile-tracy"
+        ))) {
+            bail!("no profiling backend, build with a profile-puffin, profile-tracing or profile-tracy feature");
+        }
+        #[cfg(feature = "profile-puffin")]
+        puffin::set_scopes_on(true);
+        #[cfg(feature = "profile-tracing")]
+        let _trace_guard = {
+            use tracing_subscriber::layer::SubscriberExt;
+            use tracing_subscriber::util::SubscriberInitExt;
+            let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new()
+                .file(output.with_extension("json"))
+                .build();
+            tracing_subscriber::registry()
+                .with(layer)
+                .with(crate::logging::fmt_layer())
+                .try_init()
+                .map_err(|e| anyhow!("failed to install the trace subscriber: {}", e))?;
+            guard
+        };
+        Ok(Self {
+            output,
+            #[cfg(feature = "profile-puffin")]
+            frames: puffin::GlobalFrameView::default(),
+            #[cfg(feature = "profile-tracing")]
+            _trace_guard,
+            #[cfg(feature = "profile-tracy")]
+            tracy: tracy_client::Client::start(),
+        })
+    }
+
+    //marks the end of a frame so scopes are grouped per frame in the viewer
+    pub fn finish_frame(&self) {
+        profiling::finish_frame!();
+        #[cfg(feature = "profile-tracy")]
+        self.tracy.frame_mark();
+    }
+
+    //stops collecting and writes the capture out, returning where it went. None when the only
+    //backend is tracy, which has nothing to write
+    pub fn finish(self) -> Result<Option<PathBuf>> {
+        #[cfg(feature = "profile-puffin")]
+        return self.save_puffin().map(Some);
+        #[cfg(all(not(feature = "profile-puffin"), feature = "profile-tracing"))]
+        return Ok(Some(self.output.with_extension("json")));
+        #[cfg(not(any(feature = "profile-puffin", feature = "profile-tracing")))]
+        Ok(None)
+    }
+
+    #[cfg(feature = "profile-puffin")]
+    fn save_puffin(self) -> Result<PathBuf> {
+        puffin::set_scopes_on(false);
+        let path = self.output.with_extension("puffin");
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+            self.frames.lock().write(&mut file)?;
+        }
+        Ok(path)
+    }
+}
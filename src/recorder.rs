@@ -0,0 +1,315 @@
+use crate::capture;
+use crate::error::EngineError;
+use anyhow::{anyhow, Context};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+
+//how many readback buffers are cycled through. a buffer is only waited on if it is still in
+//flight by the time the ring wraps back around to it
+const RING_SIZE: usize = 3;
+
+//where captured frames end up
+pub enum RecordingSink {
+    //numbered pngs (frame_000000.png, frame_000001.png, ...) in the directory
+    PngSequence(PathBuf),
+    //raw rgba8 frames written to the stdin of the command, e.g.
+    //ffmpeg -f rawvideo -pix_fmt rgba -s 1280x720 -i - out.mp4
+    //the frames carry no size so the recording stops if the window is resized
+    Pipe(Command),
+}
+
+struct Frame {
+    index: u64,
+    image: image::RgbaImage,
+}
+
+struct ReadbackSlot {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    //frame index of the copy in flight along with what the map callback reported
+    pending: Option<(u64, MapResult)>,
+}
+
+//empty until the map callback runs, then whether the buffer could be mapped
+type MapResult = Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>;
+
+fn take_map_result(result: &MapResult) -> Option<Result<(), wgpu::BufferAsyncError>> {
+    result.lock().unwrap_or_else(PoisonError::into_inner).take()
+}
+
+//captures every nth rendered frame. copies are recorded into a ring of buffers and mapped
+//asynchronously so the gpu isn't stalled each frame, encoding/writing happens on its own thread
+pub struct FrameRecorder {
+    every_nth: u32,
+    frame_counter: u64,
+    captured: u64,
+    slots: Vec<ReadbackSlot>,
+    next_slot: usize,
+    //size of the first frame, every later frame has to match it when piping raw frames
+    fixed_size: Option<(u32, u32)>,
+    pipe: bool,
+    sender: Option<mpsc::Sender<Frame>>,
+    writer: Option<JoinHandle<anyhow::Result<()>>>,
+}
+
+impl FrameRecorder {
+    pub fn new(sink: RecordingSink, every_nth: u32) -> crate::error::Result<Self> {
+        Self::start(sink, every_nth).map_err(EngineError::Capture)
+    }
+
+    fn start(sink: RecordingSink, every_nth: u32) -> anyhow::Result<Self> {
+        let (sender, receiver) = mpsc::channel::<Frame>();
+        let pipe = matches!(sink, RecordingSink::Pipe(_));
+        let writer = match sink {
+            RecordingSink::PngSequence(directory) => {
+                std::fs::create_dir_all(&directory)?;
+                std::thread::spawn(move || {
+                    for frame in receiver {
+                        let path = directory.join(format!("frame_{:06}.png", frame.index));
+                        capture::save_png(&frame.image, &path)?;
+                    }
+                    Ok(())
+                })
+            }
+            RecordingSink::Pipe(mut command) => {
+                let mut child: Child = command.stdin(Stdio::piped()).spawn()?;
+                let mut stdin = child
+                    .stdin
+                    .take()
+                    .ok_or_else(|| anyhow!("failed to open encoder stdin"))?;
+                std::thread::spawn(move || {
+                    for frame in receiver {
+                        stdin.write_all(frame.image.as_raw())?;
+                    }
+                    drop(stdin);
+                    child.wait()?;
+                    Ok(())
+                })
+            }
+        };
+        Ok(Self {
+            every_nth: every_nth.max(1),
+            frame_counter: 0,
+            captured: 0,
+            slots: Vec::new(),
+            next_slot: 0,
+            fixed_size: None,
+            pipe,
+            sender: Some(sender),
+            writer: Some(writer),
+        })
+    }
+
+    pub fn frames_captured(&self) -> u64 {
+        self.captured
+    }
+
+    //call once per rendered frame with the encoder that will be submitted for it. returns true
+    //if a copy was recorded, in which case after_submit must be called once it is submitted
+    pub fn record(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) -> crate::error::Result<bool> {
+        let frame = self.frame_counter;
+        self.frame_counter += 1;
+        if !frame.is_multiple_of(self.every_nth as u64) {
+            return Ok(false);
+        }
+        let size = texture.size();
+        let format = texture.format();
+        if self.pipe {
+            let (width, height) = *self.fixed_size.get_or_insert((size.width, size.height));
+            if (width, height) != (size.width, size.height) {
+                return Err(EngineError::Capture(anyhow!(
+                    "frame size changed from {}x{} to {}x{}, piped raw frames can't change size",
+                    width,
+                    height,
+                    size.width,
+                    size.height
+                )));
+            }
+        }
+        if self.slots.len() < RING_SIZE {
+            self.slots.push(ReadbackSlot {
+                buffer: capture::create_readback_buffer(device, size.width, size.height),
+                width: size.width,
+                height: size.height,
+                format,
+                pending: None,
+            });
+            self.next_slot = self.slots.len() - 1;
+        }
+        //the slot we are about to reuse may still hold an unread frame. it is the oldest one in
+        //flight, so everything captured before it has gone out by the time it has
+        let reused = self.slots[self.next_slot]
+            .pending
+            .as_ref()
+            .map(|(index, _)| *index);
+        self.drain(device, reused)?;
+        let slot = &mut self.slots[self.next_slot];
+        if slot.width != size.width || slot.height != size.height {
+            slot.buffer = capture::create_readback_buffer(device, size.width, size.height);
+            slot.width = size.width;
+            slot.height = size.height;
+        }
+        slot.format = format;
+        capture::copy_texture_to_buffer(encoder, texture, &slot.buffer);
+        slot.pending = Some((self.captured, Arc::new(Mutex::new(None))));
+        self.captured += 1;
+        Ok(true)
+    }
+
+    //starts mapping the buffer written this frame and hands off any frames that are ready
+    pub fn after_submit(&mut self, device: &wgpu::Device) -> crate::error::Result<()> {
+        if let Some((_, mapped)) = self.slots[self.next_slot].pending.as_ref() {
+            let mapped = mapped.clone();
+            self.slots[self.next_slot]
+                .buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    *mapped.lock().unwrap_or_else(PoisonError::into_inner) = Some(result);
+                });
+        }
+        self.next_slot = (self.next_slot + 1) % RING_SIZE;
+        device.poll(wgpu::Maintain::Poll);
+        self.drain(device, None)
+    }
+
+    //hands frames to the writer in the order they were captured. frames up to and including
+    //wait_until are waited for, past that it stops at the first frame whose map hasn't
+    //completed so a later frame never goes out ahead of an earlier one
+    fn drain(
+        &mut self,
+        device: &wgpu::Device,
+        wait_until: Option<u64>,
+    ) -> crate::error::Result<()> {
+        while let Some(slot) = oldest_pending(self.pending_indices()) {
+            let wait = self.slots[slot]
+                .pending
+                .as_ref()
+                .is_some_and(|(index, _)| wait_until.is_some_and(|until| *index <= until));
+            if !self.finish_slot(device, slot, wait)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn pending_indices(&self) -> impl Iterator<Item = Option<u64>> + '_ {
+        self.slots
+            .iter()
+            .map(|slot| slot.pending.as_ref().map(|(index, _)| *index))
+    }
+
+    //sends a mapped slot off to the writer, false if its map hasn't completed yet. with wait set
+    //this blocks until the map completes, a map that fails or never completes is an error
+    fn finish_slot(
+        &mut self,
+        device: &wgpu::Device,
+        slot: usize,
+        wait: bool,
+    ) -> crate::error::Result<bool> {
+        self.try_finish_slot(device, slot, wait)
+            .map_err(EngineError::Capture)
+    }
+
+    fn try_finish_slot(
+        &mut self,
+        device: &wgpu::Device,
+        slot: usize,
+        wait: bool,
+    ) -> anyhow::Result<bool> {
+        let Some(slot) = self.slots.get_mut(slot) else {
+            return Ok(false);
+        };
+        let Some((index, mapped)) = slot.pending.as_ref() else {
+            return Ok(false);
+        };
+        let index = *index;
+        let mut result = take_map_result(mapped);
+        while result.is_none() && wait {
+            //the callback has run by the time the queue is empty, if it hasn't it never will
+            let idle = device.poll(wgpu::Maintain::Wait).is_queue_empty();
+            result = take_map_result(mapped);
+            if result.is_none() && idle {
+                slot.pending = None;
+                return Err(anyhow!("frame {} was never mapped", index));
+            }
+        }
+        let Some(result) = result else {
+            return Ok(false);
+        };
+        slot.pending = None;
+        result.with_context(|| format!("failed to map frame {}", index))?;
+        let image = {
+            let data = slot.buffer.slice(..).get_mapped_range();
+            capture::image_from_padded(&data, slot.width, slot.height, slot.format)
+        };
+        slot.buffer.unmap();
+        let image = image?;
+        if let Some(sender) = self.sender.as_ref() {
+            sender
+                .send(Frame { index, image })
+                .map_err(|_| anyhow!("frame writer has stopped"))?;
+        }
+        Ok(true)
+    }
+
+    //waits for every frame in flight and for the writer to finish with them
+    pub fn finish(mut self, device: &wgpu::Device) -> crate::error::Result<u64> {
+        self.drain(device, Some(u64::MAX))?;
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            writer
+                .join()
+                .map_err(|_| EngineError::Capture(anyhow!("frame writer panicked")))?
+                .map_err(EngineError::Capture)?;
+        }
+        Ok(self.captured)
+    }
+}
+
+//the slot holding the earliest captured frame still in flight. the ring hands slots out in
+//capture order but once it wraps the oldest frame can be in any slot
+fn oldest_pending(indices: impl IntoIterator<Item = Option<u64>>) -> Option<usize> {
+    indices
+        .into_iter()
+        .enumerate()
+        .filter_map(|(slot, index)| Some((index?, slot)))
+        .min()
+        .map(|(_, slot)| slot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oldest_pending_follows_capture_order_after_the_ring_wraps() {
+        //seven captures through the ring, each landing in the slot after the last one
+        let mut slots = [None; RING_SIZE];
+        for index in 0..7u64 {
+            slots[index as usize % RING_SIZE] = Some(index);
+        }
+        assert_eq!(slots, [Some(6), Some(4), Some(5)]);
+        let mut order = Vec::new();
+        while let Some(slot) = oldest_pending(slots) {
+            order.push(slots[slot].take().unwrap());
+        }
+        assert_eq!(order, [4, 5, 6]);
+    }
+
+    #[test]
+    fn oldest_pending_skips_empty_slots() {
+        assert_eq!(oldest_pending([None, Some(9), Some(8)]), Some(2));
+        assert_eq!(oldest_pending([None, None, None]), None);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::texture;
+
+//the range GameState::set_render_scale keeps the scale in
+pub const MIN_SCALE: f32 = 0.25;
+pub const MAX_SCALE: f32 = 2.0;
+
+//the size the scene is rendered at for a window size and scale. never zero, and never past the
+//device's largest texture, which caps supersampling on big windows without changing the shape
+pub fn scaled_size((width, height): (u32, u32), scale: f32, max_dimension: u32) -> (u32, u32) {
+    let largest = width.max(height).max(1) as f32;
+    let scale = scale.min(max_dimension as f32 / largest);
+    let scale_side = |side: u32| ((side as f32 * scale).round() as u32).clamp(1, max_dimension);
+    (scale_side(width), scale_side(height))
+}
+
+//the scene drawn into a texture at a scaled resolution, then stretched or shrunk onto the
+//surface with linear filtering. below 1 it trades sharpness for fill rate on weak gpus, above 1
+//it supersamples. at exactly 2 each window pixel is the average of the four rendered for it
+pub struct RenderScaler {
+    target: texture::RenderTexture,
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl RenderScaler {
+    //format is the surface's, so scene pipelines can draw into the target unchanged
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, size: (u32, u32)) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("blit.wgsl").into()),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Blit Bind Group Layout"),
+            entries: &texture::Texture::layout_entries(
+                wgpu::ShaderStages::FRAGMENT,
+                wgpu::TextureViewDimension::D2,
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let target = texture::RenderTexture::new(
+            device,
+            size,
+            format,
+            wgpu::TextureUsages::empty(),
+            None,
+            "Scaled Scene",
+        );
+        let bind_group = Self::create_bind_group(device, &layout, &target);
+        Self {
+            target,
+            layout,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        target: &texture::RenderTexture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Blit Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&target.color.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&target.color.sampler),
+                },
+            ],
+        })
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.target.size()
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.target.color.texture.format()
+    }
+
+    //what the scene pass draws into in place of the surface
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.target.color.view
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        if self.size() == size {
+            return;
+        }
+        self.target.resize(device, size);
+        self.bind_group = Self::create_bind_group(device, &self.layout, &self.target);
+    }
+
+    //filters the scaled scene onto view, covering all of it
+    pub fn blit(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_sizes_round_and_stay_in_bounds() {
+        assert_eq!(scaled_size((1280, 720), 0.5, 8192), (640, 360));
+        assert_eq!(scaled_size((1279, 719), 0.5, 8192), (640, 360));
+        assert_eq!(scaled_size((1, 1), 0.25, 8192), (1, 1));
+        assert_eq!(scaled_size((5120, 2880), 2.0, 8192), (8192, 4608));
+    }
+
+    #[test]
+    fn blit_shader_validates() {
+        crate::pipeline_cache::validate_wgsl("blit.wgsl", include_str!("blit.wgsl")).unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{camera, texture, uniform, upload};
+
+//an offscreen colour + depth pair the scene can be rendered into, e.g. for mirrors, security
+//camera monitors or previews of a model in ui. each target carries its own camera so it can
+//look at the scene from somewhere other than the main view
+pub struct RenderTarget {
+    pub color: texture::Texture,
+    pub depth: texture::Texture,
+    pub camera: camera::Camera,
+    camera_uniform: camera::CameraUniform,
+    camera_buffer: uniform::UniformBuffer<camera::CameraUniform>,
+}
+
+impl RenderTarget {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        main_camera: &uniform::UniformBuffer<camera::CameraUniform>,
+        config: &wgpu::SurfaceConfiguration,
+        depth_format: wgpu::TextureFormat,
+        camera: camera::Camera,
+    ) -> Self {
+        let color = texture::Texture::create_offscreen_texture(device, config, "render_target");
+        let depth = texture::Texture::create_depth_texture(
+            device,
+            config,
+            depth_format,
+            "render_target_depth",
+        );
+        let mut camera_uniform = camera::CameraUniform::new();
+        camera_uniform.update_view_proj(&camera);
+        let camera_buffer = main_camera.with_same_layout(
+            device,
+            "Render Target Camera Buffer",
+            &camera_uniform,
+        );
+        Self {
+            color,
+            depth,
+            camera,
+            camera_uniform,
+            camera_buffer,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        let size = self.color.texture.size();
+        (size.width, size.height)
+    }
+
+    //stages any changes made to the camera into its uniform buffer
+    pub(crate) fn upload_camera(
+        &mut self,
+        uploader: &upload::Uploader,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+    ) {
+        self.camera_uniform.update_view_proj(&self.camera);
+        self.camera_buffer
+            .stage(uploader, encoder, device, &self.camera_uniform);
+    }
+
+    pub(crate) fn camera_bind_group(&self) -> &wgpu::BindGroup {
+        self.camera_buffer.bind_group()
+    }
+}
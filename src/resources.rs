@@ -1,43 +1,170 @@
-use crate::{model, texture};
+use crate::{bind_cache, model, picking, texture};
+use cgmath::{EuclideanSpace, InnerSpace, Matrix, Matrix4, SquareMatrix, Transform};
 use std::io::{BufReader, Cursor};
-use wgpu::util::DeviceExt;
+use bytemuck::Zeroable;
+
+pub mod watcher;
+
+//mesh buffers can be copied out of so indirect drawing can merge a model's meshes into one
+const MESH_VERTEX_USAGE: wgpu::BufferUsages =
+    wgpu::BufferUsages::VERTEX.union(wgpu::BufferUsages::COPY_SRC);
+const MESH_INDEX_USAGE: wgpu::BufferUsages =
+    wgpu::BufferUsages::INDEX.union(wgpu::BufferUsages::COPY_SRC);
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(err: wasm_bindgen::JsValue) -> anyhow::Error {
+    anyhow::anyhow!("{:?}", err)
+}
+
+//on the web there is no file system so the res folder is fetched relative to the page origin
+#[cfg(target_arch = "wasm32")]
+async fn fetch_binary(file_name: &str) -> anyhow::Result<Vec<u8>> {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    let window = web_sys::window().ok_or_else(|| anyhow::anyhow!("no browser window"))?;
+    let origin = window.location().origin().map_err(js_error)?;
+    let url = format!("{}/res/{}", origin, file_name);
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_str(&url))
+        .await
+        .map_err(js_error)?
+        .dyn_into()
+        .map_err(js_error)?;
+    if !response.ok() {
+        anyhow::bail!("failed to fetch {}: status {}", url, response.status());
+    }
+    let buffer = JsFuture::from(response.array_buffer().map_err(js_error)?)
+        .await
+        .map_err(js_error)?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
 
 pub async fn load_string(file_name: &str) -> anyhow::Result<String> {
-    let path = std::path::Path::new(env!("OUT_DIR"))
-        .join("res")
-        .join(file_name);
-    let txt = std::fs::read_to_string(path)?;
+    #[cfg(target_arch = "wasm32")]
+    let txt = String::from_utf8(fetch_binary(file_name).await?)?;
+    #[cfg(not(target_arch = "wasm32"))]
+    let txt = {
+        let path = std::path::Path::new(env!("OUT_DIR"))
+            .join("res")
+            .join(file_name);
+        std::fs::read_to_string(path)?
+    };
     Ok(txt)
 }
 
 pub async fn load_binary(file_name: &str) -> anyhow::Result<Vec<u8>> {
-    let path = std::path::Path::new(env!("OUT_DIR"))
-        .join("res")
-        .join(file_name);
-    let data = std::fs::read(path)?;
+    #[cfg(target_arch = "wasm32")]
+    let data = fetch_binary(file_name).await?;
+    #[cfg(not(target_arch = "wasm32"))]
+    let data = {
+        let path = std::path::Path::new(env!("OUT_DIR"))
+            .join("res")
+            .join(file_name);
+        std::fs::read(path)?
+    };
     Ok(data)
 }
 
+//decodes a whole sound up front, fine for effects and short music loops
+#[cfg(feature = "audio")]
+pub async fn load_sound(
+    file_name: &str,
+) -> anyhow::Result<kira::sound::static_sound::StaticSoundData> {
+    let data = load_binary(file_name).await?;
+    let sound = kira::sound::static_sound::StaticSoundData::from_cursor(
+        Cursor::new(data),
+        Default::default(),
+    )?;
+    Ok(sound)
+}
+
+#[tracing::instrument(skip(device, queue))]
 pub async fn load_texture(
     file_name: &str,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
+    sampler: &texture::SamplerSettings,
 ) -> anyhow::Result<texture::Texture> {
     let data = load_binary(file_name).await?;
-    texture::Texture::from_bytes(device, queue, &data, file_name)
+    texture::Texture::from_bytes_with_sampler(device, queue, &data, file_name, sampler)
 }
 
+//a file named by another file, such as an mtl's texture, found relative to that file's folder.
+//goes through Path so a windows path from the command line splits on its backslashes, on the
+//web that's still a forward slash so the result works as a url
+fn relative_to(file_name: &str, name: &str) -> String {
+    match std::path::Path::new(file_name).parent() {
+        Some(folder) => folder.join(name).to_string_lossy().into_owned(),
+        None => name.to_string(),
+    }
+}
+
+//cache name of the plain white texture given to materials without a diffuse map
+const UNTEXTURED: &str = "<untextured>";
+//cache name of the magenta checker drawn where a texture or shader failed
+const ERROR_TEXTURE: &str = "<error>";
+
+//how a model is turned into meshes
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ModelLoadOptions {
+    //how the model's textures are sampled. GameState::load_model drops anisotropy the device
+    //can't do
+    pub sampler: texture::SamplerSettings,
+    //combine meshes that share a material into one vertex/index buffer pair, for scenes built
+    //from many small props that never move relative to each other
+    pub merge_static_meshes: bool,
+    //give every triangle its own vertices and face normal instead of smoothing across shared
+    //vertices, replacing any normals in the file
+    pub flat_shading: bool,
+    //keep a copy of the positions and indices on the cpu so picking tests the triangles rather
+    //than just the bounding boxes
+    pub keep_triangles: bool,
+    //baked into the vertices before any instance transform, to fix up assets exported in other
+    //units or with another up axis without re-exporting them, e.g. Matrix4::from_scale(0.01)
+    //for centimeters or z_up_to_y_up. a mirroring transform flips the triangle winding
+    pub root_transform: Option<Matrix4<f32>>,
+    //moves the model so the center of its bounds, after the root transform, is at the origin
+    pub center: bool,
+}
+
+impl ModelLoadOptions {
+    //the root transform with centering folded in, None when the vertices are used as loaded
+    fn root_matrix(&self, models: &[tobj::Model]) -> Option<Matrix4<f32>> {
+        let root = self.root_transform.unwrap_or(Matrix4::identity());
+        if !self.center {
+            return self.root_transform;
+        }
+        let bounds = picking::Aabb::from_points(models.iter().flat_map(|model| {
+            model
+                .mesh
+                .positions
+                .chunks_exact(3)
+                .map(|p| root.transform_point(cgmath::point3(p[0], p[1], p[2])).into())
+        }));
+        Some(Matrix4::from_translation(-bounds.center().to_vec()) * root)
+    }
+
+    //a root transform turning a model authored with z up, as many modelling tools export, so
+    //y is up
+    pub fn z_up_to_y_up() -> Matrix4<f32> {
+        Matrix4::from_angle_x(cgmath::Deg(-90.0))
+    }
+}
+
+#[tracing::instrument(skip(device, queue, layout, cache))]
 pub async fn load_model(
     file_name: &str,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
     layout: &wgpu::BindGroupLayout,
+    cache: &mut bind_cache::BindingCache,
+    options: &ModelLoadOptions,
 ) -> anyhow::Result<model::Model> {
-    // generate file path as a string
+    //generate file path as a string
     let obj_text = load_string(file_name).await?;
-    // wraps the memory in a cursor
+    //wraps the memory in a cursor
     let obj_cursor = Cursor::new(obj_text);
-    // loads the cursor into a buffer to decrease reads for performance
+    //loads the cursor into a buffer to decrease reads for performance
     let mut obj_reader = BufReader::new(obj_cursor);
 
     let (models, obj_materials) = tobj::load_obj_buf_async(
@@ -49,104 +176,528 @@ pub async fn load_model(
         },
         //material loader portion of the function
         |p| async move {
-            //file path as string
-            let mat_text = load_string(&p).await.unwrap();
-            //load materal from BufReader from file path generated above
-            tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text)))
+            //file path as string, a missing mtl file is reported through tobj rather than panicking
+            match load_string(&relative_to(file_name, &p)).await {
+                //load materal from BufReader from file path generated above
+                Ok(mat_text) => tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text))),
+                Err(_) => Err(tobj::LoadError::OpenFileFailed),
+            }
         },
     )
     .await?;
 
     let mut materials = Vec::new();
     for material in obj_materials? {
-        //get diffuse texture name from material iter and load appropriate texture
-        let diffuse_texture = load_texture(&material.diffuse_texture, device, queue).await?;
-        //chuck it into a bind group
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout,
-            label: None,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-        });
+        //get diffuse texture name from material iter, textures already loaded by another
+        //material are shared rather than loaded again. they are cached by their path in res so
+        //models in different folders can each have their own diffuse.png. materials without
+        //one are drawn white, and ones whose texture fails to load get the error checker
+        let texture_path = if material.diffuse_texture.is_empty() {
+            UNTEXTURED.to_string()
+        } else {
+            relative_to(file_name, &material.diffuse_texture)
+        };
+        //a texture loaded with other sampler settings is cached separately, the one pixel white
+        //texture looks the same however it is sampled
+        let cache_key = if options.sampler == texture::SamplerSettings::default()
+            || texture_path == UNTEXTURED
+        {
+            texture_path.clone()
+        } else {
+            format!("{} {:?}", texture_path, options.sampler)
+        };
+        let diffuse_texture = match cache.texture(&cache_key) {
+            Some(texture) => texture,
+            None if texture_path == UNTEXTURED => untextured(device, queue, cache)?,
+            None => match load_texture(&texture_path, device, queue, &options.sampler).await {
+                Ok(texture) => cache.insert_texture(&cache_key, texture),
+                Err(e) => {
+                    tracing::error!(
+                        texture = %texture_path,
+                        "failed to load texture, drawing with the error material: {:#}",
+                        e
+                    );
+                    error_texture(device, queue, cache)?
+                }
+            },
+        };
         //return the materials struct
-        materials.push(model::Material {
-            name: material.name,
+        materials.push(create_material(
+            device,
+            layout,
+            cache,
+            material.name,
             diffuse_texture,
-            bind_group,
-        })
-    }
-    //get our meshes of
-    let meshes = models
-        .into_iter()
-        .map(|model| {
-            //positions are a flattened vec in tobj. len/3 to get number of xyz vertices
-            let vertices = (0..model.mesh.positions.len() / 3)
-                .map(|vertex| {
-                    //positions is a flat array so iterate over it to get [x,y,z], if statement
-                    //will define normal as centre coords if not defined.
+        ))
+    }
+    //meshes with no material, or one the mtl file doesn't have, get a plain white one
+    let material_count = materials.len();
+    if models
+        .iter()
+        .any(|model| model.mesh.material_id.is_none_or(|id| id >= material_count))
+    {
+        materials.push(default_material(device, queue, layout, cache)?);
+    }
+    //the untextured material is always last when it was added
+    let material_index = |material_id: Option<usize>| {
+        material_id
+            .filter(|id| *id < material_count)
+            .unwrap_or(material_count)
+    };
+    //get our meshes of. there are no awaits past this point so the scope doesn't end up held
+    //across one
+    profiling::scope!("build meshes", file_name);
+    crate::profiler::tracy_zone!("build meshes");
+    let root = options.root_matrix(&models);
+    let meshes = if options.merge_static_meshes || options.flat_shading {
+        //static meshes sharing a material can be drawn with one call once their buffers are
+        //combined, and flat shading splits the vertices up. both need the vertices on the cpu
+        //first
+        let mesh_data = models
+            .into_iter()
+            .map(|model| {
+                let mut vertices =
+                    vec![model::ModelVertex::zeroed(); model.mesh.positions.len() / 3];
+                fill_vertices(&model.mesh, &mut vertices);
+                if let Some(root) = &root {
+                    apply_root(&mut vertices, root);
+                }
+                let material = material_index(model.mesh.material_id);
+                if options.flat_shading {
+                    let (vertices, indices) = flatten(&vertices, &model.mesh.indices);
+                    (vertices, indices, material)
+                } else {
                     if model.mesh.normals.is_empty() {
-                        model::ModelVertex {
-                            position: [
-                                model.mesh.positions[vertex * 3],
-                                model.mesh.positions[vertex * 3 + 1],
-                                model.mesh.positions[vertex * 3 + 2],
-                            ],
-                            tex_coords: [
-                                model.mesh.texcoords[vertex * 2],
-                                1.0 - model.mesh.texcoords[vertex * 2 + 1],
-                            ],
-                            normal: [0.0, 0.0, 0.0],
+                        generate_smooth_normals(&mut vertices, &model.mesh.indices);
+                    }
+                    (vertices, model.mesh.indices, material)
+                }
+            })
+            .collect::<Vec<_>>();
+        let mesh_data = if options.merge_static_meshes {
+            merge_meshes_by_material(mesh_data)
+        } else {
+            mesh_data
+        };
+        mesh_data
+            .into_iter()
+            .map(|(vertices, indices, material)| {
+                let (vertex_buffer, (bounding_radius, bounds)) = create_mapped_buffer(
+                    device,
+                    &format!("{:#?} Vertex Buffer", file_name),
+                    vertices.len(),
+                    MESH_VERTEX_USAGE,
+                    |mapped: &mut [model::ModelVertex]| {
+                        mapped.copy_from_slice(&vertices);
+                        (bounding_radius(mapped), bounds(mapped))
+                    },
+                );
+                let triangles = options
+                    .keep_triangles
+                    .then(|| mesh_triangles(&vertices, &indices));
+                let (index_buffer, index_format) = create_index_buffer(
+                    device,
+                    &format!("{:#?} Index Buffer", file_name),
+                    &indices,
+                    vertices.len(),
+                );
+                model::Mesh {
+                    name: file_name.to_string(),
+                    vertex_buffer,
+                    index_buffer,
+                    index_format,
+                    num_elements: indices.len() as u32,
+                    material,
+                    bounding_radius,
+                    bounds,
+                    triangles,
+                }
+            })
+            .collect::<Vec<_>>()
+    } else {
+        models
+            .iter()
+            .map(|model| {
+                //the vertices are written straight into the mapped vertex buffer rather than
+                //built up in a vec and copied in after
+                let (vertex_buffer, (bounding_radius, bounds, triangles)) = create_mapped_buffer(
+                    device,
+                    &format!("{:#?} Vertex Buffer", file_name),
+                    model.mesh.positions.len() / 3,
+                    MESH_VERTEX_USAGE,
+                    |mapped: &mut [model::ModelVertex]| {
+                        fill_vertices(&model.mesh, mapped);
+                        if let Some(root) = &root {
+                            apply_root(mapped, root);
                         }
-                    } else {
-                        model::ModelVertex {
-                            position: [
-                                model.mesh.positions[vertex * 3],
-                                model.mesh.positions[vertex * 3 + 1],
-                                model.mesh.positions[vertex * 3 + 2],
-                            ],
-                            tex_coords: [
-                                model.mesh.texcoords[vertex * 2],
-                                1.0 - model.mesh.texcoords[vertex * 2 + 1],
-                            ],
-                            normal: [
-                                model.mesh.normals[vertex * 3],
-                                model.mesh.normals[vertex * 3 + 1],
-                                model.mesh.normals[vertex * 3 + 2],
-                            ],
+                        if model.mesh.normals.is_empty() {
+                            generate_smooth_normals(mapped, &model.mesh.indices);
                         }
-                    }
-                })
-                .collect::<Vec<_>>();
-            // chuck the vertices vec into a vertex buffer.
-            let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{:#?} Vertex Buffer", file_name)),
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
-            // index buffers from the mesh indices.
-            let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{:#?} Index Buffer", file_name)),
-                contents: bytemuck::cast_slice(&model.mesh.indices),
-                usage: wgpu::BufferUsages::INDEX,
-            });
-            // return the mesh struct into a vec
-            model::Mesh {
-                name: file_name.to_string(),
-                vertex_buffer,
-                index_buffer,
-                num_elements: model.mesh.indices.len() as u32,
-                material: model.mesh.material_id.unwrap_or(0),
-            }
-        })
-        .collect::<Vec<_>>();
+                        let triangles = options
+                            .keep_triangles
+                            .then(|| mesh_triangles(mapped, &model.mesh.indices));
+                        (bounding_radius(mapped), bounds(mapped), triangles)
+                    },
+                );
+                //index buffers from the mesh indices.
+                let (index_buffer, index_format) = create_index_buffer(
+                    device,
+                    &format!("{:#?} Index Buffer", file_name),
+                    &model.mesh.indices,
+                    model.mesh.positions.len() / 3,
+                );
+                //return the mesh struct into a vec
+                model::Mesh {
+                    name: file_name.to_string(),
+                    vertex_buffer,
+                    index_buffer,
+                    index_format,
+                    num_elements: model.mesh.indices.len() as u32,
+                    material: material_index(model.mesh.material_id),
+                    bounding_radius,
+                    bounds,
+                    triangles,
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    tracing::debug!(meshes = meshes.len(), materials = materials.len(), "loaded model");
     //return the Ok result from trying to load the model
     Ok(model::Model { meshes, materials })
 }
+
+//the plain white material used for meshes without a usable one
+pub fn default_material(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    cache: &mut bind_cache::BindingCache,
+) -> anyhow::Result<model::Material> {
+    let diffuse_texture = match cache.texture(UNTEXTURED) {
+        Some(texture) => texture,
+        None => untextured(device, queue, cache)?,
+    };
+    Ok(create_material(
+        device,
+        layout,
+        cache,
+        "untextured".to_string(),
+        diffuse_texture,
+    ))
+}
+
+//the magenta and black checker meshes are drawn with when their texture or shader failed, so
+//the problem is visible in the scene rather than a crash
+pub fn error_material(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    cache: &mut bind_cache::BindingCache,
+) -> anyhow::Result<model::Material> {
+    let diffuse_texture = error_texture(device, queue, cache)?;
+    Ok(create_material(
+        device,
+        layout,
+        cache,
+        "error".to_string(),
+        diffuse_texture,
+    ))
+}
+
+fn error_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    cache: &mut bind_cache::BindingCache,
+) -> anyhow::Result<std::sync::Arc<texture::Texture>> {
+    if let Some(texture) = cache.texture(ERROR_TEXTURE) {
+        return Ok(texture);
+    }
+    //8 pixel cells so filtering only softens their edges
+    let img = image::RgbaImage::from_fn(64, 64, |x, y| {
+        if (x / 8 + y / 8) % 2 == 0 {
+            image::Rgba([255, 0, 255, 255])
+        } else {
+            image::Rgba([0, 0, 0, 255])
+        }
+    });
+    let texture = texture::Texture::from_image(
+        device,
+        queue,
+        &image::DynamicImage::ImageRgba8(img),
+        Some(ERROR_TEXTURE),
+    )?;
+    Ok(cache.insert_texture(ERROR_TEXTURE, texture))
+}
+
+fn untextured(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    cache: &mut bind_cache::BindingCache,
+) -> anyhow::Result<std::sync::Arc<texture::Texture>> {
+    let texture = texture::Texture::from_color(device, queue, [255, 255, 255, 255], UNTEXTURED)?;
+    Ok(cache.insert_texture(UNTEXTURED, texture))
+}
+
+//chucks the texture into a bind group, materials with the same texture get the same one back
+fn create_material(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    cache: &mut bind_cache::BindingCache,
+    name: String,
+    diffuse_texture: std::sync::Arc<texture::Texture>,
+) -> model::Material {
+    let bind_group = cache.bind_group(
+        device,
+        None,
+        layout,
+        &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+            },
+        ],
+    );
+    model::Material {
+        name,
+        diffuse_texture,
+        bind_group,
+    }
+}
+
+//creates a buffer of len elements mapped at creation and hands the mapping to fill before it is
+//unmapped, so the data is written once into memory the gpu can use
+fn create_mapped_buffer<T: bytemuck::Pod, R>(
+    device: &wgpu::Device,
+    label: &str,
+    len: usize,
+    usage: wgpu::BufferUsages,
+    fill: impl FnOnce(&mut [T]) -> R,
+) -> (wgpu::Buffer, R) {
+    //an empty buffer can't be mapped, so it gets a single zeroed element instead
+    let size = (len.max(1) * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: wgpu::util::align_to(size, wgpu::COPY_BUFFER_ALIGNMENT),
+        usage,
+        mapped_at_creation: true,
+    });
+    let result = {
+        let mut mapped = buffer.slice(..).get_mapped_range_mut();
+        fill(bytemuck::cast_slice_mut(
+            &mut mapped[..len * std::mem::size_of::<T>()],
+        ))
+    };
+    buffer.unmap();
+    (buffer, result)
+}
+
+//writes the indices as u16 when every vertex can be addressed by one, otherwise as u32. returns
+//the format to bind the buffer with
+fn create_index_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    indices: &[u32],
+    vertex_count: usize,
+) -> (wgpu::Buffer, wgpu::IndexFormat) {
+    if vertex_count <= u16::MAX as usize {
+        let (buffer, ()) = create_mapped_buffer(
+            device,
+            label,
+            indices.len(),
+            MESH_INDEX_USAGE,
+            |mapped: &mut [u16]| {
+                for (out, index) in mapped.iter_mut().zip(indices) {
+                    *out = *index as u16;
+                }
+            },
+        );
+        (buffer, wgpu::IndexFormat::Uint16)
+    } else {
+        let (buffer, ()) = create_mapped_buffer(
+            device,
+            label,
+            indices.len(),
+            MESH_INDEX_USAGE,
+            |mapped: &mut [u32]| mapped.copy_from_slice(indices),
+        );
+        (buffer, wgpu::IndexFormat::Uint32)
+    }
+}
+
+//positions, texcoords, normals and colors are flattened vecs in tobj, out must hold
+//positions.len() / 3 vertices. normals are left at zero if the mesh has none, texcoords are
+//projected from above, vertices without a color are white and the second uv set copies the first
+fn fill_vertices(mesh: &tobj::Mesh, out: &mut [model::ModelVertex]) {
+    let planar = mesh.texcoords.is_empty().then(|| PlanarMapping::new(&mesh.positions));
+    for (vertex, out) in out.iter_mut().enumerate() {
+        let tex_coords = match planar.as_ref() {
+            Some(planar) => planar.tex_coords(&mesh.positions[vertex * 3..vertex * 3 + 3]),
+            None => [
+                mesh.texcoords[vertex * 2],
+                1.0 - mesh.texcoords[vertex * 2 + 1],
+            ],
+        };
+        *out = model::ModelVertex {
+            position: [
+                mesh.positions[vertex * 3],
+                mesh.positions[vertex * 3 + 1],
+                mesh.positions[vertex * 3 + 2],
+            ],
+            tex_coords,
+            normal: if mesh.normals.is_empty() {
+                [0.0, 0.0, 0.0]
+            } else {
+                [
+                    mesh.normals[vertex * 3],
+                    mesh.normals[vertex * 3 + 1],
+                    mesh.normals[vertex * 3 + 2],
+                ]
+            },
+            color: match mesh.vertex_color.get(vertex * 3..vertex * 3 + 3) {
+                Some(color) => [color[0], color[1], color[2]],
+                None => [1.0, 1.0, 1.0],
+            },
+            //obj has one uv set, so the second starts out as a copy of it
+            tex_coords_1: tex_coords,
+        };
+    }
+}
+
+//texcoords for a mesh without any, the x and z of each position stretched over the mesh's
+//footprint so a texture lies across it from above
+struct PlanarMapping {
+    min: [f32; 2],
+    extent: [f32; 2],
+}
+
+impl PlanarMapping {
+    fn new(positions: &[f32]) -> Self {
+        let mut min = [f32::MAX; 2];
+        let mut max = [f32::MIN; 2];
+        for position in positions.chunks_exact(3) {
+            for (axis, value) in [position[0], position[2]].into_iter().enumerate() {
+                min[axis] = min[axis].min(value);
+                max[axis] = max[axis].max(value);
+            }
+        }
+        //flat or empty meshes would divide by zero
+        let extent = [0, 1].map(|axis| (max[axis] - min[axis]).max(f32::EPSILON));
+        Self { min, extent }
+    }
+
+    fn tex_coords(&self, position: &[f32]) -> [f32; 2] {
+        [
+            (position[0] - self.min[0]) / self.extent[0],
+            (position[2] - self.min[1]) / self.extent[1],
+        ]
+    }
+}
+
+//bakes the root transform into the vertices. normals go through the inverse transpose so they
+//stay perpendicular when the root scales unevenly
+fn apply_root(vertices: &mut [model::ModelVertex], root: &Matrix4<f32>) {
+    let linear = cgmath::Matrix3::from_cols(root.x.truncate(), root.y.truncate(), root.z.truncate());
+    let normal_matrix = linear.invert().map_or(linear, |inverse| inverse.transpose());
+    for vertex in vertices {
+        vertex.position = root.transform_point(vertex.position.into()).into();
+        let normal = normal_matrix * cgmath::Vector3::from(vertex.normal);
+        if normal.magnitude2() > 0.0 {
+            vertex.normal = normal.normalize().into();
+        }
+    }
+}
+
+//fills in normals for a mesh that came without any. each vertex gets the sum of the face normals
+//around it before normalising, and as the unnormalised cross product's length is twice the
+//triangle's area bigger faces pull harder
+fn generate_smooth_normals(vertices: &mut [model::ModelVertex], indices: &[u32]) {
+    let mut normals = vec![cgmath::Vector3::new(0.0f32, 0.0, 0.0); vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let corners = [0, 1, 2].map(|corner| triangle[corner] as usize);
+        if corners.iter().any(|index| *index >= vertices.len()) {
+            continue;
+        }
+        let normal = face_normal(corners.map(|index| vertices[index].position));
+        for index in corners {
+            normals[index] += normal;
+        }
+    }
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        //vertices no triangle uses keep a zero normal rather than a NaN one
+        if normal.magnitude2() > 0.0 {
+            vertex.normal = normal.normalize().into();
+        }
+    }
+}
+
+//unwelds every triangle into its own three vertices, all carrying the triangle's normal
+fn flatten(vertices: &[model::ModelVertex], indices: &[u32]) -> (Vec<model::ModelVertex>, Vec<u32>) {
+    let mut flat = Vec::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        let [Some(a), Some(b), Some(c)] =
+            [0, 1, 2].map(|corner| vertices.get(triangle[corner] as usize).copied())
+        else {
+            continue;
+        };
+        let normal = face_normal([a.position, b.position, c.position]);
+        let normal = if normal.magnitude2() > 0.0 {
+            normal.normalize()
+        } else {
+            normal
+        };
+        flat.extend([a, b, c].map(|vertex| model::ModelVertex {
+            normal: normal.into(),
+            ..vertex
+        }));
+    }
+    let indices = (0..flat.len() as u32).collect();
+    (flat, indices)
+}
+
+//counter clockwise winding faces the viewer, matching the pipelines' front face
+fn face_normal(positions: [[f32; 3]; 3]) -> cgmath::Vector3<f32> {
+    let [a, b, c] = positions.map(cgmath::Vector3::from);
+    (b - a).cross(c - a)
+}
+
+fn bounding_radius(vertices: &[model::ModelVertex]) -> f32 {
+    vertices
+        .iter()
+        .map(|vertex| cgmath::Vector3::from(vertex.position).magnitude())
+        .fold(0.0, f32::max)
+}
+
+fn bounds(vertices: &[model::ModelVertex]) -> picking::Aabb {
+    picking::Aabb::from_points(vertices.iter().map(|vertex| vertex.position))
+}
+
+fn mesh_triangles(vertices: &[model::ModelVertex], indices: &[u32]) -> model::MeshTriangles {
+    model::MeshTriangles {
+        positions: vertices.iter().map(|vertex| vertex.position).collect(),
+        normals: vertices.iter().map(|vertex| vertex.normal).collect(),
+        indices: indices.to_vec(),
+    }
+}
+
+type MeshData = (Vec<model::ModelVertex>, Vec<u32>, usize);
+
+//appends every mesh onto the first one with the same material, offsetting the indices by the
+//vertices already in the combined buffer. order follows the first appearance of each material
+fn merge_meshes_by_material(meshes: Vec<MeshData>) -> Vec<MeshData> {
+    let mut merged: Vec<MeshData> = Vec::new();
+    for (vertices, indices, material) in meshes {
+        match merged.iter_mut().find(|(_, _, existing)| *existing == material) {
+            Some((merged_vertices, merged_indices, _)) => {
+                let base = merged_vertices.len() as u32;
+                merged_indices.extend(indices.iter().map(|index| index + base));
+                merged_vertices.extend(vertices);
+            }
+            None => merged.push((vertices, indices, material)),
+        }
+    }
+    merged
+}
@@ -0,0 +1,217 @@
+use anyhow::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::frame_stats::now_seconds;
+use std::time::Duration;
+
+//how long a path has to stay quiet before its callbacks run. editors often write a file in
+//several steps, or replace it with a renamed temporary, and each step is its own event
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(100);
+
+//only ever called from poll. Send so the watcher can move to the render thread after the app
+//builds its state on another one
+pub type WatchCallback = Box<dyn FnMut(&Path) + Send>;
+
+//identifies a subscription so it can be dropped again
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WatchId(u64);
+
+struct Subscription {
+    path: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct NotifyWatcher {
+    watcher: notify::RecommendedWatcher,
+    //filled from notify's own thread
+    events: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+}
+
+//one file system watcher for anything that reloads from disk to share, scripts are the only
+//built in subscriber so far. subscribers name a file and get a callback on the render thread,
+//from poll, once changes to it have settled. the directory holding each file is watched rather
+//than the file itself so replacing the file doesn't lose the watch. the web build has no file
+//system, subscribing there is an error
+pub struct FileWatcher {
+    //created on the first subscription so nothing is spawned for apps that never watch
+    #[cfg(not(target_arch = "wasm32"))]
+    watcher: Option<NotifyWatcher>,
+    subscriptions: HashMap<WatchId, Subscription>,
+    callbacks: HashMap<WatchId, WatchCallback>,
+    //how many subscriptions need each directory watched
+    directories: HashMap<PathBuf, usize>,
+    //paths that changed and when their callbacks are due, in now_seconds time since Instant
+    //isn't available in the browser
+    pending: HashMap<PathBuf, f64>,
+    debounce: Duration,
+    next_id: u64,
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            watcher: None,
+            subscriptions: HashMap::new(),
+            callbacks: HashMap::new(),
+            directories: HashMap::new(),
+            pending: HashMap::new(),
+            debounce: DEFAULT_DEBOUNCE,
+            next_id: 0,
+        }
+    }
+
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    pub fn debounce(&self) -> Duration {
+        self.debounce
+    }
+
+    //calls the callback with the path whenever the file is written, replaced or removed. the file
+    //doesn't have to exist yet but its directory does
+    pub fn subscribe(
+        &mut self,
+        path: impl AsRef<Path>,
+        callback: impl FnMut(&Path) + Send + 'static,
+    ) -> Result<WatchId> {
+        let path = watch_path(path.as_ref())?;
+        let directory = path
+            .parent()
+            .ok_or_else(|| anyhow!("{} has no parent directory to watch", path.display()))?
+            .to_path_buf();
+        if !self.directories.contains_key(&directory) {
+            self.watch_directory(&directory)?;
+        }
+        *self.directories.entry(directory).or_default() += 1;
+        let id = WatchId(self.next_id);
+        self.next_id += 1;
+        self.subscriptions.insert(id, Subscription { path });
+        self.callbacks.insert(id, Box::new(callback));
+        Ok(id)
+    }
+
+    //drops the subscription, the directory stops being watched once nothing else needs it
+    pub fn unsubscribe(&mut self, id: WatchId) {
+        let Some(subscription) = self.subscriptions.remove(&id) else {
+            return;
+        };
+        self.callbacks.remove(&id);
+        let Some(directory) = subscription.path.parent() else {
+            return;
+        };
+        if let Some(count) = self.directories.get_mut(directory) {
+            *count -= 1;
+            if *count == 0 {
+                self.directories.remove(directory);
+                self.unwatch_directory(directory);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.is_empty()
+    }
+
+    //collects what changed since the last poll and runs the callbacks for paths that have been
+    //quiet for the debounce time. called once per update
+    pub fn poll(&mut self) {
+        let now = now_seconds();
+        #[cfg(not(target_arch = "wasm32"))]
+        let events = self
+            .watcher
+            .iter()
+            .flat_map(|watcher| watcher.events.try_iter());
+        #[cfg(not(target_arch = "wasm32"))]
+        for event in events {
+            let event = match event {
+                std::result::Result::Ok(event) => event,
+                Err(e) => {
+                    tracing::warn!("file watcher error: {}", e);
+                    continue;
+                }
+            };
+            if matches!(event.kind, notify::EventKind::Access(_)) {
+                continue;
+            }
+            for path in event.paths {
+                self.pending.insert(path, now + self.debounce.as_secs_f64());
+            }
+        }
+        if self.pending.is_empty() {
+            return;
+        }
+        let due: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, due)| **due <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in due {
+            self.pending.remove(&path);
+            for (id, subscription) in &self.subscriptions {
+                if subscription.path == path {
+                    if let Some(callback) = self.callbacks.get_mut(id) {
+                        callback(&path);
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn watch_directory(&mut self, directory: &Path) -> Result<()> {
+        use notify::Watcher;
+        let watcher = match self.watcher.as_mut() {
+            Some(watcher) => watcher,
+            None => {
+                let (sender, events) = std::sync::mpsc::channel();
+                let watcher = notify::recommended_watcher(sender)?;
+                self.watcher.insert(NotifyWatcher { watcher, events })
+            }
+        };
+        watcher
+            .watcher
+            .watch(directory, notify::RecursiveMode::NonRecursive)
+            .with_context(|| format!("failed to watch {}", directory.display()))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn watch_directory(&mut self, directory: &Path) -> Result<()> {
+        bail!(
+            "can't watch {}, the web build has no file system",
+            directory.display()
+        )
+    }
+
+    fn unwatch_directory(&mut self, directory: &Path) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(watcher) = self.watcher.as_mut() {
+            use notify::Watcher;
+            let _ = watcher.watcher.unwatch(directory);
+        }
+    }
+}
+
+//the absolute path events will be reported with. the directory is resolved through any symlinks
+//the same way the watcher resolves it, the file itself may not exist yet
+fn watch_path(path: &Path) -> Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} doesn't name a file", path.display()))?;
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let directory = directory
+        .canonicalize()
+        .with_context(|| format!("failed to find {}", directory.display()))?;
+    Ok(directory.join(file_name))
+}
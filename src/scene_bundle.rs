@@ -0,0 +1,111 @@
+use crate::draw_queue::DrawStats;
+
+//how many bundles are kept, one per pass drawing the scene. render targets that come and go
+//would otherwise leave bundles for camera bind groups that no longer exist
+const MAX_BUNDLES: usize = 4;
+
+//the per frame state a recorded scene bundle depends on. anything else that ends up baked into
+//the bundle (the model, materials, pipelines, indirect and cull buffers) invalidates the cache
+//explicitly when it changes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SceneBundleKey {
+    pub camera_bind_group: u64,
+    pub instance_buffer: u64,
+    pub instance_count: u32,
+    pub color_format: wgpu::TextureFormat,
+}
+
+struct CachedBundle {
+    key: SceneBundleKey,
+    bundle: wgpu::RenderBundle,
+    stats: DrawStats,
+}
+
+//holds the static scene recorded into a render bundle so the same draws can be replayed every
+//frame without encoding them again. instance data can still change freely as the bundle only
+//references the buffers, it is only re-recorded when the key or the scene itself changes. passes
+//with their own camera or color format, such as the main pass and render_to_target, each keep a
+//bundle of their own
+pub struct SceneBundleCache {
+    enabled: bool,
+    //most recently used first
+    cached: Vec<CachedBundle>,
+    recordings: u32,
+}
+
+impl Default for SceneBundleCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SceneBundleCache {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            cached: Vec::new(),
+            recordings: 0,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.cached.clear();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    //drops every recorded bundle so the next frame records fresh ones
+    pub fn invalidate(&mut self) {
+        self.cached.clear();
+    }
+
+    //how many times the scene has been recorded, a count that keeps climbing means something is
+    //invalidating the cache every frame
+    pub fn recordings(&self) -> u32 {
+        self.recordings
+    }
+
+    //records the scene with the given closure unless a bundle was already recorded for the same
+    //key, dropping the least recently used bundle when the cache is full. does nothing while
+    //disabled
+    pub fn prepare(
+        &mut self,
+        key: SceneBundleKey,
+        record: impl FnOnce() -> (wgpu::RenderBundle, DrawStats),
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let cached = match self.cached.iter().position(|cached| cached.key == key) {
+            Some(index) => self.cached.remove(index),
+            None => {
+                let (bundle, stats) = record();
+                self.recordings += 1;
+                self.cached.truncate(MAX_BUNDLES - 1);
+                CachedBundle { key, bundle, stats }
+            }
+        };
+        self.cached.insert(0, cached);
+    }
+
+    //the recorded bundle and what it draws, if there is one for this key
+    pub fn get(&self, key: &SceneBundleKey) -> Option<(&wgpu::RenderBundle, DrawStats)> {
+        self.cached
+            .iter()
+            .find(|cached| cached.key == *key)
+            .map(|cached| (&cached.bundle, cached.stats))
+    }
+
+    pub fn len(&self) -> usize {
+        self.cached.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cached.is_empty()
+    }
+}
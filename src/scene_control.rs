@@ -0,0 +1,98 @@
+use std::sync::mpsc;
+
+//commands applied in one update are capped so a sender producing faster than frames are drawn
+//can't keep update from ever returning. anything past the cap waits for the next update
+const MAX_COMMANDS_PER_UPDATE: usize = 65536;
+
+//a change to the scene sent from outside the render loop, e.g. by a simulation on another
+//thread or a bridge reading from a socket. instances are addressed by the index returned when
+//they were spawned, commands naming an index that doesn't exist are ignored
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneCommand {
+    SetInstanceTransform {
+        index: usize,
+        position: cgmath::Vector3<f32>,
+        rotation: cgmath::Quaternion<f32>,
+    },
+    SetInstanceScale {
+        index: usize,
+        scale: f32,
+    },
+    SetInstanceTint {
+        index: usize,
+        tint: [f32; 4],
+    },
+    AddInstanceMotion {
+        index: usize,
+        motion: crate::motion::Motion,
+    },
+    ClearInstanceMotions {
+        index: usize,
+    },
+    //adds another instance of the loaded model, it takes the next index
+    SpawnModel {
+        position: cgmath::Vector3<f32>,
+        rotation: cgmath::Quaternion<f32>,
+    },
+    //spawns another instance when the named file is the loaded model, only one model is drawn so
+    //any other name is reported and ignored
+    SpawnNamedModel {
+        file_name: String,
+        position: cgmath::Vector3<f32>,
+        rotation: cgmath::Quaternion<f32>,
+    },
+    SetCameraPose {
+        eye: cgmath::Point3<f32>,
+        target: cgmath::Point3<f32>,
+    },
+    SetLight {
+        position: [f32; 3],
+        color: [f32; 3],
+    },
+    SetLightPosition {
+        position: [f32; 3],
+    },
+    SetLightColor {
+        color: [f32; 3],
+    },
+    //the camera's vertical field of view in degrees
+    SetFov {
+        fovy: f32,
+    },
+    //the scene's resolution as a multiple of the window's, see GameState::set_render_scale
+    SetRenderScale {
+        scale: f32,
+    },
+    //switches the scene between filled and wireframe polygons, where the device supports it
+    ToggleWireframe,
+    //shows or hides every instance's collision bounds
+    ToggleColliderDebug,
+}
+
+//the channel scene commands arrive on. it keeps a sender of its own so it never disconnects,
+//senders can be handed out and dropped freely
+pub struct SceneChannel {
+    sender: mpsc::Sender<SceneCommand>,
+    receiver: mpsc::Receiver<SceneCommand>,
+}
+
+impl Default for SceneChannel {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { sender, receiver }
+    }
+}
+
+impl SceneChannel {
+    pub fn sender(&self) -> mpsc::Sender<SceneCommand> {
+        self.sender.clone()
+    }
+
+    //takes what has been sent so far, in the order it was sent
+    pub(crate) fn drain(&self) -> Vec<SceneCommand> {
+        self.receiver
+            .try_iter()
+            .take(MAX_COMMANDS_PER_UPDATE)
+            .collect()
+    }
+}
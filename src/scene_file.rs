@@ -0,0 +1,75 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+//the instances of a scene as saved to and loaded from a toml file, one [[instances]] table each.
+//fields left out of a table take their defaults, so hand written scenes can be short
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+    #[serde(default)]
+    pub instances: Vec<SceneInstance>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneInstance {
+    pub position: [f32; 3],
+    //quaternion as xyz then w
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    pub tint: [f32; 4],
+    //the packed material layer
+    pub layer: u32,
+}
+
+impl Default for SceneInstance {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0; 3],
+            tint: [1.0; 4],
+            layer: 0,
+        }
+    }
+}
+
+impl SceneFile {
+    pub fn from_toml(text: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading scene {}", path.display()))?;
+        Self::from_toml(&text).with_context(|| format!("parsing scene {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_toml()?)
+            .with_context(|| format!("writing scene {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_fills_in_defaults() {
+        let scene = SceneFile::from_toml(
+            "[[instances]]\nposition = [1.0, 2.0, 3.0]\n\n[[instances]]\nscale = [2.0, 1.0, 1.0]\n",
+        )
+        .unwrap();
+        assert_eq!(scene.instances.len(), 2);
+        assert_eq!(scene.instances[0].position, [1.0, 2.0, 3.0]);
+        assert_eq!(scene.instances[0].rotation, [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(scene.instances[1].tint, [1.0; 4]);
+        let text = scene.to_toml().unwrap();
+        assert_eq!(SceneFile::from_toml(&text).unwrap(), scene);
+    }
+}
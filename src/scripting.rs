@@ -0,0 +1,239 @@
+use crate::frame_stats;
+use crate::resources::watcher::{FileWatcher, WatchId};
+use anyhow::*;
+use rhai::{Engine, Scope, AST, FLOAT, INT};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+//what a script asked for during a call, applied to the state once the call has returned
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptCommand {
+    Spawn { position: [f32; 3] },
+    MoveInstance { index: usize, position: [f32; 3] },
+    ScaleInstance { index: usize, scale: f32 },
+    SetLightPosition([f32; 3]),
+    SetLightColor([f32; 3]),
+}
+
+//the input scripts can read. keys are winit KeyCode names such as "KeyW" or "Space", the cursor is
+//in physical pixels
+#[derive(Debug, Clone, Default)]
+pub struct ScriptInput {
+    pub keys_down: HashSet<String>,
+    pub cursor: [f32; 2],
+}
+
+//shared between the host and the functions registered on the engine
+#[derive(Default)]
+struct Shared {
+    commands: Vec<ScriptCommand>,
+    input: ScriptInput,
+    //the state's instance count plus whatever has been spawned during this call
+    instance_count: usize,
+}
+
+struct LoadedScript {
+    ast: AST,
+    //a script that errored at runtime isn't called again until the file changes
+    failed: bool,
+}
+
+//runs one rhai script file. top level statements run on load, then `fn init()` once if the
+//script has one and `fn update(dt)` every frame. the file is reloaded with fresh state whenever
+//it changes on disk. numbers passed to the bindings are floats, so
+//`spawn_instance(0.0, 1.0, 0.0)` rather than `spawn_instance(0, 1, 0)`
+pub struct ScriptHost {
+    engine: Engine,
+    shared: Arc<Mutex<Shared>>,
+    scope: Scope<'static>,
+    path: Option<PathBuf>,
+    //set by the file watcher, a compile error is only reported again once the file changes
+    changed: Arc<AtomicBool>,
+    watch: Option<WatchId>,
+    script: Option<LoadedScript>,
+    last_update: Option<f64>,
+}
+
+impl Default for ScriptHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptHost {
+    pub fn new() -> Self {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let mut engine = Engine::new();
+        engine.on_print(|text| tracing::info!(target: "script", "{}", text));
+        let commands = shared.clone();
+        engine.register_fn("spawn_instance", move |x: FLOAT, y: FLOAT, z: FLOAT| -> INT {
+            let mut shared = commands.lock().unwrap();
+            shared.commands.push(ScriptCommand::Spawn {
+                position: [x as f32, y as f32, z as f32],
+            });
+            shared.instance_count += 1;
+            (shared.instance_count - 1) as INT
+        });
+        let commands = shared.clone();
+        engine.register_fn(
+            "move_instance",
+            move |index: INT, x: FLOAT, y: FLOAT, z: FLOAT| {
+                commands.lock().unwrap().commands.push(ScriptCommand::MoveInstance {
+                    index: index.max(0) as usize,
+                    position: [x as f32, y as f32, z as f32],
+                });
+            },
+        );
+        let commands = shared.clone();
+        engine.register_fn("scale_instance", move |index: INT, scale: FLOAT| {
+            commands.lock().unwrap().commands.push(ScriptCommand::ScaleInstance {
+                index: index.max(0) as usize,
+                scale: scale as f32,
+            });
+        });
+        let commands = shared.clone();
+        engine.register_fn("set_light_position", move |x: FLOAT, y: FLOAT, z: FLOAT| {
+            commands
+                .lock()
+                .unwrap()
+                .commands
+                .push(ScriptCommand::SetLightPosition([x as f32, y as f32, z as f32]));
+        });
+        let commands = shared.clone();
+        engine.register_fn("set_light_color", move |r: FLOAT, g: FLOAT, b: FLOAT| {
+            commands
+                .lock()
+                .unwrap()
+                .commands
+                .push(ScriptCommand::SetLightColor([r as f32, g as f32, b as f32]));
+        });
+        let input = shared.clone();
+        engine.register_fn("instance_count", move || -> INT {
+            input.lock().unwrap().instance_count as INT
+        });
+        let input = shared.clone();
+        engine.register_fn("key_down", move |key: &str| -> bool {
+            input.lock().unwrap().input.keys_down.contains(key)
+        });
+        let input = shared.clone();
+        engine.register_fn("cursor_x", move || -> FLOAT {
+            input.lock().unwrap().input.cursor[0] as FLOAT
+        });
+        let input = shared.clone();
+        engine.register_fn("cursor_y", move || -> FLOAT {
+            input.lock().unwrap().input.cursor[1] as FLOAT
+        });
+        Self {
+            engine,
+            shared,
+            scope: Scope::new(),
+            path: None,
+            changed: Arc::new(AtomicBool::new(false)),
+            watch: None,
+            script: None,
+            last_update: None,
+        }
+    }
+
+    //loads the script at the path, replacing the current one. its top level statements and init
+    //function run straight away, the commands they queue are returned by the next update. the
+    //path is kept even if it fails to compile so fixing the file picks it up
+    pub fn load(
+        &mut self,
+        path: impl Into<PathBuf>,
+        instance_count: usize,
+        watcher: &mut FileWatcher,
+    ) -> Result<()> {
+        let path = path.into();
+        if let Some(watch) = self.watch.take() {
+            watcher.unsubscribe(watch);
+        }
+        let changed = self.changed.clone();
+        match watcher.subscribe(&path, move |_| changed.store(true, Ordering::Relaxed)) {
+            std::result::Result::Ok(watch) => self.watch = Some(watch),
+            Err(e) => tracing::warn!(
+                "{} won't be reloaded when it changes: {:#}",
+                path.display(),
+                e
+            ),
+        }
+        self.changed.store(false, Ordering::Relaxed);
+        self.path = Some(path);
+        self.shared.lock().unwrap().instance_count = instance_count;
+        self.reload()
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub fn set_key_down(&mut self, key: &str, down: bool) {
+        let keys_down = &mut self.shared.lock().unwrap().input.keys_down;
+        if down {
+            keys_down.insert(key.to_string());
+        } else {
+            keys_down.remove(key);
+        }
+    }
+
+    pub fn set_cursor(&mut self, x: f32, y: f32) {
+        self.shared.lock().unwrap().input.cursor = [x, y];
+    }
+
+    //reloads the script if the file changed, calls its update function and returns everything it
+    //queued since the last call. instance_count is what the state currently has
+    pub fn update(&mut self, instance_count: usize) -> Result<Vec<ScriptCommand>> {
+        let now = frame_stats::now_seconds();
+        let dt = self.last_update.map_or(0.0, |last| now - last);
+        self.last_update = Some(now);
+        self.shared.lock().unwrap().instance_count = instance_count;
+        let mut result = Ok(());
+        if self.changed.swap(false, Ordering::Relaxed) {
+            result = self.reload();
+        }
+        if result.is_ok() {
+            result = self.call("update", (dt as FLOAT,));
+        }
+        let commands = std::mem::take(&mut self.shared.lock().unwrap().commands);
+        result.map(|_| commands)
+    }
+
+    //compiles the file and runs it with fresh state. on a compile error the old script keeps
+    //running
+    fn reload(&mut self) -> Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        let ast = self
+            .engine
+            .compile_file(path.clone())
+            .map_err(|e| anyhow!("failed to compile {}: {}", path.display(), e))?;
+        self.scope = Scope::new();
+        let script = self.script.insert(LoadedScript { ast, failed: false });
+        if let Err(e) = self.engine.run_ast_with_scope(&mut self.scope, &script.ast) {
+            script.failed = true;
+            bail!("{} failed: {}", path.display(), e);
+        }
+        self.call("init", ())
+    }
+
+    //calls a function the script defines, doing nothing if it doesn't define one
+    fn call(&mut self, name: &str, args: impl rhai::FuncArgs) -> Result<()> {
+        let Some(script) = self.script.as_mut().filter(|script| !script.failed) else {
+            return Ok(());
+        };
+        if !script.ast.iter_functions().any(|function| function.name == name) {
+            return Ok(());
+        }
+        let result = self
+            .engine
+            .call_fn::<rhai::Dynamic>(&mut self.scope, &script.ast, name, args);
+        if let Err(e) = result {
+            script.failed = true;
+            bail!("script {} failed: {}", name, e);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+use crate::frame_stats;
+use crate::windowing::FullscreenMode;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use winit::keyboard::KeyCode;
+
+//changes are written back once they have stopped coming for this long, so dragging the window
+//edge doesn't rewrite the file every frame
+const SAVE_DELAY_SECONDS: f64 = 1.0;
+
+//window and graphics settings read from settings.toml at startup. every field has a default so
+//the file only needs what differs from it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub window: WindowSettings,
+    pub graphics: GraphicsSettings,
+    pub bindings: KeyBindings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    //the windowed size in logical pixels, kept while fullscreen
+    pub width: f64,
+    pub height: f64,
+    //"borderless" or "exclusive", left out for a window
+    pub fullscreen: Option<FullscreenMode>,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            width: 1280.0,
+            height: 720.0,
+            fullscreen: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    //off presents as soon as a frame is ready, through mailbox or immediate where supported
+    pub vsync: bool,
+    pub fps_limit: Option<f32>,
+    //the scene's resolution as a multiple of the window's, from 0.25 to 2
+    pub render_scale: f32,
+    //a stencil aspect on the depth buffer for scene masks, read once at startup
+    pub stencil: bool,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            fps_limit: None,
+            render_scale: 1.0,
+            stencil: false,
+        }
+    }
+}
+
+//the keys for each action, by winit's KeyCode names like "KeyW" or "F1". an action can have
+//several keys or none
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub forward: Vec<KeyCode>,
+    pub backward: Vec<KeyCode>,
+    pub left: Vec<KeyCode>,
+    pub right: Vec<KeyCode>,
+    pub console: Vec<KeyCode>,
+    pub overlay: Vec<KeyCode>,
+    pub editor: Vec<KeyCode>,
+    pub debug_view: Vec<KeyCode>,
+    pub colliders: Vec<KeyCode>,
+    pub gpu_profiling: Vec<KeyCode>,
+    pub gpu_capture: Vec<KeyCode>,
+    pub recording: Vec<KeyCode>,
+    pub fullscreen: Vec<KeyCode>,
+    pub screenshot: Vec<KeyCode>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            forward: vec![KeyCode::KeyW, KeyCode::ArrowUp],
+            backward: vec![KeyCode::KeyS, KeyCode::ArrowDown],
+            left: vec![KeyCode::KeyA, KeyCode::ArrowLeft],
+            right: vec![KeyCode::KeyD, KeyCode::ArrowRight],
+            console: vec![KeyCode::Backquote],
+            overlay: vec![KeyCode::F1],
+            editor: vec![KeyCode::F6],
+            debug_view: vec![KeyCode::F7],
+            colliders: vec![KeyCode::F8],
+            gpu_profiling: vec![KeyCode::F9],
+            gpu_capture: vec![KeyCode::F2],
+            recording: vec![KeyCode::F10],
+            fullscreen: vec![KeyCode::F11],
+            screenshot: vec![KeyCode::F12],
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_toml(text: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+}
+
+//the settings together with the file they came from. changes are marked with changed and
+//written back by poll once they settle
+#[derive(Debug, Default)]
+pub struct SettingsFile {
+    pub settings: Settings,
+    path: Option<PathBuf>,
+    //when the last unsaved change was made
+    changed_at: Option<f64>,
+}
+
+impl SettingsFile {
+    //reads the file, writing the defaults out first if it doesn't exist yet
+    pub fn load(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let settings = if path.exists() {
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("reading settings {}", path.display()))?;
+            Settings::from_toml(&text)
+                .with_context(|| format!("parsing settings {}", path.display()))?
+        } else {
+            let settings = Settings::default();
+            save(&path, &settings)?;
+            settings
+        };
+        Ok(Self {
+            settings,
+            path: Some(path),
+            changed_at: None,
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    //applies an edit, scheduling a save if it changed anything
+    pub fn update(&mut self, edit: impl FnOnce(&mut Settings)) {
+        let before = self.settings.clone();
+        edit(&mut self.settings);
+        if self.settings != before {
+            self.changed_at = Some(frame_stats::now_seconds());
+        }
+    }
+
+    //writes pending changes once they have settled. settings without a file are never saved
+    pub fn poll(&mut self) -> anyhow::Result<()> {
+        match self.changed_at {
+            Some(changed_at) if frame_stats::now_seconds() - changed_at >= SAVE_DELAY_SECONDS => {
+                self.flush()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    //writes pending changes now, e.g. on exit
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        if self.changed_at.take().is_none() {
+            return Ok(());
+        }
+        match self.path.as_ref() {
+            Some(path) => save(path, &self.settings),
+            None => Ok(()),
+        }
+    }
+}
+
+fn save(path: &Path, settings: &Settings) -> anyhow::Result<()> {
+    if let Some(folder) = path.parent() {
+        std::fs::create_dir_all(folder)
+            .with_context(|| format!("creating settings folder {}", folder.display()))?;
+    }
+    std::fs::write(path, settings.to_toml()?)
+        .with_context(|| format!("writing settings {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let settings = Settings::from_toml(
+            "[window]\nfullscreen = \"borderless\"\n\n[bindings]\nforward = [\"KeyI\"]\n",
+        )
+        .unwrap();
+        assert_eq!(settings.window.fullscreen, Some(FullscreenMode::Borderless));
+        assert_eq!(settings.window.width, 1280.0);
+        assert!(settings.graphics.vsync);
+        assert_eq!(settings.bindings.forward, vec![KeyCode::KeyI]);
+        assert_eq!(settings.bindings.console, vec![KeyCode::Backquote]);
+        let text = settings.to_toml().unwrap();
+        assert_eq!(Settings::from_toml(&text).unwrap(), settings);
+    }
+}
@@ -1,15 +1,12 @@
+#include "common.wgsl"
+#ifdef DEBUG_VIEW
+#include "debug_view.wgsl"
+#endif
+
 // Vertex shader
-struct CameraUniform {
-    view_pos: vec4<f32>,
-    view_proj: mat4x4<f32>,
-};
 @group(1) @binding(0) 
 var<uniform> camera: CameraUniform;
 
-struct Light {
-    position: vec3<f32>,
-    color: vec3<f32>,
-}
 @group(2) @binding(0)
 var<uniform> light: Light;
 
@@ -17,6 +14,7 @@ struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
     @location(2) normal: vec3<f32>,
+    @location(3) color: vec3<f32>,
 }
 
 struct VertexOutput {
@@ -24,6 +22,7 @@ struct VertexOutput {
     @location(0) tex_coords: vec2<f32>,
     @location(1) world_normal: vec3<f32>,
     @location(2) world_position: vec3<f32>,
+    @location(3) color: vec4<f32>,
 }
 struct InstanceInput {
     @location(5) model_matrix_0: vec4<f32>,
@@ -33,6 +32,7 @@ struct InstanceInput {
     @location(9) normal_matrix_0: vec3<f32>,
     @location(10) normal_matrix_1: vec3<f32>,
     @location(11) normal_matrix_2: vec3<f32>,
+    @location(13) tint: vec4<f32>,
 };
  
 @vertex
@@ -54,7 +54,9 @@ fn vs_main(
 
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
-    out.world_normal = normal_matrix * model.normal;
+    out.color = vec4<f32>(model.color, 1.0) * instance.tint;
+    // the normal matrix isn't normalized so stretched instances keep perpendicular normals
+    out.world_normal = normalize(normal_matrix * model.normal);
     var world_position: vec4<f32> = model_matrix * vec4<f32>(model.position, 1.0);
     out.world_position = world_position.xyz;
     out.clip_position = camera.view_proj * world_position; 
@@ -68,9 +70,19 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords);
-    let ambient_strength = 0.1;
-    let ambient_color = light.color * ambient_strength;
+    let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords)
+        * in.color;
+#ifdef ALPHA_TEST
+    // cutout variant, texels under half coverage are dropped instead of blended
+    if object_color.a < 0.5 {
+        discard;
+    }
+#endif
+#ifdef DEBUG_VIEW
+    let distance = length(camera.view_pos.xyz - in.world_position);
+    return debug_view(DEBUG_VIEW, object_color, in.world_normal, in.tex_coords, distance);
+#else
+    let ambient_color = light.color * light.ambient;
     let light_dir = normalize(light.position - in.world_position);
     let diffuse_strength = max(dot(in.world_normal, light_dir), 0.0);
     let diffuse_color = light.color * diffuse_strength;
@@ -80,5 +92,7 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let specular_color = specular_strength * light.color;
 
     let result = (ambient_color + diffuse_color + specular_color) * object_color.xyz;
-    return vec4<f32>(result, object_color.a);
+    // premultiplied so a transparent window composites correctly, opaque texels are unchanged
+    return vec4<f32>(result * object_color.a, object_color.a);
+#endif
 }
@@ -0,0 +1,103 @@
+// the scene shader with every material packed into one texture array, each instance picks its
+// layer so differently textured instances still draw in one call
+#include "common.wgsl"
+#ifdef DEBUG_VIEW
+#include "debug_view.wgsl"
+#endif
+
+// Vertex shader
+@group(1) @binding(0) 
+var<uniform> camera: CameraUniform;
+
+@group(2) @binding(0)
+var<uniform> light: Light;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) color: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) @interpolate(flat) layer: u32,
+    @location(4) color: vec4<f32>,
+}
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(9) normal_matrix_0: vec3<f32>,
+    @location(10) normal_matrix_1: vec3<f32>,
+    @location(11) normal_matrix_2: vec3<f32>,
+    @location(12) layer: u32,
+    @location(13) tint: vec4<f32>,
+};
+ 
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput
+) -> VertexOutput {
+ let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    let normal_matrix = mat3x3<f32>(
+    instance.normal_matrix_0,
+    instance.normal_matrix_1,
+    instance.normal_matrix_2,
+    );
+
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.color = vec4<f32>(model.color, 1.0) * instance.tint;
+    out.layer = instance.layer;
+    // the normal matrix isn't normalized so stretched instances keep perpendicular normals
+    out.world_normal = normalize(normal_matrix * model.normal);
+    var world_position: vec4<f32> = model_matrix * vec4<f32>(model.position, 1.0);
+    out.world_position = world_position.xyz;
+    out.clip_position = camera.view_proj * world_position; 
+    return out;
+}
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d_array<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords, in.layer)
+        * in.color;
+#ifdef ALPHA_TEST
+    // cutout variant, texels under half coverage are dropped instead of blended
+    if object_color.a < 0.5 {
+        discard;
+    }
+#endif
+#ifdef DEBUG_VIEW
+    let distance = length(camera.view_pos.xyz - in.world_position);
+    return debug_view(DEBUG_VIEW, object_color, in.world_normal, in.tex_coords, distance);
+#else
+    let ambient_color = light.color * light.ambient;
+    let light_dir = normalize(light.position - in.world_position);
+    let diffuse_strength = max(dot(in.world_normal, light_dir), 0.0);
+    let diffuse_color = light.color * diffuse_strength;
+    let view_dir = normalize(camera.view_pos.xyz - in.world_position);
+    let half_dir = normalize(view_dir + light_dir);
+    let specular_strength = pow(max(dot(in.world_normal, half_dir), 0.0), 32.0);
+    let specular_color = specular_strength * light.color;
+
+    let result = (ambient_color + diffuse_color + specular_color) * object_color.xyz;
+    // premultiplied so a transparent window composites correctly, opaque texels are unchanged
+    return vec4<f32>(result * object_color.a, object_color.a);
+#endif
+}
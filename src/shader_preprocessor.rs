@@ -0,0 +1,758 @@
+use crate::error::ShaderError;
+use crate::pipeline_cache::PipelineCache;
+use crate::resources;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+type Result<T> = std::result::Result<T, ShaderError>;
+
+//includes are looked up in this folder of res, so `#include "common.wgsl"` reads
+//res/shaders/common.wgsl
+const SHADER_DIR: &str = "shaders";
+
+//expands a small set of directives in WGSL before it is handed to the pipeline cache:
+//
+//- `#include "file.wgsl"` pastes in a file from res/shaders, each file at most once per shader
+//  so shared structs can be included from several places
+//- `#define NAME` and `#undef NAME` set and clear flags, on top of the ones defined here
+//- `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif` keep or drop the lines between them
+//
+//registered shaders are kept so variants of them can be built later with ShaderFlags, see
+//register_variant.
+//
+//directives take a whole line. included files are loaded through resources::load_string and
+//kept, so preprocessing several shaders reads each one once. the output remembers which file and
+//line every line came from, so errors in the expanded shader can point back at the original
+#[derive(Default)]
+pub struct ShaderPreprocessor {
+    defines: HashSet<String>,
+    //loaded includes by the name they were included as
+    files: HashMap<String, String>,
+    //registered shaders by name
+    templates: HashMap<String, Template>,
+}
+
+//a registered shader's source, kept to expand variants of it
+struct Template {
+    file_name: String,
+    source: String,
+    layout: Arc<wgpu::PipelineLayout>,
+    //the variants built so far, rebuilt when the shader is registered again
+    variants: Vec<ShaderFlags>,
+}
+
+//the flags one variant of a shader is expanded with, on top of the preprocessor's own defines.
+//every flag is #define'd so #ifdef sees it, and flags with a value also get a `const NAME: u32`
+//the shader can read, e.g. NUM_CASCADES to size a loop. each distinct set of flags is its own
+//shader in the pipeline cache, built once
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ShaderFlags {
+    flags: BTreeMap<String, Option<u32>>,
+}
+
+//an expanded shader and where each of its lines came from
+pub struct ProcessedShader {
+    pub source: String,
+    //names of the files lines came from, the first is the shader itself
+    files: Vec<String>,
+    //index into files and 1 based line number in that file, for each output line
+    lines: Vec<(usize, u32)>,
+}
+
+//a file part way through being expanded
+struct Source {
+    name: String,
+    //index of the name in ProcessedShader::files
+    file: usize,
+    lines: Vec<String>,
+    next_line: usize,
+    //the #ifdef blocks open in this file, innermost last
+    conditions: Vec<Condition>,
+}
+
+struct Condition {
+    //whether the lines in the current branch are kept
+    active: bool,
+    //whether the block is inside a dropped branch, in which case neither branch is kept
+    enclosing_active: bool,
+    seen_else: bool,
+}
+
+impl ShaderPreprocessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //a flag every shader processed from now on starts with
+    pub fn define(&mut self, name: &str) {
+        self.defines.insert(name.to_string());
+    }
+
+    pub fn undefine(&mut self, name: &str) {
+        self.defines.remove(name);
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.defines.contains(name)
+    }
+
+    //makes an include available without loading it, or replaces a loaded one. later shaders
+    //including it get this source
+    pub fn insert_file(&mut self, name: &str, source: &str) {
+        self.files.insert(name.to_string(), source.to_string());
+    }
+
+    //forgets loaded includes so they are read again, e.g. after they changed on disk
+    pub fn clear_files(&mut self) {
+        self.files.clear();
+    }
+
+    //expands the directives in source, name is the file errors point to. defines made by the
+    //shader don't carry over to the next one
+    pub async fn process(&mut self, name: &str, source: &str) -> Result<ProcessedShader> {
+        self.process_with_flags(name, source, &ShaderFlags::new())
+            .await
+    }
+
+    //process with the flags of a variant added. their consts come first in the output
+    pub async fn process_with_flags(
+        &mut self,
+        name: &str,
+        source: &str,
+        flags: &ShaderFlags,
+    ) -> Result<ProcessedShader> {
+        let mut defines = self.defines.clone();
+        defines.extend(flags.flags.keys().cloned());
+        let mut included = HashSet::new();
+        let mut output = ProcessedShader {
+            source: String::with_capacity(source.len()),
+            files: vec![name.to_string()],
+            lines: Vec::new(),
+        };
+        let mut stack = vec![Source::new(name, 0, source)];
+        let constants = flags.constants();
+        if !constants.is_empty() {
+            output.files.push(format!("{} flags", name));
+            stack.push(Source::new(&output.files[1], 1, &constants));
+        }
+        while let Some(current) = stack.last_mut() {
+            let Some(line) = current.lines.get(current.next_line).cloned() else {
+                if !current.conditions.is_empty() {
+                    return Err(current.error(current.lines.len(), "#ifdef without #endif"));
+                }
+                stack.pop();
+                continue;
+            };
+            current.next_line += 1;
+            let line_number = current.next_line;
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if current.is_active() {
+                    output.source.push_str(&line);
+                    output.source.push('\n');
+                    output.lines.push((current.file, line_number as u32));
+                }
+                continue;
+            };
+            let (keyword, argument) = directive
+                .split_once(char::is_whitespace)
+                .map(|(keyword, argument)| (keyword, argument.trim()))
+                .unwrap_or((directive.trim_end(), ""));
+            let active = current.is_active();
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let flag = current.argument(line_number, keyword, argument)?;
+                    let defined = defines.contains(flag);
+                    current.conditions.push(Condition {
+                        active: defined == (keyword == "ifdef"),
+                        enclosing_active: active,
+                        seen_else: false,
+                    });
+                }
+                "else" => {
+                    let condition = current
+                        .conditions
+                        .last_mut()
+                        .filter(|condition| !condition.seen_else);
+                    let Some(condition) = condition else {
+                        return Err(current.error(line_number, "#else without #ifdef"));
+                    };
+                    condition.active = !condition.active;
+                    condition.seen_else = true;
+                }
+                "endif" => {
+                    if current.conditions.pop().is_none() {
+                        return Err(current.error(line_number, "#endif without #ifdef"));
+                    }
+                }
+                //everything below only applies to lines that are kept
+                _ if !active => (),
+                "define" => {
+                    let flag = current.argument(line_number, keyword, argument)?;
+                    defines.insert(flag.to_string());
+                }
+                "undef" => {
+                    let flag = current.argument(line_number, keyword, argument)?;
+                    defines.remove(flag);
+                }
+                "include" => {
+                    let file = argument
+                        .strip_prefix('"')
+                        .and_then(|argument| argument.strip_suffix('"'))
+                        .filter(|file| !file.is_empty())
+                        .ok_or_else(|| {
+                            current.error(line_number, "#include takes a quoted file name")
+                        })?
+                        .to_string();
+                    //already pasted in once, or being expanded right now
+                    if !included.insert(file.clone()) {
+                        continue;
+                    }
+                    if !self.files.contains_key(&file) {
+                        let loaded = resources::load_string(&format!("{}/{}", SHADER_DIR, file))
+                            .await
+                            .map_err(|e| {
+                                current.error(
+                                    line_number,
+                                    &format!("failed to include {:?}: {:#}", file, e),
+                                )
+                            })?;
+                        self.files.insert(file.clone(), loaded);
+                    }
+                    output.files.push(file.clone());
+                    let included_source =
+                        Source::new(&file, output.files.len() - 1, &self.files[&file]);
+                    stack.push(included_source);
+                }
+                _ => {
+                    return Err(
+                        current.error(line_number, &format!("unknown directive #{}", keyword))
+                    )
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    //loads a shader from res/shaders and expands it
+    pub async fn load(&mut self, file_name: &str) -> Result<ProcessedShader> {
+        let source = resources::load_string(&format!("{}/{}", SHADER_DIR, file_name))
+            .await
+            .map_err(|e| ShaderError {
+                name: file_name.to_string(),
+                location: None,
+                message: format!("failed to load: {:#}", e),
+            })?;
+        self.process(file_name, &source).await
+    }
+
+    //expands source and registers it with the pipeline cache under name. validation errors point
+    //into file_name or whichever file it included. registering a name again also rebuilds the
+    //variants made from it
+    pub async fn register(
+        &mut self,
+        pipeline_cache: &mut PipelineCache,
+        device: &wgpu::Device,
+        name: &str,
+        file_name: &str,
+        source: &str,
+        layout: Arc<wgpu::PipelineLayout>,
+    ) -> Result<()> {
+        let flags = ShaderFlags::new();
+        self.register_expanded(
+            pipeline_cache,
+            device,
+            name,
+            file_name,
+            source,
+            &layout,
+            &flags,
+        )
+        .await?;
+        let variants = self
+            .templates
+            .remove(name)
+            .map(|template| template.variants)
+            .unwrap_or_default();
+        let mut result = Ok(());
+        for flags in &variants {
+            let variant = flags.variant_name(name);
+            result = self
+                .register_expanded(
+                    pipeline_cache,
+                    device,
+                    &variant,
+                    file_name,
+                    source,
+                    &layout,
+                    flags,
+                )
+                .await;
+            if result.is_err() {
+                break;
+            }
+        }
+        self.templates.insert(
+            name.to_string(),
+            Template {
+                file_name: file_name.to_string(),
+                source: source.to_string(),
+                layout,
+                variants,
+            },
+        );
+        result
+    }
+
+    //builds the variant of a registered shader with the given flags if it doesn't exist yet and
+    //returns the name it is registered under, the one to put in a PipelineKey
+    pub async fn register_variant(
+        &mut self,
+        pipeline_cache: &mut PipelineCache,
+        device: &wgpu::Device,
+        name: &str,
+        flags: &ShaderFlags,
+    ) -> Result<String> {
+        let variant = flags.variant_name(name);
+        if pipeline_cache.has_shader(&variant) {
+            return Ok(variant);
+        }
+        let Some(template) = self.templates.get(name) else {
+            return Err(ShaderError {
+                name: name.to_string(),
+                location: None,
+                message: "no shader registered under this name".to_string(),
+            });
+        };
+        let (file_name, source, layout) = (
+            template.file_name.clone(),
+            template.source.clone(),
+            template.layout.clone(),
+        );
+        self.register_expanded(
+            pipeline_cache,
+            device,
+            &variant,
+            &file_name,
+            &source,
+            &layout,
+            flags,
+        )
+        .await?;
+        if let Some(template) = self.templates.get_mut(name) {
+            template.variants.push(flags.clone());
+        }
+        Ok(variant)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn register_expanded(
+        &mut self,
+        pipeline_cache: &mut PipelineCache,
+        device: &wgpu::Device,
+        name: &str,
+        file_name: &str,
+        source: &str,
+        layout: &Arc<wgpu::PipelineLayout>,
+        flags: &ShaderFlags,
+    ) -> Result<()> {
+        let processed = self.process_with_flags(file_name, source, flags).await?;
+        let label = format!("{} Shader", name);
+        pipeline_cache
+            .register_shader(
+                device,
+                name,
+                wgpu::ShaderModuleDescriptor {
+                    label: Some(&label),
+                    source: wgpu::ShaderSource::Wgsl(processed.source.as_str().into()),
+                },
+                layout.clone(),
+            )
+            .map_err(|error| processed.map_error(error))
+    }
+}
+
+impl ShaderFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //a flag for #ifdef, e.g. HAS_NORMAL_MAP or ALPHA_TEST
+    pub fn with(mut self, name: &str) -> Self {
+        self.set(name, true);
+        self
+    }
+
+    //a flag with a value, also readable as a u32 const, e.g. NUM_CASCADES
+    pub fn with_value(mut self, name: &str, value: u32) -> Self {
+        self.set_value(name, value);
+        self
+    }
+
+    pub fn set(&mut self, name: &str, enabled: bool) {
+        if enabled {
+            self.flags.insert(name.to_string(), None);
+        } else {
+            self.flags.remove(name);
+        }
+    }
+
+    pub fn set_value(&mut self, name: &str, value: u32) {
+        self.flags.insert(name.to_string(), Some(value));
+    }
+
+    pub fn is_set(&self, name: &str) -> bool {
+        self.flags.contains_key(name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<u32> {
+        self.flags.get(name).copied().flatten()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    //the name a shader's variant with these flags is registered under, the shader's own name
+    //when there are none
+    pub fn variant_name(&self, shader: &str) -> String {
+        if self.is_empty() {
+            shader.to_string()
+        } else {
+            format!("{}[{}]", shader, self)
+        }
+    }
+
+    //a const line for every flag with a value
+    fn constants(&self) -> String {
+        self.flags
+            .iter()
+            .filter_map(|(name, value)| Some(format!("const {}: u32 = {}u;\n", name, (*value)?)))
+            .collect()
+    }
+}
+
+//flags in name order, values after an =, e.g. ALPHA_TEST,NUM_CASCADES=4
+impl fmt::Display for ShaderFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (name, value)) in self.flags.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", name)?;
+            if let Some(value) = value {
+                write!(f, "={}", value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ProcessedShader {
+    //the file and line an output line, counting from 1, was expanded from
+    pub fn original_location(&self, line: u32) -> Option<(&str, u32)> {
+        let (file, line) = *self.lines.get((line as usize).checked_sub(1)?)?;
+        Some((&self.files[file], line))
+    }
+
+    //moves an error reported against the expanded source to the file and line it came from. the
+    //column is kept as directives never share a line with code
+    pub fn map_error(&self, error: ShaderError) -> ShaderError {
+        let Some((line, column)) = error.location else {
+            return error;
+        };
+        match self.original_location(line) {
+            Some((file, line)) => ShaderError {
+                name: file.to_string(),
+                location: Some((line, column)),
+                message: error.message,
+            },
+            None => error,
+        }
+    }
+}
+
+impl Source {
+    fn new(name: &str, file: usize, source: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            file,
+            lines: source.lines().map(str::to_string).collect(),
+            next_line: 0,
+            conditions: Vec::new(),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.conditions
+            .last()
+            .is_none_or(|condition| condition.active && condition.enclosing_active)
+    }
+
+    fn argument<'s>(&self, line: usize, keyword: &str, argument: &'s str) -> Result<&'s str> {
+        if argument.is_empty() || argument.contains(char::is_whitespace) {
+            return Err(self.error(line, &format!("#{} takes one flag name", keyword)));
+        }
+        Ok(argument)
+    }
+
+    fn error(&self, line: usize, message: &str) -> ShaderError {
+        ShaderError {
+            name: self.name.clone(),
+            location: Some((line as u32, 1)),
+            message: message.to_string(),
+        }
+    }
+}
+
+//validates every .wgsl file under dir with naga the way registering it would, and returns what
+//failed with the file and line each error came from. includes are looked up among the files in
+//dir, by their path relative to it. files other files include are checked as part of those
+//rather than on their own, as they may rely on what their includer declares. run it from a test
+//or a build step to catch broken shaders before the app starts
+#[cfg(not(target_arch = "wasm32"))]
+pub fn validate_shader_dir(dir: &std::path::Path) -> Vec<ShaderError> {
+    let pattern = dir.join("**").join("*.wgsl");
+    let paths = match glob::glob(&pattern.to_string_lossy()) {
+        Ok(paths) => paths,
+        Err(e) => {
+            return vec![ShaderError {
+                name: dir.display().to_string(),
+                location: None,
+                message: format!("bad shader folder: {}", e),
+            }]
+        }
+    };
+    let mut errors = Vec::new();
+    let mut preprocessor = ShaderPreprocessor::new();
+    let mut names = Vec::new();
+    for path in paths.flatten() {
+        let name = path
+            .strip_prefix(dir)
+            .unwrap_or(&path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        match std::fs::read_to_string(&path) {
+            Ok(source) => {
+                preprocessor.insert_file(&name, &source);
+                names.push(name);
+            }
+            Err(e) => errors.push(ShaderError {
+                name,
+                location: None,
+                message: format!("failed to read: {}", e),
+            }),
+        }
+    }
+    names.sort();
+    let mut included = HashSet::new();
+    let mut results = Vec::new();
+    for name in &names {
+        let source = preprocessor.files[name].clone();
+        let processed = pollster::block_on(preprocessor.process(name, &source));
+        if let Ok(processed) = &processed {
+            included.extend(processed.files[1..].iter().cloned());
+        }
+        results.push((name, processed));
+    }
+    for (name, processed) in results {
+        if included.contains(name) {
+            continue;
+        }
+        match processed {
+            Ok(processed) => {
+                if let Err(error) = crate::pipeline_cache::validate_wgsl(name, &processed.source) {
+                    errors.push(processed.map_error(error));
+                }
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_cache::validate_wgsl;
+
+    fn process(preprocessor: &mut ShaderPreprocessor, source: &str) -> Result<ProcessedShader> {
+        pollster::block_on(preprocessor.process("main.wgsl", source))
+    }
+
+    #[test]
+    fn nested_ifdef_and_else() {
+        let source = "\
+#define A
+#ifdef A
+a
+#ifdef B
+b
+#else
+not_b
+#endif
+#else
+not_a
+#ifndef B
+dropped_with_outer
+#endif
+#endif
+";
+        let processed = process(&mut ShaderPreprocessor::new(), source).unwrap();
+        assert_eq!(processed.source, "a\nnot_b\n");
+    }
+
+    #[test]
+    fn undef_clears_flag_for_this_shader_only() {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.define("FLAG");
+        let source = "#undef FLAG\n#ifdef FLAG\nyes\n#else\nno\n#endif\n";
+        let processed = process(&mut preprocessor, source).unwrap();
+        assert_eq!(processed.source, "no\n");
+        assert!(preprocessor.is_defined("FLAG"));
+    }
+
+    #[test]
+    fn include_is_pasted_once() {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.insert_file("common.wgsl", "struct A {}\n");
+        preprocessor.insert_file("other.wgsl", "#include \"common.wgsl\"\nfn other() {}\n");
+        let source = "#include \"common.wgsl\"\n#include \"other.wgsl\"\nfn main() {}\n";
+        let processed = process(&mut preprocessor, source).unwrap();
+        assert_eq!(
+            processed.source,
+            "struct A {}\nfn other() {}\nfn main() {}\n"
+        );
+        assert_eq!(processed.original_location(2), Some(("other.wgsl", 2)));
+        assert_eq!(processed.original_location(3), Some(("main.wgsl", 3)));
+    }
+
+    #[test]
+    fn unterminated_ifdef() {
+        let error = process(&mut ShaderPreprocessor::new(), "#ifdef A\na\nb\n")
+            .err()
+            .unwrap();
+        assert_eq!(error.name, "main.wgsl");
+        assert_eq!(error.location, Some((3, 1)));
+        assert_eq!(error.message, "#ifdef without #endif");
+    }
+
+    #[test]
+    fn unknown_directive() {
+        let error = process(
+            &mut ShaderPreprocessor::new(),
+            "fn main() {}\n#pragma once\n",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(error.location, Some((2, 1)));
+        assert_eq!(error.message, "unknown directive #pragma");
+    }
+
+    #[test]
+    fn validation_errors_point_into_includes() {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.insert_file(
+            "common.wgsl",
+            "struct A { x: f32 }\nfn broken() -> f32 { return missing; }\n",
+        );
+        let source = "// one\n// two\n#include \"common.wgsl\"\n";
+        let processed = process(&mut preprocessor, source).unwrap();
+        let error = validate_wgsl("Main", &processed.source).err().unwrap();
+        assert_eq!(error.location.map(|(line, _)| line), Some(4));
+        let error = processed.map_error(error);
+        assert_eq!(error.name, "common.wgsl");
+        assert_eq!(error.location.map(|(line, _)| line), Some(2));
+    }
+
+    #[test]
+    fn flags_define_and_add_consts() {
+        let flags = ShaderFlags::new()
+            .with("HAS_NORMAL_MAP")
+            .with_value("NUM_CASCADES", 4);
+        assert_eq!(
+            flags.variant_name("Normal"),
+            "Normal[HAS_NORMAL_MAP,NUM_CASCADES=4]"
+        );
+        assert_eq!(ShaderFlags::new().variant_name("Normal"), "Normal");
+        let source = "#ifdef HAS_NORMAL_MAP\nmapped\n#endif\n#ifdef ALPHA_TEST\ncutout\n#endif\n";
+        let processed = pollster::block_on(ShaderPreprocessor::new().process_with_flags(
+            "main.wgsl",
+            source,
+            &flags,
+        ))
+        .unwrap();
+        assert_eq!(processed.source, "const NUM_CASCADES: u32 = 4u;\nmapped\n");
+        assert_eq!(processed.original_location(1), Some(("main.wgsl flags", 1)));
+        assert_eq!(processed.original_location(2), Some(("main.wgsl", 2)));
+    }
+
+    #[test]
+    fn scene_shader_variants_validate() {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.insert_file("common.wgsl", include_str!("../res/shaders/common.wgsl"));
+        preprocessor.insert_file("debug_view.wgsl", include_str!("debug_view.wgsl"));
+        let variants = crate::debug_view::DebugView::ALL
+            .map(|view| view.flags())
+            .into_iter()
+            .chain([ShaderFlags::new().with("ALPHA_TEST")]);
+        for flags in variants {
+            for (name, source) in [
+                ("shader.wgsl", include_str!("shader.wgsl")),
+                ("shader_compact.wgsl", include_str!("shader_compact.wgsl")),
+                ("shader_array.wgsl", include_str!("shader_array.wgsl")),
+                (
+                    "shader_array_compact.wgsl",
+                    include_str!("shader_array_compact.wgsl"),
+                ),
+                ("object.wgsl", include_str!("object.wgsl")),
+            ] {
+                let processed =
+                    pollster::block_on(preprocessor.process_with_flags(name, source, &flags))
+                        .unwrap();
+                if let Err(error) = validate_wgsl(name, &processed.source) {
+                    panic!("{} with {}: {}", name, flags, processed.map_error(error));
+                }
+            }
+        }
+    }
+
+    fn shader_dir_errors(files: &[(&str, &str)]) -> Vec<ShaderError> {
+        let dir =
+            std::env::temp_dir().join(format!("shader_dir_{}_{}", std::process::id(), files.len()));
+        for (name, source) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        let errors = validate_shader_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        errors
+    }
+
+    //the harness for the shaders shipped in res, fails listing every broken file and line
+    #[test]
+    fn res_shaders_validate() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("res/shaders");
+        let errors = validate_shader_dir(&dir);
+        let report: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert!(errors.is_empty(), "{}", report.join("\n"));
+    }
+
+    #[test]
+    fn shader_dir_reports_file_and_line() {
+        let errors = shader_dir_errors(&[
+            (
+                "lib/types.wgsl",
+                "struct A { x: f32 }\nfn broken() -> f32 { return missing; }\n",
+            ),
+            ("main.wgsl", "#include \"lib/types.wgsl\"\nfn main() {}\n"),
+            ("fine.wgsl", "fn fine() -> f32 { return 1.0; }\n"),
+        ]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].name, "lib/types.wgsl");
+        assert_eq!(errors[0].location.map(|(line, _)| line), Some(2));
+    }
+}
@@ -0,0 +1,381 @@
+use crate::culling::Frustum;
+use crate::picking::{Aabb, Ray};
+use cgmath::Point3;
+
+//the most entries a leaf holds before it is split
+const LEAF_SIZE: usize = 4;
+//refits loosen the tree as things move, so it is rebuilt from scratch after this many
+const REBUILD_AFTER_REFITS: u32 = 64;
+
+#[derive(Debug, Copy, Clone)]
+enum Children {
+    //a range of entries
+    Leaf { start: usize, end: usize },
+    Inner { left: usize, right: usize },
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Node {
+    bounds: Aabb,
+    children: Children,
+}
+
+//a bounding volume hierarchy over world space boxes, keyed by small integers such as instance
+//ids. inserts, removes and moves are cheap and take effect straight away: changed entries are
+//tested one by one until refresh folds them back into the tree, refitting it when things only
+//moved and rebuilding it when entries came or went
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    //each entry's current box, None where there is no entry
+    bounds: Vec<Option<Aabb>>,
+    //parents come before their children, the root is the first
+    nodes: Vec<Node>,
+    //the entries in the tree, in the order the leaves cover them
+    order: Vec<usize>,
+    //entries changed since the tree was last refitted, the tree's boxes for them are out of date
+    stale: Vec<bool>,
+    stale_ids: Vec<usize>,
+    //entries were added or removed, which a refit can't handle
+    needs_rebuild: bool,
+    refits: u32,
+}
+
+impl SpatialIndex {
+    //an index holding one entry per box, keyed by position
+    pub fn from_bounds(bounds: impl IntoIterator<Item = Aabb>) -> Self {
+        let mut index = Self {
+            bounds: bounds.into_iter().map(Some).collect(),
+            ..Default::default()
+        };
+        index.stale = vec![false; index.bounds.len()];
+        index.rebuild();
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.iter().all(Option::is_none)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Aabb> {
+        self.bounds.get(id)?.as_ref()
+    }
+
+    //adds an entry, or moves it if there already is one
+    pub fn insert(&mut self, id: usize, bounds: Aabb) {
+        if id >= self.bounds.len() {
+            self.bounds.resize(id + 1, None);
+            self.stale.resize(id + 1, false);
+        }
+        if self.bounds[id].replace(bounds).is_none() {
+            self.needs_rebuild = true;
+        }
+        self.mark_stale(id);
+    }
+
+    //returns false if there is no such entry
+    pub fn update(&mut self, id: usize, bounds: Aabb) -> bool {
+        let Some(current) = self.bounds.get_mut(id).and_then(Option::as_mut) else {
+            return false;
+        };
+        *current = bounds;
+        self.mark_stale(id);
+        true
+    }
+
+    //returns false if there was no such entry
+    pub fn remove(&mut self, id: usize) -> bool {
+        if self.bounds.get_mut(id).and_then(Option::take).is_none() {
+            return false;
+        }
+        self.needs_rebuild = true;
+        self.mark_stale(id);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn mark_stale(&mut self, id: usize) {
+        if !self.stale[id] {
+            self.stale[id] = true;
+            self.stale_ids.push(id);
+        }
+    }
+
+    //folds the changes since the last refresh into the tree, e.g. once per update
+    pub fn refresh(&mut self) {
+        if self.needs_rebuild || self.refits >= REBUILD_AFTER_REFITS {
+            self.rebuild();
+        } else if !self.stale_ids.is_empty() {
+            self.refit();
+        }
+    }
+
+    //builds the tree from scratch, splitting each node at the median along its widest axis
+    pub fn rebuild(&mut self) {
+        self.order = (0..self.bounds.len())
+            .filter(|&id| self.bounds[id].is_some())
+            .collect();
+        self.nodes.clear();
+        if !self.order.is_empty() {
+            self.build_node(0, self.order.len());
+        }
+        self.clear_stale();
+        self.needs_rebuild = false;
+        self.refits = 0;
+    }
+
+    fn build_node(&mut self, start: usize, end: usize) -> usize {
+        let node = self.nodes.len();
+        let bounds = self.union(&self.order[start..end]);
+        self.nodes.push(Node {
+            bounds,
+            children: Children::Leaf { start, end },
+        });
+        if end - start <= LEAF_SIZE {
+            return node;
+        }
+        let centers = Aabb::from_points(
+            self.order[start..end]
+                .iter()
+                .map(|&id| self.entry(id).center().into()),
+        );
+        let size = centers.size();
+        let axis = if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        };
+        //entries sharing a center can't be told apart, they stay in one leaf however many
+        if size[axis] == 0.0 {
+            return node;
+        }
+        let middle = (end - start) / 2;
+        let bounds = &self.bounds;
+        let center = |id: &usize| bounds[*id].map_or(0.0, |bounds| bounds.center()[axis]);
+        self.order[start..end]
+            .select_nth_unstable_by(middle, |a, b| center(a).total_cmp(&center(b)));
+        let left = self.build_node(start, start + middle);
+        let right = self.build_node(start + middle, end);
+        self.nodes[node].children = Children::Inner { left, right };
+        node
+    }
+
+    //recomputes every node's box from the entries' current ones, keeping the shape of the tree
+    fn refit(&mut self) {
+        for node in (0..self.nodes.len()).rev() {
+            self.nodes[node].bounds = match self.nodes[node].children {
+                Children::Leaf { start, end } => self.union(&self.order[start..end]),
+                Children::Inner { left, right } => {
+                    self.nodes[left].bounds.union(&self.nodes[right].bounds)
+                }
+            };
+        }
+        self.clear_stale();
+        self.refits += 1;
+    }
+
+    fn clear_stale(&mut self) {
+        for id in self.stale_ids.drain(..) {
+            self.stale[id] = false;
+        }
+    }
+
+    fn entry(&self, id: usize) -> Aabb {
+        self.bounds[id].expect("the tree only holds entries that exist")
+    }
+
+    fn union(&self, ids: &[usize]) -> Aabb {
+        ids.iter()
+            .map(|&id| self.entry(id))
+            .reduce(|all, bounds| all.union(&bounds))
+            .unwrap_or_else(|| Aabb::from_points([]))
+    }
+
+    //calls found with every entry whose box passes test, skipping the parts of the tree whose
+    //boxes don't. ids are visited in no particular order
+    pub fn visit(&self, test: impl Fn(&Aabb) -> bool, mut found: impl FnMut(usize)) {
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if !test(&node.bounds) {
+                continue;
+            }
+            match node.children {
+                Children::Leaf { start, end } => {
+                    for &id in &self.order[start..end] {
+                        if !self.stale[id] && test(&self.entry(id)) {
+                            found(id);
+                        }
+                    }
+                }
+                Children::Inner { left, right } => stack.extend([left, right]),
+            }
+        }
+        //changed entries are tested against their current boxes instead
+        for &id in &self.stale_ids {
+            if self.bounds[id].is_some_and(|bounds| test(&bounds)) {
+                found(id);
+            }
+        }
+    }
+
+    //every entry whose box passes test, in id order
+    fn collect(&self, test: impl Fn(&Aabb) -> bool) -> Vec<usize> {
+        let mut ids = Vec::new();
+        self.visit(test, |id| ids.push(id));
+        ids.sort_unstable();
+        ids
+    }
+
+    //the entries whose boxes overlap the area
+    pub fn query_box(&self, area: &Aabb) -> Vec<usize> {
+        self.collect(|bounds| bounds.intersects(area))
+    }
+
+    //the entries whose boxes reach within radius of a point, e.g. what is near the player
+    pub fn query_sphere(&self, center: Point3<f32>, radius: f32) -> Vec<usize> {
+        self.collect(|bounds| bounds.distance2(center) <= radius * radius)
+    }
+
+    //the entries whose boxes may be in view, see Frustum::intersects_aabb
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<usize> {
+        self.collect(|bounds| frustum.intersects_aabb(bounds))
+    }
+
+    //the nearest entry the ray hits and the distance to the hit. hit is called with the entries
+    //whose boxes the ray enters, nearest boxes first where the tree allows, and returns the
+    //distance to the actual hit if there is one, e.g. from testing the triangles. entries whose
+    //boxes start beyond the best hit so far aren't tested
+    pub fn cast_ray(
+        &self,
+        ray: &Ray,
+        mut hit: impl FnMut(usize) -> Option<f32>,
+    ) -> Option<(usize, f32)> {
+        let mut best: Option<(usize, f32)> = None;
+        let mut consider = |id: usize, bounds: &Aabb, best: &mut Option<(usize, f32)>| {
+            let Some(entry) = bounds.intersect_ray(ray) else {
+                return;
+            };
+            if best.is_some_and(|(_, nearest)| entry >= nearest) {
+                return;
+            }
+            if let Some(distance) = hit(id) {
+                if best.is_none_or(|(_, nearest)| distance < nearest) {
+                    *best = Some((id, distance));
+                }
+            }
+        };
+        let mut stack = Vec::new();
+        if let Some(root) = self.nodes.first() {
+            if let Some(distance) = root.bounds.intersect_ray(ray) {
+                stack.push((0, distance));
+            }
+        }
+        while let Some((node, distance)) = stack.pop() {
+            if best.is_some_and(|(_, nearest)| distance >= nearest) {
+                continue;
+            }
+            match self.nodes[node].children {
+                Children::Leaf { start, end } => {
+                    for &id in &self.order[start..end] {
+                        if !self.stale[id] {
+                            consider(id, &self.entry(id), &mut best);
+                        }
+                    }
+                }
+                Children::Inner { left, right } => {
+                    let mut children: Vec<(usize, f32)> = [left, right]
+                        .into_iter()
+                        .filter_map(|child| {
+                            let distance = self.nodes[child].bounds.intersect_ray(ray)?;
+                            Some((child, distance))
+                        })
+                        .collect();
+                    //the nearer child is popped first
+                    children.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+                    stack.extend(children);
+                }
+            }
+        }
+        for &id in &self.stale_ids {
+            if let Some(bounds) = self.bounds[id] {
+                consider(id, &bounds, &mut best);
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector3;
+
+    fn cube(x: f32, z: f32) -> Aabb {
+        Aabb::from_points([[x - 0.5, -0.5, z - 0.5], [x + 0.5, 0.5, z + 0.5]])
+    }
+
+    //a 10x10 field of unit cubes two apart
+    fn field() -> SpatialIndex {
+        SpatialIndex::from_bounds(
+            (0..100).map(|i| cube((i % 10) as f32 * 2.0, (i / 10) as f32 * 2.0)),
+        )
+    }
+
+    #[test]
+    fn range_queries_match_a_linear_scan() {
+        let index = field();
+        let center = Point3::new(7.0, 0.0, 9.0);
+        let near = index.query_sphere(center, 3.0);
+        let expected: Vec<usize> = (0..100)
+            .filter(|&id| index.get(id).unwrap().distance2(center) <= 9.0)
+            .collect();
+        assert_eq!(near, expected);
+        assert!(!near.is_empty());
+        assert_eq!(index.query_box(&cube(4.0, 0.0)), vec![2]);
+    }
+
+    #[test]
+    fn moved_entries_are_found_before_and_after_refresh() {
+        let mut index = field();
+        assert!(index.update(0, cube(100.0, 100.0)));
+        index.insert(150, cube(-50.0, 0.0));
+        for _ in 0..2 {
+            assert_eq!(index.query_box(&cube(100.0, 100.0)), vec![0]);
+            assert_eq!(index.query_box(&cube(0.0, 0.0)), Vec::<usize>::new());
+            assert_eq!(index.query_box(&cube(-50.0, 0.0)), vec![150]);
+            index.refresh();
+        }
+        assert!(index.remove(150));
+        assert!(index.query_box(&cube(-50.0, 0.0)).is_empty());
+        assert_eq!(index.len(), 100);
+    }
+
+    #[test]
+    fn rays_hit_the_nearest_entry() {
+        let index = field();
+        //along the row at z = 4 from beyond its far end
+        let ray = Ray::new(Point3::new(30.0, 0.0, 4.0), -Vector3::unit_x());
+        let hit = index.cast_ray(&ray, |id| index.get(id)?.intersect_ray(&ray));
+        assert_eq!(hit.map(|(id, _)| id), Some(29));
+        assert!((hit.unwrap().1 - 11.5).abs() < 1e-5);
+        //a hit test that rejects the near cube gives the next one along
+        let hit = index.cast_ray(&ray, |id| {
+            (id != 29)
+                .then(|| index.get(id)?.intersect_ray(&ray))
+                .flatten()
+        });
+        assert_eq!(hit.map(|(id, _)| id), Some(28));
+    }
+}
@@ -0,0 +1,252 @@
+use wgpu::util::DeviceExt;
+
+//the value masks write into the stencil buffer and masked pipelines compare against. the scene
+//pass clears the stencil to 0 first
+pub const MASK_REFERENCE: u32 = 1;
+
+//replaces the stencil with the reference wherever a triangle is drawn, for drawing a mask's
+//shape. depth results are ignored so a mask is never hidden by what is behind it
+pub fn write_state() -> wgpu::StencilState {
+    let face = wgpu::StencilFaceState {
+        compare: wgpu::CompareFunction::Always,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Replace,
+        pass_op: wgpu::StencilOperation::Replace,
+    };
+    wgpu::StencilState {
+        front: face,
+        back: face,
+        read_mask: 0xff,
+        write_mask: 0xff,
+    }
+}
+
+//draws only where the reference compares true against the stencil, which it leaves as it is.
+//Equal draws inside a mask and NotEqual outside of it. pipelines with this state can go in
+//render bundles recorded with stencil_read_only
+pub fn test_state(compare: wgpu::CompareFunction) -> wgpu::StencilState {
+    let face = wgpu::StencilFaceState {
+        compare,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op: wgpu::StencilOperation::Keep,
+    };
+    wgpu::StencilState {
+        front: face,
+        back: face,
+        read_mask: 0xff,
+        write_mask: 0,
+    }
+}
+
+//a rectangle of the window in fractions of its size, from the top left corner. fractions keep
+//a mask in place through resizes and render scale changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaskRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl MaskRect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    //the whole window
+    pub fn full() -> Self {
+        Self::new(0.0, 0.0, 1.0, 1.0)
+    }
+
+    //two triangles in clip space, y up
+    fn vertices(&self) -> [[f32; 2]; 6] {
+        let left = self.x * 2.0 - 1.0;
+        let right = (self.x + self.width) * 2.0 - 1.0;
+        let top = 1.0 - self.y * 2.0;
+        let bottom = 1.0 - (self.y + self.height) * 2.0;
+        [
+            [left, top],
+            [left, bottom],
+            [right, bottom],
+            [left, top],
+            [right, bottom],
+            [right, top],
+        ]
+    }
+}
+
+//which side of the mask the scene shows on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskMode {
+    //e.g. a portal's window or a ui panel's clip region
+    Inside,
+    //e.g. a hole cut for a mirror or an opaque hud element
+    Outside,
+}
+
+impl MaskMode {
+    pub fn compare(self) -> wgpu::CompareFunction {
+        match self {
+            MaskMode::Inside => wgpu::CompareFunction::Equal,
+            MaskMode::Outside => wgpu::CompareFunction::NotEqual,
+        }
+    }
+}
+
+//the regions of the window the scene is clipped to, see GameState::set_scene_mask
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneMask {
+    pub rects: Vec<MaskRect>,
+    pub mode: MaskMode,
+}
+
+impl SceneMask {
+    pub fn inside(rects: Vec<MaskRect>) -> Self {
+        Self {
+            rects,
+            mode: MaskMode::Inside,
+        }
+    }
+
+    pub fn outside(rects: Vec<MaskRect>) -> Self {
+        Self {
+            rects,
+            mode: MaskMode::Outside,
+        }
+    }
+
+    //the stencil state the scene's pipelines are built with under this mask
+    pub fn test_state(&self) -> wgpu::StencilState {
+        test_state(self.mode.compare())
+    }
+}
+
+//the masking pass's first step: draws a mask's rectangles into the stencil aspect at the start of a
+//pass, without touching color or depth, and leaves the reference set for the draws after it
+pub struct StencilMasker {
+    pipeline: wgpu::RenderPipeline,
+    vertices: Option<wgpu::Buffer>,
+    vertex_count: u32,
+}
+
+impl StencilMasker {
+    //the formats are the pass's, the depth format needs a stencil aspect
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        rects: &[MaskRect],
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Stencil Mask Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("stencil_mask.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Stencil Mask Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Stencil Mask Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::empty(),
+                })],
+                compilation_options: Default::default(),
+            }),
+            //rectangles can be given with either winding
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: write_state(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let mut masker = Self {
+            pipeline,
+            vertices: None,
+            vertex_count: 0,
+        };
+        masker.set_rects(device, rects);
+        masker
+    }
+
+    pub fn set_rects(&mut self, device: &wgpu::Device, rects: &[MaskRect]) {
+        let vertices: Vec<[f32; 2]> = rects.iter().flat_map(MaskRect::vertices).collect();
+        self.vertex_count = vertices.len() as u32;
+        self.vertices = (!vertices.is_empty()).then(|| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Stencil Mask Vertices"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+        });
+    }
+
+    //an empty mask writes nothing, so Inside hides everything and Outside nothing
+    pub fn draw<'p>(&'p self, render_pass: &mut wgpu::RenderPass<'p>) {
+        render_pass.set_stencil_reference(MASK_REFERENCE);
+        if let Some(vertices) = self.vertices.as_ref() {
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_vertex_buffer(0, vertices.slice(..));
+            render_pass.draw(0..self.vertex_count, 0..1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rects_map_to_clip_space() {
+        let vertices = MaskRect::new(0.25, 0.0, 0.5, 0.5).vertices();
+        assert_eq!(vertices[0], [-0.5, 1.0]);
+        assert_eq!(vertices[2], [0.5, 0.0]);
+        let full = MaskRect::full().vertices();
+        assert!(full.iter().all(|[x, y]| x.abs() == 1.0 && y.abs() == 1.0));
+    }
+
+    #[test]
+    fn test_state_never_writes() {
+        let state = SceneMask::outside(vec![MaskRect::full()]).test_state();
+        assert_eq!(state.front.compare, wgpu::CompareFunction::NotEqual);
+        assert!(state.needs_ref_value());
+        assert_eq!(state.write_mask, 0);
+        assert!(write_state().is_enabled());
+    }
+
+    #[test]
+    fn mask_shader_validates() {
+        crate::pipeline_cache::validate_wgsl(
+            "stencil_mask.wgsl",
+            include_str!("stencil_mask.wgsl"),
+        )
+        .unwrap();
+    }
+}
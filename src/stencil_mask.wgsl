@@ -0,0 +1,12 @@
+// marks the shape of a mask in the stencil buffer. the color target is written with an empty mask,
+// so the fragment's output is never seen
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(position, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0);
+}
@@ -1,6 +1,160 @@
 use anyhow::*;
 use image::GenericImageView;
 
+//how a texture is filtered and wrapped when sampled. the default clamps to the edge with linear
+//magnification and nearest minification
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SamplerSettings {
+    pub address_mode_u: wgpu::AddressMode,
+    pub address_mode_v: wgpu::AddressMode,
+    pub address_mode_w: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    pub mipmap_filter: wgpu::FilterMode,
+    //1 is off, up to 16. only applies when every filter is linear and the device supports it
+    pub anisotropy: u16,
+    //turns the sampler into a comparison sampler, for depth textures
+    pub compare: Option<wgpu::CompareFunction>,
+    pub lod_max_clamp: f32,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            anisotropy: 1,
+            compare: None,
+            lod_max_clamp: 32.0,
+        }
+    }
+}
+
+impl SamplerSettings {
+    //linear filtering everywhere, trilinear on 3d textures
+    pub fn linear() -> Self {
+        Self {
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        }
+    }
+
+    //nearest filtering everywhere, for pixel art
+    pub fn nearest() -> Self {
+        Self {
+            mag_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        }
+    }
+
+    //linear filtering with anisotropy, keeps textures sharp at glancing angles
+    pub fn anisotropic(level: u16) -> Self {
+        Self {
+            anisotropy: level,
+            ..Self::linear()
+        }
+    }
+
+    pub fn with_address_mode(mut self, mode: wgpu::AddressMode) -> Self {
+        self.address_mode_u = mode;
+        self.address_mode_v = mode;
+        self.address_mode_w = mode;
+        self
+    }
+
+    //drops the anisotropy when the device can't filter anisotropically, wgpu rejects it
+    //outright unless every filter is linear so that is turned off here too
+    pub fn supported_by(mut self, features: &crate::features::DeviceFeatures) -> Self {
+        let all_linear = [self.mag_filter, self.min_filter, self.mipmap_filter]
+            .iter()
+            .all(|filter| *filter == wgpu::FilterMode::Linear);
+        if !features.anisotropic_filtering || !all_linear {
+            self.anisotropy = 1;
+        }
+        self
+    }
+
+    pub fn create_sampler(&self, device: &wgpu::Device, label: Option<&str>) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label,
+            address_mode_u: self.address_mode_u,
+            address_mode_v: self.address_mode_v,
+            address_mode_w: self.address_mode_w,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: self.lod_max_clamp,
+            compare: self.compare,
+            anisotropy_clamp: self.anisotropy.clamp(1, 16),
+            border_color: None,
+        })
+    }
+}
+
+//a colour render target with an optional depth buffer of the same size, for post processing and
+//render to texture passes. resize keeps the formats and usage
+pub struct RenderTexture {
+    pub color: Texture,
+    pub depth: Option<Texture>,
+    usage: wgpu::TextureUsages,
+    label: String,
+}
+
+impl RenderTexture {
+    pub fn new(
+        device: &wgpu::Device,
+        size: (u32, u32),
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsages,
+        depth_format: Option<wgpu::TextureFormat>,
+        label: &str,
+    ) -> Self {
+        let color = Texture::create_render_target(device, size, format, usage, label);
+        let depth = depth_format.map(|depth_format| {
+            Texture::create_render_target(
+                device,
+                size,
+                depth_format,
+                wgpu::TextureUsages::empty(),
+                &format!("{} depth", label),
+            )
+        });
+        Self {
+            color,
+            depth,
+            usage,
+            label: label.to_string(),
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        let size = self.color.texture.size();
+        (size.width, size.height)
+    }
+
+    //recreates the textures at the new size, bind groups made from the old views have to be
+    //made again
+    pub fn resize(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        if self.size() == (size.0.max(1), size.1.max(1)) {
+            return;
+        }
+        *self = Self::new(
+            device,
+            size,
+            self.color.texture.format(),
+            self.usage,
+            self.depth.as_ref().map(|depth| depth.texture.format()),
+            &self.label,
+        );
+    }
+}
+
 pub struct Texture {
     #[allow(unused)]
     pub texture: wgpu::Texture,
@@ -9,62 +163,165 @@ pub struct Texture {
 }
 
 impl Texture {
-    pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
-
-    pub fn create_depth_texture(
+    //a texture that can be drawn into and then sampled, with whatever extra usage is given on top
+    //of RENDER_ATTACHMENT | TEXTURE_BINDING. depth formats get a comparison sampler, colour
+    //formats a linear one
+    pub fn create_render_target(
         device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
+        (width, height): (u32, u32),
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsages,
         label: &str,
     ) -> Self {
-        let size = wgpu::Extent3d {
-            width: config.width.max(1),
-            height: config.height.max(1),
-            depth_or_array_layers: 1,
-        };
-        let desc = wgpu::TextureDescriptor {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some(label),
-            size,
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: Self::DEPTH_FORMAT,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            format,
+            usage: usage
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING,
             view_formats: &[],
-        };
-        let texture = device.create_texture(&desc);
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            compare: Some(wgpu::CompareFunction::LessEqual),
-            lod_min_clamp: 0.0,
-            lod_max_clamp: 100.0,
-            ..Default::default()
         });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = if format.is_depth_stencil_format() {
+            SamplerSettings {
+                mipmap_filter: wgpu::FilterMode::Linear,
+                compare: Some(wgpu::CompareFunction::LessEqual),
+                lod_max_clamp: 100.0,
+                ..Default::default()
+            }
+        } else {
+            SamplerSettings {
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }
+        };
         Self {
+            sampler: sampler.create_sampler(device, Some(label)),
             texture,
             view,
-            sampler,
         }
     }
+
+    //format comes from features::choose_depth_format so it matches the pipelines. it can be
+    //sampled like create_sampled_depth_texture's
+    pub fn create_depth_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Self {
+        Self::create_render_target(
+            device,
+            (config.width, config.height),
+            format,
+            wgpu::TextureUsages::empty(),
+            label,
+        )
+    }
+    //a depth texture of its own size that shaders can sample, e.g. a shadow map. it has a
+    //LessEqual comparison sampler, bind it through depth_layout_entries with depth_view
+    pub fn create_sampled_depth_texture(
+        device: &wgpu::Device,
+        size: (u32, u32),
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Self {
+        Self::create_render_target(device, size, format, wgpu::TextureUsages::empty(), label)
+    }
+
+    //a view of just the depth aspect, which is what a shader can bind when the format also has
+    //stencil. the texture's own view covers both so it can still be the depth attachment
+    pub fn depth_view(&self) -> wgpu::TextureView {
+        self.texture.create_view(&wgpu::TextureViewDescriptor {
+            aspect: wgpu::TextureAspect::DepthOnly,
+            ..Default::default()
+        })
+    }
+
+    //a colour texture matching the config that can be rendered into and copied out of, used in
+    //place of the surface texture when running headless
+    pub fn create_offscreen_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        label: &str,
+    ) -> Self {
+        Self::create_render_target(
+            device,
+            (config.width, config.height),
+            config.format,
+            wgpu::TextureUsages::COPY_SRC,
+            label,
+        )
+    }
+
+    #[profiling::function]
     pub fn from_bytes(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         bytes: &[u8],
         label: &str,
     ) -> Result<Self> {
+        crate::profiler::tracy_zone!("load texture");
         let img = image::load_from_memory(bytes)?;
         Self::from_image(device, queue, &img, Some(label))
     }
 
+    pub fn from_bytes_with_sampler(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: &str,
+        sampler: &SamplerSettings,
+    ) -> Result<Self> {
+        crate::profiler::tracy_zone!("load texture");
+        let img = image::load_from_memory(bytes)?;
+        Self::from_image_with_sampler(device, queue, &img, Some(label), sampler)
+    }
+
+    //swaps the sampler for one made from the settings, the texture itself is untouched
+    pub fn set_sampler(&mut self, device: &wgpu::Device, sampler: &SamplerSettings) {
+        self.sampler = sampler.create_sampler(device, None);
+    }
+
+    //a single pixel texture of one colour, for materials with nothing to sample
+    pub fn from_color(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color: [u8; 4],
+        label: &str,
+    ) -> Result<Self> {
+        let img = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            1,
+            1,
+            image::Rgba(color),
+        ));
+        Self::from_image(device, queue, &img, Some(label))
+    }
+
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         img: &image::DynamicImage,
         label: Option<&str>,
+    ) -> Result<Self> {
+        Self::from_image_with_sampler(device, queue, img, label, &SamplerSettings::default())
+    }
+
+    #[profiling::function]
+    pub fn from_image_with_sampler(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        sampler: &SamplerSettings,
     ) -> Result<Self> {
         let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
@@ -81,7 +338,10 @@ impl Texture {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            //COPY_SRC lets loaded textures be packed into arrays on the gpu
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
             view_formats: &[],
         });
 
@@ -102,20 +362,515 @@ impl Texture {
         );
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
+        let sampler = sampler.create_sampler(device, label);
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+        })
+    }
+
+    //uploads images of one size into the layers of a new texture array, in order. the view is a
+    //D2Array, bind it through array_layout_entries. layers can be replaced later with write_layer
+    pub fn array_from_images(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        images: &[&image::DynamicImage],
+        label: &str,
+        sampler: &SamplerSettings,
+    ) -> Result<Self> {
+        let first = images
+            .first()
+            .ok_or_else(|| anyhow!("a texture array needs at least one layer"))?;
+        let (width, height) = first.dimensions();
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: images.len() as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
             ..Default::default()
         });
+        let array = Self {
+            sampler: sampler.create_sampler(device, Some(label)),
+            texture,
+            view,
+        };
+        for (layer, img) in images.iter().enumerate() {
+            array.write_layer(queue, layer as u32, img)?;
+        }
+        Ok(array)
+    }
+
+    //replaces one layer of an rgba8 texture array, the image has to match the layer size
+    pub fn write_layer(
+        &self,
+        queue: &wgpu::Queue,
+        layer: u32,
+        img: &image::DynamicImage,
+    ) -> Result<()> {
+        let size = self.texture.size();
+        if layer >= size.depth_or_array_layers {
+            bail!(
+                "layer {} is past the end of a {} layer array",
+                layer,
+                size.depth_or_array_layers
+            );
+        }
+        if img.dimensions() != (size.width, size.height) {
+            bail!(
+                "layer {} image is {:?}, expected {}x{}",
+                layer,
+                img.dimensions(),
+                size.width,
+                size.height
+            );
+        }
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &img.to_rgba8(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size.width),
+                rows_per_image: Some(size.height),
+            },
+            wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..size
+            },
+        );
+        Ok(())
+    }
 
+    //copies already loaded textures into the layers of a new texture array, in order. they must
+    //all share a size and format and have been created with COPY_SRC. the view is a D2Array
+    pub fn array_from_textures(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        textures: &[&Texture],
+        label: &str,
+    ) -> Result<Self> {
+        let first = textures
+            .first()
+            .ok_or_else(|| anyhow!("a texture array needs at least one layer"))?;
+        let layer_size = first.texture.size();
+        let format = first.texture.format();
+        for (layer, texture) in textures.iter().enumerate() {
+            if texture.texture.size() != layer_size || texture.texture.format() != format {
+                bail!(
+                    "layer {} is {:?} {:?}, expected {:?} {:?}",
+                    layer,
+                    texture.texture.size(),
+                    texture.texture.format(),
+                    layer_size,
+                    format
+                );
+            }
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                depth_or_array_layers: textures.len() as u32,
+                ..layer_size
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Texture Array Copy"),
+        });
+        for (layer, source) in textures.iter().enumerate() {
+            encoder.copy_texture_to_texture(
+                source.texture.as_image_copy(),
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                layer_size,
+            );
+        }
+        queue.submit(Some(encoder.finish()));
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let sampler = SamplerSettings::default().create_sampler(device, None);
         Ok(Self {
             texture,
             view,
             sampler,
         })
     }
+
+    //copies the texture back to the cpu, blocking until the gpu is done. it has to have been made
+    //with COPY_SRC in an 8 bit rgba or bgra format, bgra comes back swizzled to rgba
+    pub fn read_back(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<image::RgbaImage> {
+        let size = self.texture.size();
+        let buffer = self.copy_to_readback_buffer(device, queue)?;
+        crate::capture::read_buffer_to_image(device, &buffer, size.width, size.height, self.texture.format())
+    }
+
+    //read_back without blocking, the only way that works on the web
+    pub async fn read_back_async(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<image::RgbaImage> {
+        let size = self.texture.size();
+        let buffer = self.copy_to_readback_buffer(device, queue)?;
+        crate::capture::read_buffer_to_image_async(
+            device,
+            &buffer,
+            size.width,
+            size.height,
+            self.texture.format(),
+        )
+        .await
+    }
+
+    //reads the texture back and writes it out as a png
+    pub fn save_png(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &std::path::Path,
+    ) -> Result<()> {
+        crate::capture::save_png(&self.read_back(device, queue)?, path)
+    }
+
+    fn copy_to_readback_buffer(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<wgpu::Buffer> {
+        crate::capture::is_bgra(self.texture.format())?;
+        if !self.texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            bail!("the texture wasn't created with COPY_SRC so it can't be read back");
+        }
+        let size = self.texture.size();
+        let buffer = crate::capture::create_readback_buffer(device, size.width, size.height);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Texture Readback"),
+        });
+        crate::capture::copy_texture_to_buffer(&mut encoder, &self.texture, &buffer);
+        queue.submit(Some(encoder.finish()));
+        Ok(buffer)
+    }
+
+    //a cubemap from six square faces of the same size, in the order +x, -x, +y, -y, +z, -z. the
+    //view is a Cube, bind it through cube_layout_entries
+    pub fn cubemap_from_faces(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: [&image::DynamicImage; 6],
+        label: &str,
+        sampler: &SamplerSettings,
+    ) -> Result<Self> {
+        let size = faces[0].width();
+        for (face, img) in faces.iter().enumerate() {
+            if img.dimensions() != (size, size) {
+                bail!(
+                    "cube face {} is {:?}, expected {}x{} like the first face",
+                    face,
+                    img.dimensions(),
+                    size,
+                    size
+                );
+            }
+        }
+        let faces = faces.map(|img| img.to_rgba8().into_raw());
+        Ok(Self::create_cubemap(device, queue, size, &faces, label, sampler))
+    }
+
+    //a cubemap resampled from an equirectangular panorama, where x runs once around the horizon
+    //with -z, the default forward, in the middle and y runs from straight up to straight down. each face is face_size
+    //pixels square. the panorama is read as 8 bit colour, so hdr images are clipped
+    pub fn from_equirect(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        face_size: u32,
+        label: &str,
+        sampler: &SamplerSettings,
+    ) -> Result<Self> {
+        if face_size == 0 || img.width() == 0 || img.height() == 0 {
+            bail!("an equirect cubemap needs a non empty panorama and face size");
+        }
+        let panorama = img.to_rgba8();
+        let faces: [Vec<u8>; 6] = std::array::from_fn(|face| {
+            let mut pixels = Vec::with_capacity((face_size * face_size * 4) as usize);
+            for y in 0..face_size {
+                for x in 0..face_size {
+                    let u = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+                    let v = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+                    let direction = cube_direction(face, u, v);
+                    pixels.extend_from_slice(&sample_equirect(&panorama, direction));
+                }
+            }
+            pixels
+        });
+        Ok(Self::create_cubemap(device, queue, face_size, &faces, label, sampler))
+    }
+
+    //a 3d texture from tightly packed texels, x fastest then y then z, for colour grading luts,
+    //noise volumes or fog density. SamplerSettings::linear gives trilinear filtering between the
+    //slices. the format has to be filterable to fit layout_entries, e.g. R8Unorm, Rgba8Unorm or
+    //Rgba16Float
+    pub fn from_3d_data(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[u8],
+        size: wgpu::Extent3d,
+        format: wgpu::TextureFormat,
+        label: &str,
+        sampler: &SamplerSettings,
+    ) -> Result<Self> {
+        let texel_size = format
+            .block_copy_size(None)
+            .filter(|_| format.block_dimensions() == (1, 1))
+            .ok_or_else(|| anyhow!("{:?} can't be uploaded as raw 3d texels", format))?;
+        let expected = size.width as usize
+            * size.height as usize
+            * size.depth_or_array_layers as usize
+            * texel_size as usize;
+        if data.len() != expected {
+            bail!(
+                "{}x{}x{} {:?} needs {} bytes, got {}",
+                size.width,
+                size.height,
+                size.depth_or_array_layers,
+                format,
+                expected,
+                data.len()
+            );
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(size.width * texel_size),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D3),
+            ..Default::default()
+        });
+        Ok(Self {
+            sampler: sampler.create_sampler(device, Some(label)),
+            texture,
+            view,
+        })
+    }
+
+    //the two entries of a layout binding a texture with this view dimension at 0 and its sampler
+    //at 1, for BindingCache::layout
+    pub fn layout_entries(
+        visibility: wgpu::ShaderStages,
+        view_dimension: wgpu::TextureViewDimension,
+    ) -> [wgpu::BindGroupLayoutEntry; 2] {
+        [
+            crate::bind_cache::texture_entry(0, visibility, view_dimension),
+            crate::bind_cache::sampler_entry(1, visibility),
+        ]
+    }
+
+    //a depth texture at 0 and its comparison sampler at 1, for the scene depth or a shadow map
+    pub fn depth_layout_entries(visibility: wgpu::ShaderStages) -> [wgpu::BindGroupLayoutEntry; 2] {
+        [
+            crate::bind_cache::depth_texture_entry(0, visibility),
+            crate::bind_cache::comparison_sampler_entry(1, visibility),
+        ]
+    }
+
+    //the layout entries for a 3d texture made by from_3d_data
+    pub fn volume_layout_entries(visibility: wgpu::ShaderStages) -> [wgpu::BindGroupLayoutEntry; 2] {
+        Self::layout_entries(visibility, wgpu::TextureViewDimension::D3)
+    }
+
+    //the layout entries for a texture array made by array_from_images or array_from_textures
+    pub fn array_layout_entries(visibility: wgpu::ShaderStages) -> [wgpu::BindGroupLayoutEntry; 2] {
+        Self::layout_entries(visibility, wgpu::TextureViewDimension::D2Array)
+    }
+
+    //the layout entries for a cubemap made by cubemap_from_faces or from_equirect
+    pub fn cube_layout_entries(visibility: wgpu::ShaderStages) -> [wgpu::BindGroupLayoutEntry; 2] {
+        Self::layout_entries(visibility, wgpu::TextureViewDimension::Cube)
+    }
+
+    fn create_cubemap(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: u32,
+        faces: &[Vec<u8>; 6],
+        label: &str,
+        sampler: &SamplerSettings,
+    ) -> Self {
+        let face_extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                depth_or_array_layers: 6,
+                ..face_extent
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        for (layer, pixels) in faces.iter().enumerate() {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                pixels,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * size),
+                    rows_per_image: Some(size),
+                },
+                face_extent,
+            );
+        }
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = sampler.create_sampler(device, Some(label));
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+}
+
+//the direction through a point on a cube face, u and v running -1..1 from the face's top left,
+//matching the face orientation wgpu samples cubemaps with
+fn cube_direction(face: usize, u: f32, v: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -v, -u],
+        1 => [-1.0, -v, u],
+        2 => [u, 1.0, v],
+        3 => [u, -1.0, -v],
+        4 => [u, -v, 1.0],
+        _ => [-u, -v, -1.0],
+    }
+}
+
+//bilinearly samples a panorama in the direction, wrapping around the horizon
+fn sample_equirect(panorama: &image::RgbaImage, direction: [f32; 3]) -> [u8; 4] {
+    use std::f32::consts::PI;
+    let [x, y, z] = direction;
+    let length = (x * x + y * y + z * z).sqrt();
+    let longitude = x.atan2(-z);
+    let latitude = (y / length).clamp(-1.0, 1.0).asin();
+    let (width, height) = panorama.dimensions();
+    let px = (0.5 + longitude / (2.0 * PI)) * width as f32 - 0.5;
+    let py = (0.5 - latitude / PI) * height as f32 - 0.5;
+    let (x0, y0) = (px.floor(), py.floor());
+    let (fx, fy) = (px - x0, py - y0);
+    let texel = |x: i64, y: i64| {
+        let x = x.rem_euclid(width as i64) as u32;
+        let y = y.clamp(0, height as i64 - 1) as u32;
+        panorama.get_pixel(x, y).0.map(f32::from)
+    };
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let top = [texel(x0, y0), texel(x0 + 1, y0)];
+    let bottom = [texel(x0, y0 + 1), texel(x0 + 1, y0 + 1)];
+    std::array::from_fn(|c| {
+        let upper = top[0][c] + (top[1][c] - top[0][c]) * fx;
+        let lower = bottom[0][c] + (bottom[1][c] - bottom[0][c]) * fx;
+        (upper + (lower - upper) * fy).round() as u8
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_centers_point_along_their_axes() {
+        let axes = [
+            [1.0, 0.0, 0.0],
+            [-1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, -1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0],
+        ];
+        for (face, axis) in axes.iter().enumerate() {
+            assert_eq!(cube_direction(face, 0.0, 0.0), *axis);
+        }
+    }
+
+    #[test]
+    fn equirect_samples_the_horizon_and_poles() {
+        //top half red, bottom half blue, with the middle column of the top half green
+        let panorama = image::RgbaImage::from_fn(64, 32, |x, y| match (x, y) {
+            (_, 16..) => image::Rgba([0, 0, 255, 255]),
+            (28..=35, _) => image::Rgba([0, 255, 0, 255]),
+            _ => image::Rgba([255, 0, 0, 255]),
+        });
+        assert_eq!(sample_equirect(&panorama, [0.0, 1.0, 0.0])[0], 255);
+        assert_eq!(sample_equirect(&panorama, [0.0, -1.0, 0.0])[2], 255);
+        //-z is the middle of the panorama, just above the horizon
+        assert_eq!(sample_equirect(&panorama, [0.0, 0.2, -1.0])[1], 255);
+        assert_eq!(sample_equirect(&panorama, [0.0, 0.2, 1.0])[0], 255);
+    }
 }
@@ -0,0 +1,141 @@
+use cgmath::prelude::*;
+use cgmath::{Deg, Quaternion, Vector3};
+
+//the colors the cycle blends between by how high the sun is: below the horizon, on it and
+//overhead. the night "sun" is dim blue moonlight so the scene never goes fully black
+const NIGHT_LIGHT: [f32; 3] = [0.12, 0.14, 0.28];
+const HORIZON_LIGHT: [f32; 3] = [1.0, 0.55, 0.3];
+const DAY_LIGHT: [f32; 3] = [1.0, 0.97, 0.9];
+const NIGHT_SKY: [f32; 3] = [0.01, 0.01, 0.03];
+const HORIZON_SKY: [f32; 3] = [0.75, 0.4, 0.3];
+const DAY_SKY: [f32; 3] = [0.35, 0.55, 0.9];
+const NIGHT_AMBIENT: f32 = 0.05;
+const DAY_AMBIENT: f32 = 0.15;
+//elevations, as the sine of the sun's angle above the horizon, over which night turns to dusk
+//and dusk to day
+const TWILIGHT: f32 = 0.25;
+
+//the lighting for one moment of the day
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Lighting {
+    //where the light sits, sun_distance from the center along the sun direction
+    pub sun_position: [f32; 3],
+    pub sun_color: [f32; 3],
+    //how much of the sun color lights surfaces facing away from it
+    pub ambient: f32,
+    //the background the scene is cleared to
+    pub sky_color: [f32; 3],
+}
+
+//drives the light through a day and night cycle. time runs from 0 to 24 hours, the sun rises in
+//the east (+x) at 6, is highest at 12 and sets in the west at 18
+#[derive(Debug, Clone)]
+pub struct TimeOfDay {
+    //real seconds a whole day takes
+    pub cycle_seconds: f32,
+    //how far the sun is from the center, far enough to light the scene from outside it
+    pub sun_distance: f32,
+    pub center: Vector3<f32>,
+    //tilts the sun's path away from overhead, towards -z, like a sun seen from higher latitudes
+    pub tilt: Deg<f32>,
+    pub paused: bool,
+    hours: f32,
+    //when tick was last called, the first call only starts the clock
+    last_tick: Option<f64>,
+}
+
+impl TimeOfDay {
+    //starts at the given hour
+    pub fn new(cycle_seconds: f32, hours: f32) -> Self {
+        Self {
+            cycle_seconds,
+            sun_distance: 50.0,
+            center: Vector3::zero(),
+            tilt: Deg(30.0),
+            paused: false,
+            hours: hours.rem_euclid(24.0),
+            last_tick: None,
+        }
+    }
+
+    pub fn hours(&self) -> f32 {
+        self.hours
+    }
+
+    pub fn set_hours(&mut self, hours: f32) {
+        self.hours = hours.rem_euclid(24.0);
+    }
+
+    //moves the clock on by real seconds, wrapping at midnight
+    pub fn advance(&mut self, seconds: f32) {
+        if self.paused || self.cycle_seconds <= 0.0 {
+            return;
+        }
+        self.set_hours(self.hours + seconds / self.cycle_seconds * 24.0);
+    }
+
+    //advances by the time since the last tick, now is in seconds from any fixed point
+    pub fn tick(&mut self, now: f64) {
+        if let Some(last) = self.last_tick.replace(now) {
+            self.advance((now - last) as f32);
+        }
+    }
+
+    //unit vector from the center towards the sun
+    pub fn sun_direction(&self) -> Vector3<f32> {
+        //midnight points straight down, the sun turns about the z axis from there
+        let angle = Deg(self.hours / 24.0 * 360.0);
+        let daily = Quaternion::from_axis_angle(Vector3::unit_z(), angle);
+        let tilt = Quaternion::from_axis_angle(Vector3::unit_x(), -self.tilt);
+        tilt * daily * -Vector3::unit_y()
+    }
+
+    pub fn lighting(&self) -> Lighting {
+        let direction = self.sun_direction();
+        let elevation = direction.y;
+        //how far through the night to dusk and dusk to day blends the sun is
+        let dusk = ((elevation + TWILIGHT) / TWILIGHT).clamp(0.0, 1.0);
+        let day = (elevation / TWILIGHT).clamp(0.0, 1.0);
+        let blend = |night: [f32; 3], horizon: [f32; 3], noon: [f32; 3]| {
+            let night = Vector3::from(night);
+            let color = night.lerp(horizon.into(), dusk);
+            color.lerp(noon.into(), day).into()
+        };
+        Lighting {
+            sun_position: (self.center + direction * self.sun_distance).into(),
+            sun_color: blend(NIGHT_LIGHT, HORIZON_LIGHT, DAY_LIGHT),
+            ambient: NIGHT_AMBIENT + (DAY_AMBIENT - NIGHT_AMBIENT) * day,
+            sky_color: blend(NIGHT_SKY, HORIZON_SKY, DAY_SKY),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sun_rises_in_the_east_and_peaks_at_noon() {
+        let mut time = TimeOfDay::new(60.0, 6.0);
+        let sunrise = time.sun_direction();
+        assert!(sunrise.x > 0.99 && sunrise.y.abs() < 1e-4);
+        time.set_hours(12.0);
+        assert!(time.sun_direction().y > 0.8);
+        time.set_hours(0.0);
+        assert!(time.sun_direction().y < -0.8);
+        assert_eq!(time.lighting().sun_color, NIGHT_LIGHT);
+    }
+
+    #[test]
+    fn ticks_advance_by_the_cycle_length() {
+        let mut time = TimeOfDay::new(240.0, 23.0);
+        time.tick(100.0);
+        assert_eq!(time.hours(), 23.0);
+        //a tenth of the day later, past midnight
+        time.tick(124.0);
+        assert!((time.hours() - 1.4).abs() < 1e-4);
+        time.paused = true;
+        time.tick(200.0);
+        assert!((time.hours() - 1.4).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,150 @@
+use cgmath::prelude::*;
+use cgmath::{Matrix3, Matrix4, Point3, Quaternion, Vector3};
+
+//where an instance is, how it is turned and how big it is. every setter marks the transform
+//dirty so the engine knows which instances need their matrices rebuilt and uploaded, and leaves
+//the ones nobody touched alone
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    position: Vector3<f32>,
+    rotation: Quaternion<f32>,
+    //per axis, non-uniform scale bends the normals so they go through the normal matrix
+    scale: Vector3<f32>,
+    dirty: bool,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::new(
+            Vector3::zero(),
+            Quaternion::one(),
+            Vector3::new(1.0, 1.0, 1.0),
+        )
+    }
+}
+
+impl Transform {
+    //starts out dirty, nothing has uploaded it yet
+    pub fn new(position: Vector3<f32>, rotation: Quaternion<f32>, scale: Vector3<f32>) -> Self {
+        Self {
+            position,
+            rotation,
+            scale,
+            dirty: true,
+        }
+    }
+
+    //at a position and rotation with no scaling
+    pub fn from_position_rotation(position: Vector3<f32>, rotation: Quaternion<f32>) -> Self {
+        Self::new(position, rotation, Vector3::new(1.0, 1.0, 1.0))
+    }
+
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    pub fn rotation(&self) -> Quaternion<f32> {
+        self.rotation
+    }
+
+    pub fn scale(&self) -> Vector3<f32> {
+        self.scale
+    }
+
+    pub fn set_position(&mut self, position: Vector3<f32>) {
+        self.position = position;
+        self.dirty = true;
+    }
+
+    pub fn set_rotation(&mut self, rotation: Quaternion<f32>) {
+        self.rotation = rotation;
+        self.dirty = true;
+    }
+
+    pub fn set_scale(&mut self, scale: Vector3<f32>) {
+        self.scale = scale;
+        self.dirty = true;
+    }
+
+    pub fn set_uniform_scale(&mut self, scale: f32) {
+        self.set_scale(Vector3::new(scale, scale, scale));
+    }
+
+    //moves by an offset in world space
+    pub fn translate(&mut self, offset: Vector3<f32>) {
+        self.set_position(self.position + offset);
+    }
+
+    //turns further, applied after the current rotation
+    pub fn rotate(&mut self, rotation: Quaternion<f32>) {
+        self.set_rotation((rotation * self.rotation).normalize());
+    }
+
+    //model space to world space
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+
+    //keeps normals perpendicular to the surface under non-uniform scale
+    pub fn normal_matrix(&self) -> Matrix3<f32> {
+        crate::normal_matrix(self.rotation, self.scale)
+    }
+
+    //a world space point in the space the model was authored in, the inverse of matrix.
+    //flattening an axis to zero scale sends points off to infinity along it
+    pub fn to_model_space(&self, point: Point3<f32>) -> Point3<f32> {
+        let local = self.rotation.invert().rotate_vector(point - Point3::from_vec(self.position));
+        Point3::from_vec(local.div_element_wise(self.scale))
+    }
+
+    //the largest stretch along any axis, what bounding spheres are scaled by
+    pub fn max_scale(&self) -> f32 {
+        self.scale
+            .x
+            .abs()
+            .max(self.scale.y.abs())
+            .max(self.scale.z.abs())
+    }
+
+    //changed since the engine last uploaded it
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    //clears the flag, returning whether it was set
+    pub(crate) fn take_dirty(&mut self) -> bool {
+        std::mem::replace(&mut self.dirty, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setters_mark_the_transform_dirty() {
+        let mut transform = Transform::default();
+        assert!(transform.take_dirty());
+        assert!(!transform.is_dirty());
+        transform.translate(Vector3::new(1.0, 2.0, 3.0));
+        assert!(transform.is_dirty());
+        assert_eq!(transform.position(), Vector3::new(1.0, 2.0, 3.0));
+        assert!(transform.take_dirty());
+        transform.set_uniform_scale(2.0);
+        assert!(transform.take_dirty());
+        assert!(!transform.take_dirty());
+    }
+
+    #[test]
+    fn matrix_scales_then_rotates_then_translates() {
+        let transform = Transform::new(
+            Vector3::new(10.0, 0.0, 0.0),
+            Quaternion::from_axis_angle(Vector3::unit_y(), cgmath::Deg(90.0)),
+            Vector3::new(2.0, 1.0, 1.0),
+        );
+        let point = transform.matrix() * cgmath::vec4(1.0, 0.0, 0.0, 1.0);
+        assert!((point.truncate() - Vector3::new(10.0, 0.0, -2.0)).magnitude() < 1e-5);
+    }
+}
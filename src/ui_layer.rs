@@ -0,0 +1,88 @@
+use crate::camera::Camera;
+use crate::console::Console;
+use crate::draw_queue::DrawStats;
+use crate::frame_stats::FrameStats;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::WindowEvent;
+
+//a debug ui drawn in its own pass over the finished scene. egui and dear imgui both implement
+//it, the feature flags pick which one is built
+pub trait UiLayer: Send {
+    fn set_visible(&mut self, visible: bool);
+
+    fn is_visible(&self) -> bool;
+
+    //hands the event to the ui, true when the ui is using it and the rest of the app should
+    //ignore it. key releases are never claimed so nothing stays held down behind the ui
+    fn on_event(&mut self, event: &WindowEvent, scale_factor: f64) -> bool;
+
+    //builds this frame's windows, edits made through them are written straight into the panel
+    fn run(
+        &mut self,
+        size: winit::dpi::PhysicalSize<u32>,
+        scale_factor: f64,
+        panel: &mut DebugPanel<'_>,
+    );
+
+    //where a text field being typed into wants the ime candidate window, in physical pixels
+    fn ime_area(&self) -> Option<(PhysicalPosition<f64>, PhysicalSize<f64>)> {
+        None
+    }
+
+    //uploads what the last run produced and draws it over whatever is already in the view
+    fn paint(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: winit::dpi::PhysicalSize<u32>,
+    );
+}
+
+//what the debug windows can see and change
+pub struct DebugPanel<'a> {
+    pub camera: &'a mut Camera,
+    pub camera_speed: &'a mut f32,
+    pub light_position: &'a mut [f32; 3],
+    pub light_color: &'a mut [f32; 3],
+    pub frame_stats: FrameStats,
+    pub draw_stats: DrawStats,
+    //drawn as a bar across the top while it is open
+    pub console: &'a mut Console,
+}
+
+//the layer for the enabled ui feature, egui when both are on and None without either
+#[cfg(feature = "egui")]
+pub fn create(
+    device: &wgpu::Device,
+    _queue: &wgpu::Queue,
+    color_format: wgpu::TextureFormat,
+) -> Option<Box<dyn UiLayer>> {
+    Some(Box::new(crate::egui_layer::EguiLayer::new(
+        device,
+        color_format,
+    )))
+}
+
+#[cfg(all(feature = "imgui", not(feature = "egui")))]
+pub fn create(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    color_format: wgpu::TextureFormat,
+) -> Option<Box<dyn UiLayer>> {
+    Some(Box::new(crate::imgui_layer::ImguiLayer::new(
+        device,
+        queue,
+        color_format,
+    )))
+}
+
+#[cfg(not(any(feature = "egui", feature = "imgui")))]
+pub fn create(
+    _device: &wgpu::Device,
+    _queue: &wgpu::Queue,
+    _color_format: wgpu::TextureFormat,
+) -> Option<Box<dyn UiLayer>> {
+    None
+}
@@ -0,0 +1,177 @@
+use crate::bind_cache::{self, StorageAccess};
+use crate::upload;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+
+//collects the bindings of a bind group so its layout and the group itself come from one list.
+//bindings are numbered in the order they are added, starting at 0
+pub struct BindGroupBuilder<'a> {
+    label: &'a str,
+    layout_entries: Vec<wgpu::BindGroupLayoutEntry>,
+    entries: Vec<wgpu::BindGroupEntry<'a>>,
+}
+
+impl<'a> BindGroupBuilder<'a> {
+    pub fn new(label: &'a str) -> Self {
+        Self {
+            label,
+            layout_entries: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    fn push(
+        mut self,
+        layout_entry: wgpu::BindGroupLayoutEntry,
+        resource: wgpu::BindingResource<'a>,
+    ) -> Self {
+        self.entries.push(wgpu::BindGroupEntry {
+            binding: layout_entry.binding,
+            resource,
+        });
+        self.layout_entries.push(layout_entry);
+        self
+    }
+
+    fn next_binding(&self) -> u32 {
+        self.layout_entries.len() as u32
+    }
+
+    pub fn uniform(self, visibility: wgpu::ShaderStages, buffer: &'a wgpu::Buffer) -> Self {
+        let entry = bind_cache::uniform_entry(self.next_binding(), visibility);
+        self.push(entry, buffer.as_entire_binding())
+    }
+
+    pub fn storage(
+        self,
+        visibility: wgpu::ShaderStages,
+        access: StorageAccess,
+        buffer: &'a wgpu::Buffer,
+    ) -> Self {
+        let entry = bind_cache::storage_entry(self.next_binding(), visibility, access);
+        self.push(entry, buffer.as_entire_binding())
+    }
+
+    pub fn texture(
+        self,
+        visibility: wgpu::ShaderStages,
+        view_dimension: wgpu::TextureViewDimension,
+        view: &'a wgpu::TextureView,
+    ) -> Self {
+        let entry = bind_cache::texture_entry(self.next_binding(), visibility, view_dimension);
+        self.push(entry, wgpu::BindingResource::TextureView(view))
+    }
+
+    pub fn sampler(self, visibility: wgpu::ShaderStages, sampler: &'a wgpu::Sampler) -> Self {
+        let entry = bind_cache::sampler_entry(self.next_binding(), visibility);
+        self.push(entry, wgpu::BindingResource::Sampler(sampler))
+    }
+
+    pub fn layout_entries(&self) -> &[wgpu::BindGroupLayoutEntry] {
+        &self.layout_entries
+    }
+
+    //creates a new layout from the bindings and a bind group using it
+    pub fn build(self, device: &wgpu::Device) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{} Layout", self.label)),
+            entries: &self.layout_entries,
+        });
+        let bind_group = self.build_with_layout(device, &layout);
+        (layout, bind_group)
+    }
+
+    //a bind group for a layout made earlier, which has to match the bindings added
+    pub fn build_with_layout(
+        self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(self.label),
+            layout,
+            entries: &self.entries,
+        })
+    }
+}
+
+//a uniform buffer holding one T, with the layout and bind group exposing it at binding 0. new
+//uniforms are a call to new plus set whenever the value changes
+pub struct UniformBuffer<T: bytemuck::Pod> {
+    buffer: wgpu::Buffer,
+    layout: Arc<wgpu::BindGroupLayout>,
+    bind_group: wgpu::BindGroup,
+    value: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> UniformBuffer<T> {
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        value: &T,
+        visibility: wgpu::ShaderStages,
+    ) -> Self {
+        let buffer = Self::create_buffer(device, label, value);
+        let (layout, bind_group) = BindGroupBuilder::new(label)
+            .uniform(visibility, &buffer)
+            .build(device);
+        Self {
+            buffer,
+            layout: Arc::new(layout),
+            bind_group,
+            value: PhantomData,
+        }
+    }
+
+    //another buffer bound through this one's layout, so both fit the same pipelines, e.g. the
+    //camera of an offscreen target
+    pub fn with_same_layout(&self, device: &wgpu::Device, label: &str, value: &T) -> Self {
+        let buffer = Self::create_buffer(device, label, value);
+        let bind_group = BindGroupBuilder::new(label)
+            .uniform(wgpu::ShaderStages::NONE, &buffer)
+            .build_with_layout(device, &self.layout);
+        Self {
+            buffer,
+            layout: self.layout.clone(),
+            bind_group,
+            value: PhantomData,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, label: &str, value: &T) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::bytes_of(value),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+
+    //writes the value through the queue, it lands before the next submission
+    pub fn set(&self, queue: &wgpu::Queue, value: &T) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(value));
+    }
+
+    //writes the value into the frame's encoder through the staging belt
+    pub(crate) fn stage(
+        &self,
+        uploader: &upload::Uploader,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+        value: &T,
+    ) {
+        uploader.write(encoder, device, &self.buffer, 0, bytemuck::bytes_of(value));
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
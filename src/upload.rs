@@ -0,0 +1,53 @@
+use std::cell::RefCell;
+
+//size of each staging chunk, bigger writes get a chunk of their own
+const CHUNK_SIZE: wgpu::BufferAddress = 1 << 16;
+
+//per frame buffer writes are staged through a belt and recorded into the frame's command encoder
+//rather than going through queue.write_buffer, which allocates hidden staging memory every call.
+//the belt sits in a RefCell so uploads can be recorded from the &self render paths
+pub struct Uploader {
+    belt: RefCell<wgpu::util::StagingBelt>,
+}
+
+impl Uploader {
+    pub fn new() -> Self {
+        Self {
+            belt: RefCell::new(wgpu::util::StagingBelt::new(CHUNK_SIZE)),
+        }
+    }
+
+    //records a copy of data into buffer at offset, executed when the encoder is submitted
+    pub fn write(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+        buffer: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        let Some(size) = wgpu::BufferSize::new(data.len() as wgpu::BufferAddress) else {
+            return;
+        };
+        self.belt
+            .borrow_mut()
+            .write_buffer(encoder, buffer, offset, size, device)
+            .copy_from_slice(data);
+    }
+
+    //must be called after the last write and before the encoder is submitted
+    pub fn finish(&self) {
+        self.belt.borrow_mut().finish();
+    }
+
+    //must be called after the encoder is submitted so the chunks can be reused
+    pub fn recall(&self) {
+        self.belt.borrow_mut().recall();
+    }
+}
+
+impl Default for Uploader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,241 @@
+use crate::resources;
+use anyhow::*;
+use image::GenericImageView;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event_loop::ActiveEventLoop;
+use winit::monitor::{MonitorHandle, VideoModeHandle};
+use winit::window::{
+    Cursor, CursorIcon, CustomCursor, CustomCursorSource, Fullscreen, Icon, Window,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FullscreenMode {
+    Borderless,
+    Exclusive,
+}
+
+//flips the window between windowed and the requested fullscreen mode on the monitor the window
+//is currently on. asking for the other mode while already fullscreen switches to it, so Alt+Enter
+//from borderless goes exclusive. the new size arrives as a Resized event. returns the mode the
+//window was switched to, None for windowed
+pub fn toggle_fullscreen(
+    window: &Window,
+    mode: FullscreenMode,
+    video_mode: Option<&VideoModeRequest>,
+) -> Option<FullscreenMode> {
+    let current = window.fullscreen().map(|fullscreen| match fullscreen {
+        Fullscreen::Borderless(_) => FullscreenMode::Borderless,
+        Fullscreen::Exclusive(_) => FullscreenMode::Exclusive,
+    });
+    if current == Some(mode) {
+        window.set_fullscreen(None);
+        return None;
+    }
+    match window.current_monitor() {
+        Some(monitor) => set_fullscreen_on(window, &monitor, mode, video_mode),
+        None => window.set_fullscreen(Some(Fullscreen::Borderless(None))),
+    }
+    Some(mode)
+}
+
+//makes the window fullscreen on the given monitor. exclusive mode uses the closest match to the
+//requested video mode, or the largest mode with the highest refresh rate if none was asked for,
+//and falls back to borderless when the platform doesn't report any modes
+pub fn set_fullscreen_on(
+    window: &Window,
+    monitor: &MonitorHandle,
+    mode: FullscreenMode,
+    video_mode: Option<&VideoModeRequest>,
+) {
+    let fullscreen = match mode {
+        FullscreenMode::Borderless => Fullscreen::Borderless(Some(monitor.clone())),
+        FullscreenMode::Exclusive => match find_video_mode(monitor, video_mode) {
+            Some(video_mode) => Fullscreen::Exclusive(video_mode),
+            None => Fullscreen::Borderless(Some(monitor.clone())),
+        },
+    };
+    window.set_fullscreen(Some(fullscreen));
+}
+
+//a video mode to look for, the refresh rate is optional
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VideoModeRequest {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate_millihertz: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VideoModeInfo {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u16,
+    pub refresh_rate_millihertz: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    pub size: PhysicalSize<u32>,
+    pub position: PhysicalPosition<i32>,
+    pub scale_factor: f64,
+    pub refresh_rate_millihertz: Option<u32>,
+    pub video_modes: Vec<VideoModeInfo>,
+}
+
+impl MonitorInfo {
+    pub fn from_handle(monitor: &MonitorHandle) -> Self {
+        Self {
+            name: monitor.name(),
+            size: monitor.size(),
+            position: monitor.position(),
+            scale_factor: monitor.scale_factor(),
+            refresh_rate_millihertz: monitor.refresh_rate_millihertz(),
+            video_modes: monitor
+                .video_modes()
+                .map(|mode| VideoModeInfo {
+                    width: mode.size().width,
+                    height: mode.size().height,
+                    bit_depth: mode.bit_depth(),
+                    refresh_rate_millihertz: mode.refresh_rate_millihertz(),
+                })
+                .collect(),
+        }
+    }
+}
+
+//every monitor the platform reports, in the order used by MonitorSelection::monitor_index
+pub fn list_monitors(window: &Window) -> Vec<MonitorInfo> {
+    window
+        .available_monitors()
+        .map(|monitor| MonitorInfo::from_handle(&monitor))
+        .collect()
+}
+
+pub fn monitor_by_index(window: &Window, index: usize) -> Option<MonitorHandle> {
+    window.available_monitors().nth(index)
+}
+
+//picks the video mode matching the request, preferring the exact refresh rate if one was given
+//and otherwise the highest available. with no request the largest mode wins
+pub fn find_video_mode(
+    monitor: &MonitorHandle,
+    request: Option<&VideoModeRequest>,
+) -> Option<VideoModeHandle> {
+    match request {
+        Some(request) => monitor
+            .video_modes()
+            .filter(|mode| mode.size() == PhysicalSize::new(request.width, request.height))
+            .max_by_key(|mode| {
+                let exact = request
+                    .refresh_rate_millihertz
+                    .is_some_and(|rate| rate == mode.refresh_rate_millihertz());
+                (exact, mode.refresh_rate_millihertz(), mode.bit_depth())
+            }),
+        None => monitor.video_modes().max_by_key(|mode| {
+            let size = mode.size();
+            (size.width * size.height, mode.refresh_rate_millihertz())
+        }),
+    }
+}
+
+//which monitor the window should live on and how
+#[derive(Debug, Clone, Default)]
+pub struct MonitorSelection {
+    pub monitor_index: usize,
+    pub fullscreen: Option<FullscreenMode>,
+    pub video_mode: Option<VideoModeRequest>,
+}
+
+//moves the window onto the selected monitor, going fullscreen there if asked. returns false if
+//the monitor doesn't exist
+pub fn apply_monitor_selection(window: &Window, selection: &MonitorSelection) -> bool {
+    let Some(monitor) = monitor_by_index(window, selection.monitor_index) else {
+        return false;
+    };
+    match selection.fullscreen {
+        Some(mode) => set_fullscreen_on(window, &monitor, mode, selection.video_mode.as_ref()),
+        None => {
+            window.set_fullscreen(None);
+            window.set_outer_position(monitor.position());
+        }
+    }
+    true
+}
+
+//a cursor change requested by the app. custom cursors can only be created once we have an
+//active event loop, so they are held as a source until then
+#[derive(Debug)]
+pub enum CursorRequest {
+    Icon(CursorIcon),
+    Custom(CustomCursorSource),
+}
+
+impl CursorRequest {
+    pub fn into_cursor(self, event_loop: &ActiveEventLoop) -> Cursor {
+        match self {
+            CursorRequest::Icon(icon) => Cursor::Icon(icon),
+            CursorRequest::Custom(source) => {
+                let cursor: CustomCursor = event_loop.create_custom_cursor(source);
+                Cursor::Custom(cursor)
+            }
+        }
+    }
+}
+
+impl From<CursorIcon> for CursorRequest {
+    fn from(icon: CursorIcon) -> Self {
+        CursorRequest::Icon(icon)
+    }
+}
+
+impl From<CustomCursorSource> for CursorRequest {
+    fn from(source: CustomCursorSource) -> Self {
+        CursorRequest::Custom(source)
+    }
+}
+
+pub fn icon_from_image(img: &image::DynamicImage) -> Result<Icon> {
+    let (width, height) = img.dimensions();
+    let icon = Icon::from_rgba(img.to_rgba8().into_raw(), width, height)?;
+    Ok(icon)
+}
+
+pub fn cursor_from_image(
+    img: &image::DynamicImage,
+    hotspot_x: u16,
+    hotspot_y: u16,
+) -> Result<CustomCursorSource> {
+    let (width, height) = img.dimensions();
+    let too_large = || anyhow!("cursor image is {}x{}, larger than a cursor can be", width, height);
+    let cursor_width = u16::try_from(width).map_err(|_| too_large())?;
+    let cursor_height = u16::try_from(height).map_err(|_| too_large())?;
+    let source = CustomCursor::from_rgba(
+        img.to_rgba8().into_raw(),
+        cursor_width,
+        cursor_height,
+        hotspot_x,
+        hotspot_y,
+    )?;
+    Ok(source)
+}
+
+//loads an image from the res folder and turns it into a window icon
+pub async fn load_icon(file_name: &str) -> Result<Icon> {
+    let data = resources::load_binary(file_name).await?;
+    let img = image::load_from_memory(&data)?;
+    icon_from_image(&img)
+}
+
+//loads an image from the res folder and turns it into a custom cursor, the hotspot is the pixel
+//that acts as the click point
+pub async fn load_cursor(
+    file_name: &str,
+    hotspot_x: u16,
+    hotspot_y: u16,
+) -> Result<CustomCursorSource> {
+    let data = resources::load_binary(file_name).await?;
+    let img = image::load_from_memory(&data)?;
+    cursor_from_image(&img, hotspot_x, hotspot_y)
+}